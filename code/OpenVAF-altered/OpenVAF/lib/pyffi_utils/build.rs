@@ -0,0 +1,4 @@
+fn main() {
+    let interpreter_config = pyo3_build_config::get();
+    interpreter_config.emit_pyo3_cfgs();
+}
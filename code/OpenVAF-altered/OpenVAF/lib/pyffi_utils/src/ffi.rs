@@ -3,6 +3,9 @@ use std::os::raw::c_ulong;
 
 use pyo3_ffi::*;
 
+/// # Safety
+///
+/// `op` must point to a valid `dict`.
 #[allow(non_snake_case)]
 #[inline(always)]
 pub unsafe fn PyDict_GET_SIZE(op: *mut PyObject) -> Py_ssize_t {
@@ -22,6 +25,7 @@ const PY_TPFLAGS_IMMUTABLETYPE: c_ulong = 0;
 const TY_FLAGS: c_ulong = Py_TPFLAGS_DEFAULT | Py_TPFLAGS_BASETYPE | PY_TPFLAGS_IMMUTABLETYPE;
 // | Py_TPFLAGS_HAVE_VECTORCALL;
 
+#[macro_export]
 macro_rules! zero {
     ($ty:ty) => {{
         union Init {
@@ -42,6 +46,7 @@ pub const fn new_type<T>() -> PyTypeObject {
     res
 }
 
+#[macro_export]
 macro_rules! ob_type {
     ($obj:expr) => {
         (*$obj).ob_type
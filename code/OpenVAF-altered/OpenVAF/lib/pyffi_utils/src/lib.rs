@@ -0,0 +1,12 @@
+//! Helpers for writing python extensions directly against `pyo3_ffi` that are shared by
+//! `verilogae_py` and `melange_py`.
+
+#[macro_use]
+mod offsets;
+#[macro_use]
+mod ffi;
+pub mod numpy;
+pub mod typeref;
+pub mod unicode;
+
+pub use ffi::{new_type, PyDict_GET_SIZE};
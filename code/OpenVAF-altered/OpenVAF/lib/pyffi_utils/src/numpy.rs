@@ -73,6 +73,7 @@ pub struct NumpyArray {
 
 impl NumpyArray {
     // #[inline(never)]
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn new(ptr: *mut PyObject) -> Result<Self, PyArrayError> {
         let capsule = unsafe { PyObject_GetAttr(ptr, ARRAY_STRUCT_STR) };
         let array = unsafe { (*(capsule as *mut PyCapsule)).pointer as *mut PyArrayInterface };
@@ -111,6 +112,10 @@ impl NumpyArray {
         unsafe { *(*self.array).shape }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn stride(&self) -> isize {
         unsafe { *(*self.array).strides / (*self.array).itemsize as isize }
     }
@@ -0,0 +1,73 @@
+use std::ffi::c_void;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::Once;
+
+use pyo3_ffi::{
+    PyCapsule_GetPointer, PyErr_Clear, PyImport_ImportModule, PyMapping_GetItemString, PyObject,
+    PyObject_GenericGetDict, PyObject_GetAttrString, PyTypeObject, PyUnicode_InternFromString,
+    Py_XDECREF,
+};
+
+pub static mut PATHLIB_PATH: *mut PyTypeObject = ptr::null_mut();
+pub static mut ARRAY_STRUCT_STR: *mut PyObject = ptr::null_mut();
+
+static INIT: Once = Once::new();
+
+/// Initializes the python objects used by [`OsStr`](crate::unicode::OsStr) and
+/// [`NumpyArray`](crate::numpy::NumpyArray). Must be called (with the GIL held) before either is
+/// used, usually from the extension's `init_typerefs`.
+#[cold]
+pub fn init_typerefs() {
+    INIT.call_once(|| unsafe {
+        ARRAY_STRUCT_STR =
+            PyUnicode_InternFromString("__array_struct__\0".as_ptr() as *const c_char);
+        let pathlib = PyImport_ImportModule("pathlib\0".as_ptr() as *const c_char);
+        assert!(!pathlib.is_null(), "failed to import pathlib!");
+        PATHLIB_PATH = lookup_module_type(pathlib, "Path\0");
+    });
+}
+
+/// # Safety
+///
+/// `module` must be a valid module object and `name` must be nul terminated.
+#[cold]
+pub unsafe fn lookup_module_type(module: *mut PyObject, name: &str) -> *mut PyTypeObject {
+    let mod_dict = PyObject_GenericGetDict(module, ptr::null_mut());
+    let ptr = PyMapping_GetItemString(mod_dict, name.as_ptr() as *const c_char);
+    Py_XDECREF(ptr);
+    Py_XDECREF(mod_dict);
+    ptr as *mut PyTypeObject
+}
+
+/// # Safety
+///
+/// Must be called with the GIL held.
+#[cold]
+pub unsafe fn load_numpy_types() -> Option<*mut PyTypeObject> {
+    let numpy = PyImport_ImportModule("numpy\0".as_ptr() as *const c_char);
+    if numpy.is_null() {
+        PyErr_Clear();
+        return None;
+    }
+    let array = lookup_module_type(numpy, "ndarray\0");
+    Py_XDECREF(numpy);
+    Some(array)
+}
+
+#[cold]
+pub fn get_numpy_api() -> Option<*const *const c_void> {
+    unsafe {
+        let numpy = PyImport_ImportModule("numpy.core.multiarray\0".as_ptr() as *const c_char);
+        if numpy.is_null() {
+            PyErr_Clear();
+            return None;
+        }
+        let capsule = PyObject_GetAttrString(numpy as _, "_ARRAY_API\0".as_ptr() as *const c_char);
+        if capsule.is_null() {
+            PyErr_Clear();
+            return None;
+        }
+        Some(PyCapsule_GetPointer(capsule, ptr::null_mut()) as _)
+    }
+}
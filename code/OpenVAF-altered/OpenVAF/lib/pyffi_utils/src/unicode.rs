@@ -1,16 +1,18 @@
 use std::fmt::Debug;
+use std::{slice, str};
 
 use pyo3_ffi::*;
-use verilogae_ffi::Slice;
 
 use crate::typeref::PATHLIB_PATH;
 
 pub struct OsStr {
     py: *mut PyObject,
-    pub data: Slice<u8>,
+    ptr: *const u8,
+    len: usize,
 }
 
 impl OsStr {
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn new_path(py: *mut PyObject) -> Option<Option<OsStr>> {
         match unsafe { PyObject_IsInstance(py, PATHLIB_PATH as *mut PyObject) } {
             1 => {
@@ -33,11 +35,19 @@ impl OsStr {
             if ptr.is_null() {
                 return None;
             }
-            let data = Slice::from_raw_parts(ptr, size as usize);
-            Some(OsStr { py, data })
+            Some(OsStr { py, ptr, len: size as usize })
         }
     }
 
+    pub fn as_bytes(&self) -> &[u8] {
+        // the utf8 buffer lives as long as the python string we hold a reference to
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(self.as_bytes()) }
+    }
+
     // #[cfg(windows)]
     // pub fn new(py: *mut PyObject) -> Option<OsStr> {
     //     // Get an owned allocated wide char buffer from PyString, which we have to deallocate
@@ -68,10 +78,6 @@ impl Drop for OsStr {
 
 impl Debug for OsStr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let slice = unsafe {
-            std::str::from_utf8(std::slice::from_raw_parts(self.data.ptr, self.data.len)).unwrap()
-        };
-
-        write!(f, "{}", slice)
+        write!(f, "{}", self.as_str())
     }
 }
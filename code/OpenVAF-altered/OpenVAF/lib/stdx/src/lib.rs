@@ -1,5 +1,8 @@
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::{env, fs};
+use std::{env, fs, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
 
 mod ieee64;
 pub mod iter;
@@ -55,6 +58,45 @@ pub fn integration_test_dir(test: &str) -> PathBuf {
     project_root().join("integration_tests").join(test)
 }
 
+/// A temporary directory with the sources of a test that is removed once the test is done
+pub struct TestDir(Utf8PathBuf);
+
+impl Deref for TestDir {
+    type Target = Utf8Path;
+
+    fn deref(&self) -> &Utf8Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        // the compiled libraries are never unloaded so this may fail on windows
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes `files` (paths relative to the directory) to the temporary directory `dir`.
+/// Returns `None` if the test must be skipped because no linker is available.
+pub fn write_test_module(dir: &str, files: &[(&str, &str)]) -> io::Result<Option<TestDir>> {
+    // skipping in CI for now as we don't have a toolchain there
+    // currently
+    if IS_CI && cfg!(windows) {
+        return Ok(None);
+    }
+
+    let tmp: Utf8PathBuf = env::temp_dir().try_into().expect("only utf8 paths are supported");
+    let dir = TestDir(tmp.join(dir));
+    // remove stale files from interrupted runs
+    let _ = fs::remove_dir_all(&dir.0);
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, contents)?;
+    }
+    Ok(Some(dir))
+}
+
 pub fn is_va_file(path: &Path) -> bool {
     path.extension().and_then(|ext| ext.to_str()).map_or(false, |ext| ext == "va")
 }
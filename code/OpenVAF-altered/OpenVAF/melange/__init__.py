@@ -0,0 +1 @@
+from .melange import *
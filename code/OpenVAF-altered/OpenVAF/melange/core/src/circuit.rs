@@ -2,16 +2,16 @@ use std::ops::{Index, IndexMut};
 
 use ahash::AHashMap;
 use anyhow::{bail, Result};
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use indexmap::IndexMap;
 use log::warn;
 use stdx::{impl_debug_display, impl_idx_from};
 use typed_index_collections::TiVec;
 use typed_indexmap::{TiMap, TiSet};
 
-use crate::devices::{default_devices, DeviceImpl, DeviceInfo, ParamId};
+use crate::devices::{default_devices, DeviceImpl, DeviceInfo, ParamId, Type};
 use crate::expr::{Arena, CircuitParam, CircuitParamCtx};
-use crate::veriloga::{self, compile_va};
+use crate::veriloga::{self, compile_va, load_osdi};
 use crate::Expr;
 
 /// A circuit is the core data structure of melange, it contains a complete description of a
@@ -38,9 +38,14 @@ pub struct Circuit {
 impl Circuit {
     /// Creates a new empty circuit
     pub fn new(name: String, earena: &mut Arena) -> Circuit {
+        Circuit::with_ctx(name, earena.add_ctx())
+    }
+
+    /// Creates a new empty circuit whose parameters are defined in an existing context
+    pub(crate) fn with_ctx(name: String, ctx: CircuitParamCtx) -> Circuit {
         let mut circ = Circuit {
             name,
-            ctx: earena.add_ctx(),
+            ctx,
             nodes: TiSet::with_capacity(16),
             devices: TiMap::with_capacity(32),
             models: TiVec::with_capacity(16),
@@ -106,10 +111,16 @@ impl Circuit {
         name: String,
         item: impl Into<NameSpaceEntry>,
     ) -> Result<()> {
-        let item = item.into();
-        if let Some(old) = self.namespace.insert(name.clone(), item) {
+        self.ensure_undeclared(&name)?;
+        self.namespace.insert(name, item.into());
+        Ok(())
+    }
+
+    /// Returns an error if an item with the name `name` already exists
+    fn ensure_undeclared(&self, name: &str) -> Result<()> {
+        if let Some(&old) = self.namespace.get(name) {
             let old = old.kind();
-            unreachable!("an {old} '{name}' was already declared in this circuit")
+            bail!("an {old} '{name}' was already declared in this circuit")
         }
         Ok(())
     }
@@ -146,8 +157,9 @@ impl Circuit {
             device,
             parameters: ParamList::default(),
         };
-        let id = self.models.push_and_get_key(model);
+        let id = self.models.next_key();
         self.insert_into_namespace(name, id)?;
+        self.models.push(model);
         Ok(id)
     }
 
@@ -187,6 +199,7 @@ impl Circuit {
         device: DeviceId,
         terminal_connections: Vec<Node>,
     ) -> Result<(InstanceId, ModelId)> {
+        self.ensure_undeclared(&name)?;
         let instance_id = self.instances.next_key();
         let model = CircuitModel {
             src: CircuitModelSrc::Implicit(instance_id),
@@ -224,8 +237,9 @@ impl Circuit {
             parameters: ParamList::default(),
             connections: terminal_connections,
         };
-        let id = self.instances.push_and_get_key(instance);
+        let id = self.instances.next_key();
         self.insert_into_namespace(name, id)?;
+        self.instances.push(instance);
         Ok(id)
     }
 
@@ -312,11 +326,31 @@ impl Circuit {
         &mut self,
         path: Utf8PathBuf,
         opts: &veriloga::Opts,
+    ) -> Result<Vec<DeviceId>> {
+        let compilation_result = compile_va(&path, opts)?;
+        self.register_compiled_devices(compilation_result, &path)
+    }
+
+    /// Loads a precompiled OSDI library and registers all contained devices within the circuit.
+    /// Devices are handled exactly like those produced by [`load_veriloga_file`].
+    ///
+    /// # Returns
+    ///
+    /// The list of **newly added** devices.
+    /// An error if the library can not be loaded or does not target a supported OSDI version.
+    pub fn load_osdi_file(&mut self, path: Utf8PathBuf) -> Result<Vec<DeviceId>> {
+        let devices = load_osdi(&path)?;
+        self.register_compiled_devices(devices, &path)
+    }
+
+    fn register_compiled_devices(
+        &mut self,
+        devices: Vec<Box<dyn DeviceImpl>>,
+        path: &Utf8Path,
     ) -> Result<Vec<DeviceId>> {
         let mut new_devices = Vec::new();
         let mut ret_err = None;
-        let compilation_result = compile_va(&path, opts)?;
-        for dev_impl in compilation_result {
+        for dev_impl in devices {
             let name = dev_impl.get_name();
             if let Some(old_dev) = self.devices.raw.get(name) {
                 match &old_dev.va_file {
@@ -360,6 +394,42 @@ impl Circuit {
         self.devices.index(name)
     }
 
+    /// Lookup an instance by name
+    ///
+    /// # Returns
+    ///
+    /// The instance in this circuit that has the name `name`
+    pub fn lookup_instance(&self, name: &str) -> Option<InstanceId> {
+        match self.namespace.get(name)? {
+            NameSpaceEntry::Instance(instance) => Some(*instance),
+            _ => None,
+        }
+    }
+
+    /// Lookup an explicitly created model by name
+    ///
+    /// # Returns
+    ///
+    /// The model in this circuit that has the name `name`
+    pub fn lookup_model(&self, name: &str) -> Option<ModelId> {
+        match self.namespace.get(name)? {
+            NameSpaceEntry::Model(model) => Some(*model),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the parameter `param_name` of `dev` expects a string
+    ///
+    /// # Returns
+    ///
+    /// `false` if `dev` has no parameter `param_name`
+    pub fn is_str_param(&self, dev: DeviceId, param_name: &str) -> bool {
+        matches!(
+            self[dev].parameters.lookup_param(param_name),
+            Some((_, info)) if info.ty == Type::String
+        )
+    }
+
     /// Lookup various information about a device implementation
     ///
     /// # Returns
@@ -475,7 +545,7 @@ impl Circuit {
     ///
     /// The parameters index and an expression that can be used to read the parameter.
     /// `None` if no parameter `name` was found
-    pub fn lookup_param(&mut self, name: &str, earena: &Arena) -> Option<(CircuitParam, Expr)> {
+    pub fn lookup_param(&self, name: &str, earena: &Arena) -> Option<(CircuitParam, Expr)> {
         earena.lookup_param_by_name(self.ctx, name)
     }
 }
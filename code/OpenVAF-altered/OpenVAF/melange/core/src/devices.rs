@@ -10,10 +10,12 @@ use typed_index_collections::TiSlice;
 
 use crate::circuit::Node;
 pub use crate::devices::params::{DeviceParams, ParamId, Type};
+use crate::devices::capacitor::Capacitor;
 use crate::devices::resistor::Resistor;
 use crate::devices::vsource::VoltageSrc;
-use crate::simulation::{MatrixEntryIter, SimBuilder, SimInfo};
+use crate::simulation::{EvalRetFlags, MatrixEntryIter, SimBuilder, SimInfo};
 
+mod capacitor;
 mod params;
 mod resistor;
 mod vsource;
@@ -89,7 +91,22 @@ pub trait InstanceImpl {
 
     fn populate_matrix_ptrs(&mut self, matrix_entries: MatrixEntryIter);
 
-    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<()>;
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<EvalRetFlags>;
+
+    /// The largest time step that the last evaluation allows (for example to hit
+    /// the corner of a `transition`)
+    fn bound_step(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    /// Called when the time point of the last evaluation is accepted, the states
+    /// it wrote become the previous states of the following evaluations.
+    fn accept_step(&mut self) {}
+
+    /// Called when the time point of the last evaluation is rejected, the
+    /// following evaluations start from the states of the last accepted time
+    /// point again.
+    fn reject_step(&mut self) {}
 
     unsafe fn load_matrix_resist(&self);
     unsafe fn load_matrix_react(&self, alpha: f64);
@@ -117,5 +134,5 @@ pub struct DeviceInfo {
 }
 
 pub(crate) fn default_devices() -> impl Iterator<Item = Box<dyn DeviceImpl>> {
-    [VoltageSrc::init_dev(), Resistor::init_dev()].into_iter()
+    [VoltageSrc::init_dev(), Resistor::init_dev(), Capacitor::init_dev()].into_iter()
 }
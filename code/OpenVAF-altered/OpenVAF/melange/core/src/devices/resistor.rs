@@ -9,7 +9,7 @@ use typed_index_collections::TiSlice;
 use super::{ModelImpl, ParamId, SimInfo};
 use crate::circuit::Node;
 use crate::devices::{update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type};
-use crate::simulation::{MatrixEntryIter, SimBuilder};
+use crate::simulation::{EvalRetFlags, MatrixEntryIter, SimBuilder};
 
 pub struct Resistor;
 
@@ -89,8 +89,8 @@ impl InstanceImpl for ResistorInstance {
         }
     }
 
-    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<()> {
-        Ok(())
+    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<EvalRetFlags> {
+        Ok(EvalRetFlags::empty())
     }
 
     unsafe fn load_matrix_resist(&self) {
@@ -1,5 +1,5 @@
 use std::cell::Cell;
-
+use std::f64::consts::PI;
 use std::ptr::NonNull;
 use std::rc::Rc;
 
@@ -10,7 +10,7 @@ use typed_index_collections::TiSlice;
 
 use crate::circuit::Node;
 use crate::devices::{update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type};
-use crate::simulation::{MatrixEntryIter, SimBuilder};
+use crate::simulation::{EvalFlags, EvalRetFlags, MatrixEntryIter, SimBuilder};
 
 use super::{ModelImpl, ParamId, SimInfo};
 
@@ -25,6 +25,22 @@ impl VoltageSrc {
 const DC: ParamId = ParamId(0u32);
 const MAG: ParamId = ParamId(1u32);
 const PHASE: ParamId = ParamId(2u32);
+const TYPE: ParamId = ParamId(3u32);
+const SINEDC: ParamId = ParamId(4u32);
+const AMPL: ParamId = ParamId(5u32);
+const FREQ: ParamId = ParamId(6u32);
+const SINEPHASE: ParamId = ParamId(7u32);
+const DAMP: ParamId = ParamId(8u32);
+const DELAY: ParamId = ParamId(9u32);
+const VAL0: ParamId = ParamId(10u32);
+const VAL1: ParamId = ParamId(11u32);
+const RISE: ParamId = ParamId(12u32);
+const FALL: ParamId = ParamId(13u32);
+const WIDTH: ParamId = ParamId(14u32);
+const PERIOD: ParamId = ParamId(15u32);
+
+/// Corners of a pulse closer than this (relative) distance are considered to be reached
+const CORNER_RTOL: f64 = 1e-12;
 
 const MATRIX_ANODE_BR: usize = 0;
 const MATRIX_BR_ANODE: usize = 1;
@@ -45,6 +61,19 @@ impl DeviceImpl for VoltageSrc {
         res.insert_instance_param("dc", Type::Real);
         res.insert_instance_param("mag", Type::Real);
         res.insert_instance_param("phase", Type::Real);
+        res.insert_instance_param("type", Type::String);
+        res.insert_instance_param("sinedc", Type::Real);
+        res.insert_instance_param("ampl", Type::Real);
+        res.insert_instance_param("freq", Type::Real);
+        res.insert_instance_param("sinephase", Type::Real);
+        res.insert_instance_param("damp", Type::Real);
+        res.insert_instance_param("delay", Type::Real);
+        res.insert_instance_param("val0", Type::Real);
+        res.insert_instance_param("val1", Type::Real);
+        res.insert_instance_param("rise", Type::Real);
+        res.insert_instance_param("fall", Type::Real);
+        res.insert_instance_param("width", Type::Real);
+        res.insert_instance_param("period", Type::Real);
         res
     }
 
@@ -53,15 +82,162 @@ impl DeviceImpl for VoltageSrc {
     }
 }
 
+/// The time dependent value of the source during transient analysis
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum WaveformKind {
+    Dc,
+    Sine,
+    Pulse,
+}
+
+impl WaveformKind {
+    fn from_name(name: &str) -> Option<WaveformKind> {
+        let kind = match name {
+            "dc" => WaveformKind::Dc,
+            "sine" => WaveformKind::Sine,
+            "pulse" => WaveformKind::Pulse,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Waveform {
+    kind: WaveformKind,
+    sinedc: f64,
+    ampl: f64,
+    freq: f64,
+    /// phase of the sine at `delay` in degrees
+    sinephase: f64,
+    damp: f64,
+    delay: f64,
+    val0: f64,
+    val1: f64,
+    rise: f64,
+    fall: f64,
+    width: f64,
+    period: f64,
+}
+
+impl Default for Waveform {
+    fn default() -> Self {
+        Waveform {
+            kind: WaveformKind::Dc,
+            sinedc: 0.0,
+            ampl: 0.0,
+            freq: 0.0,
+            sinephase: 0.0,
+            damp: 0.0,
+            delay: 0.0,
+            val0: 0.0,
+            val1: 0.0,
+            rise: 0.0,
+            fall: 0.0,
+            width: f64::INFINITY,
+            period: f64::INFINITY,
+        }
+    }
+}
+
+impl Waveform {
+    /// Sets a real waveform parameter, returns `false` if `param` does not belong to the waveform
+    fn set_real_param(&mut self, param: ParamId, val: f64) -> bool {
+        let dst = match param {
+            SINEDC => &mut self.sinedc,
+            AMPL => &mut self.ampl,
+            FREQ => &mut self.freq,
+            SINEPHASE => &mut self.sinephase,
+            DAMP => &mut self.damp,
+            DELAY => &mut self.delay,
+            VAL0 => &mut self.val0,
+            VAL1 => &mut self.val1,
+            RISE => &mut self.rise,
+            FALL => &mut self.fall,
+            WIDTH => &mut self.width,
+            PERIOD => &mut self.period,
+            _ => return false,
+        };
+        *dst = val;
+        true
+    }
+
+    /// The time since the start of the current period of a pulse (`None` before `delay`)
+    fn pulse_time(&self, time: f64) -> Option<f64> {
+        if time < self.delay {
+            return None;
+        }
+        let time = time - self.delay;
+        if self.period.is_finite() && self.period > 0.0 {
+            Some(time.rem_euclid(self.period))
+        } else {
+            Some(time)
+        }
+    }
+
+    fn eval(&self, dc: f64, time: f64) -> f64 {
+        match self.kind {
+            WaveformKind::Dc => dc,
+            WaveformKind::Sine => {
+                let phase = self.sinephase.to_radians();
+                let time = (time - self.delay).max(0.0);
+                let ampl = self.ampl * (-time * self.damp).exp();
+                self.sinedc + ampl * (2.0 * PI * self.freq * time + phase).sin()
+            }
+            WaveformKind::Pulse => {
+                let time = match self.pulse_time(time) {
+                    Some(time) => time,
+                    None => return self.val0,
+                };
+                let high_end = self.rise + self.width;
+                if time < self.rise {
+                    self.val0 + (self.val1 - self.val0) * time / self.rise
+                } else if time < high_end {
+                    self.val1
+                } else if time < high_end + self.fall {
+                    self.val1 + (self.val0 - self.val1) * (time - high_end) / self.fall
+                } else {
+                    self.val0
+                }
+            }
+        }
+    }
+
+    /// The first corner of a pulse after `time`. Time steps must end at these corners.
+    fn next_corner(&self, time: f64) -> f64 {
+        if self.kind != WaveformKind::Pulse {
+            return f64::INFINITY;
+        }
+        let tol = CORNER_RTOL * time.abs();
+        let local_time = match self.pulse_time(time + tol) {
+            Some(local_time) => local_time,
+            None => return self.delay,
+        };
+        let start = time + tol - local_time;
+        let corners =
+            [self.rise, self.rise + self.width, self.rise + self.width + self.fall, self.period];
+        corners
+            .into_iter()
+            .map(|corner| start + corner)
+            .find(|&corner| corner > time + tol)
+            .unwrap_or(f64::INFINITY)
+    }
+}
+
 #[derive(Default)]
 struct VoltageSrcModel {
     dc: Cell<f64>,
     mag: Cell<f64>,
     phase: Cell<f64>,
+    waveform: Cell<Waveform>,
+    invalid_type: Cell<Option<String>>,
 }
 
 impl ModelImpl for VoltageSrcModel {
     fn process_params(&self) -> Result<()> {
+        if let Some(kind) = self.invalid_type.take() {
+            bail!("vsource: unknown type '{kind}', expected dc, sine or pulse")
+        }
         Ok(())
     }
 
@@ -70,11 +246,33 @@ impl ModelImpl for VoltageSrcModel {
             DC => &self.dc,
             MAG => &self.mag,
             PHASE => &self.phase,
-            _ => unreachable!("vsource: unknown num param {param:?}"),
+            _ => {
+                let mut waveform = self.waveform.get();
+                if !waveform.set_real_param(param, val) {
+                    unreachable!("vsource: unknown num param {param:?}")
+                }
+                self.waveform.set(waveform);
+                return;
+            }
         };
         dst.set(val);
     }
 
+    fn set_str_param(&self, param: ParamId, val: &str) {
+        match param {
+            TYPE => match WaveformKind::from_name(val) {
+                Some(kind) => {
+                    let mut waveform = self.waveform.get();
+                    waveform.kind = kind;
+                    self.waveform.set(waveform);
+                }
+                // errors are reported by process_params
+                None => self.invalid_type.set(Some(val.to_owned())),
+            },
+            _ => unreachable!("vsource: unknown str param {param:?}"),
+        }
+    }
+
     fn new_instance(self: Rc<Self>) -> Box<dyn super::InstanceImpl> {
         Box::new(VoltageSrcInstance {
             anode: Node::GROUND,
@@ -82,6 +280,9 @@ impl ModelImpl for VoltageSrcModel {
             branch: Node::GROUND,
             dc: self.dc.get(),
             ac: Complex64::from_polar(self.mag.get(), self.phase.get()),
+            waveform: self.waveform.get(),
+            val: self.dc.get(),
+            bound_step: f64::INFINITY,
             matrix_entries: [NonNull::dangling(); 4],
         })
     }
@@ -93,6 +294,10 @@ struct VoltageSrcInstance {
     branch: Node,
     dc: f64,
     ac: Complex64,
+    waveform: Waveform,
+    /// The voltage at the last evaluation
+    val: f64,
+    bound_step: f64,
     matrix_entries: [NonNull<Cell<f64>>; 4],
 }
 
@@ -127,19 +332,46 @@ impl InstanceImpl for VoltageSrcInstance {
         match param {
             DC => self.dc = val,
             MAG => self.ac = Complex64::from_polar(val, self.ac.arg()),
-            PHASE => self.ac = Complex64::from_polar(self.ac.norm_sqr(), val),
-            _ => unreachable!("vsource: unknown num param {param:?}"),
+            PHASE => self.ac = Complex64::from_polar(self.ac.norm(), val),
+            _ => {
+                if !self.waveform.set_real_param(param, val) {
+                    unreachable!("vsource: unknown num param {param:?}")
+                }
+            }
         };
     }
 
+    fn set_str_param(&mut self, param: ParamId, val: &str) {
+        match param {
+            // the model reports invalid types
+            TYPE => {
+                if let Some(kind) = WaveformKind::from_name(val) {
+                    self.waveform.kind = kind
+                }
+            }
+            _ => unreachable!("vsource: unknown str param {param:?}"),
+        }
+    }
+
     fn populate_matrix_ptrs(&mut self, matrix_entries: MatrixEntryIter) {
         for (dst, entry) in zip(&mut self.matrix_entries, matrix_entries) {
             *dst = entry.resist();
         }
     }
 
-    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<()> {
-        Ok(())
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<EvalRetFlags> {
+        if sim_info.flags.contains(EvalFlags::ANALYSIS_TRAN) {
+            self.val = self.waveform.eval(self.dc, sim_info.abstime);
+            self.bound_step = self.waveform.next_corner(sim_info.abstime) - sim_info.abstime;
+        } else {
+            self.val = self.dc;
+            self.bound_step = f64::INFINITY;
+        }
+        Ok(EvalRetFlags::empty())
+    }
+
+    fn bound_step(&self) -> f64 {
+        self.bound_step
     }
 
     unsafe fn load_matrix_resist(&self) {
@@ -157,7 +389,7 @@ impl InstanceImpl for VoltageSrcInstance {
     fn load_residual_resist(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>) {
         rhs[self.anode] += prev_solve[self.branch];
         rhs[self.cathode] -= prev_solve[self.branch];
-        rhs[self.branch] -= self.dc;
+        rhs[self.branch] -= self.val;
         rhs[self.branch] += prev_solve[self.anode] - prev_solve[self.cathode];
     }
 
@@ -5,12 +5,18 @@
 //! [description]: crate::elaboration::CircuitDescription
 //! [circuit]: crate::circuit::Circuit
 
+use ahash::AHashMap;
 use anyhow::{bail, Context, Result};
 use camino::Utf8PathBuf;
-use typed_index_collections::TiVec;
+use stdx::iter::zip;
+use typed_index_collections::{TiSlice, TiVec};
 
 use crate::circuit::{Circuit, DeviceId, InstanceId, ModelId, NameSpaceEntry, Node};
-use crate::{veriloga, Arena, Expr};
+use crate::expr::{CircuitParam, CircuitParamCtx};
+use crate::{veriloga, Arena, Expr, ExprEvalCtx, Value};
+
+/// The temperature (in Kelvin) used to evaluate parameters during elaboration
+const ELABORATION_TEMPERATURE: f64 = 300.15;
 
 /// A textual description of a circuit from which a circuit can be built.
 /// This serves primarily as an intermediate step for the netlist parser.
@@ -24,18 +30,29 @@ use crate::{veriloga, Arena, Expr};
 pub struct CircuitDescription {
     /// The name of the described circuit
     pub name: String,
+    /// The context in which the parameters of the circuit are defined
+    pub ctx: CircuitParamCtx,
+    /// Circuit parameters and the expressions that determine their value.
+    /// Each expression may only depend on the parameters listed before it.
+    pub parameters: Vec<(CircuitParam, Expr)>,
     /// A listing of all instance within the described circuit
     pub instances: TiVec<InstanceId, CircuitInstanceDescription>,
-    /// A listing of all models within the described circuit
+    /// A listing of all models within the described circuit.
+    ///
+    /// Models named `<name>.<N>` (where `N` is an integer) are bins of the model `<name>`.
+    /// Instances of `<name>` use the bin whose `lmin`/`lmax`/`wmin`/`wmax` parameters
+    /// contain the `l` and `w` (divided by `nf`) of the instance.
     pub models: TiVec<ModelId, CircuitModelDescription>,
+    /// A listing of all subcircuit definitions within the described circuit
+    pub subcircuits: Vec<SubcircuitDescription>,
     /// A list of Verilog-A files that need to be compiled
     pub va_files: Vec<Utf8PathBuf>,
-
-    /// Arena allocator for expressions
-    pub earena: Arena,
+    /// A list of precompiled OSDI libraries that need to be loaded
+    pub osdi_files: Vec<Utf8PathBuf>,
 }
 
 /// A device instance inside a [`CircuitDescription`](create::circuit::CircuitDescription).
+#[derive(Clone)]
 pub struct CircuitInstanceDescription {
     /// The name of this instance
     pub name: String,
@@ -72,6 +89,7 @@ pub struct CircuitInstanceDescription {
     pub terminal_connections: Vec<String>,
 }
 
+#[derive(Clone)]
 pub struct CircuitModelDescription {
     pub name: String,
     pub device: String,
@@ -81,15 +99,58 @@ pub struct CircuitModelDescription {
 /// A list of `<param>=<value>` pairs specified by the user
 pub type ParamDescription = Vec<(String, Expr)>;
 
+/// A subcircuit definition inside a [`CircuitDescription`].
+pub struct SubcircuitDescription {
+    /// The name of the subcircuit
+    pub name: String,
+    /// Names of the nodes inside the subcircuit that are connected to the terminals of an
+    /// instance
+    pub ports: Vec<String>,
+    /// The context in which the parameters of the subcircuit are defined
+    pub ctx: CircuitParamCtx,
+    /// Subcircuit parameters and their default values.
+    /// Each default may only depend on the parameters listed before it.
+    pub parameters: Vec<(CircuitParam, Expr)>,
+    /// Models defined inside the subcircuit
+    pub models: Vec<CircuitModelDescription>,
+    /// Instances inside the subcircuit
+    pub instances: Vec<CircuitInstanceDescription>,
+}
+
 impl CircuitDescription {
+    /// Creates an empty circuit description whose parameters are defined in a new context
+    /// of `earena`
+    pub fn new(name: String, earena: &mut Arena) -> CircuitDescription {
+        CircuitDescription {
+            name,
+            ctx: earena.add_ctx(),
+            parameters: Vec::new(),
+            instances: TiVec::new(),
+            models: TiVec::new(),
+            subcircuits: Vec::new(),
+            va_files: Vec::new(),
+            osdi_files: Vec::new(),
+        }
+    }
+
     /// Creates a circuit descriptor by elaborating the information in the descriptor.
     /// During elaboration the following tasks are performed:
     ///
-    /// * compile any Verilog-A models
+    /// * compile any Verilog-A models and load any OSDI libraries
     /// * resolve any model/subcircuit/device references to their definition
+    /// * select the matching bin of binned models
     /// * create implicit models for instances without separate model definition
     /// * match model/instance parameters to parameter ids provided by device
     /// * for each node name connected to a device terminal create a node
+    /// * flatten subcircuit instances into the circuit
+    ///
+    /// The instances, nodes and models inside a subcircuit instance are named hierarchically
+    /// by prefixing their name with `<instance>.` (for example `x1.r1`). Nodes connected to
+    /// the ports of a subcircuit keep the name of the node in the parent instead. Models
+    /// defined inside a subcircuit shadow models with the same name defined outside.
+    ///
+    /// Models are only elaborated if they are used by an instance. This allows using model
+    /// libraries that contain models for devices that are not available.
     ///
     /// All these tasks can fail if the user provided an invalid circuit descriptor.
     /// Currently only the first error is returned using anyhow. In the future all errors should be
@@ -103,28 +164,425 @@ impl CircuitDescription {
     /// If any of the following conditions occurs, an error is returned instead:
     /// * Verilog-A compilation fails
     /// * A model/subcircuit/device is not found
+    /// * No bin of a binned model matches an instance
     pub fn elaborate(self, earena: &mut Arena, opts: &veriloga::Opts) -> Result<Circuit> {
-        let mut res = Circuit::new(self.name, earena);
-        for va_file in self.va_files {
-            res.load_veriloga_file(va_file, opts)?;
+        let CircuitDescription {
+            name,
+            ctx,
+            parameters,
+            instances,
+            models,
+            subcircuits,
+            va_files,
+            osdi_files,
+        } = self;
+
+        let mut circ = Circuit::with_ctx(name, ctx);
+        for va_file in va_files {
+            circ.load_veriloga_file(va_file, opts)?;
+        }
+        for osdi_file in osdi_files {
+            circ.load_osdi_file(osdi_file)?;
         }
 
-        for model in self.models {
-            let name = model.name.clone();
-            res.elaborate_model(model)
-                .with_context(|| format!("while elaborating model '{name}'"))?;
+        let mut elaborator = Elaborator {
+            circ,
+            earena,
+            parameters: &parameters,
+            param_values: None,
+            models: ModelDescriptions::new(&models, String::new()),
+            subcircuits: &subcircuits,
+            subckt_names: subcircuits
+                .iter()
+                .enumerate()
+                .map(|(i, subckt)| (subckt.name.as_str(), i))
+                .collect(),
+            subckt_models: subcircuits.iter().map(|_| None).collect(),
+        };
+        for inst in &instances {
+            elaborator.elaborate_instance(inst, None)?;
         }
 
-        for inst in self.instances {
-            let name = inst.name.clone();
-            res.elaborate_instance(inst)
-                .with_context(|| format!("while elaborating instance '{name}'"))?;
+        let mut res = elaborator.circ;
+        for (param, val) in parameters {
+            res.param_assignments.insert(param, val);
         }
 
         Ok(res)
     }
 }
 
+/// Evaluates the circuit parameters so that instance parameters can be evaluated during
+/// elaboration.
+fn parameter_values(
+    earena: &Arena,
+    parameters: &[(CircuitParam, Expr)],
+) -> Result<Vec<(CircuitParam, Value)>> {
+    let mut eval_ctx = ExprEvalCtx::new(earena);
+    let mut res = Vec::with_capacity(parameters.len() + 1);
+    res.push((CircuitParam::TEMPERATURE, ELABORATION_TEMPERATURE.into()));
+    eval_ctx.set_param(CircuitParam::TEMPERATURE, ELABORATION_TEMPERATURE.into());
+    for &(param, val) in parameters {
+        let val = val.eval(eval_ctx.borrow()).with_context(|| {
+            let (name, _) = earena.lookup_param_info(param).expect("parameter belongs to arena");
+            format!("while evaluating parameter '{name}'")
+        })?;
+        eval_ctx.set_param(param, val);
+        res.push((param, val));
+    }
+    Ok(res)
+}
+
+/// A subcircuit instance whose contents are being elaborated
+struct Frame<'a> {
+    subckt: &'a SubcircuitDescription,
+    /// The index of `subckt` in [`CircuitDescription::subcircuits`]
+    idx: usize,
+    /// The values of all parameters of the subcircuit (in the order they were defined in
+    /// the context of the subcircuit). These expressions only read circuit parameters and
+    /// preceding parameters of the subcircuit.
+    args: Box<[Expr]>,
+    /// The names of instances, models and nodes inside the subcircuit start with this
+    /// prefix (`<instance>.`)
+    prefix: String,
+    /// The nodes connected to the ports of the subcircuit
+    ports: AHashMap<&'a str, String>,
+    /// How many subcircuit instances enclose this one
+    depth: usize,
+}
+
+impl Frame<'_> {
+    fn node(&self, node: &str) -> String {
+        if node == "ground" {
+            return node.to_owned();
+        }
+        match self.ports.get(node) {
+            Some(node) => node.clone(),
+            None => format!("{}{node}", self.prefix),
+        }
+    }
+
+    /// Binds the subcircuit parameters read by `val` to the values of this instance
+    fn bind(&self, earena: &mut Arena, val: Expr) -> Expr {
+        if self.args.is_empty() {
+            return val;
+        }
+        Expr::func_call(earena, self.subckt.ctx, val, self.args.clone())
+    }
+}
+
+/// Subcircuit definitions can be nested at most this deep (prevents infinite recursion)
+const MAX_SUBCKT_DEPTH: usize = 64;
+
+/// Flattens the hierarchy of a [`CircuitDescription`] into a [`Circuit`]
+struct Elaborator<'a> {
+    circ: Circuit,
+    earena: &'a mut Arena,
+    parameters: &'a [(CircuitParam, Expr)],
+    /// The values of `parameters` during elaboration, only computed if required
+    param_values: Option<Vec<(CircuitParam, Value)>>,
+    models: ModelDescriptions<'a>,
+    subcircuits: &'a [SubcircuitDescription],
+    subckt_names: AHashMap<&'a str, usize>,
+    /// The models defined within each subcircuit, created when the subcircuit is first used
+    subckt_models: Vec<Option<ModelDescriptions<'a>>>,
+}
+
+impl<'a> Elaborator<'a> {
+    /// Elaborates `inst`. If `frame` is not `None` the instance is part of the subcircuit
+    /// instance `frame`.
+    fn elaborate_instance(
+        &mut self,
+        inst: &'a CircuitInstanceDescription,
+        frame: Option<&Frame<'a>>,
+    ) -> Result<()> {
+        let name = match frame {
+            Some(frame) => format!("{}{}", frame.prefix, inst.name),
+            None => inst.name.clone(),
+        };
+        self.elaborate_instance_(inst, name.clone(), frame)
+            .with_context(|| format!("while elaborating instance '{name}'"))
+    }
+
+    fn elaborate_instance_(
+        &mut self,
+        inst: &'a CircuitInstanceDescription,
+        name: String,
+        frame: Option<&Frame<'a>>,
+    ) -> Result<()> {
+        let terminal_connections = match frame {
+            Some(frame) => inst.terminal_connections.iter().map(|node| frame.node(node)).collect(),
+            None => inst.terminal_connections.clone(),
+        };
+        let parameters = match frame {
+            Some(frame) => inst
+                .parameters
+                .iter()
+                .map(|(param, val)| (param.clone(), frame.bind(self.earena, *val)))
+                .collect(),
+            None => inst.parameters.clone(),
+        };
+
+        // models defined inside a subcircuit shadow all other definitions
+        let local_model = match frame {
+            Some(frame) if self.subckt_models(frame.idx).contains(&inst.master) => {
+                let model = self.select_model(inst, Some(frame))?;
+                Some(self.elaborate_local_model(model, frame)?)
+            }
+            _ => None,
+        };
+
+        let master = if let Some(model) = local_model {
+            model
+        } else if self.circ.namespace.contains_key(&inst.master) {
+            inst.master.clone()
+        } else if let Some(&subckt) = self.subckt_names.get(&*inst.master) {
+            return self.elaborate_subckt(subckt, name, terminal_connections, parameters, frame);
+        } else if self.models.contains(&inst.master) {
+            let model = self.select_model(inst, frame)?;
+            self.models.elaborate(&mut self.circ, model)?
+        } else {
+            // not found, reported by Circuit::elaborate_instance
+            inst.master.clone()
+        };
+
+        let inst = CircuitInstanceDescription { name, master, parameters, terminal_connections };
+        self.circ.elaborate_instance(inst)?;
+        Ok(())
+    }
+
+    /// Flattens an instance of the subcircuit `subckt`. `parameters` and the names of the
+    /// `nodes` connected to the ports must already be resolved in the parent.
+    fn elaborate_subckt(
+        &mut self,
+        subckt: usize,
+        name: String,
+        nodes: Vec<String>,
+        parameters: ParamDescription,
+        parent: Option<&Frame<'a>>,
+    ) -> Result<()> {
+        let subcircuits = self.subcircuits;
+        let descr = &subcircuits[subckt];
+        if nodes.len() != descr.ports.len() {
+            bail!(
+                "subcircuit '{}' has {} ports but {} nodes were connected",
+                descr.name,
+                descr.ports.len(),
+                nodes.len()
+            )
+        }
+        let depth = parent.map_or(0, |parent| parent.depth + 1);
+        if depth > MAX_SUBCKT_DEPTH {
+            bail!("subcircuits are nested too deeply (is subcircuit '{}' recursive?)", descr.name)
+        }
+
+        let params: Vec<_> = self.earena.ctx_params(descr.ctx).collect();
+        let mut args = vec![None; params.len()];
+        for &(param, default) in &descr.parameters {
+            let pos = params.iter().position(|&it| it == param);
+            args[pos.expect("parameter belongs to the context of the subcircuit")] = Some(default);
+        }
+        for (param_name, val) in parameters {
+            match self.earena.lookup_param_by_name(descr.ctx, &param_name) {
+                Some((param, _)) => {
+                    let pos = params.iter().position(|&it| it == param).unwrap();
+                    args[pos] = Some(val);
+                }
+                // SPICE allows a multiplier on all instances, only the default is supported
+                None if param_name == "m" && val == 1.0.into() => (),
+                None => bail!("subcircuit '{}' has no parameter '{param_name}'", descr.name),
+            }
+        }
+        let args = zip(params, args)
+            .map(|(param, arg)| match arg {
+                Some(arg) => Ok(arg),
+                None => {
+                    let (param, _) = self.earena.lookup_param_info(param).unwrap();
+                    bail!("no value was provided for parameter '{param}' of '{}'", descr.name)
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        let frame = Frame {
+            subckt: descr,
+            idx: subckt,
+            args,
+            prefix: format!("{name}."),
+            ports: zip(&descr.ports, nodes).map(|(port, node)| (port.as_str(), node)).collect(),
+            depth,
+        };
+        for inst in &descr.instances {
+            self.elaborate_instance(inst, Some(&frame))?;
+        }
+        Ok(())
+    }
+
+    fn subckt_models(&mut self, subckt: usize) -> &mut ModelDescriptions<'a> {
+        let subcircuits = self.subcircuits;
+        let descr = &subcircuits[subckt];
+        self.subckt_models[subckt].get_or_insert_with(|| {
+            ModelDescriptions::new(TiSlice::from_ref(&descr.models[..]), format!("{}.", descr.name))
+        })
+    }
+
+    /// Selects the model used by `inst`: either the model with the same name or the bin of
+    /// the binned model that matches its geometry. Models defined in the subcircuit of
+    /// `frame` are preferred over global models, but the parameters are always evaluated
+    /// in the scope of `frame`.
+    fn select_model(
+        &mut self,
+        inst: &CircuitInstanceDescription,
+        frame: Option<&Frame<'a>>,
+    ) -> Result<ModelId> {
+        let local = frame.filter(|frame| self.subckt_models(frame.idx).contains(&inst.master));
+        let models = match local {
+            Some(frame) => self.subckt_models(frame.idx),
+            None => &mut self.models,
+        };
+        if let Some(&model) = models.by_name.get(&inst.master) {
+            return Ok(model);
+        }
+
+        if self.param_values.is_none() {
+            self.param_values = Some(parameter_values(self.earena, self.parameters)?);
+        }
+        let mut eval_ctx = ExprEvalCtx::new(self.earena);
+        for &(param, val) in self.param_values.as_deref().unwrap_or_default() {
+            eval_ctx.set_param(param, val);
+        }
+        // the parameters of the instance and the models only read the parameters of the
+        // subcircuit they are defined in (and circuit parameters)
+        if let Some(frame) = frame {
+            for (param, arg) in zip(self.earena.ctx_params(frame.subckt.ctx), &*frame.args) {
+                let val = arg.eval(eval_ctx.borrow())?;
+                eval_ctx.set_param(param, val);
+            }
+        }
+
+        let models = match local {
+            Some(frame) => self.subckt_models[frame.idx].as_ref().unwrap(),
+            None => &self.models,
+        };
+        models.select_bin(inst, &mut eval_ctx)
+    }
+
+    /// Elaborates a model defined in the subcircuit of `frame` and returns its name. If the
+    /// model parameters depend on the parameters of the subcircuit, a separate model is
+    /// created for each instance of the subcircuit.
+    fn elaborate_local_model(&mut self, model: ModelId, frame: &Frame<'a>) -> Result<String> {
+        let models = self.subckt_models[frame.idx].as_mut().unwrap();
+        let descrs = models.models;
+        let descr = &descrs[model];
+        let ctx = frame.subckt.ctx;
+        if !descr.parameters.iter().any(|&(_, val)| self.earena.reads_ctx(val, ctx)) {
+            return models.elaborate(&mut self.circ, model);
+        }
+
+        let name = format!("{}{}", frame.prefix, descr.name);
+        let parameters = descr
+            .parameters
+            .iter()
+            .map(|(param, val)| (param.clone(), frame.bind(self.earena, *val)))
+            .collect();
+        let descr = CircuitModelDescription {
+            name: name.clone(),
+            device: descr.device.clone(),
+            parameters,
+        };
+        self.circ
+            .elaborate_model(descr)
+            .with_context(|| format!("while elaborating model '{name}'"))?;
+        Ok(name)
+    }
+}
+
+/// The model descriptions and whether they were already elaborated
+struct ModelDescriptions<'a> {
+    models: &'a TiSlice<ModelId, CircuitModelDescription>,
+    /// The names of the elaborated models start with this prefix
+    prefix: String,
+    elaborated: TiVec<ModelId, bool>,
+    by_name: AHashMap<&'a str, ModelId>,
+    /// The bins of each binned model
+    bins: AHashMap<&'a str, Vec<ModelId>>,
+}
+
+impl<'a> ModelDescriptions<'a> {
+    fn new(
+        models: &'a TiSlice<ModelId, CircuitModelDescription>,
+        prefix: String,
+    ) -> ModelDescriptions<'a> {
+        let mut by_name = AHashMap::with_capacity(models.len());
+        let mut bins: AHashMap<&str, Vec<ModelId>> = AHashMap::new();
+        for (id, model) in models.iter_enumerated() {
+            by_name.insert(model.name.as_str(), id);
+            if let Some((base, bin)) = model.name.rsplit_once('.') {
+                if !bin.is_empty() && bin.bytes().all(|c| c.is_ascii_digit()) {
+                    bins.entry(base).or_default().push(id);
+                }
+            }
+        }
+        let elaborated = vec![false; models.len()].into();
+        ModelDescriptions { models, prefix, elaborated, by_name, bins }
+    }
+
+    /// Whether a model (or binned model) `name` exists
+    fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name) || self.bins.contains_key(name)
+    }
+
+    /// Elaborates `model` (if that did not happen already) and returns its name
+    fn elaborate(&mut self, circ: &mut Circuit, model: ModelId) -> Result<String> {
+        let descr = &self.models[model];
+        let name = format!("{}{}", self.prefix, descr.name);
+        if !self.elaborated[model] {
+            let descr = CircuitModelDescription { name: name.clone(), ..descr.clone() };
+            circ.elaborate_model(descr)
+                .with_context(|| format!("while elaborating model '{name}'"))?;
+            self.elaborated[model] = true;
+        }
+        Ok(name)
+    }
+
+    /// Selects the bin of a binned model that matches the geometry of `inst`
+    fn select_bin(
+        &self,
+        inst: &CircuitInstanceDescription,
+        eval_ctx: &mut ExprEvalCtx,
+    ) -> Result<ModelId> {
+        let bins = match self.bins.get(&*inst.master) {
+            Some(bins) => bins,
+            None => bail!("model '{}' not found", inst.master),
+        };
+        let mut geometry_param = |name: &str| -> Result<Option<f64>> {
+            let val = inst.parameters.iter().rev().find(|(param, _)| param == name);
+            val.map(|(_, val)| val.eval_num(eval_ctx.borrow())).transpose()
+        };
+        let (l, w) = match (geometry_param("l")?, geometry_param("w")?) {
+            (Some(l), Some(w)) => (l, w),
+            _ => bail!("the binned model '{}' requires the parameters l and w", inst.master),
+        };
+        let w = w / geometry_param("nf")?.unwrap_or(1.0);
+
+        for &bin in bins {
+            let descr = &self.models[bin];
+            let mut limit = |name: &str, default: f64| -> Result<f64> {
+                let val = descr.parameters.iter().rev().find(|(param, _)| param == name);
+                match val {
+                    Some((_, val)) => val.eval_num(eval_ctx.borrow()),
+                    None => Ok(default),
+                }
+            };
+            let (lmin, lmax) = (limit("lmin", 0.0)?, limit("lmax", f64::INFINITY)?);
+            let (wmin, wmax) = (limit("wmin", 0.0)?, limit("wmax", f64::INFINITY)?);
+            if lmin <= l && l < lmax && wmin <= w && w < wmax {
+                return Ok(bin);
+            }
+        }
+
+        bail!("no bin of model '{}' matches l = {l} and w = {w}", inst.master)
+    }
+}
 impl Circuit {
     /// Creates a circuit model from a [`CircuitDescription`]
     pub fn elaborate_model(&mut self, descr: CircuitModelDescription) -> Result<ModelId> {
@@ -194,7 +652,7 @@ impl Circuit {
             }
 
             None => {
-                bail!("'{}' not found", instance.name);
+                bail!("'{}' not found", instance.master);
             }
         };
 
@@ -34,6 +34,10 @@ impl Expr {
         Expr::Eval(arena.alloc(ExprData::Param(param)))
     }
 
+    pub fn str(arena: &mut Arena, val: &str) -> Expr {
+        Value::Str(arena.intern.get_or_intern(val)).into()
+    }
+
     pub fn cond(arena: &mut Arena, cond: Expr, then_val: Expr, else_val: Expr) -> Result<Expr> {
         let res = match cond {
             _ if then_val == else_val => then_val,
@@ -154,7 +158,7 @@ impl Expr {
                 };
                 Ok(ptr.into())
             }
-            Expr::Value(arg) => Ok((1.0 / arg.to_num()?).into()),
+            Expr::Value(arg) => Ok((-arg.to_num()?).into()),
         }
     }
 
@@ -563,7 +567,7 @@ impl UnaryOp {
             UnaryOp::ASinH => arg.asinh(),
             UnaryOp::Ceil => arg.ceil(),
             UnaryOp::Floor => arg.floor(),
-            UnaryOp::Int => arg.round(),
+            UnaryOp::Int => arg.trunc(),
             UnaryOp::Neg => -arg,
             UnaryOp::Inv => 1.0 / arg,
         }
@@ -676,6 +680,38 @@ impl Arena {
     ) -> impl Iterator<Item = CircuitParam> + ExactSizeIterator {
         self.params[ctx].keys().map(move |param| CircuitParam { ctx, param })
     }
+
+    /// Checks whether `expr` reads any parameter in `ctx`
+    pub fn reads_ctx(&self, expr: Expr, ctx: CircuitParamCtx) -> bool {
+        match expr {
+            Expr::Eval(ptr) => self.ptr_reads_ctx(ptr, ctx),
+            Expr::Value(_) => false,
+        }
+    }
+
+    fn ptr_reads_ctx(&self, ptr: ExprPtr, ctx: CircuitParamCtx) -> bool {
+        match self.exprs[ptr.0 as usize] {
+            ExprData::Param(param) => param.ctx == ctx,
+            ExprData::Cond(ref cond) => {
+                self.ptr_reads_ctx(cond.cond, ctx)
+                    || self.reads_ctx(cond.then_val, ctx)
+                    || self.reads_ctx(cond.else_val, ctx)
+            }
+            ExprData::UserFunc(ref func) => {
+                self.ptr_reads_ctx(func.expr, ctx)
+                    || func.args.iter().any(|&arg| self.reads_ctx(arg, ctx))
+            }
+            ExprData::Equal(lhs, rhs)
+            | ExprData::NotEqual(lhs, rhs)
+            | ExprData::Commutative { lhs, rhs, .. } => {
+                self.ptr_reads_ctx(lhs, ctx) || self.reads_ctx(rhs, ctx)
+            }
+            ExprData::Binary { lhs, rhs, .. } => {
+                self.reads_ctx(lhs, ctx) || self.reads_ctx(rhs, ctx)
+            }
+            ExprData::Unary { arg, .. } => self.ptr_reads_ctx(arg, ctx),
+        }
+    }
 }
 
 struct ParamInfo {
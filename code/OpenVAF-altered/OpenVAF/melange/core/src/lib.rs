@@ -1,6 +1,6 @@
 pub use crate::circuit::Circuit;
 pub use crate::elaboration::CircuitDescription;
-pub use crate::expr::{Arena, Expr, ExprEvalCtx, Value};
+pub use crate::expr::{Arena, CircuitParam, CircuitParamCtx, Expr, ExprEvalCtx, Value};
 
 // #[macro_use]
 // mod utils;
@@ -8,6 +8,7 @@ pub mod circuit;
 mod devices;
 pub mod elaboration;
 mod expr;
+pub mod netlist;
 pub mod simulation;
 mod utils;
 mod veriloga;
@@ -0,0 +1,113 @@
+//! Netlist parsers that produce a [`CircuitDescription`].
+//!
+//! Besides the circuit itself, netlists contain simulator options and analysis
+//! statements. These are returned alongside the [`CircuitDescription`] in a
+//! [`Netlist`] so that the caller can decide how to run the simulation.
+
+use anyhow::{bail, Result};
+
+use crate::simulation::{IntegrationMethod, SimConfig, TranOpts};
+use crate::{CircuitDescription, Expr};
+
+pub use expr::{parse_number, NumberSyntax};
+
+mod expr;
+pub mod spectre;
+pub mod spice;
+mod statements;
+
+/// A parsed netlist
+pub struct Netlist {
+    /// The first line of a SPICE netlist, empty for formats without a title
+    pub title: String,
+    pub circuit: CircuitDescription,
+    /// Simulator options in the order they were specified (later values override earlier ones)
+    pub options: Vec<(String, OptionValue)>,
+    pub analyses: Vec<Analysis>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum OptionValue {
+    /// An option that was specified without a value
+    Flag,
+    Num(f64),
+    Str(String),
+}
+
+/// The spacing of the frequencies of an AC analysis
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sweep {
+    /// `points` linearly spaced frequencies
+    Lin,
+    /// `points` frequencies per decade
+    Dec,
+    /// `points` frequencies per octave
+    Oct,
+}
+
+/// An analysis statement. All values are expressions because they may depend on the
+/// circuit parameters.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Analysis {
+    /// [`Simulation::dc_op`](crate::simulation::Simulation::dc_op)
+    Op,
+    /// [`Simulation::ac`](crate::simulation::Simulation::ac) for each frequency of the sweep
+    Ac { sweep: Sweep, points: Expr, start: Expr, stop: Expr },
+    /// [`Simulation::tran`](crate::simulation::Simulation::tran)
+    Tran { step: Expr, stop: Expr, max_step: Option<Expr> },
+}
+
+impl Netlist {
+    /// The last value of the option `name`
+    pub fn option(&self, name: &str) -> Option<&OptionValue> {
+        self.options.iter().rev().find(|(option, _)| option == name).map(|(_, val)| val)
+    }
+
+    fn num_option(&self, name: &str) -> Result<Option<f64>> {
+        match self.option(name) {
+            Some(OptionValue::Num(val)) => Ok(Some(*val)),
+            Some(val) => bail!("option {name} expects a number but found {val:?}"),
+            None => Ok(None),
+        }
+    }
+
+    /// The solver configuration determined by the options `reltol`, `vntol`, `abstol` and
+    /// `itl1` (other options are ignored).
+    pub fn sim_config(&self) -> Result<SimConfig> {
+        let mut res = SimConfig::default();
+        if let Some(rtol) = self.num_option("reltol")? {
+            res.rtol = rtol;
+        }
+        if let Some(atol) = self.num_option("vntol")? {
+            res.voltage_atol = atol;
+        }
+        if let Some(atol) = self.num_option("abstol")? {
+            res.current_atol = atol;
+        }
+        if let Some(maxiters) = self.num_option("itl1")? {
+            res.maxiters = maxiters as u32;
+        }
+        Ok(res)
+    }
+
+    /// The transient options determined by the options `method` and `trtol`
+    pub fn tran_opts(&self) -> Result<TranOpts> {
+        let mut res = TranOpts::default();
+        match self.option("method") {
+            Some(OptionValue::Str(method)) => {
+                res.method = match &**method {
+                    "gear" | "gear2" => IntegrationMethod::Gear2,
+                    "trap" | "trapezoidal" => IntegrationMethod::Trapezoidal,
+                    "euler" | "be" => IntegrationMethod::BackwardEuler,
+                    _ => bail!("unknown integration method '{method}'"),
+                }
+            }
+            Some(val) => bail!("option method expects a name but found {val:?}"),
+            None => (),
+        }
+        if let Some(trtol) = self.num_option("trtol")? {
+            res.trtol = trtol;
+        }
+        Ok(res)
+    }
+}
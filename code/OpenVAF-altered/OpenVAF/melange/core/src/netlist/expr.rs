@@ -0,0 +1,572 @@
+//! The expression syntax shared by all netlist formats.
+//!
+//! Expressions are first parsed into an [`Ast`] so that the parameters they
+//! reference are known before any parameter is defined (netlists may use a
+//! parameter before its definition). The [`Ast`] is then lowered to an [`Expr`].
+
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+use crate::expr::CircuitParamCtx;
+use crate::{Arena, Expr};
+
+/// Determines how the scale factors after numbers are interpreted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NumberSyntax {
+    /// Case insensitive scale factors (`m` is milli, `meg` is mega) followed by arbitrary units
+    Spice,
+    /// Case sensitive single character scale factors (`m` is milli, `M` is mega)
+    Spectre,
+}
+
+/// Parses a number with an optional scale factor (like `10meg` or `1.5e-3`).
+pub fn parse_number(src: &str, syntax: NumberSyntax) -> Option<f64> {
+    let (mantissa, suffix) = split_number(src)?;
+    let val: f64 = mantissa.parse().ok()?;
+    let scale = match syntax {
+        NumberSyntax::Spice => spice_scale(suffix)?,
+        NumberSyntax::Spectre => spectre_scale(suffix)?,
+    };
+    Some(val * scale)
+}
+
+/// Splits a number into the (floating point) mantissa and the remaining suffix
+fn split_number(src: &str) -> Option<(&str, &str)> {
+    let bytes = src.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    let mut num_digits = digits(&mut i);
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        num_digits += digits(&mut i);
+    }
+    if num_digits == 0 {
+        return None;
+    }
+
+    if i < bytes.len() && matches!(bytes[i], b'e' | b'E') {
+        let mut j = i + 1;
+        if j < bytes.len() && matches!(bytes[j], b'+' | b'-') {
+            j += 1;
+        }
+        if digits(&mut j) != 0 {
+            i = j;
+        }
+    }
+
+    Some(src.split_at(i))
+}
+
+fn spice_scale(suffix: &str) -> Option<f64> {
+    if !suffix.bytes().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let suffix = suffix.to_ascii_lowercase();
+    let scale = if suffix.starts_with("meg") {
+        1e6
+    } else if suffix.starts_with("mil") {
+        25.4e-6
+    } else {
+        match suffix.as_bytes().first() {
+            Some(b't') => 1e12,
+            Some(b'g') => 1e9,
+            Some(b'k') => 1e3,
+            Some(b'm') => 1e-3,
+            Some(b'u') => 1e-6,
+            Some(b'n') => 1e-9,
+            Some(b'p') => 1e-12,
+            Some(b'f') => 1e-15,
+            Some(b'a') => 1e-18,
+            // any other letters are units
+            _ => 1.0,
+        }
+    };
+    Some(scale)
+}
+
+fn spectre_scale(suffix: &str) -> Option<f64> {
+    let scale = match suffix {
+        "" => 1.0,
+        "T" => 1e12,
+        "G" => 1e9,
+        "M" => 1e6,
+        "K" | "k" => 1e3,
+        "_" => 1.0,
+        "%" => 1e-2,
+        "c" => 1e-2,
+        "m" => 1e-3,
+        "u" => 1e-6,
+        "n" => 1e-9,
+        "p" => 1e-12,
+        "f" => 1e-15,
+        "a" => 1e-18,
+        _ => return None,
+    };
+    Some(scale)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// The binding power of the operator, higher values bind stronger
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne => 3,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 4,
+            BinaryOp::Add | BinaryOp::Sub => 5,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 6,
+            BinaryOp::Pow => 8,
+        }
+    }
+}
+
+/// The precedence of unary operators (between multiplication and exponentiation)
+const UNARY_PRECEDENCE: u8 = 7;
+
+/// An expression that has been parsed but not lowered yet
+#[derive(Clone, PartialEq, Debug)]
+pub enum Ast {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Unary(UnaryOp, Box<Ast>),
+    Binary(BinaryOp, Box<Ast>, Box<Ast>),
+    Cond(Box<[Ast; 3]>),
+    Call(String, Vec<Ast>),
+}
+
+impl Ast {
+    /// Parses a complete expression
+    pub fn parse(src: &str, syntax: NumberSyntax) -> Result<Ast> {
+        let tokens = match tokenize(src, syntax) {
+            Ok(tokens) => tokens,
+            Err(err) => bail!("invalid expression '{src}': {err}"),
+        };
+        let mut parser = Parser { tokens, pos: 0 };
+        let res = parser.expr(0);
+        let res = match res {
+            Ok(_) if parser.pos != parser.tokens.len() => {
+                Err(format!("unexpected {}", parser.tokens[parser.pos].describe()))
+            }
+            res => res,
+        };
+        match res {
+            Ok(res) => Ok(res),
+            Err(err) => bail!("invalid expression '{src}': {err}"),
+        }
+    }
+
+    /// Calls `f` for all parameters referenced by this expression
+    pub fn visit_idents<'a>(&'a self, f: &mut impl FnMut(&'a str)) {
+        match self {
+            Ast::Num(_) | Ast::Str(_) => (),
+            Ast::Ident(name) => f(name),
+            Ast::Unary(_, arg) => arg.visit_idents(f),
+            Ast::Binary(_, lhs, rhs) => {
+                lhs.visit_idents(f);
+                rhs.visit_idents(f);
+            }
+            Ast::Cond(args) => args.iter().for_each(|arg| arg.visit_idents(f)),
+            Ast::Call(_, args) => args.iter().for_each(|arg| arg.visit_idents(f)),
+        }
+    }
+
+    /// Returns the value of the expression if it is a (possibly negated) number literal
+    pub fn as_num(&self) -> Option<f64> {
+        match self {
+            Ast::Num(val) => Some(*val),
+            Ast::Unary(UnaryOp::Neg, arg) => arg.as_num().map(|val| -val),
+            _ => None,
+        }
+    }
+
+    /// Lowers the expression to an [`Expr`]. Parameters are looked up in the contexts `scopes`
+    /// (in the listed order).
+    pub fn lower(&self, arena: &mut Arena, scopes: &[CircuitParamCtx]) -> Result<Expr> {
+        let res = match self {
+            Ast::Num(val) => (*val).into(),
+            Ast::Str(val) => Expr::str(arena, val),
+            Ast::Ident(name) => resolve(arena, scopes, name)?,
+            Ast::Unary(op, arg) => {
+                let arg = arg.lower(arena, scopes)?;
+                match op {
+                    UnaryOp::Neg => Expr::neg(arena, arg)?,
+                    UnaryOp::Not => Expr::eq(arena, arg, 0.0.into()),
+                }
+            }
+            Ast::Binary(op, lhs, rhs) => {
+                let lhs = lhs.lower(arena, scopes)?;
+                let rhs = rhs.lower(arena, scopes)?;
+                match op {
+                    BinaryOp::Add => Expr::add(arena, lhs, rhs)?,
+                    BinaryOp::Sub => {
+                        let rhs = Expr::neg(arena, rhs)?;
+                        Expr::add(arena, lhs, rhs)?
+                    }
+                    BinaryOp::Mul => Expr::mul(arena, lhs, rhs)?,
+                    BinaryOp::Div => {
+                        let rhs = Expr::inv(arena, rhs)?;
+                        Expr::mul(arena, lhs, rhs)?
+                    }
+                    BinaryOp::Pow => Expr::pow(arena, lhs, rhs)?,
+                    BinaryOp::Mod => Expr::fmod(arena, lhs, rhs)?,
+                    BinaryOp::Eq => Expr::eq(arena, lhs, rhs),
+                    BinaryOp::Ne => Expr::neq(arena, lhs, rhs),
+                    BinaryOp::Lt => Expr::lt(arena, lhs, rhs)?,
+                    BinaryOp::Le => Expr::le(arena, lhs, rhs)?,
+                    BinaryOp::Gt => Expr::lt(arena, rhs, lhs)?,
+                    BinaryOp::Ge => Expr::le(arena, rhs, lhs)?,
+                    BinaryOp::And => Expr::logic_and(arena, lhs, rhs)?,
+                    BinaryOp::Or => Expr::logic_or(arena, lhs, rhs)?,
+                }
+            }
+            Ast::Cond(args) => {
+                let [cond, then_val, else_val] = &**args;
+                let cond = cond.lower(arena, scopes)?;
+                let then_val = then_val.lower(arena, scopes)?;
+                let else_val = else_val.lower(arena, scopes)?;
+                Expr::cond(arena, cond, then_val, else_val)?
+            }
+            Ast::Call(name, args) => lower_call(arena, scopes, name, args)?,
+        };
+        Ok(res)
+    }
+}
+
+fn resolve(arena: &mut Arena, scopes: &[CircuitParamCtx], name: &str) -> Result<Expr> {
+    let name = match name {
+        "temper" => "temp",
+        "pi" => return Ok(PI.into()),
+        _ => name,
+    };
+    for &ctx in scopes {
+        if let Some((_, expr)) = arena.lookup_param_by_name(ctx, name) {
+            return Ok(expr);
+        }
+    }
+    if let Some((_, expr)) = arena.lookup_param_by_name(CircuitParamCtx::ROOT, name) {
+        return Ok(expr);
+    }
+    bail!("unknown parameter '{name}'")
+}
+
+fn lower_call(
+    arena: &mut Arena,
+    scopes: &[CircuitParamCtx],
+    name: &str,
+    args: &[Ast],
+) -> Result<Expr> {
+    let mut lowered = Vec::with_capacity(args.len());
+    for arg in args {
+        lowered.push(arg.lower(arena, scopes)?);
+    }
+
+    let expect_args = |cnt: usize| {
+        if args.len() != cnt {
+            bail!("{name} expects {cnt} arguments but {} were provided", args.len())
+        }
+        Ok(())
+    };
+
+    let unary: Option<fn(&mut Arena, Expr) -> Result<Expr>> = match name {
+        "sqrt" => Some(Expr::sqrt),
+        "exp" => Some(Expr::exp),
+        "log" | "ln" => Some(Expr::log),
+        "log10" => Some(Expr::log10),
+        "abs" => Some(Expr::abs),
+        "sin" => Some(Expr::sin),
+        "cos" => Some(Expr::cos),
+        "tan" => Some(Expr::tan),
+        "atan" => Some(Expr::atam),
+        "asin" => Some(Expr::asin),
+        "acos" => Some(Expr::acos),
+        "sinh" => Some(Expr::sinh),
+        "cosh" => Some(Expr::cosh),
+        "tanh" => Some(Expr::tanh),
+        "asinh" => Some(Expr::asinh),
+        "atanh" => Some(Expr::atanh),
+        "ceil" => Some(Expr::ceil),
+        "floor" => Some(Expr::floor),
+        "int" => Some(Expr::int),
+        _ => None,
+    };
+    if let Some(unary) = unary {
+        expect_args(1)?;
+        return unary(arena, lowered[0]);
+    }
+
+    let binary: Option<fn(&mut Arena, Expr, Expr) -> Result<Expr>> = match name {
+        "pow" => Some(Expr::pow),
+        "atan2" => Some(Expr::atan2),
+        "hypot" => Some(Expr::hypot),
+        "min" => Some(Expr::min),
+        "max" => Some(Expr::max),
+        "fmod" => Some(Expr::fmod),
+        _ => None,
+    };
+    if let Some(binary) = binary {
+        expect_args(2)?;
+        return binary(arena, lowered[0], lowered[1]);
+    }
+
+    match name {
+        "if" | "ternary_fcn" => {
+            expect_args(3)?;
+            Expr::cond(arena, lowered[0], lowered[1], lowered[2])
+        }
+        // statistical variations are not supported, only the nominal value is used
+        "agauss" | "gauss" | "aunif" | "unif" | "limit" => match lowered.first() {
+            Some(&nominal) => Ok(nominal),
+            None => bail!("{name} expects at least 1 argument"),
+        },
+        _ => bail!("unknown function '{name}'"),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(BinaryOp),
+    Not,
+    Question,
+    Colon,
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Num(val) => format!("number {val}"),
+            Token::Str(val) => format!("string \"{val}\""),
+            Token::Ident(name) => format!("identifier '{name}'"),
+            Token::Op(op) => format!("operator {op:?}"),
+            Token::Not => "'!'".to_owned(),
+            Token::Question => "'?'".to_owned(),
+            Token::Colon => "':'".to_owned(),
+            Token::LParen => "'('".to_owned(),
+            Token::RParen => "')'".to_owned(),
+            Token::Comma => "','".to_owned(),
+        }
+    }
+}
+
+fn tokenize(src: &str, syntax: NumberSyntax) -> Result<Vec<Token>, String> {
+    let bytes = src.as_bytes();
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        let mut len = 1;
+        let token = match c {
+            _ if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'+' => Token::Op(BinaryOp::Add),
+            b'-' => Token::Op(BinaryOp::Sub),
+            b'*' if next == Some(b'*') => {
+                len = 2;
+                Token::Op(BinaryOp::Pow)
+            }
+            b'*' => Token::Op(BinaryOp::Mul),
+            b'/' => Token::Op(BinaryOp::Div),
+            b'^' => Token::Op(BinaryOp::Pow),
+            b'%' => Token::Op(BinaryOp::Mod),
+            b'!' if next != Some(b'=') => Token::Not,
+            b'=' | b'!' | b'<' | b'>' | b'&' | b'|' => {
+                let (op, op_len) = match (c, next) {
+                    (b'=', Some(b'=')) => (BinaryOp::Eq, 2),
+                    (b'!', Some(b'=')) => (BinaryOp::Ne, 2),
+                    (b'<', Some(b'=')) => (BinaryOp::Le, 2),
+                    (b'<', _) => (BinaryOp::Lt, 1),
+                    (b'>', Some(b'=')) => (BinaryOp::Ge, 2),
+                    (b'>', _) => (BinaryOp::Gt, 1),
+                    (b'&', Some(b'&')) => (BinaryOp::And, 2),
+                    (b'|', Some(b'|')) => (BinaryOp::Or, 2),
+                    _ => return Err(format!("unexpected character '{}'", c as char)),
+                };
+                len = op_len;
+                Token::Op(op)
+            }
+            b'?' => Token::Question,
+            b':' => Token::Colon,
+            b'(' => Token::LParen,
+            b')' => Token::RParen,
+            b',' => Token::Comma,
+            b'"' => match src[i + 1..].find('"') {
+                Some(end) => {
+                    len = end + 2;
+                    Token::Str(src[i + 1..i + 1 + end].to_owned())
+                }
+                None => return Err("unterminated string".to_owned()),
+            },
+            b'0'..=b'9' | b'.' => {
+                let (mantissa, _) = split_number(&src[i..]).ok_or("invalid number")?;
+                len = mantissa.len();
+                // the scale factor (and units) directly follow the number
+                while bytes.get(i + len).map_or(false, |c| c.is_ascii_alphabetic()) {
+                    len += 1;
+                }
+                let literal = &src[i..i + len];
+                match parse_number(literal, syntax) {
+                    Some(val) => Token::Num(val),
+                    None => return Err(format!("invalid number '{literal}'")),
+                }
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' => {
+                while bytes.get(i + len).map_or(false, |&c| c.is_ascii_alphanumeric() || c == b'_')
+                {
+                    len += 1;
+                }
+                Token::Ident(src[i..i + len].to_owned())
+            }
+            _ => {
+                let c = src[i..].chars().next().unwrap();
+                return Err(format!("unexpected character '{c}'"));
+            }
+        };
+        res.push(token);
+        i += len;
+    }
+    Ok(res)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let res = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        res
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => {
+                Err(format!("expected {} but found {}", expected.describe(), token.describe()))
+            }
+            None => Err(format!("expected {}", expected.describe())),
+        }
+    }
+
+    /// Parses an expression whose binary operators bind stronger than `min_precedence`
+    fn expr(&mut self, min_precedence: u8) -> Result<Ast, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(&Token::Op(op)) if op.precedence() > min_precedence => {
+                    self.pos += 1;
+                    // exponentiation is right associative
+                    let precedence =
+                        if op == BinaryOp::Pow { op.precedence() - 1 } else { op.precedence() };
+                    let rhs = self.expr(precedence)?;
+                    lhs = Ast::Binary(op, Box::new(lhs), Box::new(rhs));
+                }
+                Some(Token::Question) if min_precedence == 0 => {
+                    self.pos += 1;
+                    let then_val = self.expr(0)?;
+                    self.expect(Token::Colon)?;
+                    let else_val = self.expr(0)?;
+                    lhs = Ast::Cond(Box::new([lhs, then_val, else_val]));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Ast, String> {
+        let op = match self.peek() {
+            Some(Token::Op(BinaryOp::Sub)) => UnaryOp::Neg,
+            Some(Token::Not) => UnaryOp::Not,
+            Some(Token::Op(BinaryOp::Add)) => {
+                self.pos += 1;
+                return self.unary();
+            }
+            _ => return self.primary(),
+        };
+        self.pos += 1;
+        let arg = self.expr(UNARY_PRECEDENCE)?;
+        Ok(Ast::Unary(op, Box::new(arg)))
+    }
+
+    fn primary(&mut self) -> Result<Ast, String> {
+        let res = match self.next() {
+            Some(Token::Num(val)) => Ast::Num(val),
+            Some(Token::Str(val)) => Ast::Str(val),
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Ast::Ident(name));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() == Some(&Token::RParen) {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        args.push(self.expr(0)?);
+                        match self.next() {
+                            Some(Token::Comma) => continue,
+                            Some(Token::RParen) => break,
+                            Some(token) => return Err(format!("unexpected {}", token.describe())),
+                            None => return Err("expected ')'".to_owned()),
+                        }
+                    }
+                }
+                Ast::Call(name, args)
+            }
+            Some(Token::LParen) => {
+                let res = self.expr(0)?;
+                self.expect(Token::RParen)?;
+                res
+            }
+            Some(token) => return Err(format!("unexpected {}", token.describe())),
+            None => return Err("unexpected end of expression".to_owned()),
+        };
+        Ok(res)
+    }
+}
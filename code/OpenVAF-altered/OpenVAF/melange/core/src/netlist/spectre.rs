@@ -0,0 +1,428 @@
+//! Parser for a subset of the Spectre netlist format.
+//!
+//! The following statements are supported: instances, `model`, `subckt`/`ends`
+//! (also `inline subckt`), `parameters`, `include` (optionally with `section=`),
+//! library sections, `ahdl_include` and `simulator lang=...`. The `dc`, `ac` and
+//! `tran` analyses are mapped to [`Analysis`](super::Analysis) and `options`
+//! statements to simulator options. Other analyses and control statements (like
+//! `save` or `info`) are ignored with a warning.
+//!
+//! Spectre netlists are case sensitive. Lines after `simulator lang=spice` (and
+//! files with a SPICE extension like `.sp`) are parsed by the [SPICE
+//! parser](super::spice) until the next `simulator lang=spectre`.
+//!
+//! Verilog-A files included with `ahdl_include` are compiled when the circuit is
+//! elaborated. Models of the builtin `bsim4` device are mapped to the `bsim4va`
+//! Verilog-A module.
+
+use std::rc::Rc;
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use log::warn;
+
+use crate::netlist::expr::{Ast, BinaryOp, NumberSyntax};
+use crate::netlist::spice;
+use crate::netlist::statements::{
+    self, is_assignment, parse_assignments, parse_options, path_arg, read_file, tokenize, word,
+    AnalysisAst, Instance, Loc, Model, Sections, Statements, Token,
+};
+use crate::netlist::{Netlist, Sweep};
+use crate::Arena;
+
+const SPECTRE: NumberSyntax = NumberSyntax::Spectre;
+
+/// Analyses and control statements that are ignored
+const IGNORED_ANALYSES: [&str; 14] = [
+    "info",
+    "noise",
+    "sp",
+    "xf",
+    "pz",
+    "stb",
+    "sweep",
+    "montecarlo",
+    "pss",
+    "pac",
+    "hb",
+    "alter",
+    "altergroup",
+    "set",
+];
+
+/// Values of the `type` parameter of sources, these are names and not parameter references
+const SOURCE_TYPES: [&str; 6] = ["dc", "sine", "pulse", "exp", "pwl", "sffm"];
+
+/// Names of the ground node
+const GROUND: [&str; 1] = ["0"];
+
+/// Parses the netlist at `path` and all files it includes.
+pub fn parse_file(path: &Utf8Path, earena: &mut Arena) -> Result<Netlist> {
+    parse_str(&read_file(path, 0)?, path, earena)
+}
+
+/// Parses the netlist `src`. Relative paths of included files are resolved relative to the
+/// directory of `path`.
+pub fn parse_str(src: &str, path: &Utf8Path, earena: &mut Arena) -> Result<Netlist> {
+    let mut parser = Parser::default();
+    parser.read(src, Rc::new(path.to_owned()), None, 0)?;
+    // spectre netlists have no title
+    parser.statements.lower(String::new(), path, earena)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    Spectre,
+    Spice,
+}
+
+impl Lang {
+    /// The language a file starts in
+    fn of_file(path: &Utf8Path) -> Lang {
+        match path.extension() {
+            Some("sp" | "spi" | "spice" | "cir" | "ckt") => Lang::Spice,
+            _ => Lang::Spectre,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    statements: Statements,
+}
+
+impl Parser {
+    fn read_file(&mut self, path: &Utf8Path, section: Option<&str>, depth: usize) -> Result<()> {
+        let src = read_file(path, depth)?;
+        self.read(&src, Rc::new(path.to_owned()), section, depth)
+    }
+
+    /// Parses `src`. If `section` is specified only the statements inside the library
+    /// section `section` are parsed.
+    fn read(
+        &mut self,
+        src: &str,
+        file: Rc<Utf8PathBuf>,
+        section: Option<&str>,
+        depth: usize,
+    ) -> Result<()> {
+        let dir = file.parent().unwrap_or_else(|| Utf8Path::new(""));
+        let mut lang = Lang::of_file(&file);
+        // consecutive lines in SPICE syntax (and the number of the first line) are parsed
+        // together so that continuation lines work
+        let mut spice_lines = String::new();
+        let mut spice_start = 1;
+        let mut sections = Sections::new(section);
+
+        let mut lines = src.lines().enumerate();
+        while let Some((i, line)) = lines.next() {
+            if lang == Lang::Spice && !line.trim_start().starts_with("simulator") {
+                if !sections.is_skipping() && sections.is_active() {
+                    if spice_lines.is_empty() {
+                        spice_start = i + 1;
+                    }
+                    spice_lines.push_str(line);
+                    spice_lines.push('\n');
+                }
+                continue;
+            }
+            if !spice_lines.is_empty() {
+                self.read_spice(&spice_lines, &file, spice_start, depth)?;
+                spice_lines.clear();
+            }
+
+            let loc = Loc { file: file.clone(), line: i + 1 };
+            let mut text = strip_comment(line).trim_end().to_owned();
+            while text.ends_with('\\') {
+                text.pop();
+                match lines.next() {
+                    Some((_, next)) => text.push_str(strip_comment(next).trim_end()),
+                    None => break,
+                }
+            }
+
+            let tokens = tokenize(&text, false, &loc)?;
+            let statement = match tokens.first() {
+                Some(Token::Word(statement)) => statement.as_str(),
+                Some(token) => bail!("{loc}: expected a statement but found {token}"),
+                None => continue,
+            };
+
+            if sections.is_skipping() {
+                if statement == "endsection" {
+                    let name = match tokens.get(1) {
+                        Some(Token::Word(name)) => Some(name.as_str()),
+                        _ => None,
+                    };
+                    sections.end(name, &loc);
+                }
+                continue;
+            }
+
+            match statement {
+                "simulator" => match keyword_arg(&tokens[1..], "lang") {
+                    Some("spice") => lang = Lang::Spice,
+                    Some("spectre") => lang = Lang::Spectre,
+                    Some(lang) => bail!("{loc}: unknown language {lang}"),
+                    None => (),
+                },
+                "library" | "endlibrary" => (),
+                "section" => {
+                    let name = match tokens.get(1) {
+                        Some(name) => word(name).with_context(|| loc.to_string())?,
+                        None => bail!("{loc}: expected section <name>"),
+                    };
+                    sections.begin(name);
+                }
+                "endsection" => {
+                    if sections.end(None, &loc) {
+                        return Ok(());
+                    }
+                }
+                _ if !sections.is_active() => (),
+                "include" => {
+                    let path = path_arg(&tokens, 1, dir)
+                        .with_context(|| format!("{loc}: invalid include statement"))?;
+                    let section = keyword_arg(&tokens[2..], "section");
+                    self.read_file(&path, section, depth + 1)
+                        .with_context(|| format!("{loc}: while reading {path}"))?;
+                }
+                "ahdl_include" => {
+                    let path = path_arg(&tokens, 1, dir)
+                        .with_context(|| format!("{loc}: invalid ahdl_include statement"))?;
+                    self.statements.va_files.push(path);
+                }
+                _ => self.parse_statement(&tokens, loc)?,
+            }
+        }
+
+        if !spice_lines.is_empty() {
+            self.read_spice(&spice_lines, &file, spice_start, depth)?;
+        }
+
+        sections.finish(&file)
+    }
+
+    /// Parses lines in SPICE syntax
+    fn read_spice(
+        &mut self,
+        src: &str,
+        file: &Rc<Utf8PathBuf>,
+        first_line: usize,
+        depth: usize,
+    ) -> Result<()> {
+        let mut reader = spice::Reader::default();
+        reader.read(src, file.clone(), None, depth, first_line)?;
+        self.statements.osdi_files.extend(reader.osdi_files);
+        for line in reader.lines {
+            spice::parse_line(&mut self.statements, line)?;
+        }
+        Ok(())
+    }
+
+    fn parse_statement(&mut self, tokens: &[Token], loc: Loc) -> Result<()> {
+        let statement = word(&tokens[0])?;
+        match statement {
+            "parameters" => {
+                let params =
+                    parse_assignments(&tokens[1..], SPECTRE).with_context(|| loc.to_string())?;
+                self.statements.scope().def_params(params, &loc);
+            }
+            "subckt" | "inline" => {
+                let header = if statement == "inline" { &tokens[1..] } else { tokens };
+                let subckt = statements::parse_subckt(header, SPECTRE, &loc)
+                    .with_context(|| loc.to_string())?;
+                self.statements.begin_subckt(subckt);
+            }
+            "ends" => self.statements.end_subckt(&loc)?,
+            "model" => {
+                let model = parse_model(tokens, loc.clone()).with_context(|| loc.to_string())?;
+                self.statements.scope().models.push(model);
+            }
+            "global" => {
+                if tokens[1..].iter().any(|node| *node != Token::Word("0".to_owned())) {
+                    warn!("{loc}: global nodes are not supported")
+                }
+            }
+            "save" | "ic" | "nodeset" => warn!("{loc}: ignoring unsupported statement {statement}"),
+            "real" | "function" | "statistics" | "if" => {
+                bail!("{loc}: {statement} is not supported")
+            }
+            _ => self.parse_instance(tokens, loc)?,
+        }
+        Ok(())
+    }
+
+    /// Parses `<name> [(]<nodes>*[)] <master> <param>=<value>*`. Instances without nodes may
+    /// be analyses or control statements.
+    fn parse_instance(&mut self, tokens: &[Token], loc: Loc) -> Result<()> {
+        let name = word(&tokens[0])?.to_owned();
+        let (nodes, master, params) = if tokens.get(1) == Some(&Token::LParen) {
+            let len = tokens[2..].iter().position(|token| *token == Token::RParen);
+            let len = len.with_context(|| format!("{loc}: unterminated node list"))?;
+            match tokens.get(len + 3) {
+                Some(master) => (&tokens[2..len + 2], master, &tokens[len + 4..]),
+                None => bail!("{loc}: {name}: expected a master after the node list"),
+            }
+        } else {
+            let end = (1..tokens.len()).find(|&i| is_assignment(tokens, i)).unwrap_or(tokens.len());
+            match tokens[1..end].split_last() {
+                Some((master, nodes)) => (nodes, master, &tokens[end..]),
+                None => bail!("{loc}: {name}: expected <nodes> <master>"),
+            }
+        };
+        let master = word(master).with_context(|| loc.to_string())?;
+
+        if nodes.is_empty() {
+            match master {
+                "options" => {
+                    let options =
+                        parse_options(params, SPECTRE).with_context(|| loc.to_string())?;
+                    self.statements.options.extend(options);
+                    return Ok(());
+                }
+                "dc" | "ac" | "tran" => {
+                    let analysis =
+                        parse_analysis(master, params, &loc).with_context(|| loc.to_string())?;
+                    self.statements.analyses.extend(analysis.map(|analysis| (analysis, loc)));
+                    return Ok(());
+                }
+                _ if IGNORED_ANALYSES.contains(&master) => {
+                    warn!("{loc}: ignoring unsupported analysis {name} ({master})");
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+
+        let nodes = nodes.iter().map(node).collect::<Result<Vec<_>>>()?;
+        let mut params = parse_assignments(params, SPECTRE).with_context(|| loc.to_string())?;
+        for (param, val) in &mut params {
+            match val {
+                Ast::Ident(ty) if param == "type" && SOURCE_TYPES.contains(&&**ty) => {
+                    *val = Ast::Str(ty.clone())
+                }
+                _ => (),
+            }
+        }
+        let inst = Instance { name, master: master.to_owned(), nodes, params, loc };
+        self.statements.scope().instances.push(inst);
+        Ok(())
+    }
+}
+
+/// Removes comments: lines starting with `*` and everything after `//`
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('*') {
+        return "";
+    }
+
+    let mut in_str = false;
+    let mut prev = '\0';
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_str = !in_str,
+            '/' if prev == '/' && !in_str => return &line[..i - 1],
+            _ => (),
+        }
+        prev = c;
+    }
+    line
+}
+
+/// The value of a `<name>=<value>` pair in `tokens` if the value is a name or string
+fn keyword_arg<'a>(tokens: &'a [Token], name: &str) -> Option<&'a str> {
+    tokens.windows(3).find_map(|window| match window {
+        [Token::Word(key), Token::Eq, Token::Word(val) | Token::Str(val)] if key == name => {
+            Some(val.as_str())
+        }
+        _ => None,
+    })
+}
+
+fn node(token: &Token) -> Result<String> {
+    statements::node(token, &GROUND)
+}
+
+/// Parses `model <name> <master> <param>=<value>*`
+fn parse_model(tokens: &[Token], loc: Loc) -> Result<Model> {
+    if let Some(Token::Expr(_)) = tokens.get(3) {
+        bail!("model groups are not supported, use <name>.<N> to define model bins")
+    }
+    let mut model = statements::parse_model(tokens, SPECTRE, loc)?;
+    if model.device == "bsim4" {
+        for (param, val) in &mut model.params {
+            match val {
+                Ast::Ident(ty) if param == "type" => {
+                    let polarity = match &**ty {
+                        "n" => 1.0,
+                        "p" => -1.0,
+                        _ => bail!("invalid type {ty}, expected n or p"),
+                    };
+                    *val = Ast::Num(polarity);
+                }
+                _ => (),
+            }
+        }
+        model.device = "bsim4va".to_owned();
+    }
+    Ok(model)
+}
+
+/// Parses the parameters of the `dc`, `ac` and `tran` analyses. Returns `None` for
+/// analyses that are ignored.
+fn parse_analysis(kind: &str, params: &[Token], loc: &Loc) -> Result<Option<AnalysisAst>> {
+    let mut params = parse_assignments(params, SPECTRE)?;
+    let mut take = |name: &str| {
+        let pos = params.iter().position(|(param, _)| param == name)?;
+        Some(params.remove(pos).1)
+    };
+
+    let res = match kind {
+        "dc" => {
+            let sweep = ["param", "dev", "mod", "start", "stop", "values"];
+            if sweep.iter().any(|param| take(param).is_some()) {
+                warn!("{loc}: ignoring dc sweep, only operating point analyses are supported");
+                return Ok(None);
+            }
+            AnalysisAst::Op
+        }
+        "ac" => {
+            if let Some(freq) = take("freq") {
+                AnalysisAst::Ac {
+                    sweep: Sweep::Lin,
+                    points: Ast::Num(1.0),
+                    start: freq.clone(),
+                    stop: freq,
+                }
+            } else {
+                let start = take("start").context("ac: missing start frequency")?;
+                let stop = take("stop").context("ac: missing stop frequency")?;
+                let (sweep, points) = if let Some(points) = take("dec") {
+                    (Sweep::Dec, points)
+                } else if let Some(points) = take("oct") {
+                    (Sweep::Oct, points)
+                } else if let Some(points) = take("lin") {
+                    (Sweep::Lin, points)
+                } else {
+                    bail!("ac: expected dec, oct or lin")
+                };
+                AnalysisAst::Ac { sweep, points, start, stop }
+            }
+        }
+        _ => {
+            let stop = take("stop").context("tran: missing stop time")?;
+            // spectre uses (stop - start)/50 by default
+            let step = take("step").unwrap_or_else(|| {
+                Ast::Binary(BinaryOp::Div, Box::new(stop.clone()), Box::new(Ast::Num(50.0)))
+            });
+            let max_step = take("maxstep");
+            AnalysisAst::Tran { step, stop, max_step }
+        }
+    };
+
+    for (param, _) in params {
+        warn!("{loc}: ignoring unsupported parameter {param} of {kind} analysis");
+    }
+    Ok(Some(res))
+}
@@ -0,0 +1,476 @@
+//! Parser for the ngspice netlist dialect.
+//!
+//! The netlist is read in two passes. The first pass reads the netlist (and all
+//! included files) into logical lines: comments are removed, continuation lines
+//! are joined and `.include`/`.lib` statements are replaced with the contents of
+//! the included file (section). Inside `.control` blocks only `pre_osdi` is
+//! recognized, all other commands of the control language are ignored.
+//!
+//! The second pass converts the logical lines into a
+//! [`CircuitDescription`](crate::CircuitDescription).
+//! Parameters may be used before they are defined, so all parameters of a
+//! subcircuit (or the top level) are defined before any other statement is
+//! processed. `.param` statements inside a subcircuit define subcircuit
+//! parameters that can be overwritten by the instance.
+//!
+//! Melange has no builtin MOSFET models. MOSFET models with level 14 or 54
+//! (BSIM4) are mapped to the `bsim4va` Verilog-A module, which must be loaded
+//! with `pre_osdi`. Models of any other type refer to the device with the same
+//! name (for example an OSDI module).
+
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use log::warn;
+use stdx::iter::zip;
+
+use crate::netlist::expr::{Ast, BinaryOp, NumberSyntax};
+use crate::netlist::statements::{
+    self, is_assignment, is_value, parse_assignments, parse_options, path_arg, read_file, tokenize,
+    value, word, AnalysisAst, Instance, Line, Loc, Model, Sections, Statements, Token,
+};
+use crate::netlist::{Netlist, OptionValue, Sweep};
+use crate::Arena;
+
+const SPICE: NumberSyntax = NumberSyntax::Spice;
+
+/// Names of the ground node
+const GROUND: [&str; 2] = ["0", "gnd"];
+
+/// Parses the netlist at `path` and all files it includes.
+pub fn parse_file(path: &Utf8Path, earena: &mut Arena) -> Result<Netlist> {
+    parse_str(&read_file(path, 0)?, path, earena)
+}
+
+/// Parses the netlist `src`. Relative paths of included files are resolved relative to the
+/// directory of `path`.
+pub fn parse_str(src: &str, path: &Utf8Path, earena: &mut Arena) -> Result<Netlist> {
+    // the first line is always the title
+    let (title, body) = src.split_once('\n').unwrap_or((src, ""));
+    let mut reader = Reader::default();
+    reader.read(body, Rc::new(path.to_owned()), None, 0, 2)?;
+
+    let mut statements = Statements::default();
+    for line in reader.lines {
+        parse_line(&mut statements, line)?;
+    }
+
+    let scale = match statements.options.iter().rev().find(|(name, _)| name == "scale") {
+        Some((_, OptionValue::Num(scale))) => Some(*scale),
+        Some((_, val)) => bail!("option scale expects a number but found {val:?}"),
+        None => None,
+    };
+    if let Some(scale) = scale {
+        for inst in statements.instances_mut() {
+            if inst.name.starts_with('m') {
+                scale_geometry(&mut inst.params, scale);
+            }
+        }
+    }
+
+    statements.osdi_files = reader.osdi_files;
+    statements.lower(title.trim().to_owned(), path, earena)
+}
+
+#[derive(Default)]
+pub(super) struct Reader {
+    pub lines: Vec<Line>,
+    pub osdi_files: Vec<Utf8PathBuf>,
+}
+
+impl Reader {
+    fn read_file(&mut self, path: &Utf8Path, section: Option<&str>, depth: usize) -> Result<()> {
+        let src = read_file(path, depth)?;
+        self.read(&src, Rc::new(path.to_owned()), section, depth, 1)
+    }
+
+    /// Reads the logical lines of `src` (which starts at line `first_line` of `file`). If
+    /// `section` is specified only the lines inside the library section `section` are read.
+    pub(super) fn read(
+        &mut self,
+        src: &str,
+        file: Rc<Utf8PathBuf>,
+        section: Option<&str>,
+        depth: usize,
+        first_line: usize,
+    ) -> Result<()> {
+        let lines = logical_lines(src, &file, first_line)?;
+        let dir = file.parent().unwrap_or_else(|| Utf8Path::new(""));
+
+        let mut sections = Sections::new(section);
+        let mut in_control = false;
+        for line in lines {
+            let lowercase = line.text.to_ascii_lowercase();
+            let mut words = lowercase.split_whitespace();
+            let statement = words.next().unwrap_or_default();
+
+            if in_control {
+                match statement {
+                    ".endc" => in_control = false,
+                    "pre_osdi" => {
+                        let tokens = tokenize(&line.text, false, &line.loc)?;
+                        let path = path_arg(&tokens, 1, dir)
+                            .with_context(|| format!("{}: invalid pre_osdi command", line.loc))?;
+                        self.osdi_files.push(path);
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+
+            if sections.is_skipping() {
+                if statement == ".endl" {
+                    sections.end(words.next(), &line.loc);
+                }
+                continue;
+            }
+
+            match statement {
+                ".lib" => {
+                    let tokens = tokenize(&line.text, false, &line.loc)?;
+                    match tokens.len() {
+                        // the start of a library section
+                        2 => {
+                            let name = word(&tokens[1]).with_context(|| line.loc.to_string())?;
+                            sections.begin(&name.to_ascii_lowercase());
+                        }
+                        // an included library section
+                        3 if sections.is_active() => {
+                            let path = path_arg(&tokens, 1, dir)
+                                .with_context(|| format!("{}: invalid .lib statement", line.loc))?;
+                            let name = word(&tokens[2]).with_context(|| line.loc.to_string())?;
+                            let name = name.to_ascii_lowercase();
+                            self.read_file(&path, Some(&name), depth + 1).with_context(|| {
+                                format!("{}: while reading section {name} of {path}", line.loc)
+                            })?;
+                        }
+                        3 => (),
+                        _ => {
+                            bail!("{}: expected .lib <file> <section> or .lib <section>", line.loc)
+                        }
+                    }
+                }
+                ".endl" => {
+                    if sections.end(None, &line.loc) {
+                        return Ok(());
+                    }
+                }
+                _ if !sections.is_active() => (),
+                ".include" | ".inc" => {
+                    let tokens = tokenize(&line.text, false, &line.loc)?;
+                    let path = path_arg(&tokens, 1, dir)
+                        .with_context(|| format!("{}: invalid .include statement", line.loc))?;
+                    self.read_file(&path, None, depth + 1)
+                        .with_context(|| format!("{}: while reading {path}", line.loc))?;
+                }
+                ".control" => in_control = true,
+                ".end" => break,
+                _ => self.lines.push(line),
+            }
+        }
+
+        sections.finish(&file)
+    }
+}
+
+/// Splits `src` into logical lines
+fn logical_lines(src: &str, file: &Rc<Utf8PathBuf>, first_line: usize) -> Result<Vec<Line>> {
+    let mut res: Vec<Line> = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let line_number = first_line + i;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(continuation) = line.strip_prefix('+') {
+            match res.last_mut() {
+                Some(last) => {
+                    last.text.push(' ');
+                    last.text.push_str(continuation);
+                }
+                None => bail!("{file}:{line_number}: continuation line without a preceding line"),
+            }
+            continue;
+        }
+        res.push(Line { text: line.to_owned(), loc: Loc { file: file.clone(), line: line_number } })
+    }
+    Ok(res)
+}
+
+/// Removes comment lines (starting with `*`) and inline comments (starting with `;` or ` $`)
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('*') {
+        return "";
+    }
+
+    let mut quote = None;
+    let mut prev_whitespace = true;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(end), _) if c == end => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{') => quote = Some('}'),
+            (None, ';') => return &line[..i],
+            (None, '$') if prev_whitespace => return &line[..i],
+            _ => (),
+        }
+        prev_whitespace = c.is_whitespace();
+    }
+    line
+}
+
+/// Parses a logical line and adds it to `statements`
+pub(super) fn parse_line(statements: &mut Statements, line: Line) -> Result<()> {
+    let tokens = tokenize(&line.text, true, &line.loc)?;
+    let loc = line.loc;
+    let statement = match tokens.first() {
+        Some(Token::Word(statement)) => statement.clone(),
+        Some(token) => bail!("{loc}: expected a statement but found {token}"),
+        None => return Ok(()),
+    };
+
+    match &*statement {
+        ".param" => {
+            let params = parse_assignments(&tokens[1..], SPICE).with_context(|| loc.to_string())?;
+            statements.scope().def_params(params, &loc);
+        }
+        ".subckt" => {
+            let subckt =
+                statements::parse_subckt(&tokens, SPICE, &loc).with_context(|| loc.to_string())?;
+            statements.begin_subckt(subckt);
+        }
+        ".ends" => statements.end_subckt(&loc)?,
+        ".model" => {
+            let model = parse_model(&tokens, loc.clone()).with_context(|| loc.to_string())?;
+            statements.scope().models.push(model);
+        }
+        ".option" | ".options" | ".opt" => {
+            let options = parse_options(&tokens[1..], SPICE).with_context(|| loc.to_string())?;
+            statements.options.extend(options)
+        }
+        ".op" | ".tran" | ".ac" => {
+            let analysis = parse_analysis(&tokens, &loc).with_context(|| loc.to_string())?;
+            statements.analyses.push((analysis, loc));
+        }
+        ".func" | ".if" | ".global" => bail!("{loc}: {statement} is not supported"),
+        _ if statement.starts_with('.') => {
+            warn!("{loc}: ignoring unsupported statement {statement}")
+        }
+        _ => {
+            let inst = parse_instance(&tokens, loc.clone()).with_context(|| loc.to_string())?;
+            statements.scope().instances.push(inst);
+        }
+    }
+    Ok(())
+}
+
+/// Parses `.model <name> <type> [(] <param>=<value>* [)]`
+fn parse_model(tokens: &[Token], loc: Loc) -> Result<Model> {
+    let mut model = statements::parse_model(tokens, SPICE, loc)?;
+    if let ty @ ("nmos" | "pmos") = &*model.device {
+        let params = &mut model.params;
+        let level = params.iter().rev().find(|(param, _)| param == "level");
+        let level = level.and_then(|(_, level)| level.as_num()).unwrap_or(1.0);
+        // other levels are kept, elaboration fails if the model is used
+        if level == 14.0 || level == 54.0 {
+            if !params.iter().any(|(param, _)| param == "type") {
+                let polarity = if ty == "nmos" { 1.0 } else { -1.0 };
+                params.push(("type".to_owned(), Ast::Num(polarity)));
+            }
+            model.device = "bsim4va".to_owned();
+        }
+    }
+    Ok(model)
+}
+
+fn node(token: &Token) -> Result<String> {
+    statements::node(token, &GROUND)
+}
+
+/// Parses an element line (an instance)
+fn parse_instance(tokens: &[Token], loc: Loc) -> Result<Instance> {
+    let name = word(&tokens[0])?.to_owned();
+    let kind = name.as_bytes()[0];
+
+    let (master, nodes, params) = match kind {
+        b'v' | b'i' => {
+            let master = if kind == b'v' { "vsource" } else { "isource" };
+            let (nodes, params) = match tokens.get(1..3) {
+                Some(nodes) => (nodes, source_params(&tokens[3..])?),
+                None => bail!("{name}: expected two nodes"),
+            };
+            let nodes = nodes.iter().map(node).collect::<Result<Vec<_>>>()?;
+            (master.to_owned(), nodes, params)
+        }
+
+        b'r' | b'c' | b'l' => {
+            let (device, param) = match kind {
+                b'r' => ("resistor", "r"),
+                b'c' => ("capacitor", "c"),
+                _ => ("inductor", "l"),
+            };
+            let end = (1..tokens.len()).find(|&i| is_assignment(tokens, i)).unwrap_or(tokens.len());
+            let mut params = parse_assignments(&tokens[end..], SPICE)?;
+            if end < 3 {
+                bail!("{name}: expected two nodes")
+            }
+            let (nodes, rem) = tokens[1..end].split_at(2);
+            let (val, model) = match rem {
+                [] => (None, None),
+                [val] if is_value(val) => (Some(val), None),
+                [model] => (None, Some(model)),
+                [val, model] => (Some(val), Some(model)),
+                [_, _, token, ..] => bail!("unexpected {token}"),
+            };
+            if let Some(val) = val {
+                params.insert(0, (param.to_owned(), value(val, SPICE)?));
+            }
+            let master = match model {
+                Some(model) => word(model)?,
+                None => device,
+            };
+            let nodes = nodes.iter().map(node).collect::<Result<Vec<_>>>()?;
+            (master.to_owned(), nodes, params)
+        }
+
+        // all remaining elements (including subcircuits) list the nodes followed by the master
+        _ => {
+            let end = (1..tokens.len()).find(|&i| is_assignment(tokens, i)).unwrap_or(tokens.len());
+            let params = parse_assignments(&tokens[end..], SPICE)?;
+            let (master, nodes) = match tokens[1..end].split_last() {
+                Some((master, nodes)) => (word(master)?.to_owned(), nodes),
+                None => bail!("{name}: expected <nodes> <model>"),
+            };
+            let nodes = nodes.iter().map(node).collect::<Result<Vec<_>>>()?;
+            (master, nodes, params)
+        }
+    };
+
+    Ok(Instance { name, master, nodes, params, loc })
+}
+
+/// Applies `.option scale` to the geometry parameters of a MOSFET
+fn scale_geometry(params: &mut [(String, Ast)], scale: f64) {
+    for (name, val) in params {
+        let factor = match &**name {
+            "l" | "w" | "pd" | "ps" => scale,
+            "ad" | "as" => scale * scale,
+            _ => continue,
+        };
+        let scaled = Ast::Binary(BinaryOp::Mul, Box::new(val.clone()), Box::new(Ast::Num(factor)));
+        *val = scaled;
+    }
+}
+
+/// Parses the specification of an independent source (`[dc] <val>`, `ac <mag> [<phase>]`,
+/// `sin(...)` and `pulse(...)`) into `vsource` parameters.
+fn source_params(tokens: &[Token]) -> Result<Vec<(String, Ast)>> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        let keyword = match token {
+            Token::Word(keyword) if !is_value(token) => keyword,
+            // a value without keyword is the dc value
+            _ if i == 1 => {
+                res.push(("dc".to_owned(), value(token, SPICE)?));
+                continue;
+            }
+            _ => bail!("unexpected {token}"),
+        };
+
+        match &**keyword {
+            "dc" => {
+                let val = tokens.get(i).context("missing dc value")?;
+                res.push(("dc".to_owned(), value(val, SPICE)?));
+                i += 1;
+            }
+            "ac" => {
+                let mag = tokens.get(i).context("missing ac magnitude")?;
+                res.push(("mag".to_owned(), value(mag, SPICE)?));
+                i += 1;
+                if let Some(phase) = tokens.get(i).filter(|token| is_value(token)) {
+                    // melange expects radians
+                    let phase = Ast::Binary(
+                        BinaryOp::Mul,
+                        Box::new(value(phase, SPICE)?),
+                        Box::new(Ast::Num(PI / 180.0)),
+                    );
+                    res.push(("phase".to_owned(), phase));
+                    i += 1;
+                }
+            }
+            "sin" | "pulse" => {
+                if tokens.get(i) != Some(&Token::LParen) {
+                    bail!("expected {keyword}(...)")
+                }
+                let len = tokens[i..].iter().position(|token| *token == Token::RParen);
+                let len = len.with_context(|| format!("unterminated {keyword}(..."))?;
+                let args: Vec<_> = tokens[i + 1..i + len]
+                    .iter()
+                    .filter(|token| **token != Token::Comma)
+                    .map(|arg| value(arg, SPICE))
+                    .collect::<Result<_>>()?;
+                i += len + 1;
+
+                let (ty, names, required): (_, &[_], _) = if keyword == "sin" {
+                    ("sine", &["sinedc", "ampl", "freq", "delay", "damp", "sinephase"], 3)
+                } else {
+                    ("pulse", &["val0", "val1", "delay", "rise", "fall", "width", "period"], 2)
+                };
+                if args.len() < required || args.len() > names.len() {
+                    bail!(
+                        "{keyword} expects between {required} and {} arguments but {} were provided",
+                        names.len(),
+                        args.len()
+                    )
+                }
+                res.push(("type".to_owned(), Ast::Str(ty.to_owned())));
+                for (name, arg) in zip(names, args) {
+                    res.push(((*name).to_owned(), arg))
+                }
+            }
+            _ => bail!("{keyword} sources are not supported"),
+        }
+    }
+    Ok(res)
+}
+
+/// Parses `.op`, `.tran <tstep> <tstop> [<tstart> [<tmax>]]` and
+/// `.ac <dec|oct|lin> <points> <fstart> <fstop>`
+fn parse_analysis(tokens: &[Token], loc: &Loc) -> Result<AnalysisAst> {
+    let arg = |i: usize| match tokens.get(i) {
+        Some(token) => value(token, SPICE),
+        None => bail!("{}: missing argument {i}", tokens[0]),
+    };
+
+    let res = match word(&tokens[0])? {
+        ".tran" => {
+            let step = arg(1)?;
+            let stop = arg(2)?;
+            if let Some(start) = tokens.get(3).filter(|token| is_value(token)) {
+                if value(start, SPICE)?.as_num() != Some(0.0) {
+                    warn!("{loc}: .tran ignores tstart, the solution is stored from t = 0");
+                }
+            }
+            let max_step =
+                if tokens.len() > 4 && is_value(&tokens[4]) { Some(arg(4)?) } else { None };
+            AnalysisAst::Tran { step, stop, max_step }
+        }
+        ".ac" => {
+            let sweep = match word(tokens.get(1).context(".ac: missing sweep type")?)? {
+                "dec" => Sweep::Dec,
+                "oct" => Sweep::Oct,
+                "lin" => Sweep::Lin,
+                sweep => bail!("unknown sweep type {sweep}"),
+            };
+            AnalysisAst::Ac { sweep, points: arg(2)?, start: arg(3)?, stop: arg(4)? }
+        }
+        _ => AnalysisAst::Op,
+    };
+    Ok(res)
+}
@@ -0,0 +1,669 @@
+//! The parts of the netlist parsers that do not depend on the netlist format.
+//!
+//! Both parsers read included files with [`read_file`] and select the statements
+//! of included library sections with [`Sections`]. They split lines into
+//! [`Token`]s and sort the statements they parse into [`Statements`]:
+//! parameters, models and instances of the top level and of each subcircuit
+//! together with simulator options and analyses. All values are
+//! kept as [`Ast`]s until the whole netlist is parsed, because parameters may be
+//! used before they are defined. [`Statements::lower`] then defines the
+//! parameters of each scope (in the order of their dependencies) and lowers all
+//! values to [`Expr`]s.
+
+use std::fmt::{self, Display};
+use std::rc::Rc;
+use std::{env, fs};
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
+use log::warn;
+use stdx::iter::zip;
+
+use crate::elaboration::{
+    CircuitInstanceDescription, CircuitModelDescription, ParamDescription, SubcircuitDescription,
+};
+use crate::expr::CircuitParamCtx;
+use crate::netlist::expr::{Ast, NumberSyntax};
+use crate::netlist::{parse_number, Analysis, Netlist, OptionValue, Sweep};
+use crate::{Arena, CircuitDescription, CircuitParam, Expr};
+
+/// Included files and library sections may be nested at most this deep
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+#[derive(Clone, Debug)]
+pub(super) struct Loc {
+    pub file: Rc<Utf8PathBuf>,
+    pub line: usize,
+}
+
+impl Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// A logical line (with all continuation lines joined and comments removed)
+pub(super) struct Line {
+    pub text: String,
+    pub loc: Loc,
+}
+
+/// Reads a netlist file that is included `depth` levels deep
+pub(super) fn read_file(path: &Utf8Path, depth: usize) -> Result<String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        bail!("included files are nested too deeply")
+    }
+    fs::read_to_string(path).with_context(|| format!("failed to read {path}"))
+}
+
+/// Tracks the library sections of a file while it is read. If a file is included
+/// with a section (`selected`), only the statements inside that section are read.
+/// Otherwise only the statements outside of all sections are read.
+pub(super) struct Sections<'a> {
+    selected: Option<&'a str>,
+    found: bool,
+    /// The name of the library section that is currently skipped
+    skipped: Option<String>,
+}
+
+impl<'a> Sections<'a> {
+    pub fn new(selected: Option<&'a str>) -> Sections<'a> {
+        Sections { selected, found: false, skipped: None }
+    }
+
+    /// Whether the statements are inside a skipped section. Only the end of the
+    /// section must be passed to [`end`](Sections::end) in that case.
+    pub fn is_skipping(&self) -> bool {
+        self.skipped.is_some()
+    }
+
+    /// Whether the statements outside of skipped sections are read
+    pub fn is_active(&self) -> bool {
+        self.selected.is_none() || self.found
+    }
+
+    /// Handles the start of the section `name`
+    pub fn begin(&mut self, name: &str) {
+        if self.selected == Some(name) {
+            self.found = true;
+        } else {
+            self.skipped = Some(name.to_owned());
+        }
+    }
+
+    /// Handles the end of a section (`name` is the optional name repeated at its end).
+    /// Returns `true` if the selected section ended and the rest of the file must be ignored.
+    pub fn end(&mut self, name: Option<&str>, loc: &Loc) -> bool {
+        match self.skipped.take() {
+            Some(skipped) => {
+                if let Some(name) = name.filter(|&name| name != skipped) {
+                    warn!("{loc}: the end of section {name} does not match section {skipped}")
+                }
+                false
+            }
+            None => self.found,
+        }
+    }
+
+    /// Checks that the selected section was found once the whole file was read
+    pub fn finish(&self, file: &Utf8Path) -> Result<()> {
+        match self.selected {
+            Some(section) if !self.found => bail!("section {section} not found in {file}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Expands environment variables (`$NAME` or `${NAME}`) in a path
+fn expand_env(path: &str) -> Result<String> {
+    let mut res = String::with_capacity(path.len());
+    let mut rem = path;
+    while let Some(start) = rem.find('$') {
+        res.push_str(&rem[..start]);
+        rem = &rem[start + 1..];
+        let (name, len) = if let Some(braced) = rem.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => bail!("unterminated environment variable in {path}"),
+            }
+        } else {
+            let end = rem.find(|c: char| !c.is_ascii_alphanumeric() && c != '_');
+            let end = end.unwrap_or(rem.len());
+            (&rem[..end], end)
+        };
+        match env::var(name) {
+            Ok(val) => res.push_str(&val),
+            Err(_) => bail!("environment variable {name} (used in {path}) is not set"),
+        }
+        rem = &rem[len..];
+    }
+    res.push_str(rem);
+    Ok(res)
+}
+
+/// The path in argument `i` of a statement, relative paths are resolved relative to `dir`.
+pub(super) fn path_arg(tokens: &[Token], i: usize, dir: &Utf8Path) -> Result<Utf8PathBuf> {
+    let path = match tokens.get(i) {
+        Some(Token::Word(path) | Token::Str(path) | Token::Expr(path)) => path,
+        Some(token) => bail!("expected a path but found {token}"),
+        None => bail!("expected a path"),
+    };
+    let path = Utf8PathBuf::from(expand_env(path)?);
+    if path.is_relative() {
+        Ok(dir.join(path))
+    } else {
+        Ok(path)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(super) enum Token {
+    /// A name or a number
+    Word(String),
+    /// The content of a `{...}` or `'...'` expression
+    Expr(String),
+    /// The content of a `"..."` string
+    Str(String),
+    Eq,
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Expr(expr) => write!(f, "({expr})"),
+            Token::Str(str) => write!(f, "\"{str}\""),
+            Token::Eq => write!(f, "="),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+/// Splits a logical line into tokens. Everything except strings is converted to lowercase
+/// if `lowercase` is set (for case insensitive formats).
+pub(super) fn tokenize(text: &str, lowercase: bool, loc: &Loc) -> Result<Vec<Token>> {
+    let case = |text: &str| if lowercase { text.to_ascii_lowercase() } else { text.to_owned() };
+    let bytes = text.as_bytes();
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let token = match c {
+            _ if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'=' => Token::Eq,
+            b'(' => Token::LParen,
+            b')' => Token::RParen,
+            b',' => Token::Comma,
+            b'{' | b'\'' | b'"' => {
+                let end = match c {
+                    b'{' => b'}',
+                    _ => c,
+                };
+                let len = match bytes[i + 1..].iter().position(|&c| c == end) {
+                    Some(len) => len,
+                    None => bail!("{loc}: unterminated {}", c as char),
+                };
+                let content = &text[i + 1..i + 1 + len];
+                i += len + 2;
+                let token = match c {
+                    b'"' => Token::Str(content.to_owned()),
+                    _ => Token::Expr(case(content)),
+                };
+                res.push(token);
+                continue;
+            }
+            _ => {
+                let start = i;
+                while i < bytes.len() {
+                    let c = bytes[i];
+                    let is_operator_eq = c == b'='
+                        && (bytes.get(i + 1) == Some(&b'=')
+                            || matches!(bytes[i - 1], b'=' | b'<' | b'>' | b'!'));
+                    if c.is_ascii_whitespace()
+                        || matches!(c, b'(' | b')' | b',' | b'{' | b'\'' | b'"')
+                        || (c == b'=' && !is_operator_eq)
+                    {
+                        break;
+                    }
+                    i += 1;
+                }
+                res.push(Token::Word(case(&text[start..i])));
+                continue;
+            }
+        };
+        res.push(token);
+        i += 1;
+    }
+    Ok(res)
+}
+
+pub(super) fn word(token: &Token) -> Result<&str> {
+    match token {
+        Token::Word(word) => Ok(word),
+        Token::Str(str) => Ok(str),
+        _ => bail!("expected a name but found {token}"),
+    }
+}
+
+/// Parses a single value (a number, a name or an expression)
+pub(super) fn value(token: &Token, syntax: NumberSyntax) -> Result<Ast> {
+    match token {
+        Token::Word(src) | Token::Expr(src) => Ast::parse(src, syntax),
+        Token::Str(str) => Ok(Ast::Str(str.clone())),
+        _ => bail!("expected a value but found {token}"),
+    }
+}
+
+/// Whether `token` is a value and not a name
+pub(super) fn is_value(token: &Token) -> bool {
+    match token {
+        Token::Word(word) => {
+            word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '.' | '-' | '+'))
+        }
+        Token::Expr(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether a `<name>=<value>` pair starts at `tokens[i]`
+pub(super) fn is_assignment(tokens: &[Token], i: usize) -> bool {
+    matches!(tokens[i], Token::Word(_)) && tokens.get(i + 1) == Some(&Token::Eq)
+}
+
+/// Parses a list of `<name>=<value>` pairs. The value extends until the next pair so it
+/// may contain whitespace (like `x = 1 + 2`).
+pub(super) fn parse_assignments(
+    tokens: &[Token],
+    syntax: NumberSyntax,
+) -> Result<Vec<(String, Ast)>> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if !is_assignment(tokens, i) {
+            bail!("expected <name>=<value> but found {}", tokens[i]);
+        }
+        let name = word(&tokens[i])?.to_owned();
+
+        let start = i + 2;
+        let mut end = start;
+        let mut depth = 0;
+        while end < tokens.len() && (depth != 0 || end == start || !is_assignment(tokens, end)) {
+            match tokens[end] {
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                _ => (),
+            }
+            end += 1;
+        }
+
+        let val = match &tokens[start..end] {
+            [] => bail!("missing value for {name}"),
+            [token] => value(token, syntax)?,
+            tokens => {
+                let src: Vec<_> = tokens.iter().map(|token| token.to_string()).collect();
+                Ast::parse(&src.join(" "), syntax)?
+            }
+        };
+        res.push((name, val));
+        i = end;
+    }
+    Ok(res)
+}
+
+/// Parses a list of options, each option is either a `<name>=<value>` pair or a flag.
+pub(super) fn parse_options(
+    tokens: &[Token],
+    syntax: NumberSyntax,
+) -> Result<Vec<(String, OptionValue)>> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let name = word(&tokens[i])?.to_owned();
+        if tokens.get(i + 1) != Some(&Token::Eq) {
+            res.push((name, OptionValue::Flag));
+            i += 1;
+            continue;
+        }
+        let val = match tokens.get(i + 2) {
+            Some(Token::Word(val)) => match parse_number(val, syntax) {
+                Some(val) => OptionValue::Num(val),
+                None => OptionValue::Str(val.clone()),
+            },
+            Some(Token::Str(val)) => OptionValue::Str(val.clone()),
+            Some(token @ Token::Expr(_)) => match value(token, syntax)?.as_num() {
+                Some(val) => OptionValue::Num(val),
+                None => bail!("the value of option {name} must be a constant"),
+            },
+            _ => bail!("missing value for option {name}"),
+        };
+        res.push((name, val));
+        i += 3;
+    }
+    Ok(res)
+}
+
+/// The name of the node `token`. All names in `ground` refer to the ground node.
+pub(super) fn node(token: &Token, ground: &[&str]) -> Result<String> {
+    let name = word(token)?;
+    let name = if ground.contains(&name) { "ground" } else { name };
+    Ok(name.to_owned())
+}
+
+/// Parses a subcircuit definition `<keyword> <name> [(]<ports>*[)] [params:] <param>=<default>*`
+pub(super) fn parse_subckt(tokens: &[Token], syntax: NumberSyntax, loc: &Loc) -> Result<Scope> {
+    let name = match tokens.get(1) {
+        Some(name) => word(name)?.to_owned(),
+        None => bail!("expected {} <name> <ports>", tokens[0]),
+    };
+    let end = (2..tokens.len()).find(|&i| is_assignment(tokens, i)).unwrap_or(tokens.len());
+    let mut ports = Vec::new();
+    for token in &tokens[2..end] {
+        match token {
+            Token::LParen | Token::RParen => (),
+            _ => match word(token)? {
+                "params:" => (),
+                port => ports.push(port.to_owned()),
+            },
+        }
+    }
+
+    let mut res = Scope::subckt(name, ports, loc.clone());
+    res.def_params(parse_assignments(&tokens[end..], syntax)?, loc);
+    Ok(res)
+}
+
+/// Parses a model definition `<keyword> <name> <type> [(] <param>=<value>* [)]`. The
+/// device of the model is its type, netlist formats map builtin types to devices afterwards.
+pub(super) fn parse_model(tokens: &[Token], syntax: NumberSyntax, loc: Loc) -> Result<Model> {
+    let (name, ty, mut params) = match tokens {
+        [_, name, ty, params @ ..] => (word(name)?, word(ty)?, params),
+        _ => bail!("expected {} <name> <type> <parameters>", tokens[0]),
+    };
+    if let [Token::LParen, inner @ .., Token::RParen] = params {
+        params = inner;
+    }
+    let params = parse_assignments(params, syntax)?;
+    Ok(Model { name: name.to_owned(), device: ty.to_owned(), params, loc })
+}
+
+pub(super) struct Model {
+    pub name: String,
+    pub device: String,
+    pub params: Vec<(String, Ast)>,
+    pub loc: Loc,
+}
+
+pub(super) struct Instance {
+    pub name: String,
+    pub master: String,
+    pub nodes: Vec<String>,
+    pub params: Vec<(String, Ast)>,
+    pub loc: Loc,
+}
+
+/// An [`Analysis`] whose values have not been lowered yet
+pub(super) enum AnalysisAst {
+    Op,
+    Ac { sweep: Sweep, points: Ast, start: Ast, stop: Ast },
+    Tran { step: Ast, stop: Ast, max_step: Option<Ast> },
+}
+
+type Params = IndexMap<String, (Ast, Loc), ahash::RandomState>;
+
+/// The contents of the top level or a subcircuit
+#[derive(Default)]
+pub(super) struct Scope {
+    pub name: String,
+    pub ports: Vec<String>,
+    pub loc: Option<Loc>,
+    /// Later definitions of a parameter replace earlier ones
+    pub params: Params,
+    pub models: Vec<Model>,
+    pub instances: Vec<Instance>,
+}
+
+impl Scope {
+    pub fn subckt(name: String, ports: Vec<String>, loc: Loc) -> Scope {
+        Scope { name, ports, loc: Some(loc), ..Scope::default() }
+    }
+
+    pub fn def_params(&mut self, params: Vec<(String, Ast)>, loc: &Loc) {
+        for (name, val) in params {
+            self.params.insert(name, (val, loc.clone()));
+        }
+    }
+}
+
+/// The statements of a netlist sorted by their kind
+#[derive(Default)]
+pub(super) struct Statements {
+    pub top: Scope,
+    pub subckts: Vec<Scope>,
+    /// Subcircuit definitions that have not been closed yet. Subcircuit definitions may be
+    /// nested, nested definitions are treated like definitions at the top level.
+    open_subckts: Vec<Scope>,
+    pub options: Vec<(String, OptionValue)>,
+    pub analyses: Vec<(AnalysisAst, Loc)>,
+    /// Verilog-A files that are compiled during elaboration
+    pub va_files: Vec<Utf8PathBuf>,
+    /// OSDI libraries that are loaded during elaboration
+    pub osdi_files: Vec<Utf8PathBuf>,
+}
+
+impl Statements {
+    /// The scope that statements are currently added to
+    pub fn scope(&mut self) -> &mut Scope {
+        self.open_subckts.last_mut().unwrap_or(&mut self.top)
+    }
+
+    pub fn begin_subckt(&mut self, subckt: Scope) {
+        self.open_subckts.push(subckt)
+    }
+
+    pub fn end_subckt(&mut self, loc: &Loc) -> Result<()> {
+        match self.open_subckts.pop() {
+            Some(subckt) => self.subckts.push(subckt),
+            None => bail!("{loc}: end of a subcircuit definition without a subcircuit"),
+        }
+        Ok(())
+    }
+
+    /// All instances (of the top level and all subcircuits)
+    pub fn instances_mut(&mut self) -> impl Iterator<Item = &mut Instance> {
+        let scopes = std::iter::once(&mut self.top).chain(&mut self.subckts);
+        scopes.flat_map(|scope| &mut scope.instances)
+    }
+
+    /// Lowers the statements of the netlist at `path` into a [`Netlist`]
+    pub fn lower(self, title: String, path: &Utf8Path, earena: &mut Arena) -> Result<Netlist> {
+        if let Some(subckt) = self.open_subckts.last() {
+            bail!("{}: subcircuit {} is never closed", subckt.loc.as_ref().unwrap(), subckt.name)
+        }
+
+        let name = path.file_stem().unwrap_or("circuit").to_owned();
+        let mut circuit = CircuitDescription::new(name, earena);
+        circuit.va_files = self.va_files;
+        circuit.osdi_files = self.osdi_files;
+        let scopes = [circuit.ctx];
+        circuit.parameters = define_params(&self.top.params, &scopes, earena)?;
+        for model in self.top.models {
+            circuit.models.push(lower_model(model, &scopes, earena)?);
+        }
+        for inst in self.top.instances {
+            circuit.instances.push(lower_instance(inst, &scopes, earena)?);
+        }
+        for subckt in self.subckts {
+            let subckt = lower_subckt(subckt, circuit.ctx, earena)?;
+            circuit.subcircuits.push(subckt);
+        }
+
+        let mut analyses = Vec::with_capacity(self.analyses.len());
+        for (analysis, loc) in self.analyses {
+            let analysis =
+                lower_analysis(analysis, &scopes, earena).with_context(|| loc.to_string())?;
+            analyses.push(analysis);
+        }
+
+        Ok(Netlist { title, circuit, options: self.options, analyses })
+    }
+}
+
+/// Defines all `params` in the context `scopes[0]` and lowers their values. Parameters are
+/// defined in the order of their dependencies.
+fn define_params(
+    params: &Params,
+    scopes: &[CircuitParamCtx],
+    earena: &mut Arena,
+) -> Result<Vec<(CircuitParam, Expr)>> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum State {
+        New,
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        param: usize,
+        params: &Params,
+        state: &mut [State],
+        order: &mut Vec<usize>,
+    ) -> Result<()> {
+        match state[param] {
+            State::Done => return Ok(()),
+            State::Visiting => {
+                let (name, (_, loc)) = params.get_index(param).unwrap();
+                bail!("{loc}: parameter {name} depends on itself")
+            }
+            State::New => (),
+        }
+        state[param] = State::Visiting;
+        let mut deps = Vec::new();
+        params[param].0.visit_idents(&mut |name| deps.extend(params.get_index_of(name)));
+        for dep in deps {
+            visit(dep, params, state, order)?;
+        }
+        state[param] = State::Done;
+        order.push(param);
+        Ok(())
+    }
+
+    let mut state = vec![State::New; params.len()];
+    let mut order = Vec::with_capacity(params.len());
+    for param in 0..params.len() {
+        visit(param, params, &mut state, &mut order)?;
+    }
+
+    let mut defined = Vec::with_capacity(order.len());
+    for &param in &order {
+        let (name, (_, loc)) = params.get_index(param).unwrap();
+        let (param, _) =
+            earena.def_param(scopes[0], name.clone()).with_context(|| loc.to_string())?;
+        defined.push(param);
+    }
+
+    let mut res = Vec::with_capacity(order.len());
+    for (param, &i) in zip(defined, &order) {
+        let (name, (val, loc)) = params.get_index(i).unwrap();
+        let val = val
+            .lower(earena, scopes)
+            .with_context(|| format!("{loc}: invalid value for parameter {name}"))?;
+        res.push((param, val));
+    }
+    Ok(res)
+}
+
+fn lower_params(
+    params: Vec<(String, Ast)>,
+    scopes: &[CircuitParamCtx],
+    earena: &mut Arena,
+    loc: &Loc,
+) -> Result<ParamDescription> {
+    params
+        .into_iter()
+        .map(|(name, val)| {
+            let val = val
+                .lower(earena, scopes)
+                .with_context(|| format!("{loc}: invalid value for {name}"))?;
+            Ok((name, val))
+        })
+        .collect()
+}
+
+fn lower_model(
+    model: Model,
+    scopes: &[CircuitParamCtx],
+    earena: &mut Arena,
+) -> Result<CircuitModelDescription> {
+    let parameters = lower_params(model.params, scopes, earena, &model.loc)?;
+    Ok(CircuitModelDescription { name: model.name, device: model.device, parameters })
+}
+
+fn lower_instance(
+    inst: Instance,
+    scopes: &[CircuitParamCtx],
+    earena: &mut Arena,
+) -> Result<CircuitInstanceDescription> {
+    let parameters = lower_params(inst.params, scopes, earena, &inst.loc)?;
+    Ok(CircuitInstanceDescription {
+        name: inst.name,
+        master: inst.master,
+        parameters,
+        terminal_connections: inst.nodes,
+    })
+}
+
+fn lower_analysis(
+    analysis: AnalysisAst,
+    scopes: &[CircuitParamCtx],
+    earena: &mut Arena,
+) -> Result<Analysis> {
+    let mut lower = |val: Ast| val.lower(earena, scopes);
+    let res = match analysis {
+        AnalysisAst::Op => Analysis::Op,
+        AnalysisAst::Ac { sweep, points, start, stop } => {
+            Analysis::Ac { sweep, points: lower(points)?, start: lower(start)?, stop: lower(stop)? }
+        }
+        AnalysisAst::Tran { step, stop, max_step } => Analysis::Tran {
+            step: lower(step)?,
+            stop: lower(stop)?,
+            max_step: max_step.map(lower).transpose()?,
+        },
+    };
+    Ok(res)
+}
+
+/// Lowers a subcircuit definition. The parameters of the subcircuit are defined in a new
+/// context, parameters that are not found in this context are looked up in `global_ctx`.
+fn lower_subckt(
+    subckt: Scope,
+    global_ctx: CircuitParamCtx,
+    earena: &mut Arena,
+) -> Result<SubcircuitDescription> {
+    let ctx = earena.add_ctx();
+    let scopes = [ctx, global_ctx];
+    let parameters = define_params(&subckt.params, &scopes, earena)?;
+    let mut res = SubcircuitDescription {
+        name: subckt.name,
+        ports: subckt.ports,
+        ctx,
+        parameters,
+        models: Vec::with_capacity(subckt.models.len()),
+        instances: Vec::with_capacity(subckt.instances.len()),
+    };
+    for model in subckt.models {
+        res.models.push(lower_model(model, &scopes, earena)?);
+    }
+    for inst in subckt.instances {
+        res.instances.push(lower_instance(inst, &scopes, earena)?);
+    }
+    Ok(res)
+}
@@ -11,14 +11,17 @@ use typed_index_collections::{TiSlice, TiVec};
 use crate::circuit::{CircuitModelSrc, InstanceId, ModelId, Node};
 use crate::devices::{InstanceImpl, ModelImpl, Type};
 use crate::expr::{CircuitParam, ExprEvalCtxRef};
-use crate::simulation::flags::{EvalFlags, OperatingPointAnalysis, SimulationState};
+pub use crate::simulation::flags::{EvalFlags, EvalRetFlags};
+use crate::simulation::flags::{OperatingPointAnalysis, SimulationState};
 pub use crate::simulation::matrix::MatrixEntryIter;
 use crate::simulation::matrix::{MatrixBuilder, SimulationMatrix};
+pub use crate::simulation::tran::{IntegrationMethod, TranOpts, TranSolution};
 use crate::utils::PrettyPrint;
 use crate::{Arena, Circuit, Value};
 
 mod flags;
 mod matrix;
+mod tran;
 
 pub struct Simulation<'a> {
     circ: &'a Circuit,
@@ -369,30 +372,70 @@ impl Simulation<'_> {
             return Ok(());
         }
 
-        let flags = analysis.eval_flags();
+        if self.newton(analysis.eval_flags(), 0f64, None)?.is_none() {
+            bail!("Simulation failed to converge after {} iterations", self.config.maxiters)
+        }
+
+        self.state = op_flag;
+        Ok(())
+    }
 
+    /// Solves the circuit equations at `abstime` with newtons method, starting
+    /// from the current solution. During time integration `integration` contains
+    /// the integration coefficient `alpha` of the charges and the contribution of
+    /// previous time points to the time derivative (see [`tran`](Simulation::tran)).
+    ///
+    /// Returns the flags returned by the devices during the last iteration or `None`
+    /// if the iteration did not converge.
+    fn newton(
+        &mut self,
+        flags: EvalFlags,
+        abstime: f64,
+        integration: Option<(f64, &TiSlice<Node, f64>)>,
+    ) -> Result<Option<EvalRetFlags>> {
         let debug = self.config.debug;
         let matrix =
             self.matrix.as_mut().context("Simulation must be populated before it can run")?;
+        let load_react = flags.contains(EvalFlags::CALC_REACT_RESIDUAL);
 
         let mut i = 0;
         loop {
-            let sim_info = SimInfo { abstime: 0f64, prev_solve: &self.solution, flags };
+            if load_react {
+                self.residual_react.raw.fill(0f64);
+            }
+            if integration.is_some() {
+                matrix.ac_matrix.write_zero();
+            }
+
+            let mut ret_flags = EvalRetFlags::empty();
+            let sim_info = SimInfo { abstime, prev_solve: &self.solution, flags };
             for inst in &mut *self.instance_data {
-                inst.eval(sim_info)?;
+                ret_flags |= inst.eval(sim_info)?;
 
                 // this is save because we call populate_matrix_ptrs during Simulation construction
                 unsafe { inst.load_matrix_resist() }
                 inst.load_residual_resist(&self.solution, &mut self.residual_resist);
 
-                if analysis.time_integration() {
-                    let alpha = 0.0;
-                    unsafe { inst.load_matrix_react(alpha) }
+                if load_react {
                     inst.load_residual_react(&self.solution, &mut self.residual_react);
                 }
+
+                if let Some((alpha, _)) = integration {
+                    // the reactive entries are stored in the imaginary part of the ac matrix
+                    unsafe { inst.load_matrix_react(alpha) }
+                }
             }
 
-            // TODO time integration for tran analysis
+            if let Some((alpha, history)) = integration {
+                for (dst, src) in zip(matrix.nonlinear_matrix.data(), matrix.ac_matrix.data()) {
+                    dst.set(dst.get() + src.get().im)
+                }
+                for ((dst, charge), history) in
+                    zip(&mut self.residual_resist.raw, &self.residual_react.raw).zip(&history.raw)
+                {
+                    *dst += alpha * charge + history;
+                }
+            }
 
             if debug {
                 print_stdout(Self::matrix_table(&self.nodes, &matrix.nonlinear_matrix)).unwrap();
@@ -411,7 +454,8 @@ impl Simulation<'_> {
 
             // reset matrix
             matrix.nonlinear_matrix.write_zero();
-            let mut found_solution = true;
+            // limiting changed the solution so the iteration can not have converged yet
+            let mut found_solution = !ret_flags.contains(EvalRetFlags::LIM);
             for ((dst, delta), node_info) in
                 zip(&mut self.solution.raw[1..], &mut self.residual_resist.raw[1..])
                     .zip(&self.nodes.raw[1..])
@@ -419,29 +463,27 @@ impl Simulation<'_> {
                 let delta = replace(delta, 0f64);
                 let new_val = *dst - delta;
                 let atol = node_info.atol;
-                let tol = atol.max(new_val * self.config.rtol);
-                if delta > tol {
+                let tol = atol.max(new_val.abs() * self.config.rtol);
+                if delta.abs() > tol {
                     found_solution = false;
                 }
                 *dst = new_val;
             }
+            self.residual_resist.raw[0] = 0f64;
 
             if debug {
                 print_stdout(Self::vec_table(&self.solution.raw, &self.nodes.raw)).unwrap();
             }
 
             if found_solution && i > 0 {
-                break;
+                return Ok(Some(ret_flags));
             }
             i += 1;
 
             if i == self.config.maxiters {
-                bail!("Simulation failed to converge after {i} iterations")
+                return Ok(None);
             }
         }
-
-        self.state = op_flag;
-        Ok(())
     }
 
     pub fn set_omega(&mut self, omega: f64) {
@@ -18,6 +18,21 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// The flags returned by the evaluation of an instance
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub struct EvalRetFlags: u32 {
+        /// `$limit` modified the solution, the iteration has not converged
+        const LIM = EVAL_RET_FLAG_LIM;
+        const FATAL = EVAL_RET_FLAG_FATAL;
+        const FINISH = EVAL_RET_FLAG_FINISH;
+        const STOP = EVAL_RET_FLAG_STOP;
+        /// An event occurred before the evaluated time point, the time point must
+        /// be rejected and repeated with the step returned by `bound_step`
+        const REJECT = EVAL_RET_FLAG_REJECT;
+    }
+}
+
 macro_rules! private_flags {
     ($($vis: vis const $name: ident = $($val:ident)|*;)*) => {
 
@@ -38,30 +53,32 @@ private_flags! {
     pub(super) const DC_OP = OP | ANALYSIS_DC;
     pub(super) const AC_OP = OP | ANALYSIS_AC;
     // pub(super) const NOISE_OP = Self::OP.0.bits | ANALYSIS_NOISE;
-    // pub(super) const LARGE_SIGNAL_IC_OP = Self::OP.0.bits | ANALYSIS_TRAN | ANALYSIS_IC;
+    // the charges at the initial condition are the starting point of the time integration
+    pub(super) const LARGE_SIGNAL_IC_OP =
+        OP | ANALYSIS_TRAN | ANALYSIS_IC | CALC_REACT_RESIDUAL;
 
     pub(super) const AC = CALC_RESIST_JACOBIAN | CALC_REACT_JACOBIAN | ANALYSIS_AC;
     // pub(super) const NOISE = CALC_RESIST_JACOBIAN | CALC_REACT_JACOBIAN | CALC_NOISE | ANALYSIS_NOISE;
-    // pub(super) const LARGE_SIGNAL = ANALYSIS_TRAN
-    //     | CALC_RESIST_JACOBIAN
-    //     | CALC_RESIST_RESIDUAL
-    //     | CALC_REACT_JACOBIAN
-    //     | CALC_REACT_RESIDUAL;
+    pub(super) const LARGE_SIGNAL = ANALYSIS_TRAN
+        | CALC_RESIST_JACOBIAN
+        | CALC_RESIST_RESIDUAL
+        | CALC_REACT_JACOBIAN
+        | CALC_REACT_RESIDUAL;
 }
 
-// impl EvalFlags {
-//     pub(super) const TRAN_IC_OP: Self = Self::LARGE_SIGNAL_IC_OP;
-//     pub(super) const HB_IC_OP: Self = Self::LARGE_SIGNAL_IC_OP;
-//     pub(super) const HB: Self = Self::LARGE_SIGNAL;
-//     pub(super) const TRAN: Self = Self::LARGE_SIGNAL;
-// }
+impl EvalFlags {
+    pub(super) const TRAN_IC_OP: Self = Self::LARGE_SIGNAL_IC_OP;
+    // pub(super) const HB_IC_OP: Self = Self::LARGE_SIGNAL_IC_OP;
+    // pub(super) const HB: Self = Self::LARGE_SIGNAL;
+    pub(super) const TRAN: Self = Self::LARGE_SIGNAL;
+}
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub(super) enum OperatingPointAnalysis {
     DC,
     AC,
     // Noise,
-    // TranIc,
+    TranIc,
     // Tran,
     // HBIc,
 }
@@ -72,23 +89,18 @@ impl OperatingPointAnalysis {
             OperatingPointAnalysis::DC => EvalFlags::DC_OP,
             OperatingPointAnalysis::AC => EvalFlags::AC_OP,
             // OperatingPointAnalysis::Noise => EvalFlags::NOISE_OP,
-            // OperatingPointAnalysis::TranIc => EvalFlags::TRAN_IC_OP,
+            OperatingPointAnalysis::TranIc => EvalFlags::TRAN_IC_OP,
             // OperatingPointAnalysis::Tran => EvalFlags::TRAN,
             // OperatingPointAnalysis::HBIc => EvalFlags::HB_IC_OP,
         }
     }
 
-    pub fn time_integration(self) -> bool {
-        // matches!(self, OperatingPointAnalysis::Tran)
-        false
-    }
-
     pub fn solution_flags(self) -> SimulationState {
         match self {
             OperatingPointAnalysis::DC => SimulationState::AT_DC_OP,
             OperatingPointAnalysis::AC => SimulationState::AT_AC_OP,
             // OperatingPointAnalysis::Noise => SimulationState::AT_NOISE_OP,
-            // OperatingPointAnalysis::TranIc => todo!(),
+            OperatingPointAnalysis::TranIc => SimulationState::AT_TRAN_OP,
             // OperatingPointAnalysis::Tran => todo!(),
             // OperatingPointAnalysis::HBIc => todo!(),
         }
@@ -103,6 +115,7 @@ bitflags! {
         // const AT_NOISE_OP = 0b00000100;
         const HAS_AC_EVAL = 0b00001000;
         const AT_AC = 0b00010000;
+        const AT_TRAN_OP = 0b00100000;
         const AT_OP = Self::AT_DC_OP.0.bits() | Self::AT_AC_OP.0.bits();// | Self::AT_NOISE_OP.0.bits;
     }
 }
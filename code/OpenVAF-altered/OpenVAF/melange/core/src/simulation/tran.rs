@@ -0,0 +1,306 @@
+//! Transient analysis.
+//!
+//! The circuit equations `f(x) + dq(x)/dt = 0` are solved at discrete time
+//! points. The time derivative of the charges is approximated by a linear
+//! combination `alpha * q(x) + history` of the charges at the new time point and
+//! at previously accepted time points. The coefficients depend on the
+//! [`IntegrationMethod`] and on the previous time steps.
+//!
+//! The time step is controlled by an estimate of the local truncation error
+//! (obtained by comparing the solution with a polynomial extrapolation of the
+//! previous time points), by the step bounds requested by the devices and by
+//! the convergence of newtons method.
+//!
+//! The step bound of a device is measured from the time point of its last
+//! evaluation and limits the step that follows an accepted time point. A device
+//! that detects an event (for example a zero crossing) which lies before the
+//! evaluated time point returns [`EvalRetFlags::REJECT`]. Such a time point is
+//! rejected and repeated with the step bound of the devices, which is measured
+//! from the last accepted time point in that case, so that it ends at the
+//! event.
+
+use std::collections::VecDeque;
+
+use anyhow::{bail, Result};
+use stdx::iter::zip;
+use typed_index_collections::TiVec;
+
+use crate::circuit::Node;
+use crate::simulation::flags::{EvalFlags, OperatingPointAnalysis};
+use crate::simulation::{EvalRetFlags, Simulation};
+
+/// Steps that exceed the truncation error are repeated with a step that is at
+/// least this much smaller.
+const MIN_REDUCTION: f64 = 0.9;
+/// Safety factor applied to the step predicted from the truncation error.
+const LTE_SAFETY: f64 = 0.9;
+/// The time step grows by at most this factor from one time point to the next.
+const MAX_GROWTH: f64 = 2.0;
+/// The time step is cut by this factor if newtons method does not converge.
+const NON_CONVERGENCE_CUT: f64 = 0.125;
+/// The first time step relative to the maximum time step.
+const FIRST_STEP: f64 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrationMethod {
+    BackwardEuler,
+    Trapezoidal,
+    /// Second order backward differentiation formula
+    Gear2,
+}
+
+impl IntegrationMethod {
+    fn order(self) -> usize {
+        match self {
+            IntegrationMethod::BackwardEuler => 1,
+            IntegrationMethod::Trapezoidal | IntegrationMethod::Gear2 => 2,
+        }
+    }
+
+    /// The ratio between the truncation error and the difference between the
+    /// solution and a predictor of the same order (assuming equidistant steps).
+    fn error_constant(self) -> f64 {
+        match self {
+            IntegrationMethod::BackwardEuler => 1.0 / 3.0,
+            IntegrationMethod::Trapezoidal => 1.0 / 13.0,
+            IntegrationMethod::Gear2 => 2.0 / 11.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TranOpts {
+    pub method: IntegrationMethod,
+    /// The largest allowed time step, defaults to `tstep`
+    pub max_step: Option<f64>,
+    /// The analysis is aborted if the time step falls below this value
+    pub min_step: f64,
+    /// The truncation error may exceed the solver tolerances by this factor
+    pub trtol: f64,
+}
+
+impl Default for TranOpts {
+    fn default() -> Self {
+        TranOpts {
+            method: IntegrationMethod::Trapezoidal,
+            max_step: None,
+            min_step: 1e-18,
+            trtol: 7.0,
+        }
+    }
+}
+
+/// The solution at every accepted time point of a transient analysis.
+#[derive(Debug, Clone)]
+pub struct TranSolution {
+    pub time: Vec<f64>,
+    pub solution: TiVec<Node, Vec<f64>>,
+}
+
+impl TranSolution {
+    fn push(&mut self, time: f64, solution: &[f64]) {
+        self.time.push(time);
+        for (dst, &val) in zip(&mut self.solution.raw, solution) {
+            dst.push(val)
+        }
+    }
+}
+
+struct TimePoint {
+    time: f64,
+    solution: Box<[f64]>,
+    charges: Box<[f64]>,
+    /// The time derivative of the charges
+    current: Box<[f64]>,
+}
+
+/// The integration coefficients for a step of length `step` after the accepted
+/// time points `points` (the most recent one first). Returns the effective
+/// integration method, `alpha` and the coefficients of the charges (and their
+/// time derivative for the trapezoidal rule) at the previous time points.
+fn coefficients(
+    method: IntegrationMethod,
+    step: f64,
+    points: &VecDeque<TimePoint>,
+) -> (IntegrationMethod, f64, [f64; 3]) {
+    match method {
+        IntegrationMethod::Gear2 if points.len() >= 2 => {
+            let prev_step = points[0].time - points[1].time;
+            let span = step + prev_step;
+            let alpha = (2.0 * step + prev_step) / (step * span);
+            let c1 = -span / (step * prev_step);
+            let c2 = step / (prev_step * span);
+            (method, alpha, [c1, c2, 0.0])
+        }
+        IntegrationMethod::Trapezoidal => (method, 2.0 / step, [-2.0 / step, 0.0, -1.0]),
+        // gear2 requires two previous time points, the first step uses backward euler instead
+        _ => (IntegrationMethod::BackwardEuler, 1.0 / step, [-1.0 / step, 0.0, 0.0]),
+    }
+}
+
+/// Extrapolates the solution at `time` with the polynomial through `points`.
+fn predict(points: &[&TimePoint], time: f64, node: usize) -> f64 {
+    let mut res = 0.0;
+    for (i, point) in points.iter().enumerate() {
+        let mut weight = 1.0;
+        for (j, other) in points.iter().enumerate() {
+            if i != j {
+                weight *= (time - other.time) / (point.time - other.time);
+            }
+        }
+        res += weight * point.solution[node];
+    }
+    res
+}
+
+impl Simulation<'_> {
+    /// Runs a transient analysis from the operating point at `t = 0` until `tstop`.
+    /// `tstep` is the largest time step (unless [`TranOpts::max_step`] is set).
+    /// The analysis ends early if a device calls `$finish` or `$stop`.
+    pub fn tran(&mut self, tstop: f64, tstep: f64, opts: &TranOpts) -> Result<TranSolution> {
+        if tstop <= 0.0 || tstep <= 0.0 {
+            bail!("tran: tstop ({tstop}) and tstep ({tstep}) must be positive")
+        }
+        let max_step = opts.max_step.unwrap_or(tstep).min(tstop);
+
+        self.solve_op(OperatingPointAnalysis::TranIc)?;
+        // the solution and the evaluated devices no longer belong to an operating point
+        self.state.clear();
+        self.accept_step();
+
+        let num_nodes = self.nodes.len();
+        let mut res =
+            TranSolution { time: Vec::new(), solution: vec![Vec::new(); num_nodes].into() };
+        res.push(0.0, &self.solution.raw);
+
+        let mut points = VecDeque::with_capacity(3);
+        points.push_front(TimePoint {
+            time: 0.0,
+            solution: self.solution.raw.clone().into_boxed_slice(),
+            charges: self.residual_react.raw.clone().into_boxed_slice(),
+            // the operating point is a steady state
+            current: vec![0f64; num_nodes].into_boxed_slice(),
+        });
+
+        let mut history: TiVec<Node, f64> = vec![0f64; num_nodes].into();
+        let mut step = (max_step * FIRST_STEP).min(self.bound_step());
+        let mut time = 0.0;
+
+        while time < tstop {
+            step = step.min(max_step);
+            let last = time + step >= tstop;
+            if last {
+                step = tstop - time;
+            }
+            if step < opts.min_step {
+                bail!("tran: time step too small at t = {time} s")
+            }
+
+            let (method, alpha, [c1, c2, c_current]) = coefficients(opts.method, step, &points);
+            for (i, dst) in history.raw.iter_mut().enumerate() {
+                let mut val = c1 * points[0].charges[i] + c_current * points[0].current[i];
+                if c2 != 0.0 {
+                    val += c2 * points[1].charges[i];
+                }
+                *dst = val;
+            }
+
+            let new_time = if last { tstop } else { time + step };
+            let ret_flags = self.newton(EvalFlags::TRAN, new_time, Some((alpha, &*history)))?;
+            let ret_flags = match ret_flags {
+                Some(ret_flags) => ret_flags,
+                None => {
+                    self.reject_step(&points[0]);
+                    step *= NON_CONVERGENCE_CUT;
+                    continue;
+                }
+            };
+
+            // estimate the truncation error once enough time points are available
+            let order = method.order();
+            let mut next_step = step * MAX_GROWTH;
+            if points.len() > order {
+                let ratio = self.truncation_error(&points, order, new_time, method, opts.trtol);
+                if ratio > 0.0 {
+                    let factor = LTE_SAFETY * ratio.powf(-1.0 / (order + 1) as f64);
+                    next_step = step * factor.clamp(NON_CONVERGENCE_CUT, MAX_GROWTH);
+                }
+                if ratio > 1.0 {
+                    self.reject_step(&points[0]);
+                    step = next_step.min(step * MIN_REDUCTION);
+                    continue;
+                }
+            }
+
+            // an event occurred before `new_time`, repeat the step so that it ends at the event
+            let bound = self.bound_step();
+            if ret_flags.contains(EvalRetFlags::REJECT) {
+                self.reject_step(&points[0]);
+                // always make progress, even if a device bounds the retry by the step it rejected
+                step = bound.min(step * MIN_REDUCTION);
+                continue;
+            }
+
+            let current =
+                zip(&self.residual_react.raw, &history.raw).map(|(q, h)| alpha * q + h).collect();
+            if points.len() == 3 {
+                points.pop_back();
+            }
+            points.push_front(TimePoint {
+                time: new_time,
+                solution: self.solution.raw.clone().into_boxed_slice(),
+                charges: self.residual_react.raw.clone().into_boxed_slice(),
+                current,
+            });
+            res.push(new_time, &self.solution.raw);
+            self.accept_step();
+
+            time = new_time;
+            step = next_step.min(bound);
+
+            if ret_flags.intersects(EvalRetFlags::FINISH | EvalRetFlags::STOP) {
+                break;
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn bound_step(&self) -> f64 {
+        self.instance_data.iter().map(|inst| inst.bound_step()).fold(f64::INFINITY, f64::min)
+    }
+
+    fn accept_step(&mut self) {
+        for inst in &mut *self.instance_data {
+            inst.accept_step()
+        }
+    }
+
+    /// Restores the solution and the device states of the last accepted time point.
+    fn reject_step(&mut self, accepted: &TimePoint) {
+        self.solution.raw.copy_from_slice(&accepted.solution);
+        for inst in &mut *self.instance_data {
+            inst.reject_step()
+        }
+    }
+
+    /// The largest ratio between the estimated truncation error of an unknown
+    /// and its tolerance.
+    fn truncation_error(
+        &self,
+        points: &VecDeque<TimePoint>,
+        order: usize,
+        time: f64,
+        method: IntegrationMethod,
+        trtol: f64,
+    ) -> f64 {
+        let predictor: Vec<_> = points.iter().take(order + 1).collect();
+        let mut res = 0f64;
+        for (i, (&val, node_info)) in zip(&self.solution.raw, &self.nodes.raw).enumerate().skip(1) {
+            let error = method.error_constant() * (val - predict(&predictor, time, i)).abs();
+            let tol = trtol * node_info.atol.max(val.abs() * self.config.rtol);
+            res = res.max(error / tol);
+        }
+        res
+    }
+}
@@ -1,11 +1,12 @@
 use anyhow::Result;
-use camino::Utf8PathBuf;
-use stdx::project_root;
+use camino::{Utf8Path, Utf8PathBuf};
+use stdx::{project_root, write_test_module};
 
-use crate::expr::CircuitParam;
-use crate::simulation::SimConfig;
+use crate::expr::{CircuitParam, Expr};
+use crate::netlist::Analysis;
+use crate::simulation::{IntegrationMethod, SimConfig, TranOpts};
 use crate::utils::PrettyPrint;
-use crate::{veriloga, Arena, Circuit, ExprEvalCtx};
+use crate::{netlist, veriloga, Arena, Circuit, ExprEvalCtx};
 
 const ATOL: f64 = 1e-9;
 const RTOL: f64 = 1e-2;
@@ -137,3 +138,658 @@ fn veriloga() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn tran() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("integration_tests")
+        .join("DIODE")
+        .join("diode.va");
+    circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 0.9.into())?;
+
+    let (_, diode1) =
+        circ.new_device_instance_by_name("diode1".to_owned(), "diode_va", vec![node_x, gnd])?;
+    circ.set_model_param(diode1, "rs", 5f64.into())?;
+    circ.set_model_param(diode1, "is", 1e-13.into())?;
+    circ.set_model_param(diode1, "cj0", 1e-12.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    let op = sim.dc_op()?.to_owned();
+
+    // a circuit driven by dc sources remains at its operating point
+    for method in
+        [IntegrationMethod::BackwardEuler, IntegrationMethod::Trapezoidal, IntegrationMethod::Gear2]
+    {
+        let opts = TranOpts { method, ..TranOpts::default() };
+        let res = sim.tran(1e-9, 1e-11, &opts)?;
+        assert_approx_eq!(*res.time.last().unwrap(), 1e-9);
+        for (node, waveform) in res.solution.iter_enumerated() {
+            for &val in waveform {
+                assert_approx_eq!(val, op[node]);
+            }
+        }
+    }
+
+    assert_approx_eq!(sim.dc_lead_current(vsrc1)?[0], curr);
+    Ok(())
+}
+
+const RAMP: &str = r#"
+`include "disciplines.vams"
+module ramp(out);
+    inout out;
+    electrical out;
+    analog V(out) <+ transition($abstime > 0 ? 1 : 0, 1n, 2n);
+endmodule
+"#;
+
+#[test]
+fn tran_transition_corners() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_out = circ.node("out".to_owned());
+
+    let Some(dir) = write_test_module("melange_ramp", &[("ramp.va", RAMP)])? else {
+        return Ok(());
+    };
+    circ.load_veriloga_file(dir.join("ramp.va"), &veriloga::Opts::default())?;
+    circ.new_device_instance_by_name("ramp1".to_owned(), "ramp", vec![node_out])?;
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_out, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let res = sim.tran(5e-9, 1e-9, &TranOpts::default())?;
+
+    // the input changes at the first time point, the ramp starts 1ns later and lasts 2ns
+    let change = res.time[1];
+    let (begin, end) = (change + 1e-9, change + 3e-9);
+    for corner in [begin, end] {
+        assert!(
+            res.time.iter().any(|&time| (time - corner).abs() < 1e-18),
+            "no time point at the corner t = {corner}: {:?}",
+            res.time
+        );
+    }
+    for (&time, &val) in res.time.iter().zip(&res.solution[node_out]) {
+        let exact = ((time - begin) / (end - begin)).clamp(0.0, 1.0);
+        assert!((val - exact).abs() < 1e-6, "v(out) = {val} at t = {time}, expected {exact}");
+    }
+    Ok(())
+}
+
+const CROSSER: &str = r#"
+`include "disciplines.vams"
+module crosser(out);
+    inout out;
+    electrical out;
+    real hit;
+    analog begin
+        hit = 0;
+        @(cross($abstime - 2.5n, 1)) hit = 1;
+        V(out) <+ hit;
+    end
+endmodule
+"#;
+
+#[test]
+fn tran_cross_rejects_step() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_out = circ.node("out".to_owned());
+
+    let Some(dir) = write_test_module("melange_crosser", &[("crosser.va", CROSSER)])? else {
+        return Ok(());
+    };
+    circ.load_veriloga_file(dir.join("crosser.va"), &veriloga::Opts::default())?;
+    circ.new_device_instance_by_name("crosser1".to_owned(), "crosser", vec![node_out])?;
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_out, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let res = sim.tran(5e-9, 1e-9, &TranOpts::default())?;
+
+    // nothing bounds the step before the crossing, the time point that overshoots
+    // it is rejected and repeated so that it ends at the crossing
+    assert!(
+        res.time.iter().any(|&time| (time - 2.5e-9).abs() < 1e-12),
+        "no time point at the crossing: {:?}",
+        res.time
+    );
+    assert!(res.time.windows(2).all(|times| times[0] < times[1]));
+    Ok(())
+}
+
+const STOPPER: &str = r#"
+`include "disciplines.vams"
+module stopper(out);
+    inout out;
+    electrical out;
+    analog begin
+        V(out) <+ 1;
+        if ($abstime > 2n)
+            $finish(0);
+    end
+endmodule
+"#;
+
+#[test]
+fn tran_finish() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_out = circ.node("out".to_owned());
+
+    let Some(dir) = write_test_module("melange_stopper", &[("stopper.va", STOPPER)])? else {
+        return Ok(());
+    };
+    circ.load_veriloga_file(dir.join("stopper.va"), &veriloga::Opts::default())?;
+    circ.new_device_instance_by_name("stopper1".to_owned(), "stopper", vec![node_out])?;
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_out, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let res = sim.tran(10e-9, 1e-9, &TranOpts::default())?;
+
+    // the run ends at the first accepted time point after 2ns
+    let last = *res.time.last().unwrap();
+    assert!(last > 2e-9 && last <= 3e-9, "the analysis ended at t = {last}");
+    assert!(res.time[..res.time.len() - 1].iter().all(|&time| time <= 2e-9));
+    Ok(())
+}
+
+#[test]
+fn dc_negative_voltage() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("integration_tests")
+        .join("DIODE")
+        .join("diode.va");
+    circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+
+    // the diode of the veriloga test with reversed terminals: all newton updates are negative
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", (-0.9).into())?;
+
+    let (_, diode1) =
+        circ.new_device_instance_by_name("diode1".to_owned(), "diode_va", vec![gnd, node_x])?;
+    circ.set_model_param(diode1, "rs", 5f64.into())?;
+    circ.set_model_param(diode1, "is", 1e-13.into())?;
+    circ.set_model_param(diode1, "n", 1.05.into())?;
+    circ.set_model_param(diode1, "rth", 100.into())?;
+    circ.set_model_param(diode1, "cj0", 1e-15.into())?;
+    circ.set_model_param(diode1, "vj", 0.5.into())?;
+    circ.set_model_param(diode1, "m", 0.6.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    assert_approx_eq!(sim.dc_lead_current(vsrc1)?[0], 0.0365);
+
+    Ok(())
+}
+
+#[test]
+fn ac_rc_lowpass() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_in = circ.node("in".to_owned());
+    let node_out = circ.node("out".to_owned());
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_in, gnd])?;
+    circ.set_instance_param(vsrc1, "mag", 1.0.into())?;
+
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_in, node_out])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let (cap1, _) =
+        circ.new_device_instance_by_name("cap1".to_owned(), "capacitor", vec![node_out, gnd])?;
+    circ.set_instance_param(cap1, "c", 1e-9.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    // the capacitor is an open circuit at DC
+    assert_approx_eq!(sim.dc_op()?[node_out], 0.0);
+
+    // at the corner frequency 1/(RC) the output is 1/(1 + j)
+    sim.set_omega(1e6);
+    let solution = sim.ac()?;
+    assert_approx_eq_cmplx!(solution[node_out], 0.5 - j 0.5);
+
+    Ok(())
+}
+
+#[test]
+fn ac_source_phase() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "mag", 2.0.into())?;
+    circ.set_instance_param(vsrc1, "phase", std::f64::consts::FRAC_PI_2.into())?;
+
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_x, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    sim.set_omega(1e3);
+    let solution = sim.ac()?;
+    // setting the phase keeps the magnitude
+    assert_approx_eq_cmplx!(solution[node_x], 0.0 + j 2.0);
+
+    Ok(())
+}
+
+const DELAY_LINE: &str = r#"
+`include "disciplines.vams"
+module delay_line(a, c);
+    inout a, c;
+    electrical a, c;
+    parameter real td = 0 from [0:inf);
+    analog V(c) <+ absdelay(V(a), td);
+endmodule
+"#;
+
+#[test]
+fn ac_absdelay_phase() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_in = circ.node("in".to_owned());
+    let node_out = circ.node("out".to_owned());
+
+    let Some(dir) = write_test_module("melange_delay_line", &[("delay_line.va", DELAY_LINE)])?
+    else {
+        return Ok(());
+    };
+    circ.load_veriloga_file(dir.join("delay_line.va"), &veriloga::Opts::default())?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_in, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 0.5.into())?;
+    circ.set_instance_param(vsrc1, "mag", 1.0.into())?;
+    let (line1, _) = circ.new_device_instance_by_name(
+        "line1".to_owned(),
+        "delay_line",
+        vec![node_in, node_out],
+    )?;
+    circ.set_instance_param(line1, "td", 1e-9.into())?;
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_out, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    // the delay passes the input through at DC
+    assert_approx_eq!(sim.dc_op()?[node_out], 0.5);
+
+    // a delay only shifts the phase, by -omega * td
+    sim.set_omega(1e9);
+    let out = sim.ac()?[node_out];
+    assert_approx_eq!(out.norm(), 1.0);
+    assert_approx_eq!(out.arg(), -1.0);
+
+    Ok(())
+}
+
+#[test]
+fn tran_rc() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_in = circ.node("in".to_owned());
+    let node_out = circ.node("out".to_owned());
+
+    // a (nearly ideal) step from 0 to 1 at t = 0
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_in, gnd])?;
+    circ.set_instance_param(vsrc1, "type", Expr::str(&mut arena, "pulse"))?;
+    circ.set_instance_param(vsrc1, "val0", 0.0.into())?;
+    circ.set_instance_param(vsrc1, "val1", 1.0.into())?;
+    circ.set_instance_param(vsrc1, "rise", 1e-13.into())?;
+
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_in, node_out])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+    let (cap1, _) =
+        circ.new_device_instance_by_name("cap1".to_owned(), "capacitor", vec![node_out, gnd])?;
+    circ.set_instance_param(cap1, "c", 1e-12.into())?;
+    let rc = 1e-9;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    for method in
+        [IntegrationMethod::BackwardEuler, IntegrationMethod::Trapezoidal, IntegrationMethod::Gear2]
+    {
+        let opts = TranOpts { method, ..TranOpts::default() };
+        let res = sim.tran(5.0 * rc, rc / 100.0, &opts)?;
+        assert_approx_eq!(*res.time.last().unwrap(), 5.0 * rc);
+        for (&time, &val) in res.time.iter().zip(&res.solution[node_out]) {
+            let exact = 1.0 - (-time / rc).exp();
+            assert!(
+                (val - exact).abs() < 1e-2,
+                "{method:?}: v(out) = {val} at t = {time}, expected {exact}"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn tran_sine() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "type", Expr::str(&mut arena, "sine"))?;
+    circ.set_instance_param(vsrc1, "sinedc", 0.5.into())?;
+    circ.set_instance_param(vsrc1, "ampl", 1.0.into())?;
+    circ.set_instance_param(vsrc1, "freq", 1e9.into())?;
+    circ.set_instance_param(vsrc1, "delay", 1e-9.into())?;
+
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_x, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    let res = sim.tran(3e-9, 1e-11, &TranOpts::default())?;
+    assert_approx_eq!(*res.time.last().unwrap(), 3e-9);
+    for (&time, &val) in res.time.iter().zip(&res.solution[node_x]) {
+        // the sine starts at the end of the delay
+        let exact = 0.5 + (std::f64::consts::TAU * 1e9 * (time - 1e-9).max(0.0)).sin();
+        assert!((val - exact).abs() < 1e-6, "v(X) = {val} at t = {time}, expected {exact}");
+    }
+    Ok(())
+}
+
+#[test]
+fn const_unary_ops() -> Result<()> {
+    let mut arena = Arena::new();
+    assert_eq!(Expr::neg(&mut arena, 2.0.into())?, (-2.0).into());
+    // int() truncates towards zero like ngspice
+    assert_eq!(Expr::int(&mut arena, 2.7.into())?, 2.0.into());
+    assert_eq!(Expr::int(&mut arena, (-2.7).into())?, (-2.0).into());
+    Ok(())
+}
+
+#[test]
+fn spice_netlist() -> Result<()> {
+    let src = "voltage divider
+.param rtot = 2*r1
+vsrc1 in 0 dc {vdd}
+r1 in out {r1}
+r2 out gnd 'rtot - r1' ; the same resistance as r1
+.param vdd=1.8
+* comments do not end continued lines
++ r1=1k
+.op
+.end
+";
+    let mut arena = Arena::new();
+    let netlist = netlist::spice::parse_str(src, Utf8Path::new("divider.sp"), &mut arena)?;
+    assert_eq!(netlist.title, "voltage divider");
+    assert_eq!(netlist.analyses, vec![Analysis::Op]);
+
+    let circ = netlist.circuit.elaborate(&mut arena, &veriloga::Opts::default())?;
+    let out = circ.lookup_node("out").expect("node out");
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    assert_approx_eq!(sim.dc_op()?[out], 0.9);
+    Ok(())
+}
+
+#[test]
+fn spice_subcircuit() -> Result<()> {
+    let src = "nested subcircuits
+.subckt series a b r=1k
+r1 a mid {r/2}
+r2 mid b {r/2}
+.ends
+.subckt divider top out bot rtop=1k rbot=rtop
+x1 top out series r=rtop
+x2 out bot series r=rbot
+.ends
+vsrc1 in 0 dc 1.8
+xdiv in out 0 divider rtop=3k
+.end
+";
+    let mut arena = Arena::new();
+    let netlist = netlist::spice::parse_str(src, Utf8Path::new("subckt.sp"), &mut arena)?;
+    let circ = netlist.circuit.elaborate(&mut arena, &veriloga::Opts::default())?;
+    let out = circ.lookup_node("out").expect("node out");
+    let mid = circ.lookup_node("xdiv.x1.mid").expect("node xdiv.x1.mid");
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let res = sim.dc_op()?;
+    assert_approx_eq!(res[out], 0.9);
+    assert_approx_eq!(res[mid], 1.35);
+    Ok(())
+}
+
+#[test]
+fn spice_subcircuit_global_model() -> Result<()> {
+    let src = "global model inside a subcircuit
+.model rmod resistor
+.subckt half a b r=1k
+r1 a mid {r} rmod
+r2 mid b {r} rmod
+.ends
+vsrc1 in 0 dc 1.8
+x1 in 0 half r=2k
+.end
+";
+    let mut arena = Arena::new();
+    let netlist = netlist::spice::parse_str(src, Utf8Path::new("global_model.sp"), &mut arena)?;
+    let circ = netlist.circuit.elaborate(&mut arena, &veriloga::Opts::default())?;
+    let mid = circ.lookup_node("x1.mid").expect("node x1.mid");
+    let vsrc1 = circ.lookup_instance("vsrc1").expect("instance vsrc1");
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    assert_approx_eq!(sim.dc_op()?[mid], 0.9);
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    assert_approx_eq!(curr, -1.8 / 4e3);
+    Ok(())
+}
+
+#[test]
+fn spectre_netlist() -> Result<()> {
+    let src = r#"// voltage divider
+parameters vdd=1.8 rval=1k
+vsrc1 (in 0) vsource type=dc dc=vdd
+r1 (in out) resistor r=rval
+simulator lang=spice
+r2 out 0 {rval}
+simulator lang=spectre
+op dc
+tr tran stop=1n
+"#;
+    let mut arena = Arena::new();
+    let netlist = netlist::spectre::parse_str(src, Utf8Path::new("divider.scs"), &mut arena)?;
+    assert_eq!(netlist.analyses.len(), 2);
+    assert_eq!(netlist.analyses[0], Analysis::Op);
+
+    let circ = netlist.circuit.elaborate(&mut arena, &veriloga::Opts::default())?;
+    let out = circ.lookup_node("out").expect("node out");
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    assert_approx_eq!(sim.dc_op()?[out], 0.9);
+    Ok(())
+}
+
+/// A stand-in for the SKY130 library that only contains the transistor used by the
+/// track and hold example
+const SKY130_LIB: &str = "* sky130 stub
+.lib tt
+.subckt sky130_fd_pr__nfet_01v8_lvt d g s b w=1 l=1
+m1 d g s b nlvt w={w} l={l}
+.model nlvt nmos level=54 version=4.5 toxe=4.148e-9
+.ends
+.endl tt
+";
+
+#[test]
+fn spice_track_hold() -> Result<()> {
+    // the decks of the SKY130 examples are stored next to the OpenVAF workspace
+    let netlists = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("../../../tests/sky-use/skywater-examples/netlists");
+    if !netlists.exists() {
+        return Ok(());
+    }
+    let Some(pdk) = write_test_module(
+        "melange_track_hold",
+        &[("sky130/sky130/sky130A/libs.tech/ngspice/sky130.lib.spice", SKY130_LIB)],
+    )?
+    else {
+        return Ok(());
+    };
+    std::env::set_var("DELOREAN_ROOT", pdk.as_str());
+
+    for (deck, step) in [("track_hold_sim1.spice", 1e-9), ("track_hold_sim2.spice", 1e-7)] {
+        let mut arena = Arena::new();
+        let netlist = netlist::spice::parse_file(&netlists.join(deck), &mut arena)?;
+        assert_eq!(netlist.option("method"), Some(&netlist::OptionValue::Str("gear".to_owned())));
+        let tran_step = match &netlist.analyses[..] {
+            [Analysis::Tran { step, .. }] => *step,
+            analyses => panic!("{deck}: expected a single .tran analysis but found {analyses:?}"),
+        };
+        let mut ctx = ExprEvalCtx::new(&arena);
+        for &(param, val) in &netlist.circuit.parameters {
+            let val = val.eval(ctx.borrow())?;
+            ctx.set_param(param, val);
+        }
+        assert_approx_eq!(tran_step.eval_num(ctx.borrow())?, step);
+
+        let circuit = &netlist.circuit;
+        let names: Vec<_> = circuit.instances.iter().map(|inst| &*inst.name).collect();
+        assert_eq!(names, ["x1", "cl", "vin", "vclk"]);
+        let [nfet] = &circuit.subcircuits[..] else { panic!("{deck}: expected one subcircuit") };
+        assert_eq!(nfet.name, "sky130_fd_pr__nfet_01v8_lvt");
+        assert_eq!(nfet.models[0].device, "bsim4va");
+
+        // like ngspice, BSIM4 must be loaded as an OSDI library (with pre_osdi)
+        let err = netlist.circuit.elaborate(&mut arena, &veriloga::Opts::default()).unwrap_err();
+        assert!(format!("{err:#}").contains("'bsim4va' not found"), "{err:#}");
+    }
+    Ok(())
+}
+
+const SPECTRE_CORNERS: &str = "// resistor corners
+library corners
+section fast
+model rcorner resistor r=1k
+endsection fast
+section slow
+model rcorner resistor r=3k
+endsection slow
+endlibrary corners
+";
+
+const SPECTRE_DIVIDER: &str = r#"// divider with a subcircuit and included models
+include "corners.scs" section=slow
+subckt half (top bot)
+parameters rbot=500
+r1 (top mid) rcorner
+r2 (mid bot) resistor r=rbot
+ends half
+vsrc1 (in 0) vsource dc=1.6
+x1 (in 0) half rbot=1k
+"#;
+
+#[test]
+fn spectre_subcircuit_include() -> Result<()> {
+    let Some(dir) = write_test_module(
+        "melange_spectre_include",
+        &[("corners.scs", SPECTRE_CORNERS), ("divider.scs", SPECTRE_DIVIDER)],
+    )?
+    else {
+        return Ok(());
+    };
+    let mut arena = Arena::new();
+    let netlist = netlist::spectre::parse_file(&dir.join("divider.scs"), &mut arena)?;
+    // only the model of the included section is defined
+    let models: Vec<_> = netlist.circuit.models.iter().map(|model| &*model.name).collect();
+    assert_eq!(models, ["rcorner"]);
+
+    let circ = netlist.circuit.elaborate(&mut arena, &veriloga::Opts::default())?;
+    let mid = circ.lookup_node("x1.mid").expect("node x1.mid");
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    // r1 = 3k (slow corner) and r2 = 1k
+    assert_approx_eq!(sim.dc_op()?[mid], 0.4);
+
+    // a missing section is an error
+    let src = SPECTRE_DIVIDER.replace("section=slow", "section=typical");
+    let mut arena = Arena::new();
+    let err = match netlist::spectre::parse_str(&src, &dir.join("divider.scs"), &mut arena) {
+        Ok(_) => panic!("included a missing section"),
+        Err(err) => err,
+    };
+    assert!(format!("{err:#}").contains("section typical not found"), "{err:#}");
+    Ok(())
+}
@@ -9,7 +9,8 @@ use libc::c_void;
 use libloading::Library;
 use log::{debug, error, info, warn};
 use openvaf::{
-    AbsPathBuf, CompilationDestination, CompilationTermination, LintLevel, OptLevel, Target,
+    AbsPathBuf, CompilationDestination, CompilationTermination, ElidedParamMode, LintLevel,
+    OptLevel, Target,
 };
 
 use crate::devices::DeviceImpl;
@@ -79,8 +80,17 @@ pub fn compile_va(path: &Utf8Path, opts: &Opts) -> Result<Vec<Box<dyn DeviceImpl
         dump_ir: false, 
         dump_unopt_ir: false, 
         // RDUBI LINE
-        params_to_leave: vec![],
+        keep_dynamic: vec![],
         param_defaults: vec![],
+        elision_files: vec![],
+        elision_dir: None,
+        elision_spice: None,
+        elision_report: None,
+        binned: false,
+        shared_elision: false,
+        assume_temperature: None,
+        assume_simparams: vec![],
+        elided_params: ElidedParamMode::default(),
     };
 
     let res = openvaf::compile(&openvaf_opts);
@@ -91,7 +101,12 @@ pub fn compile_va(path: &Utf8Path, opts: &Opts) -> Result<Vec<Box<dyn DeviceImpl
             bail!("openvaf: compilation of {path} failed");
         }
     };
-    let libs = unsafe { load_osdi_lib(&lib_file)? };
+    load_osdi(&lib_file)
+}
+
+/// Loads the devices of a precompiled OSDI shared library (for example from `pre_osdi`).
+pub fn load_osdi(path: &Utf8Path) -> Result<Vec<Box<dyn DeviceImpl>>> {
+    let libs = unsafe { load_osdi_lib(path)? };
     let libs = libs.iter().map(|descriptor| Box::new(OsdiDevice { descriptor }) as _).collect();
     Ok(libs)
 }
@@ -103,9 +118,9 @@ unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<&'static [OsdiDescriptor]> {
     let major_version: &u32 = *lib.get(b"OSDI_VERSION_MAJOR\0")?;
     let minor_version: &u32 = *lib.get(b"OSDI_VERSION_MINOR\0")?;
 
-    if *major_version != 0 || *minor_version != 3 {
+    if *major_version != 0 || *minor_version != 4 {
         bail!(
-            "melange only supports OSDI v0.3 but {path} targets v{major_version}.{minor_version}",
+            "melange only supports OSDI v0.4 but {path} targets v{major_version}.{minor_version}",
         );
     }
 
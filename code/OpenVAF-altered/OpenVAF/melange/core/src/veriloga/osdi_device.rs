@@ -12,7 +12,7 @@ use typed_index_collections::TiSlice;
 
 use crate::circuit::Node;
 use crate::devices::{DeviceImpl, DeviceParams, InstanceImpl, ModelImpl, ParamId, Type};
-use crate::simulation::{MatrixEntryIter, SimBuilder, SimInfo};
+use crate::simulation::{EvalRetFlags, MatrixEntryIter, SimBuilder, SimInfo};
 use crate::veriloga::osdi_0_4::{
     OsdiDescriptor, OsdiInitInfo, OsdiJacobianEntry, OsdiNode, OsdiNodePair, OsdiParamOpvar,
     OsdiSimInfo, OsdiSimParas, ACCESS_FLAG_SET, EVAL_RET_FLAG_FATAL, INIT_ERR_OUT_OF_BOUNDS,
@@ -246,6 +246,8 @@ impl ModelImpl for OsdiModel {
             descriptor: self.descriptor,
             data: alloc(self.descriptor.instance_size as usize),
             model_data: self.data,
+            prev_states: vec![0.0; self.descriptor.num_states as usize].into_boxed_slice(),
+            next_states: vec![0.0; self.descriptor.num_states as usize].into_boxed_slice(),
             _model: self,
         })
     }
@@ -255,6 +257,11 @@ struct OsdiInstance {
     descriptor: &'static OsdiDescriptor,
     data: *mut c_void,
     model_data: *mut c_void,
+    /// The states of `$limit` and analog operators at the last accepted time point
+    prev_states: Box<[f64]>,
+    /// The states written by the last evaluation, they replace `prev_states`
+    /// once its time point is accepted
+    next_states: Box<[f64]>,
     _model: Rc<OsdiModel>, // only kept to ensure the data stays live
 }
 
@@ -283,6 +290,15 @@ impl OsdiInstance {
         }
     }
 
+    fn state_idx(&self) -> &[Cell<u32>] {
+        let ptr = self.data as *mut u8;
+        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        unsafe {
+            let ptr = ptr.add(self.descriptor.state_idx_off as usize) as *mut Cell<u32>;
+            slice::from_raw_parts_mut(ptr, self.descriptor.num_states as usize)
+        }
+    }
+
     fn collapsed(&self) -> &[bool] {
         let ptr = self.data as *mut u8;
         // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
@@ -412,6 +428,10 @@ impl InstanceImpl for OsdiInstance {
             sim_builder.ensure_matrix_entry(column, row)
         }
 
+        for (i, idx) in self.state_idx().iter().enumerate() {
+            idx.set(i as u32)
+        }
+
         Ok(())
     }
 
@@ -459,7 +479,7 @@ impl InstanceImpl for OsdiInstance {
         }
     }
 
-    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<()> {
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<EvalRetFlags> {
         let sim_params = OsdiSimParas {
             names: &mut ptr::null_mut(),
             vals: ptr::null_mut(),
@@ -471,8 +491,8 @@ impl InstanceImpl for OsdiInstance {
             paras: sim_params,
             abstime: sim_info.abstime,
             prev_solve: sim_info.prev_solve.as_ptr() as *mut f64,
-            prev_state: ptr::null_mut(),
-            next_state: ptr::null_mut(),
+            prev_state: self.prev_states.as_mut_ptr(),
+            next_state: self.next_states.as_mut_ptr(),
             flags: sim_info.flags.bits(),
         };
 
@@ -486,12 +506,25 @@ impl InstanceImpl for OsdiInstance {
             bail!("Simulation aborted with $fatal")
         }
 
-        // TODO only during tran
-        // if (ret_flags & EVAL_RET_FLAG_FINISH) != 0 {
-        //     bail!("Simulation aborted with $finish")
-        // }
+        // $finish and $stop only end transient analyses, the simulation decides what to do
+        Ok(EvalRetFlags::from_bits_truncate(ret_flags))
+    }
 
-        Ok(())
+    fn bound_step(&self) -> f64 {
+        let offset = self.descriptor.bound_step_offset;
+        if offset == u32::MAX {
+            return f64::INFINITY;
+        }
+        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        unsafe { *((self.data as *mut u8).add(offset as usize) as *const f64) }
+    }
+
+    fn accept_step(&mut self) {
+        swap(&mut self.prev_states, &mut self.next_states)
+    }
+
+    fn reject_step(&mut self) {
+        self.next_states.copy_from_slice(&self.prev_states)
     }
 
     unsafe fn load_matrix_resist(&self) {
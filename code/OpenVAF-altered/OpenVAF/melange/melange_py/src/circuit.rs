@@ -0,0 +1,451 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::{ptr, slice, str};
+
+use anyhow::{bail, Result};
+use libc::c_void;
+use melange_core::circuit::{DeviceId, InstanceId, Node};
+use melange_core::{Arena, Circuit, Expr};
+use pyffi_utils::new_type;
+use pyffi_utils::unicode::OsStr;
+use pyo3_ffi::structmember::{PyMemberDef, READONLY, T_OBJECT};
+use pyo3_ffi::*;
+
+use crate::errors::{raise_error, raise_runtime_exception, raise_type_exception};
+use crate::simulation::PySimulation;
+
+pub static mut CIRCUIT_TY: PyTypeObject = {
+    let mut res = new_type::<PyCircuit>();
+    res.tp_name = "melange.Circuit\0".as_ptr() as *const c_char;
+    res.tp_doc = "A circuit that can be simulated with melange\0".as_ptr() as *const c_char;
+    res.tp_members = unsafe { &mut CIRCUIT_MEMBERS } as *mut _;
+    res.tp_methods = unsafe { &mut CIRCUIT_METHODS } as *mut _;
+    res.tp_new = Some(PyCircuit::new);
+    res.tp_dealloc = Some(PyCircuit::dealloc);
+    res
+};
+
+static mut CIRCUIT_MEMBERS: [PyMemberDef; 2] = [
+    PyMemberDef {
+        name: "name\0".as_ptr() as *mut c_char,
+        type_code: T_OBJECT,
+        offset: PyCircuit::offset_to.name as isize,
+        flags: READONLY,
+        doc: "The name of the circuit\0".as_ptr() as *mut c_char,
+    },
+    unsafe { zero!(PyMemberDef) },
+];
+
+static mut CIRCUIT_METHODS: [PyMethodDef; 4] = [
+    PyMethodDef {
+        ml_name: "load_veriloga_file\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PyCircuit::load_veriloga_file },
+        ml_flags: METH_O,
+        ml_doc: "compiles a Verilog-A file and makes all contained modules available as devices.\nReturns the names of the new devices.\0".as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "load_osdi_file\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PyCircuit::load_osdi_file },
+        ml_flags: METH_O,
+        ml_doc: "loads a compiled OSDI library and makes all contained devices available.\nReturns the names of the new devices.\0".as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "prepare_sim\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PyCircuit::prepare_sim },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "creates a Simulation of this circuit.\nThe temperature `temp` (in Kelvin) and the values of all circuit parameters are passed as keyword arguments.\nEach value can either be a number or a 1D numpy array. All simulations are performed for every element of the arrays.\nThe circuit can not be modified while a Simulation of it exists.\0".as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+with_offsets! {
+    #[repr(C)]
+    pub struct PyCircuit {
+        ob_base: PyObject,
+        name: *mut PyObject,
+        pub arena: Arena,
+        pub circ: Circuit,
+        /// The number of simulations that currently borrow `circ`
+        pub simulations: usize,
+    }
+}
+
+impl PyCircuit {
+    unsafe extern "C" fn new(
+        subtype: *mut PyTypeObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let mut name: *const c_char = ptr::null();
+        let mut kwlist = ["name\0".as_ptr() as *mut c_char, ptr::null_mut()];
+        if PyArg_ParseTupleAndKeywords(
+            args,
+            kwds,
+            "s:Circuit\0".as_ptr() as *const c_char,
+            kwlist.as_mut_ptr(),
+            &mut name,
+        ) == 0
+        {
+            return ptr::null_mut();
+        }
+
+        let ptr = (*subtype).tp_alloc.unwrap()(subtype, 0);
+        if ptr.is_null() {
+            return ptr::null_mut();
+        }
+
+        let res = &mut *(ptr as *mut Self);
+        let mut arena = Arena::new();
+        let circ = Circuit::new(str_arg(name).to_owned(), &mut arena);
+        ptr::write(&mut res.arena, arena);
+        ptr::write(&mut res.circ, circ);
+        res.name = PyUnicode_FromString(name);
+        res.simulations = 0;
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(sel: *mut PyObject) {
+        let sel_ = &mut *(sel as *mut Self);
+        Py_XDECREF(sel_.name);
+        ptr::drop_in_place(&mut sel_.circ);
+        ptr::drop_in_place(&mut sel_.arena);
+        (*ob_type!(sel)).tp_free.unwrap()(sel as *mut c_void);
+    }
+
+    /// Raises an exception if `sel` is borrowed by a simulation
+    pub unsafe fn ensure_mutable(sel: *mut PyObject, fun: &str) -> bool {
+        // only read the counter: simulations hold shared references to other fields
+        let simulations = ptr::addr_of!((*(sel as *mut Self)).simulations).read();
+        if simulations != 0 {
+            raise_runtime_exception(&format!(
+                "{fun}() can not modify the circuit while a Simulation of it exists"
+            ));
+            return false;
+        }
+        true
+    }
+
+    unsafe extern "C" fn load_veriloga_file(
+        sel: *mut PyObject,
+        path: *mut PyObject,
+    ) -> *mut PyObject {
+        if !Self::ensure_mutable(sel, "load_veriloga_file") {
+            return ptr::null_mut();
+        }
+        let sel = &mut *(sel as *mut Self);
+        let path = match OsStr::new_path(path) {
+            Some(Some(path)) => path,
+            Some(None) => {
+                return raise_type_exception(
+                    "load_veriloga_file() argument 'path' must be a pathlib Path or str",
+                )
+            }
+            None => return ptr::null_mut(),
+        };
+        let devices = sel.circ.load_veriloga_file(path.as_str().into(), &Default::default());
+        sel.device_names(devices)
+    }
+
+    unsafe extern "C" fn load_osdi_file(sel: *mut PyObject, path: *mut PyObject) -> *mut PyObject {
+        if !Self::ensure_mutable(sel, "load_osdi_file") {
+            return ptr::null_mut();
+        }
+        let sel = &mut *(sel as *mut Self);
+        let path = match OsStr::new_path(path) {
+            Some(Some(path)) => path,
+            Some(None) => {
+                return raise_type_exception(
+                    "load_osdi_file() argument 'path' must be a pathlib Path or str",
+                )
+            }
+            None => return ptr::null_mut(),
+        };
+        let devices = sel.circ.load_osdi_file(path.as_str().into());
+        sel.device_names(devices)
+    }
+
+    unsafe fn device_names(&self, devices: Result<Vec<DeviceId>>) -> *mut PyObject {
+        let devices = match devices {
+            Ok(devices) => devices,
+            Err(err) => return raise_error(err),
+        };
+        let res = PyList_New(devices.len() as isize);
+        for (i, dev) in devices.into_iter().enumerate() {
+            let name = self.circ[dev].name;
+            let name =
+                PyUnicode_FromStringAndSize(name.as_ptr() as *const c_char, name.len() as isize);
+            PyList_SetItem(res, i as isize, name);
+        }
+        res
+    }
+
+    unsafe extern "C" fn prepare_sim(
+        sel: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let arg_cnt = PyTuple_GET_SIZE(args);
+        if arg_cnt != 0 {
+            return raise_type_exception(&format!(
+                "prepare_sim() {arg_cnt} unexpected positional arguments"
+            ));
+        }
+        PySimulation::new(sel, kwds)
+    }
+}
+
+pub static mut CIRCUIT_INSTANCE_TY: PyTypeObject = {
+    let mut res = new_type::<PyCircuitInstance>();
+    res.tp_name = "melange.CircuitInstance\0".as_ptr() as *const c_char;
+    res.tp_doc = "An instance of a device (or model) within a Circuit\0".as_ptr() as *const c_char;
+    res.tp_members = unsafe { &mut CIRCUIT_INSTANCE_MEMBERS } as *mut _;
+    res.tp_methods = unsafe { &mut CIRCUIT_INSTANCE_METHODS } as *mut _;
+    res.tp_new = Some(PyCircuitInstance::new);
+    res.tp_dealloc = Some(PyCircuitInstance::dealloc);
+    res
+};
+
+static mut CIRCUIT_INSTANCE_MEMBERS: [PyMemberDef; 3] = [
+    PyMemberDef {
+        name: "name\0".as_ptr() as *mut c_char,
+        type_code: T_OBJECT,
+        offset: PyCircuitInstance::offset_to.name as isize,
+        flags: READONLY,
+        doc: "The name of the instance\0".as_ptr() as *mut c_char,
+    },
+    PyMemberDef {
+        name: "circuit\0".as_ptr() as *mut c_char,
+        type_code: T_OBJECT,
+        offset: PyCircuitInstance::offset_to.circ as isize,
+        flags: READONLY,
+        doc: "The circuit that contains the instance\0".as_ptr() as *mut c_char,
+    },
+    unsafe { zero!(PyMemberDef) },
+];
+
+static mut CIRCUIT_INSTANCE_METHODS: [PyMethodDef; 2] = [
+    PyMethodDef {
+        ml_name: "set_param\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PyCircuitInstance::set_param },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "sets the value of an instance parameter.\nThe value can be a number or a str. A str is the name of a circuit parameter unless the instance parameter itself is a string.\nCircuit parameters are created when they are first used and their values are provided to Circuit.prepare_sim.\0".as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+with_offsets! {
+    #[repr(C)]
+    pub struct PyCircuitInstance {
+        ob_base: PyObject,
+        circ: *mut PyObject,
+        name: *mut PyObject,
+        instance: InstanceId,
+    }
+}
+
+impl PyCircuitInstance {
+    unsafe extern "C" fn new(
+        subtype: *mut PyTypeObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let mut circ: *mut PyObject = ptr::null_mut();
+        let mut name: *const c_char = ptr::null();
+        let mut device: *const c_char = ptr::null();
+        let mut ports: *mut PyObject = ptr::null_mut();
+        let mut kwlist = [
+            "circuit\0".as_ptr() as *mut c_char,
+            "name\0".as_ptr() as *mut c_char,
+            "device\0".as_ptr() as *mut c_char,
+            "ports\0".as_ptr() as *mut c_char,
+            ptr::null_mut(),
+        ];
+        if PyArg_ParseTupleAndKeywords(
+            args,
+            kwds,
+            "O!ssO:CircuitInstance\0".as_ptr() as *const c_char,
+            kwlist.as_mut_ptr(),
+            ptr::addr_of_mut!(CIRCUIT_TY),
+            &mut circ,
+            &mut name,
+            &mut device,
+            &mut ports,
+        ) == 0
+        {
+            return ptr::null_mut();
+        }
+
+        if !PyCircuit::ensure_mutable(circ, "CircuitInstance") {
+            return ptr::null_mut();
+        }
+
+        let ports = match str_list(ports) {
+            Some(ports) => ports,
+            None => {
+                return raise_type_exception(
+                    "CircuitInstance() argument 'ports' must be a sequence of str",
+                )
+            }
+        };
+
+        let py_circ = &mut *(circ as *mut PyCircuit);
+        let instance = match new_instance(&mut py_circ.circ, str_arg(name), str_arg(device), ports)
+        {
+            Ok(instance) => instance,
+            Err(err) => return raise_error(err),
+        };
+
+        let ptr = (*subtype).tp_alloc.unwrap()(subtype, 0);
+        if ptr.is_null() {
+            return ptr::null_mut();
+        }
+
+        let res = &mut *(ptr as *mut Self);
+        Py_INCREF(circ);
+        res.circ = circ;
+        res.name = PyUnicode_FromString(name);
+        res.instance = instance;
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(sel: *mut PyObject) {
+        let sel_ = &mut *(sel as *mut Self);
+        Py_XDECREF(sel_.circ);
+        Py_XDECREF(sel_.name);
+        (*ob_type!(sel)).tp_free.unwrap()(sel as *mut c_void);
+    }
+
+    unsafe extern "C" fn set_param(
+        sel: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        let mut name: *const c_char = ptr::null();
+        let mut val: *mut PyObject = ptr::null_mut();
+        let mut kwlist =
+            ["name\0".as_ptr() as *mut c_char, "value\0".as_ptr() as *mut c_char, ptr::null_mut()];
+        if PyArg_ParseTupleAndKeywords(
+            args,
+            kwds,
+            "sO:set_param\0".as_ptr() as *const c_char,
+            kwlist.as_mut_ptr(),
+            &mut name,
+            &mut val,
+        ) == 0
+        {
+            return ptr::null_mut();
+        }
+
+        if !PyCircuit::ensure_mutable(sel.circ, "set_param") {
+            return ptr::null_mut();
+        }
+
+        let name = str_arg(name);
+        let PyCircuit { arena, circ, .. } = &mut *(sel.circ as *mut PyCircuit);
+        let val = if PyFloat_Check(val) != 0 {
+            PyFloat_AsDouble(val).into()
+        } else if PyLong_Check(val) != 0 {
+            let val = PyLong_AsDouble(val);
+            if !PyErr_Occurred().is_null() {
+                return ptr::null_mut();
+            }
+            val.into()
+        } else if let Some(val) = str_obj(val) {
+            let dev = circ[circ[sel.instance].model].device;
+            if circ.is_str_param(dev, name) {
+                Expr::str(arena, val)
+            } else {
+                match circ_param(circ, arena, val) {
+                    Ok(val) => val,
+                    Err(err) => return raise_error(err),
+                }
+            }
+        } else {
+            return raise_type_exception(&format!(
+                "set_param() value of '{name}' must be a float, int or str"
+            ));
+        };
+
+        if let Err(err) = circ.set_instance_param(sel.instance, name, val) {
+            return raise_error(err);
+        }
+
+        let none = Py_None();
+        Py_INCREF(none);
+        none
+    }
+}
+
+/// Creates an instance of the model or device `master` connected to the nodes `ports`.
+/// The nodes `gnd` and `0` are aliases for the ground node.
+fn new_instance(
+    circ: &mut Circuit,
+    name: &str,
+    master: &str,
+    ports: Vec<String>,
+) -> Result<InstanceId> {
+    let nodes: Vec<Node> = ports
+        .into_iter()
+        .map(|port| match &*port {
+            "0" | "gnd" => circ.node("ground".to_owned()),
+            _ => circ.node(port),
+        })
+        .collect();
+    if let Some(model) = circ.lookup_model(master) {
+        return circ.new_model_instance(name.to_owned(), model, nodes);
+    }
+    let Some(dev) = circ.lookup_device(master) else { bail!("unknown device or model '{master}'") };
+    let (instance, _) = circ.new_device_instance(name.to_owned(), dev, nodes)?;
+    Ok(instance)
+}
+
+/// Returns an expression that reads the circuit parameter `name` (which is created if it does not
+/// exist yet)
+fn circ_param(circ: &mut Circuit, arena: &mut Arena, name: &str) -> Result<Expr> {
+    if let Some((_, val)) = circ.lookup_param(name, arena) {
+        return Ok(val);
+    }
+    let (_, val) = circ.def_param(name.to_owned(), None, arena)?;
+    Ok(val)
+}
+
+/// Converts a string returned by `PyArg_ParseTuple` (`s` format)
+pub unsafe fn str_arg<'a>(ptr: *const c_char) -> &'a str {
+    // python guarantees that the string is valid utf-8
+    str::from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())
+}
+
+/// Converts a python `str` object. Returns `None` if `obj` is not a `str`.
+pub unsafe fn str_obj<'a>(obj: *mut PyObject) -> Option<&'a str> {
+    if PyUnicode_Check(obj) == 0 {
+        return None;
+    }
+    let mut size = 0;
+    let ptr = PyUnicode_AsUTF8AndSize(obj, &mut size) as *const u8;
+    if ptr.is_null() {
+        PyErr_Clear();
+        return None;
+    }
+    Some(str::from_utf8_unchecked(slice::from_raw_parts(ptr, size as usize)))
+}
+
+/// Converts a sequence of python `str` objects
+unsafe fn str_list(seq: *mut PyObject) -> Option<Vec<String>> {
+    if PySequence_Check(seq) == 0 || PyUnicode_Check(seq) != 0 {
+        return None;
+    }
+    let len = PySequence_Size(seq);
+    let mut res = Vec::with_capacity(len.max(0) as usize);
+    for i in 0..len {
+        let item = PySequence_GetItem(seq, i);
+        if item.is_null() {
+            PyErr_Clear();
+            return None;
+        }
+        let val = str_obj(item).map(str::to_owned);
+        Py_DECREF(item);
+        res.push(val?);
+    }
+    Some(res)
+}
@@ -0,0 +1,31 @@
+use std::os::raw::c_char;
+
+use pyo3_ffi::*;
+
+#[cold]
+#[inline(never)]
+unsafe fn raise_exception(exception: *mut PyObject, msg: &str) -> *mut PyObject {
+    let err_msg = PyUnicode_FromStringAndSize(msg.as_ptr() as *const c_char, msg.len() as isize);
+    PyErr_SetObject(exception, err_msg);
+    Py_DECREF(err_msg);
+    std::ptr::null_mut()
+}
+
+#[cold]
+#[inline(never)]
+pub fn raise_type_exception(msg: &str) -> *mut PyObject {
+    unsafe { raise_exception(PyExc_TypeError, msg) }
+}
+
+#[cold]
+#[inline(never)]
+pub fn raise_runtime_exception(msg: &str) -> *mut PyObject {
+    unsafe { raise_exception(PyExc_RuntimeError, msg) }
+}
+
+/// Raises a `RuntimeError` that contains `err` and all its causes
+#[cold]
+#[inline(never)]
+pub fn raise_error(err: anyhow::Error) -> *mut PyObject {
+    raise_runtime_exception(&format!("{err:#}"))
+}
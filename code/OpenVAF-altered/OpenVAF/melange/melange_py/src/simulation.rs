@@ -0,0 +1,469 @@
+use std::f64::consts::PI;
+use std::mem::size_of;
+use std::os::raw::c_char;
+use std::ptr;
+
+use anyhow::{bail, Context, Result};
+use libc::c_void;
+use melange_core::circuit::{InstanceId, Node};
+use melange_core::simulation::{SimConfig, Simulation};
+use melange_core::{Arena, Circuit, CircuitParam, ExprEvalCtx, Value};
+use num_complex::Complex64;
+use pyffi_utils::new_type;
+use pyffi_utils::numpy::{ItemType, NumpyArray};
+use pyo3_ffi::*;
+use typed_index_collections::TiVec;
+
+use crate::circuit::{str_arg, str_obj, PyCircuit};
+use crate::errors::{raise_error, raise_runtime_exception, raise_type_exception};
+use crate::typeref::{NUMPY_API, NUMPY_ARR_TYPE, NUMPY_CDOUBLE_DESCR, NUMPY_DOUBLE_DESCR};
+
+pub static mut SIMULATION_TY: PyTypeObject = {
+    let mut res = new_type::<PySimulation>();
+    res.tp_name = "melange.Simulation\0".as_ptr() as *const c_char;
+    res.tp_doc =
+        "A simulation of a Circuit created with Circuit.prepare_sim\0".as_ptr() as *const c_char;
+    res.tp_methods = unsafe { &mut SIMULATION_METHODS } as *mut _;
+    res.tp_dealloc = Some(PySimulation::dealloc);
+    res
+};
+
+static mut SIMULATION_METHODS: [PyMethodDef; 7] = [
+    PyMethodDef {
+        ml_name: "dc_op\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PySimulation::dc_op },
+        ml_flags: METH_NOARGS,
+        ml_doc: "calculates the DC operating point for each set of parameters\0".as_ptr()
+            as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "voltage\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PySimulation::voltage },
+        ml_flags: METH_O,
+        ml_doc: "returns the voltage of a node at the DC operating points (as a numpy array)\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "lead_current\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PySimulation::lead_current },
+        ml_flags: METH_VARARGS,
+        ml_doc: "lead_current(instance, terminal)\n--\n\nreturns the current flowing into a terminal of an instance at the DC operating points (as a numpy array)\0".as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "ac\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::ac },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "ac(freq)\n--\n\nperforms a small signal analysis at the frequency `freq` (in Hz) for each set of parameters.\nThe operating points calculated by dc_op are reused.\0".as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "ac_voltage\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PySimulation::ac_voltage },
+        ml_flags: METH_O,
+        ml_doc: "returns the complex small signal voltage of a node (as a numpy array)\0".as_ptr()
+            as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "ac_lead_current\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PySimulation::ac_lead_current },
+        ml_flags: METH_VARARGS,
+        ml_doc: "ac_lead_current(instance, terminal)\n--\n\nreturns the complex small signal current flowing into a terminal of an instance (as a numpy array)\0".as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+/// The value of a circuit parameter, either constant or different for each point of the sweep
+enum SweepVal {
+    Scalar(f64),
+    Array(Box<[f64]>),
+}
+
+#[repr(C)]
+pub struct PySimulation {
+    ob_base: PyObject,
+    circ: *mut PyObject,
+    // the circuit (and arena) can not be modified or freed while `circ` is borrowed
+    arena: &'static Arena,
+    sim: Simulation<'static>,
+    eval_ctx: ExprEvalCtx<'static>,
+    /// The number of parameter sets that are simulated
+    len: usize,
+    params: Vec<(CircuitParam, SweepVal)>,
+    /// The parameter set `sim` was last prepared for
+    prepared: Option<usize>,
+    dc_solutions: Vec<TiVec<Node, f64>>,
+    ac_solutions: Vec<TiVec<Node, Complex64>>,
+    ac_freq: f64,
+}
+
+impl PySimulation {
+    /// Creates a simulation of `circ` with the parameters `kwds` (passed to `Circuit.prepare_sim`)
+    pub unsafe fn new(circ: *mut PyObject, kwds: *mut PyObject) -> *mut PyObject {
+        let py_circ = circ as *mut PyCircuit;
+        let arena: &'static Arena = &*ptr::addr_of!((*py_circ).arena);
+        let circuit: &'static Circuit = &*ptr::addr_of!((*py_circ).circ);
+
+        let mut len = 1;
+        let mut params = Vec::new();
+        let mut has_temp = false;
+        if !kwds.is_null() {
+            let mut pos = 0;
+            let mut key: *mut PyObject = ptr::null_mut();
+            let mut val: *mut PyObject = ptr::null_mut();
+            while PyDict_Next(kwds, &mut pos, &mut key, &mut val) != 0 {
+                let name = match str_obj(key) {
+                    Some(name) => name,
+                    None => return raise_type_exception("prepare_sim() keywords must be str"),
+                };
+                let param = if name == "temp" {
+                    has_temp = true;
+                    CircuitParam::TEMPERATURE
+                } else {
+                    match circuit.lookup_param(name, arena) {
+                        Some((param, _)) => param,
+                        None => {
+                            return raise_type_exception(&format!(
+                                "prepare_sim() got an unexpected keyword argument '{name}' (the circuit has no such parameter)"
+                            ))
+                        }
+                    }
+                };
+                let val = match sweep_val(name, val, &mut len) {
+                    Some(val) => val,
+                    None => return ptr::null_mut(),
+                };
+                params.push((param, val));
+            }
+        }
+
+        if !has_temp {
+            return raise_type_exception("prepare_sim() missing required keyword argument 'temp'");
+        }
+
+        let sim = match circuit.setup_simulation(SimConfig::default()) {
+            Ok(sim) => sim,
+            Err(err) => return raise_error(err),
+        };
+
+        let ptr = SIMULATION_TY.tp_alloc.unwrap()(ptr::addr_of_mut!(SIMULATION_TY), 0);
+        if ptr.is_null() {
+            return ptr::null_mut();
+        }
+
+        Py_INCREF(circ);
+        *ptr::addr_of_mut!((*py_circ).simulations) += 1;
+        ptr::write(
+            ptr as *mut Self,
+            PySimulation {
+                ob_base: ptr.read(),
+                circ,
+                arena,
+                sim,
+                eval_ctx: ExprEvalCtx::new(arena),
+                len,
+                params,
+                prepared: None,
+                dc_solutions: Vec::new(),
+                ac_solutions: Vec::new(),
+                ac_freq: 0.0,
+            },
+        );
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(sel: *mut PyObject) {
+        let sel_ = sel as *mut Self;
+        let circ = (*sel_).circ;
+        // drop everything that borrows the circuit before releasing it
+        ptr::drop_in_place(ptr::addr_of_mut!((*sel_).sim));
+        ptr::drop_in_place(ptr::addr_of_mut!((*sel_).eval_ctx));
+        ptr::drop_in_place(ptr::addr_of_mut!((*sel_).params));
+        ptr::drop_in_place(ptr::addr_of_mut!((*sel_).dc_solutions));
+        ptr::drop_in_place(ptr::addr_of_mut!((*sel_).ac_solutions));
+        *ptr::addr_of_mut!((*(circ as *mut PyCircuit)).simulations) -= 1;
+        Py_DECREF(circ);
+        (*ob_type!(sel)).tp_free.unwrap()(sel as *mut c_void);
+    }
+
+    fn circuit(&self) -> &Circuit {
+        unsafe { &*ptr::addr_of!((*(self.circ as *mut PyCircuit)).circ) }
+    }
+
+    /// Evaluates the circuit parameters of the parameter set `point` and prepares `sim` for
+    /// simulating them. If a DC operating point was already calculated for `point` it is used as
+    /// the initial guess.
+    fn prepare(&mut self, point: usize) -> Result<()> {
+        if self.prepared == Some(point) {
+            return Ok(());
+        }
+        self.prepared = None;
+        for (param, val) in &self.params {
+            let val = match val {
+                SweepVal::Scalar(val) => *val,
+                SweepVal::Array(vals) => vals[point],
+            };
+            self.eval_ctx.set_param(*param, Value::Num(val));
+        }
+        self.sim.prepare_solver(self.eval_ctx.borrow(), self.arena)?;
+        if let Some(op) = self.dc_solutions.get(point) {
+            self.sim.set_initial_guess(op);
+        }
+        self.prepared = Some(point);
+        Ok(())
+    }
+
+    fn lookup_node(&self, name: &str) -> Result<Node> {
+        let name = match name {
+            "0" | "gnd" => "ground",
+            _ => name,
+        };
+        self.circuit().lookup_node(name).with_context(|| format!("unknown node '{name}'"))
+    }
+
+    /// Returns the instance `name` and the index of its `terminal`
+    fn lookup_terminal(&self, name: &str, terminal: &str) -> Result<(InstanceId, usize)> {
+        let circ = self.circuit();
+        let inst =
+            circ.lookup_instance(name).with_context(|| format!("unknown instance '{name}'"))?;
+        let dev = &circ[circ[circ[inst].model].device];
+        let Some(pos) = dev.terminals.iter().position(|&it| it == terminal) else {
+            bail!("{} has no terminal '{terminal}' (found {:?})", dev.name, dev.terminals)
+        };
+        if pos >= circ[inst].connections.len() {
+            bail!("terminal '{terminal}' of '{name}' is not connected")
+        }
+        Ok((inst, pos))
+    }
+
+    unsafe extern "C" fn dc_op(sel: *mut PyObject, _args: *mut PyObject) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        // the solutions are recalculated from scratch
+        sel.dc_solutions.clear();
+        sel.ac_solutions.clear();
+        sel.prepared = None;
+        for point in 0..sel.len {
+            let res = sel.prepare(point).and_then(|_| sel.sim.dc_op());
+            match res {
+                Ok(op) => sel.dc_solutions.push(op.to_owned()),
+                Err(err) => {
+                    sel.dc_solutions.clear();
+                    return raise_error(err);
+                }
+            }
+        }
+        let none = Py_None();
+        Py_INCREF(none);
+        none
+    }
+
+    unsafe extern "C" fn voltage(sel: *mut PyObject, node: *mut PyObject) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        let Some(node) = str_obj(node) else {
+            return raise_type_exception("voltage() argument 'node' must be a str");
+        };
+        if sel.dc_solutions.is_empty() {
+            return raise_runtime_exception("dc_op() must be called before voltage()");
+        }
+        let node = match sel.lookup_node(node) {
+            Ok(node) => node,
+            Err(err) => return raise_error(err),
+        };
+        let res: Vec<f64> = sel.dc_solutions.iter().map(|op| op[node]).collect();
+        new_array(&res, NUMPY_DOUBLE_DESCR)
+    }
+
+    unsafe extern "C" fn lead_current(sel: *mut PyObject, args: *mut PyObject) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        let mut inst: *const c_char = ptr::null();
+        let mut terminal: *const c_char = ptr::null();
+        if PyArg_ParseTuple(
+            args,
+            "ss:lead_current\0".as_ptr() as *const c_char,
+            &mut inst,
+            &mut terminal,
+        ) == 0
+        {
+            return ptr::null_mut();
+        }
+
+        let res = sel.lookup_terminal(str_arg(inst), str_arg(terminal)).and_then(|(inst, pos)| {
+            (0..sel.len)
+                .map(|point| {
+                    sel.prepare(point)?;
+                    Ok(sel.sim.dc_lead_current(inst)?[pos])
+                })
+                .collect::<Result<Vec<_>>>()
+        });
+        match res {
+            Ok(res) => new_array(&res, NUMPY_DOUBLE_DESCR),
+            Err(err) => raise_error(err),
+        }
+    }
+
+    unsafe extern "C" fn ac(
+        sel: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        let mut freq = 0f64;
+        let mut kwlist = ["freq\0".as_ptr() as *mut c_char, ptr::null_mut()];
+        if PyArg_ParseTupleAndKeywords(
+            args,
+            kwds,
+            "d:ac\0".as_ptr() as *const c_char,
+            kwlist.as_mut_ptr(),
+            &mut freq,
+        ) == 0
+        {
+            return ptr::null_mut();
+        }
+
+        sel.ac_solutions.clear();
+        sel.ac_freq = freq;
+        for point in 0..sel.len {
+            let res = sel.prepare(point).and_then(|_| {
+                sel.sim.set_omega(2.0 * PI * freq);
+                sel.sim.ac()
+            });
+            match res {
+                Ok(solution) => sel.ac_solutions.push(solution.to_owned()),
+                Err(err) => {
+                    sel.ac_solutions.clear();
+                    return raise_error(err);
+                }
+            }
+        }
+        let none = Py_None();
+        Py_INCREF(none);
+        none
+    }
+
+    unsafe extern "C" fn ac_voltage(sel: *mut PyObject, node: *mut PyObject) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        let Some(node) = str_obj(node) else {
+            return raise_type_exception("ac_voltage() argument 'node' must be a str");
+        };
+        if sel.ac_solutions.is_empty() {
+            return raise_runtime_exception("ac() must be called before ac_voltage()");
+        }
+        let node = match sel.lookup_node(node) {
+            Ok(node) => node,
+            Err(err) => return raise_error(err),
+        };
+        let res: Vec<Complex64> = sel.ac_solutions.iter().map(|solution| solution[node]).collect();
+        new_array(&res, NUMPY_CDOUBLE_DESCR)
+    }
+
+    unsafe extern "C" fn ac_lead_current(sel: *mut PyObject, args: *mut PyObject) -> *mut PyObject {
+        let sel = &mut *(sel as *mut Self);
+        let mut inst: *const c_char = ptr::null();
+        let mut terminal: *const c_char = ptr::null();
+        if PyArg_ParseTuple(
+            args,
+            "ss:ac_lead_current\0".as_ptr() as *const c_char,
+            &mut inst,
+            &mut terminal,
+        ) == 0
+        {
+            return ptr::null_mut();
+        }
+        if sel.ac_solutions.is_empty() {
+            return raise_runtime_exception("ac() must be called before ac_lead_current()");
+        }
+
+        let omega = 2.0 * PI * sel.ac_freq;
+        let res = sel.lookup_terminal(str_arg(inst), str_arg(terminal)).and_then(|(inst, pos)| {
+            (0..sel.len)
+                .map(|point| {
+                    sel.prepare(point)?;
+                    sel.sim.set_omega(omega);
+                    Ok(sel.sim.ac_lead_current(inst)?[pos])
+                })
+                .collect::<Result<Vec<_>>>()
+        });
+        match res {
+            Ok(res) => new_array(&res, NUMPY_CDOUBLE_DESCR),
+            Err(err) => raise_error(err),
+        }
+    }
+}
+
+/// Reads the value of the circuit parameter `name` passed to `prepare_sim`. All arrays must have
+/// the same length `len` (`1` if no array was found yet).
+unsafe fn sweep_val(name: &str, val: *mut PyObject, len: &mut usize) -> Option<SweepVal> {
+    if PyFloat_Check(val) != 0 {
+        return Some(SweepVal::Scalar(PyFloat_AsDouble(val)));
+    }
+    if PyLong_Check(val) != 0 {
+        let val = PyLong_AsDouble(val);
+        if !PyErr_Occurred().is_null() {
+            return None;
+        }
+        return Some(SweepVal::Scalar(val));
+    }
+    if NUMPY_ARR_TYPE != Some(ob_type!(val)) {
+        raise_type_exception(&format!(
+            "prepare_sim() '{name}' must be a float, int or numpy array"
+        ));
+        return None;
+    }
+
+    let arr = match NumpyArray::new(val) {
+        Ok(arr) => arr,
+        Err(_) => {
+            raise_type_exception(&format!(
+                "prepare_sim() '{name}' must be a one dimensional numpy array of numbers"
+            ));
+            return None;
+        }
+    };
+    let data = arr.data();
+    let stride = arr.stride();
+    let vals: Box<[f64]> = (0..arr.len())
+        .map(|i| match arr.kind {
+            ItemType::Float => *(data as *const f64).offset(i * stride),
+            ItemType::Int => *(data as *const i32).offset(i * stride) as f64,
+            ItemType::Long => *(data as *const i64).offset(i * stride) as f64,
+        })
+        .collect();
+
+    if vals.len() == 1 {
+        return Some(SweepVal::Scalar(vals[0]));
+    }
+    if *len == 1 {
+        *len = vals.len();
+    } else if *len != vals.len() {
+        raise_type_exception(&format!(
+            "prepare_sim() all arrays must have the same length but '{name}' has length {} (expected {len})",
+            vals.len()
+        ));
+        return None;
+    }
+    Some(SweepVal::Array(vals))
+}
+
+/// Creates a one dimensional numpy array with the type `descr` and the contents of `vals`
+unsafe fn new_array<T: Copy>(vals: &[T], descr: *mut PyObject) -> *mut PyObject {
+    let Some(new_arr) = NUMPY_API else {
+        return raise_runtime_exception("numpy is required to return simulation results");
+    };
+    Py_INCREF(descr);
+    let mut len = vals.len() as Py_intptr_t;
+    let mut stride = size_of::<T>() as Py_intptr_t;
+    let dst = new_arr(
+        NUMPY_ARR_TYPE.unwrap(), // base_type (normal numpy array)
+        descr,                   // type descriptor
+        1,                       //nd
+        &mut len,                //dims
+        &mut stride,             // strides
+        ptr::null_mut(),         //data (to be allocated)
+        0,                       // flags
+        ptr::null_mut(),         // obj (to be created)
+    );
+    if dst.is_null() {
+        return dst;
+    }
+    let arr = NumpyArray::new(dst).unwrap();
+    ptr::copy_nonoverlapping(vals.as_ptr(), arr.data() as *mut T, vals.len());
+    dst
+}
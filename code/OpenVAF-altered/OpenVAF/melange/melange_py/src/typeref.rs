@@ -0,0 +1,48 @@
+use std::ffi::c_void;
+use std::os::raw::c_int;
+use std::sync::Once;
+
+use pyffi_utils::typeref::{get_numpy_api, load_numpy_types};
+use pyo3_ffi::{PyObject, PyTypeObject, Py_intptr_t};
+
+const NPY_DOUBLE: c_int = 12;
+const NPY_CDOUBLE: c_int = 15;
+
+pub type PyArrayNew = extern "C" fn(
+    subtype: *mut PyTypeObject,
+    descr: *mut PyObject,
+    nd: c_int,
+    dims: *mut Py_intptr_t,
+    strides: *mut Py_intptr_t,
+    data: *mut c_void,
+    flags: c_int,
+    obj: *mut PyObject,
+) -> *mut PyObject;
+
+pub static mut NUMPY_ARR_TYPE: Option<*mut PyTypeObject> = None;
+pub static mut NUMPY_API: Option<PyArrayNew> = None;
+pub static mut NUMPY_DOUBLE_DESCR: *mut PyObject = std::ptr::null_mut();
+pub static mut NUMPY_CDOUBLE_DESCR: *mut PyObject = std::ptr::null_mut();
+
+static INIT: Once = Once::new();
+
+#[cold]
+pub fn init_typerefs() {
+    INIT.call_once(|| unsafe {
+        pyffi_utils::typeref::init_typerefs();
+        NUMPY_ARR_TYPE = load_numpy_types();
+
+        if let Some(numpy_api) = get_numpy_api() {
+            let api = *(numpy_api.offset(94) as *const PyArrayNew);
+            let py_array_descr_from_type =
+                *(numpy_api.offset(45) as *const fn(type_: c_int) -> *mut PyObject);
+
+            NUMPY_DOUBLE_DESCR = py_array_descr_from_type(NPY_DOUBLE);
+            assert!(!NUMPY_DOUBLE_DESCR.is_null());
+            NUMPY_CDOUBLE_DESCR = py_array_descr_from_type(NPY_CDOUBLE);
+            assert!(!NUMPY_CDOUBLE_DESCR.is_null());
+
+            NUMPY_API = Some(api);
+        }
+    });
+}
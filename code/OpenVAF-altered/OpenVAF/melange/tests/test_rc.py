@@ -0,0 +1,38 @@
+import numpy as np
+from melange import Circuit, CircuitInstance
+
+# a resistive divider with a capacitor at its output, swept over the source voltage
+circ = Circuit("rc_lowpass")
+vin = CircuitInstance(circ, "vin", "vsource", ports=["in", "gnd"])
+vin.set_param("dc", "vdc")
+vin.set_param("mag", 1.0)
+r1 = CircuitInstance(circ, "r1", "resistor", ports=["in", "out"])
+r1.set_param("r", 1e3)
+r2 = CircuitInstance(circ, "r2", "resistor", ports=["out", "gnd"])
+r2.set_param("r", 1e3)
+c1 = CircuitInstance(circ, "c1", "capacitor", ports=["out", "gnd"])
+c1.set_param("c", 1e-9)
+
+vdc = np.array([0.0, 1.0, 2.0])
+sim = circ.prepare_sim(temp=300.0, vdc=vdc)
+
+sim.dc_op()
+assert np.allclose(sim.voltage("out"), vdc / 2)
+assert np.allclose(sim.lead_current("r2", "A"), vdc / 2e3)
+
+freq = 1e5
+sim.ac(freq)
+zc = 1 / (2j * np.pi * freq * 1e-9)
+zp = 1e3 * zc / (1e3 + zc)
+assert np.allclose(sim.ac_voltage("out"), zp / (1e3 + zp))
+assert np.allclose(sim.ac_voltage("in"), 1.0)
+
+# the circuit is borrowed by the simulation
+try:
+    CircuitInstance(circ, "r3", "resistor", ports=["in", "gnd"])
+except RuntimeError:
+    pass
+else:
+    raise AssertionError("modifying a simulated circuit must fail")
+del sim
+CircuitInstance(circ, "r3", "resistor", ports=["in", "gnd"])
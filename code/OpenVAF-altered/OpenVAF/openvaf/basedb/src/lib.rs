@@ -14,7 +14,7 @@ pub use lint_attrs::{AttrDiagnostic, LintAttrTree, LintAttrs};
 use lints::{Lint, LintData, LintLevel, LintRegistry};
 use parking_lot::RwLock;
 use salsa::Durability;
-use syntax::sourcemap::SourceMap;
+use syntax::sourcemap::{FileSpan, SourceMap};
 use syntax::{Parse, Preprocess, SourceFile, SourceProvider, TextRange, TextSize};
 use typed_index_collections::{TiSlice, TiVec};
 pub use vfs::{AbsPathBuf, FileId, FileReadError, Vfs, VfsEntry, VfsPath};
@@ -23,10 +23,13 @@ pub use vfs::{AbsPathBuf, FileId, FileReadError, Vfs, VfsEntry, VfsPath};
 use smol_str::SmolStr;
 use syntax::name::Name;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CliParamDefaultValue {
     Int(i32),
     Float(f64),
+    Str(SmolStr),
+    /// The parameter is not fixed but assumed to only take values from this range
+    Range(ParamRange),
 }
 
 impl CliParamDefaultValue {
@@ -34,25 +37,123 @@ impl CliParamDefaultValue {
         Self::Float(value)
     }
 
-    pub fn as_i32(self) -> Option<i32> {
-        match self {
+    pub fn as_i32(&self) -> Option<i32> {
+        match *self {
             CliParamDefaultValue::Int(value) => Some(value),
-            CliParamDefaultValue::Float(_) => None,
+            _ => None,
         }
     }
 
-    pub fn as_f64(self) -> Option<f64> {
-        match self {
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
             CliParamDefaultValue::Float(f) => Some(f),
-            CliParamDefaultValue::Int(_) => None,
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            CliParamDefaultValue::Str(str) => Some(str),
+            _ => None,
+        }
+    }
+
+    pub fn as_range(&self) -> Option<&ParamRange> {
+        match self {
+            CliParamDefaultValue::Range(range) => Some(range),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// A set of values a parameter is assumed to take.
+///
+/// Unlike a default the parameter is still read at runtime. The assumption allows
+/// the compiler to fold any computation that has the same result for all values
+/// in the range and setup rejects values outside of the range.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamRange {
+    /// All values between `lo` and `hi`, infinite bounds are allowed
+    Interval { lo: f64, lo_inclusive: bool, hi: f64, hi_inclusive: bool },
+    /// Exactly one of the listed values
+    OneOf(Vec<f64>),
+}
+
+impl ParamRange {
+    pub fn contains(&self, val: f64) -> bool {
+        match *self {
+            ParamRange::Interval { lo, lo_inclusive, hi, hi_inclusive } => {
+                (lo < val || lo_inclusive && lo == val) && (val < hi || hi_inclusive && hi == val)
+            }
+            ParamRange::OneOf(ref vals) => vals.contains(&val),
+        }
+    }
+}
+
+impl std::fmt::Display for ParamRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ParamRange::Interval { lo, lo_inclusive, hi, hi_inclusive } => {
+                let open = if lo_inclusive { '[' } else { '(' };
+                let close = if hi_inclusive { ']' } else { ')' };
+                write!(f, "{open}{lo}, {hi}{close}")
+            }
+            ParamRange::OneOf(ref vals) => {
+                for (i, val) in vals.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{val}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CliParamDefault {
     pub name: Name,
     pub value: CliParamDefaultValue,
+    /// Location of the default in an elision file (if it was read from a file)
+    pub span: Option<FileSpan>,
+}
+
+/// Simulator inputs that are assumed to be fixed. Reads of these inputs are
+/// replaced with the assumed value and setup checks that the simulator agrees.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SimAssumptions {
+    /// The assumed ambient temperature in Kelvin
+    pub temperature: Option<f64>,
+    /// Assumed values of `$simparam` (including `mfactor` for `$mfactor`)
+    pub simparams: Vec<(SmolStr, f64)>,
+}
+
+impl SimAssumptions {
+    pub fn simparam(&self, name: &str) -> Option<f64> {
+        self.simparams.iter().rev().find(|(param, _)| param == name).map(|&(_, val)| val)
+    }
+
+    pub fn mfactor(&self) -> Option<f64> {
+        self.simparam("mfactor")
+    }
+
+    /// The assumed simparams in the order they were first specified, later
+    /// values of the same simparam override earlier ones
+    pub fn resolved_simparams(&self) -> Vec<(&str, f64)> {
+        let mut res: Vec<(&str, f64)> = Vec::with_capacity(self.simparams.len());
+        for (name, val) in &self.simparams {
+            match res.iter_mut().find(|(param, _)| *param == name.as_str()) {
+                Some((_, dst)) => *dst = *val,
+                None => res.push((name.as_str(), *val)),
+            }
+        }
+        res
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.temperature.is_none() && self.simparams.is_empty()
+    }
 }
 
 pub trait VfsStorage {
@@ -73,6 +174,12 @@ pub trait BaseDB: VfsStorage + salsa::Database {
 
     #[salsa::input]
     fn cli_param_defaults(&self, root_file: FileId) -> Arc<[CliParamDefault]>;
+    /// Parameters that must stay settable at runtime even if they are elided
+    #[salsa::input]
+    fn cli_dynamic_params(&self, root_file: FileId) -> Arc<[Name]>;
+
+    #[salsa::input]
+    fn sim_assumptions(&self, root_file: FileId) -> Arc<SimAssumptions>;
 
     fn parse(&self, root_file: FileId) -> Parse<SourceFile>;
     fn preprocess(&self, root_file: FileId) -> Preprocess;
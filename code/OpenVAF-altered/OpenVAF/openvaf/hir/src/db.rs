@@ -88,8 +88,6 @@ impl CompilationDB {
         let root_file = vfs.ensure_file_id(root_file);
         vfs.set_file_contents(root_file, contents.into());
 
-        println!("Root file id is {:?}", root_file);
-
         let mut res =
             Self { storage: salsa::Storage::default(), vfs: Arc::new(RwLock::new(vfs)), root_file };
 
@@ -142,6 +140,8 @@ impl CompilationDB {
         res.set_global_lint_overwrites(root_file, overwrites);
         let defaults: Arc<[_]> = param_defaults.cloned().collect();
         res.set_cli_param_defaults(root_file, defaults);
+        res.set_cli_dynamic_params(root_file, Arc::new([]));
+        res.set_sim_assumptions(root_file, Arc::default());
         Ok(res)
     }
 }
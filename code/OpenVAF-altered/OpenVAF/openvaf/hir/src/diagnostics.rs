@@ -1,9 +1,11 @@
 use basedb::AstIdMap;
 use hir_def::db::HirDefDB;
+use hir_def::elision::ElisionDiagnostic;
 use hir_def::nameres::diagnostics::DefDiagnosticWrapped;
 use hir_def::nameres::{DefMap, LocalScopeId, ScopeDefItem, ScopeOrigin};
 use hir_def::DefWithBodyId;
 use hir_ty::diagnostics::InferenceDiagnosticWrapped;
+use hir_ty::elision::ElidedValueDiagnostic;
 use hir_ty::validation::{
     self, BodyValidationDiagnostic, BodyValidationDiagnosticWrapped,
     TypeValidationDiagnosticWrapped,
@@ -41,6 +43,8 @@ pub(crate) fn collect(db: &CompilationDB, root_file: FileId, sink: &mut impl Dia
     }
 
     collect_def_map(db, &def_map, root_file, &parse, &sm, &ast_id_map, sink);
+    collect_elision(db, root_file, sink);
+
     let root_scope = def_map.root();
     for child in def_map[root_scope].children.values() {
         if let ScopeOrigin::Module(module) = def_map[*child].origin {
@@ -68,6 +72,15 @@ pub(crate) fn collect(db: &CompilationDB, root_file: FileId, sink: &mut impl Dia
     }
 }
 
+pub(crate) fn collect_elision(
+    db: &CompilationDB,
+    root_file: FileId,
+    sink: &mut impl DiagnosticSink,
+) {
+    sink.add_diagnostics(&ElisionDiagnostic::collect(db, root_file), root_file, db);
+    sink.add_diagnostics(&ElidedValueDiagnostic::collect(db, root_file), root_file, db);
+}
+
 // FIXME bundle required syntax info into struct in BaseDB
 #[allow(clippy::too_many_arguments)]
 fn collect_scope(
@@ -33,8 +33,9 @@ use smol_str::SmolStr;
 use syntax::ast;
 
 pub use basedb::diagnostics::DiagnosticSink;
+pub use basedb::{ParamRange, SimAssumptions};
 pub use hir_def::body::{ConstraintValue, ParamConstraint};
-pub use hir_def::expr::CaseCond;
+pub use hir_def::expr::{CaseCond, Event, GlobalEvent};
 pub use hir_def::nameres::diagnostics::PathResolveError;
 pub use hir_def::{BuiltIn, Case, Literal, ParamSysFun, Path, Type};
 pub use hir_ty::builtin;
@@ -60,6 +61,7 @@ pub mod signatures {
         IDTMOD_NO_IC, IDT_IC, IDT_IC_ASSERT, IDT_IC_ASSERT_NATURE, IDT_IC_ASSERT_TOL, IDT_NO_IC,
         LIMIT_BUILTIN_FUNCTION, MAX_INT, MAX_REAL, NATURE_ACCESS_BRANCH, NATURE_ACCESS_NODES,
         NATURE_ACCESS_NODE_GND, NATURE_ACCESS_PORT_FLOW, SIMPARAM_DEFAULT, SIMPARAM_NO_DEFAULT,
+        SLEW_NEG_MAX, SLEW_NO_MAX,
     };
     pub use hir_ty::types::{BOOL_EQ, INT_EQ, INT_OP, REAL_EQ, REAL_OP, STR_EQ};
 }
@@ -81,10 +83,21 @@ impl CompilationUnit {
         diagnostics::collect(db, self.root_file, sink)
     }
 
+    /// Only collects the diagnostics of the parameter elisions. This is useful
+    /// when only the elided parameters changed since [`Self::diagnostics`] was called.
+    pub fn elision_diagnostics(self, db: &CompilationDB, sink: &mut impl DiagnosticSink) {
+        diagnostics::collect_elision(db, self.root_file, sink)
+    }
+
     pub fn root_file(self) -> FileId {
         self.root_file
     }
 
+    /// The simulator inputs that are assumed fixed during compilation.
+    pub fn sim_assumptions(self, db: &CompilationDB) -> Arc<SimAssumptions> {
+        db.sim_assumptions(self.root_file)
+    }
+
     pub fn test_diagnostics(&self, db: &CompilationDB) -> String {
         let mut buf = Buffer::no_color();
         {
@@ -486,6 +499,21 @@ impl Parameter {
         db.param_ty(self.id)
     }
 
+    /// Returns whether the value of this parameter was fixed on the CLI (elided)
+    pub fn is_elided(self, db: &CompilationDB) -> bool {
+        db.cli_param_defaults_by_id(db.compilation_unit().root_file())
+            .get(&self.id)
+            .map_or(false, |default| default.value.as_range().is_none())
+    }
+
+    /// Returns the range of values this parameter is assumed to take (if any).
+    /// Unlike elided parameters the value is still read at runtime.
+    pub fn assumed_range(self, db: &CompilationDB) -> Option<ParamRange> {
+        let defaults = db.cli_param_defaults_by_id(db.compilation_unit().root_file());
+        let range = defaults.get(&self.id)?.value.as_range()?.clone();
+        matches!(self.ty(db), Type::Real | Type::Integer).then_some(range)
+    }
+
     pub fn get_attr(&self, db: &CompilationDB, ast: &AstCache, name: &str) -> Option<ast::Attr> {
         ast.resolve_attribute(name, self.id.lookup(db).ast_id(db).erased())
     }
@@ -1,32 +1,184 @@
 use std::path::Path;
+use std::sync::Arc;
 
-use basedb::AbsPathBuf;
+use basedb::{AbsPathBuf, BaseDB, CliParamDefault, CliParamDefaultValue};
 use expect_test::expect_file;
 use hir::CompilationDB;
+use hir_def::elision::ElisionDiagnostic;
+use hir_ty::elision::ElidedValueDiagnostic;
 use mini_harness::{harness, Result};
 use stdx::{ignore_dev_tests, ignore_never, is_va_file, openvaf_test_data, project_root};
+use syntax::name::Name;
 
 fn integration_test(dir: &Path) -> Result {
     let name = dir.file_name().unwrap().to_str().unwrap().to_lowercase();
     let main_file = dir.join(format!("{name}.va"));
 
-    let db =
-        CompilationDB::new_fs(AbsPathBuf::assert(main_file.canonicalize().unwrap()), &[], &[], &[])
-            .unwrap();
+    let db = CompilationDB::new_fs(
+        AbsPathBuf::assert(main_file.canonicalize().unwrap()),
+        &[],
+        &[],
+        &[],
+        &[],
+    )
+    .unwrap();
     expect_file![dir.join("frontend.log")].assert_eq(&db.compilation_unit().test_diagnostics(&db));
 
     Ok(())
 }
 
 fn ui_test(file: &Path) -> Result {
-    let db = CompilationDB::new_fs(AbsPathBuf::assert(file.canonicalize().unwrap()), &[], &[], &[])
-        .unwrap();
+    let db =
+        CompilationDB::new_fs(AbsPathBuf::assert(file.canonicalize().unwrap()), &[], &[], &[], &[])
+            .unwrap();
     let actual = db.compilation_unit().test_diagnostics(&db);
     expect_file![file.with_extension("log")].assert_eq(&actual);
     Ok(())
 }
 
+fn elision_diagnostics() -> Result {
+    let mut db = CompilationDB::new_virtual(
+        r#"
+module foo;
+    parameter real vth0 = 0.4;
+    parameter real toxe = 4e-9;
+endmodule
+
+module bar;
+    parameter real vth0 = 0.4;
+endmodule
+"#,
+    )
+    .unwrap();
+
+    let default = |name, val| CliParamDefault {
+        name: Name::resolve(name),
+        value: CliParamDefaultValue::Float(val),
+        span: None,
+    };
+    let root_file = db.compilation_unit().root_file();
+    let defaults: Arc<[_]> = vec![
+        default("vth0", 0.5),
+        default("tox", 4e-9),
+        default("toxe", 4e-9),
+        default("toxe", 5e-9),
+        default("xyz", 1.0),
+    ]
+    .into();
+    db.set_cli_param_defaults(root_file, defaults);
+
+    let actual = ElisionDiagnostic::collect(&db, root_file);
+    let expected = vec![
+        ElisionDiagnostic::AmbiguousParam {
+            name: Name::resolve("vth0"),
+            span: None,
+            modules: vec![Name::resolve("foo"), Name::resolve("bar")],
+        },
+        ElisionDiagnostic::UnknownParam {
+            name: Name::resolve("tox"),
+            span: None,
+            candidate: Some(Name::resolve("toxe")),
+        },
+        ElisionDiagnostic::Duplicate { name: Name::resolve("toxe"), span: None, prev: None },
+        ElisionDiagnostic::UnknownParam { name: Name::resolve("xyz"), span: None, candidate: None },
+        ElisionDiagnostic::Summary { applied: 2, ignored: 3, kept: 0 },
+    ];
+    assert_eq!(actual, expected);
+
+    // the elided value is used for every module that declares the parameter
+    let resolved = db.cli_param_defaults_by_id(root_file);
+    assert_eq!(resolved.len(), 3);
+    Ok(())
+}
+
+fn dynamic_params() -> Result {
+    let mut db = CompilationDB::new_virtual(
+        r#"
+module foo;
+    parameter real vth0 = 0.4;
+    parameter real u0 = 0.04;
+    parameter real toxe = 4e-9;
+endmodule
+
+// kept dynamic parameters are not elided and therefore never ambiguous
+module bar;
+    parameter real vth0 = 0.3;
+endmodule
+"#,
+    )
+    .unwrap();
+
+    let default = |name, val| CliParamDefault {
+        name: Name::resolve(name),
+        value: CliParamDefaultValue::Float(val),
+        span: None,
+    };
+    let root_file = db.compilation_unit().root_file();
+    let defaults: Arc<[_]> =
+        vec![default("vth0", 0.5), default("u0", 0.03), default("toxe", 5e-9)].into();
+    db.set_cli_param_defaults(root_file, defaults);
+    let dynamic: Arc<[_]> = vec![Name::resolve("vth0"), Name::resolve("u1")].into();
+    db.set_cli_dynamic_params(root_file, dynamic);
+
+    let actual = ElisionDiagnostic::collect(&db, root_file);
+    let expected = vec![
+        ElisionDiagnostic::UnknownDynamicParam {
+            name: Name::resolve("u1"),
+            candidate: Some(Name::resolve("u0")),
+        },
+        ElisionDiagnostic::Summary { applied: 2, ignored: 0, kept: 1 },
+    ];
+    assert_eq!(actual, expected);
+
+    let resolved = db.cli_param_defaults_by_id(root_file);
+    let mut names: Vec<_> = resolved.values().map(|default| default.name.clone()).collect();
+    names.sort();
+    assert_eq!(names, vec![Name::resolve("toxe"), Name::resolve("u0")]);
+    Ok(())
+}
+
+fn elided_value_diagnostics() -> Result {
+    let mut db = CompilationDB::new_virtual(
+        r#"
+module foo;
+    parameter real r = 1.0 from (0:inf);
+    parameter real g = 1.0 from (0:inf);
+    parameter integer level = 1 from [1:3] exclude 2;
+    parameter real s = 1.0;
+endmodule
+"#,
+    )
+    .unwrap();
+
+    let default = |name, value| CliParamDefault { name: Name::resolve(name), value, span: None };
+    let root_file = db.compilation_unit().root_file();
+    let defaults: Arc<[_]> = vec![
+        default("r", CliParamDefaultValue::Int(2)),
+        default("g", CliParamDefaultValue::Float(-1.0)),
+        default("level", CliParamDefaultValue::Float(2.0)),
+        default("s", CliParamDefaultValue::Str("foo".into())),
+    ]
+    .into();
+    db.set_cli_param_defaults(root_file, defaults);
+
+    let actual = ElidedValueDiagnostic::collect(&db, root_file);
+    let summary: Vec<_> = actual
+        .iter()
+        .map(|diag| match diag {
+            ElidedValueDiagnostic::OutOfBounds { name, kind, .. } => format!("{name}: {kind:?}"),
+            ElidedValueDiagnostic::TypeMismatch { name, expected, .. } => {
+                format!("{name}: expected {expected}")
+            }
+        })
+        .collect();
+    assert_eq!(summary, ["g: From", "level: Exclude", "s: expected real"]);
+    Ok(())
+}
+
 harness! {
     Test::from_dir_filtered("integration", &integration_test, &Path::is_dir, &ignore_dev_tests, &project_root().join("integration_tests")),
-    Test::from_dir_filtered("ui", &ui_test, &is_va_file, &ignore_never, &openvaf_test_data("ui"))
+    Test::from_dir_filtered("ui", &ui_test, &is_va_file, &ignore_never, &openvaf_test_data("ui")),
+    Test::new("elision_diagnostics", &elision_diagnostics),
+    Test::new("dynamic_params", &dynamic_params),
+    Test::new("elided_value_diagnostics", &elided_value_diagnostics)
 }
@@ -152,16 +152,22 @@ impl LowerCtx<'_> {
     }
 
     fn collect_event_stmt(&mut self, event_stmt: &ast::EventStmt) -> StmtId {
-        let kind = if event_stmt.initial_step_token().is_some() {
-            GlobalEvent::InitialStep
+        let global = if event_stmt.initial_step_token().is_some() {
+            Some(GlobalEvent::InitialStep)
         } else if event_stmt.final_step_token().is_some() {
-            GlobalEvent::FinalStep
+            Some(GlobalEvent::FinalStep)
         } else {
-            return self.collect_opt_stmt(event_stmt.stmt());
+            None
         };
 
-        let phases = event_stmt.sim_phases().map(|lit| lit.unescaped_value()).collect();
-        let event = Event::Global { kind, phases };
+        let event = if let Some(kind) = global {
+            let phases = event_stmt.sim_phases().map(|lit| lit.unescaped_value()).collect();
+            Event::Global { kind, phases }
+        } else if let Some(event) = event_stmt.event() {
+            Event::Monitored(self.collect_expr(event))
+        } else {
+            return self.collect_opt_stmt(event_stmt.stmt());
+        };
         let stmt = Stmt::EventControl { event, body: self.collect_opt_stmt(event_stmt.stmt()) };
 
         self.alloc_stmt(stmt, AstPtr::new(event_stmt).cast().unwrap(), event_stmt.attrs())
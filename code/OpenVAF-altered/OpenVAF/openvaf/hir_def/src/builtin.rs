@@ -121,6 +121,9 @@ pub enum BuiltIn {
     last_crossing = 108u8,
     slew = 109u8,
     transition = 110u8,
+    cross = 111u8,
+    above = 112u8,
+    timer = 113u8,
 }
 #[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 #[allow(nonstandard_style, unreachable_pub)]
@@ -186,8 +189,6 @@ impl BuiltIn {
             | BuiltIn::laplace_zd
             | BuiltIn::laplace_zp
             | BuiltIn::last_crossing
-            | BuiltIn::slew
-            | BuiltIn::transition
             | BuiltIn::fclose
             | BuiltIn::fopen
             | BuiltIn::fdisplay
@@ -237,6 +238,13 @@ impl BuiltIn {
             _ => false,
         }
     }
+    #[allow(clippy::match_like_matches_macro)]
+    pub fn is_event_fun(self) -> bool {
+        match self {
+            BuiltIn::cross | BuiltIn::above | BuiltIn::timer => true,
+            _ => false,
+        }
+    }
 }
 pub fn insert_builtin_scope(dst: &mut IndexMap<Name, ScopeDefItem, RandomState>) {
     dst.insert(kw::abs, BuiltIn::abs.into());
@@ -370,6 +378,9 @@ pub fn insert_builtin_scope(dst: &mut IndexMap<Name, ScopeDefItem, RandomState>)
     dst.insert(kw::last_crossing, BuiltIn::last_crossing.into());
     dst.insert(kw::slew, BuiltIn::slew.into());
     dst.insert(kw::transition, BuiltIn::transition.into());
+    dst.insert(kw::cross, BuiltIn::cross.into());
+    dst.insert(kw::above, BuiltIn::above.into());
+    dst.insert(kw::timer, BuiltIn::timer.into());
 }
 pub fn insert_module_builtin_scope(dst: &mut IndexMap<Name, ScopeDefItem, RandomState>) {
     dst.insert(sysfun::mfactor, ParamSysFun::mfactor.into());
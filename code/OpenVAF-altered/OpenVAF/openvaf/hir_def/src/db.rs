@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use basedb::{BaseDB, CliParamDefaultValue, FileId};
+use basedb::{BaseDB, CliParamDefault, FileId};
 use stdx::Upcast;
 
 use crate::body::{Body, BodySourceMap, ParamExprs};
@@ -10,6 +10,7 @@ use crate::data::{
 };
 use crate::item_tree::ItemTree;
 use crate::nameres::{DefMap, ScopeOrigin};
+use crate::ScopeDefItem;
 use crate::{
     AliasParamId, AliasParamLoc, BlockId, BlockLoc, BranchId, BranchLoc, DefWithBodyId,
     DisciplineAttrId, DisciplineAttrLoc, DisciplineId, DisciplineLoc, FunctionArgId,
@@ -18,7 +19,6 @@ use crate::{
 };
 use ahash::AHashMap;
 use syntax::name::Name;
-use crate::ScopeDefItem;
 
 #[salsa::query_group(InternDatabase)]
 pub trait InternDB: BaseDB {
@@ -79,13 +79,13 @@ pub trait HirDefDB: InternDB + Upcast<dyn BaseDB> {
     fn body_source_map(&self, def: DefWithBodyId) -> Arc<BodySourceMap>;
 
     #[salsa::transparent]
-    fn param_ids_by_name(&self, root_file: FileId) -> Arc<AHashMap<Name, ParamId>>;
+    fn param_ids_by_name(&self, root_file: FileId) -> Arc<AHashMap<Name, Vec<ParamId>>>;
 
     #[salsa::transparent]
     fn cli_param_defaults_by_id(
         &self,
         root_file: FileId,
-    ) -> Arc<AHashMap<ParamId, (Name, CliParamDefaultValue)>>;
+    ) -> Arc<AHashMap<ParamId, CliParamDefault>>;
 
     #[salsa::invoke(DisciplineData::discipline_data_query)]
     fn discipline_data(&self, discipline: DisciplineId) -> Arc<DisciplineData>;
@@ -146,14 +146,15 @@ pub fn find_module(db: &dyn HirDefDB, root_file: FileId) -> ModuleId {
         .expect("No Module found")
 }
 
-fn param_ids_by_name(db: &dyn HirDefDB, root_file: FileId) -> Arc<AHashMap<Name, ParamId>> {
+fn param_ids_by_name(db: &dyn HirDefDB, root_file: FileId) -> Arc<AHashMap<Name, Vec<ParamId>>> {
     let def_map = db.def_map(root_file);
     let mut stack = vec![def_map.root()];
     let mut map = AHashMap::default();
 
     while let Some(scope_id) = stack.pop() {
         let scope = &def_map[scope_id];
-        stack.extend(scope.children.values().copied());
+        // reversed so that modules are visited in declaration order
+        stack.extend(scope.children.values().rev().copied());
 
         if !matches!(scope.origin, ScopeOrigin::Root | ScopeOrigin::Module(_)) {
             continue;
@@ -161,7 +162,7 @@ fn param_ids_by_name(db: &dyn HirDefDB, root_file: FileId) -> Arc<AHashMap<Name,
 
         for (name, decl) in scope.declarations.iter() {
             if let ScopeDefItem::ParamId(id) = decl {
-                map.entry(name.clone()).or_insert(*id);
+                map.entry(name.clone()).or_insert_with(Vec::new).push(*id);
             }
         }
     }
@@ -172,18 +173,24 @@ fn param_ids_by_name(db: &dyn HirDefDB, root_file: FileId) -> Arc<AHashMap<Name,
 fn cli_param_defaults_by_id(
     db: &dyn HirDefDB,
     root_file: FileId,
-) -> Arc<AHashMap<ParamId, (Name, CliParamDefaultValue)>> {
+) -> Arc<AHashMap<ParamId, CliParamDefault>> {
     let defaults = db.upcast().cli_param_defaults(root_file);
     if defaults.is_empty() {
         return Arc::new(AHashMap::new());
     }
 
     let ids_by_name = db.param_ids_by_name(root_file);
+    let dynamic_params = db.upcast().cli_dynamic_params(root_file);
     let mut resolved = AHashMap::with_capacity(defaults.len());
 
+    // unknown names are reported by `ElisionDiagnostic::collect`, later entries
+    // take precedence over earlier ones
     for entry in defaults.iter() {
-        if let Some(param_id) = ids_by_name.get(&entry.name) {
-            resolved.insert(*param_id, (entry.name.clone(), entry.value));
+        if dynamic_params.contains(&entry.name) {
+            continue;
+        }
+        for param_id in ids_by_name.get(&entry.name).into_iter().flatten() {
+            resolved.insert(*param_id, entry.clone());
         }
     }
 
@@ -0,0 +1,206 @@
+use ahash::AHashMap;
+use basedb::diagnostics::{Diagnostic, Label, LabelStyle, Report};
+use basedb::lints::builtin::elision_mismatch;
+use basedb::lints::{Lint, LintSrc};
+use basedb::{BaseDB, FileId};
+use stdx::impl_display;
+use syntax::name::Name;
+use syntax::sourcemap::FileSpan;
+
+use crate::db::HirDefDB;
+use crate::nameres::ScopeOrigin;
+use crate::Lookup;
+
+/// Problems with the parameter defaults (elisions) that were passed on the CLI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElisionDiagnostic {
+    UnknownParam { name: Name, span: Option<FileSpan>, candidate: Option<Name> },
+    Duplicate { name: Name, span: Option<FileSpan>, prev: Option<FileSpan> },
+    AmbiguousParam { name: Name, span: Option<FileSpan>, modules: Vec<Name> },
+    UnknownDynamicParam { name: Name, candidate: Option<Name> },
+    Summary { applied: usize, ignored: usize, kept: usize },
+}
+
+use ElisionDiagnostic::*;
+
+impl_display! {
+    match ElisionDiagnostic{
+        UnknownParam{name, ..} => "elided parameter '{}' does not exist", name;
+        Duplicate{name, ..} => "parameter '{}' was elided multiple times", name;
+        AmbiguousParam{name, ..} => "elided parameter '{}' is declared in multiple modules", name;
+        UnknownDynamicParam{name, ..} => "parameter '{}' can not be kept dynamic because it does not exist", name;
+        Summary{applied, ignored, kept: 0} => "elided {} parameter(s), ignored {} elision(s)", applied, ignored;
+        Summary{applied, ignored, kept} => "elided {} parameter(s), ignored {} elision(s), kept {} parameter(s) dynamic", applied, ignored, kept;
+    }
+}
+
+impl ElisionDiagnostic {
+    pub fn collect(db: &dyn HirDefDB, root_file: FileId) -> Vec<ElisionDiagnostic> {
+        let defaults = db.upcast().cli_param_defaults(root_file);
+        let dynamic_params = db.upcast().cli_dynamic_params(root_file);
+        if defaults.is_empty() && dynamic_params.is_empty() {
+            return Vec::new();
+        }
+
+        let ids_by_name = db.param_ids_by_name(root_file);
+        let mut res = Vec::new();
+
+        for name in dynamic_params.iter() {
+            if !ids_by_name.contains_key(name) {
+                let candidate = closest_name(name, ids_by_name.keys());
+                res.push(UnknownDynamicParam { name: name.clone(), candidate });
+            }
+        }
+
+        if defaults.is_empty() {
+            return res;
+        }
+
+        let mut seen: AHashMap<&Name, Option<FileSpan>> = AHashMap::new();
+        let mut ignored = 0;
+        let mut kept = 0;
+
+        for entry in defaults.iter() {
+            let ids = match ids_by_name.get(&entry.name) {
+                Some(ids) => ids,
+                None => {
+                    let candidate = closest_name(&entry.name, ids_by_name.keys());
+                    res.push(UnknownParam {
+                        name: entry.name.clone(),
+                        span: entry.span,
+                        candidate,
+                    });
+                    ignored += 1;
+                    continue;
+                }
+            };
+
+            if let Some(prev) = seen.insert(&entry.name, entry.span) {
+                // the last entry wins so the previous one is ignored
+                res.push(Duplicate { name: entry.name.clone(), span: entry.span, prev });
+                ignored += 1;
+                continue;
+            }
+
+            // dynamic parameters are not elided and keep their declared default
+            if dynamic_params.contains(&entry.name) {
+                kept += 1;
+                continue;
+            }
+
+            if ids.len() > 1 {
+                let modules = ids
+                    .iter()
+                    .filter_map(|id| {
+                        let scope = id.lookup(db).scope;
+                        match scope.def_map(db)[scope.local_scope].origin {
+                            ScopeOrigin::Module(module) => {
+                                Some(db.module_data(module).name.clone())
+                            }
+                            _ => None,
+                        }
+                    })
+                    .collect();
+                res.push(AmbiguousParam { name: entry.name.clone(), span: entry.span, modules });
+            }
+        }
+
+        res.push(Summary { applied: defaults.len() - ignored - kept, ignored, kept });
+        res
+    }
+}
+
+impl Diagnostic for ElisionDiagnostic {
+    fn lint(&self, _root_file: FileId, _db: &dyn BaseDB) -> Option<(Lint, LintSrc)> {
+        match self {
+            Summary { .. } => None,
+            _ => Some((elision_mismatch, LintSrc::GLOBAL)),
+        }
+    }
+
+    fn build_report(&self, _root_file: FileId, _db: &dyn BaseDB) -> Report {
+        let label = |style, span: &Option<FileSpan>, message: &str| {
+            span.map(|span| Label {
+                style,
+                file_id: span.file,
+                range: span.range.into(),
+                message: message.to_owned(),
+            })
+        };
+
+        let report = match self {
+            UnknownParam { span, candidate, .. } => {
+                let report = Report::warning().with_labels(
+                    label(LabelStyle::Primary, span, "not found").into_iter().collect(),
+                );
+                match candidate {
+                    Some(candidate) => {
+                        report.with_notes(vec![format!("help: did you mean '{}'?", candidate)])
+                    }
+                    None => report,
+                }
+            }
+            Duplicate { span, prev, .. } => Report::warning()
+                .with_labels(
+                    label(LabelStyle::Secondary, prev, "first elided here")
+                        .into_iter()
+                        .chain(label(LabelStyle::Primary, span, "elided again here"))
+                        .collect(),
+                )
+                .with_notes(vec!["only the last value is used".to_owned()]),
+            AmbiguousParam { span, modules, .. } => {
+                let modules: Vec<_> =
+                    modules.iter().map(|module| format!("'{}'", module)).collect();
+                Report::warning()
+                    .with_labels(
+                        label(LabelStyle::Primary, span, "elided here").into_iter().collect(),
+                    )
+                    .with_notes(vec![format!(
+                        "the value is used for the parameter in every module: {}",
+                        modules.join(", ")
+                    )])
+            }
+            UnknownDynamicParam { candidate, .. } => {
+                let report = Report::warning();
+                match candidate {
+                    Some(candidate) => {
+                        report.with_notes(vec![format!("help: did you mean '{}'?", candidate)])
+                    }
+                    None => report,
+                }
+            }
+            Summary { .. } => Report::note(),
+        };
+
+        report.with_message(self.to_string())
+    }
+}
+
+/// Finds the parameter name that is most likely meant by a misspelled `name`.
+fn closest_name<'a>(name: &Name, candidates: impl Iterator<Item = &'a Name>) -> Option<Name> {
+    let name = name.to_ascii_lowercase();
+    let max_dist = (name.len() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(&name, &candidate.to_ascii_lowercase()), candidate))
+        .filter(|(dist, _)| *dist <= max_dist)
+        .min_by(|(dist1, name1), (dist2, name2)| dist1.cmp(dist2).then_with(|| name1.cmp(name2)))
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let subst = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = subst.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
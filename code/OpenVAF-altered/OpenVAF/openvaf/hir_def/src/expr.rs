@@ -143,7 +143,12 @@ pub enum GlobalEvent {
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 #[non_exhaustive]
 pub enum Event {
-    Global { kind: GlobalEvent, phases: Vec<String> },
+    Global {
+        kind: GlobalEvent,
+        phases: Vec<String>,
+    },
+    /// A call to an event function (`cross`, `above` or `timer`)
+    Monitored(ExprId),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -162,8 +167,12 @@ impl Stmt {
     #[inline]
     pub fn walk_child_exprs(&self, mut f: impl FnMut(ExprId)) {
         match *self {
-            Stmt::Empty | Stmt::Missing | Stmt::Block { .. } | Stmt::EventControl { .. } => (),
+            Stmt::Empty
+            | Stmt::Missing
+            | Stmt::Block { .. }
+            | Stmt::EventControl { event: Event::Global { .. }, .. } => (),
             Stmt::If { cond: expr, .. }
+            | Stmt::EventControl { event: Event::Monitored(expr), .. }
             | Stmt::ForLoop { cond: expr, .. }
             | Stmt::WhileLoop { cond: expr, .. }
             | Stmt::Expr(expr) => f(expr),
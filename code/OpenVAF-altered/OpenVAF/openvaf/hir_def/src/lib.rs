@@ -2,6 +2,7 @@ pub mod body;
 mod builtin;
 mod data;
 pub mod db;
+pub mod elision;
 pub mod expr;
 mod item_tree;
 pub mod nameres;
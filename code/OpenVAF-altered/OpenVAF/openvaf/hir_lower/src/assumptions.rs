@@ -0,0 +1,76 @@
+use hir::{CompilationDB, ParamSysFun, Type};
+use lasso::Rodeo;
+use mir::builder::InstBuilder;
+use mir::{Function, Opcode, Value};
+use mir_build::{FunctionBuilder, FunctionBuilderContext};
+
+use crate::ctx::LoweringCtx;
+use crate::fmt::DisplayKind;
+use crate::{CallBackKind, HirInterner, ParamKind, RetFlag};
+
+/// Aborts with the message `msg` if `val` differs from `assumed`. `msg` receives
+/// the actual and the assumed value as arguments.
+fn check_assumption(ctx: &mut LoweringCtx, val: Value, assumed: f64, msg: &str) {
+    let assumed = ctx.fconst(assumed);
+    let mismatch_bb = ctx.create_block();
+    let exit = ctx.create_block();
+    let is_ok = ctx.ins().binary1(Opcode::Feq, val, assumed);
+    ctx.ins().br(is_ok, exit, mismatch_bb);
+    ctx.switch_to_block(mismatch_bb);
+    let msg = ctx.sconst(msg);
+    let kind = CallBackKind::Print {
+        kind: DisplayKind::Fatal,
+        arg_tys: vec![Type::Real.into(), Type::Real.into()].into_boxed_slice(),
+    };
+    ctx.call(kind, &[msg, val, assumed]);
+    ctx.call(CallBackKind::SetRetFlag(RetFlag::Abort), &[]);
+    ctx.ins().jump(exit);
+    ctx.switch_to_block(exit);
+}
+
+impl HirInterner {
+    /// Inserts checks into the instance setup `func` that abort if the simulator
+    /// temperature, `$mfactor` or a `$simparam` differ from the value they were
+    /// assumed to have (and folded to) during compilation.
+    pub fn insert_assumption_checks(
+        &mut self,
+        db: &CompilationDB,
+        func: &mut Function,
+        literals: &mut Rodeo,
+    ) {
+        let assumptions = db.compilation_unit().sim_assumptions(db);
+        if assumptions.is_empty() {
+            return;
+        }
+
+        let mut ctx = FunctionBuilderContext::default();
+        let (builder, term) = FunctionBuilder::edit(func, literals, &mut ctx, false);
+        let mut ctx = LoweringCtx::new(db, builder, true, self);
+
+        if let Some(temperature) = assumptions.temperature {
+            let val = ctx.use_param(ParamKind::Temperature);
+            let msg = "simulator temperature %g K differs from the assumed temperature %g K\n";
+            check_assumption(&mut ctx, val, temperature, msg);
+        }
+
+        for (i, (name, assumed)) in assumptions.simparams.iter().enumerate() {
+            // only the last value of a simparam is used
+            if assumptions.simparams[i + 1..].iter().any(|(other, _)| other == name) {
+                continue;
+            }
+            let val = if name == "mfactor" {
+                ctx.use_param(ParamKind::ParamSysFun(ParamSysFun::mfactor))
+            } else {
+                // the simulator may not know the simparam, in that case the assumption holds
+                let name = ctx.sconst(name);
+                let default = ctx.fconst(*assumed);
+                ctx.call1(CallBackKind::SimParamOpt, &[name, default])
+            };
+            let msg = format!("simparam {name} = %g differs from the assumed value %g\n");
+            check_assumption(&mut ctx, val, *assumed, &msg);
+        }
+
+        ctx.ensured_sealed();
+        ctx.func.func.layout.append_inst_to_bb(term, ctx.current_block())
+    }
+}
@@ -1,8 +1,6 @@
 use hir::Node;
 use hir::{BodyRef, ExprId};
-use mir::builder::InstBuilder;
 use mir::{Block, Value};
-use stdx::iter::zip;
 
 use crate::ctx::LoweringCtx;
 use crate::ParamKind;
@@ -70,14 +68,12 @@ impl<'c1, 'c2> BodyLoweringCtx<'_, 'c1, 'c2> {
     pub fn lower_multi_select<const N: usize>(
         &mut self,
         cond: Value,
-        lower_body: impl FnMut(BodyLoweringCtx<'_, 'c1, 'c2>, bool) -> [Value; N],
+        mut lower_body: impl FnMut(BodyLoweringCtx<'_, 'c1, 'c2>, bool) -> [Value; N],
     ) -> [Value; N] {
-        let ((then_bb, mut then_vals), (else_bb, else_vals)) =
-            self.lower_cond_with(cond, lower_body);
-        for (then_val, else_val) in zip(&mut then_vals, else_vals) {
-            *then_val = self.ctx.ins().phi(&[(then_bb, *then_val), (else_bb, else_val)]);
-        }
-        then_vals
+        self.ctx.make_multi_select(cond, |ctx, branch| {
+            let ctx = BodyLoweringCtx { ctx, body: self.body, path: self.path };
+            lower_body(ctx, branch)
+        })
     }
 }
 
@@ -4,7 +4,7 @@ use mir::{FunctionSignature, Param};
 use stdx::Ieee64;
 
 use crate::fmt::{DisplayKind, FmtArg};
-use crate::LimitState;
+use crate::{LimitState, OperatorState};
 
 use std::fmt::Display;
 
@@ -23,6 +23,7 @@ pub enum RetFlag {
     Finish, 
     Stop, 
     Limited, 
+    RejectStep,
 }
 
 impl std::fmt::Display for RetFlag {
@@ -32,6 +33,7 @@ impl std::fmt::Display for RetFlag {
             Self::Finish => "finish", 
             Self::Stop => "stop", 
             Self::Limited => "limited", 
+            Self::RejectStep => "reject_step",
         };
         write!(f, "{}", txt)
     }
@@ -51,6 +53,7 @@ pub enum CallBackKind {
     Analysis,
     BuiltinLimit { name: Spur, num_args: u32 },
     StoreLimit(LimitState),
+    StoreOperatorState(OperatorState),
     TimeDerivative,
     WhiteNoise { name: Spur, idx: u32 },
     FlickerNoise { name: Spur, idx: u32 },
@@ -121,6 +124,12 @@ impl CallBackKind {
                 returns: 1,
                 has_sideeffects: false,
             },
+            CallBackKind::StoreOperatorState(state) => FunctionSignature {
+                name: format!("$store[{state:?}]"),
+                params: 1,
+                returns: 0,
+                has_sideeffects: true,
+            },
             CallBackKind::LimDiscontinuity => FunctionSignature {
                 name: "$discontinuty[-1]".to_owned(),
                 params: 0,
@@ -185,6 +194,7 @@ impl CallBackKind {
             CallBackKind::SimParam
                 | CallBackKind::SimParamOpt
                 | CallBackKind::StoreLimit(_)
+                | CallBackKind::StoreOperatorState(_)
                 | CallBackKind::Analysis
                 | CallBackKind::SimParamStr
                 | CallBackKind::LimDiscontinuity
@@ -1,20 +1,24 @@
-use ahash::AHashSet;
-use hir::{CompilationDB, Node, Type, Variable};
+use std::sync::Arc;
+
+use ahash::{AHashMap, AHashSet};
+use basedb::{CliParamDefault, CliParamDefaultValue, FileId, ParamRange, SimAssumptions};
+use hir::{CompilationDB, Node, ParamSysFun, Parameter, Type, Variable};
+use hir_def::db::HirDefDB;
+use hir_def::ParamId;
+use hir_ty::elision::coerce_elided_value;
 use mir::builder::{InsertBuilder, InstBuilder};
 use mir::{
     Block, DataFlowGraph, FuncRef, Inst, Opcode, SourceLoc, Value, FALSE, F_ZERO, INFINITY, TRUE,
 };
 use mir_build::{FuncInstBuilder, FunctionBuilder, Place};
+use stdx::iter::zip;
 use typed_indexmap::TiSet;
 
 use crate::{
-    CallBackKind, HirInterner, ImplicitEquation, ImplicitEquationKind, LimitState, ParamKind,
-    PlaceKind,
+    CallBackKind, HirInterner, ImplicitEquation, ImplicitEquationKind, LimitState, OperatorState,
+    ParamKind, PlaceKind, RetFlag,
 };
 
-use std::collections::HashMap;
-use hir::Parameter;
-
 pub struct LoweringCtx<'a, 'c> {
     pub db: &'a CompilationDB,
     pub func: FunctionBuilder<'c>,
@@ -28,8 +32,13 @@ pub struct LoweringCtx<'a, 'c> {
     /// but necessary to avoid accidental correlation/opimization.
     /// For example white_noise(x) - white_noise(x) is not zero.
     pub num_noise_sources: u32,
+    /// The root file of the compilation unit that is being lowered.
+    /// Elision defaults passed on the CLI are associated with this file.
+    pub root_file: FileId,
+    param_defaults: Arc<AHashMap<ParamId, CliParamDefault>>,
+    /// Simulator inputs that were assumed fixed on the CLI
+    assumptions: Arc<SimAssumptions>,
     // BEGIN RDUBI CHANGES
-    pub params_to_leave: &'a Vec<u32>,
     // pub param_ref_freqs: HashMap<Parameter, u32>,
     // END RDUBI CHANGES
 }
@@ -40,8 +49,10 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
         func: FunctionBuilder<'c>,
         no_equations: bool,
         intern: &'a mut HirInterner,
-        params_to_leave: &'a Vec<u32>,
     ) -> Self {
+        let root_file = db.compilation_unit().root_file();
+        let param_defaults = db.cli_param_defaults_by_id(root_file);
+        let assumptions = db.compilation_unit().sim_assumptions(db);
         Self {
             db,
             func,
@@ -51,8 +62,10 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
             inside_lim: false,
             intern,
             num_noise_sources: 0,
+            root_file,
+            param_defaults,
+            assumptions,
             // param_ref_freqs: HashMap::new(),
-            params_to_leave,
         }
     }
 
@@ -61,6 +74,36 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
         self
     }
 
+    /// Returns the value a parameter was elided to on the CLI (if any).
+    /// The value is converted to the type of the parameter.
+    pub fn param_default(&self, param: Parameter) -> Option<CliParamDefaultValue> {
+        let default = self.param_defaults.get(&hir::get_id(param))?;
+        if default.value.as_range().is_some() {
+            return None;
+        }
+        coerce_elided_value(&default.value, &param.ty(self.db))
+    }
+
+    /// Returns the range of values a parameter is assumed to take (if any).
+    pub fn param_range(&self, param: Parameter) -> Option<ParamRange> {
+        let default = self.param_defaults.get(&hir::get_id(param))?;
+        match coerce_elided_value(&default.value, &param.ty(self.db))? {
+            CliParamDefaultValue::Range(range) => Some(range),
+            _ => None,
+        }
+    }
+
+    /// Returns a constant with the value a parameter was elided to on the CLI (if any).
+    pub fn elided_param_val(&mut self, param: Parameter) -> Option<Value> {
+        let val = match self.param_default(param)? {
+            CliParamDefaultValue::Int(val) => self.iconst(val),
+            CliParamDefaultValue::Float(val) => self.fconst(val),
+            CliParamDefaultValue::Str(val) => self.sconst(&val),
+            CliParamDefaultValue::Range(_) => unreachable!("ranges are not elided"),
+        };
+        Some(val)
+    }
+
     /// This function should be used for reading variables to correctly
     /// handle value tagging
     pub fn read_variable(&mut self, var: Variable) -> Value {
@@ -125,6 +168,27 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
         *entry.or_insert_with(|| self.func.func.dfg.make_param(len.into()))
     }
 
+    /// Reads the ambient temperature, a temperature assumed on the CLI is a constant.
+    pub fn temperature(&mut self) -> Value {
+        match self.assumptions.temperature {
+            Some(temperature) => self.fconst(temperature),
+            None => self.use_param(ParamKind::Temperature),
+        }
+    }
+
+    /// Reads a `$param_sysfun`, `$mfactor` is a constant if it was assumed on the CLI.
+    pub fn param_sysfun(&mut self, param: ParamSysFun) -> Value {
+        match self.assumptions.mfactor() {
+            Some(mfactor) if param == ParamSysFun::mfactor => self.fconst(mfactor),
+            _ => self.use_param(ParamKind::ParamSysFun(param)),
+        }
+    }
+
+    /// Returns the value of a `$simparam` that was assumed on the CLI (if any).
+    pub fn assumed_simparam(&self, name: &str) -> Option<f64> {
+        self.assumptions.simparam(name)
+    }
+
     pub fn def_param(&mut self, kind: ParamKind, val: Value) {
         self.intern.params.insert(kind, val);
     }
@@ -230,6 +294,32 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
         val
     }
 
+    /// Allocates a new slot in the state vector for an analog operator
+    pub fn operator_state(&mut self) -> OperatorState {
+        let state = OperatorState::from(self.intern.operator_states);
+        self.intern.operator_states += 1;
+        state
+    }
+
+    /// Limits the next time step (measured from the current time) to `step`.
+    /// `step` must not be negative. If the step is bound multiple times the
+    /// smallest bound is used.
+    pub fn bound_step(&mut self, step: Value) {
+        let bound = self.use_place(PlaceKind::BoundStep);
+        let is_smaller = self.ins().flt(step, bound);
+        let bound =
+            self.make_select(is_smaller, |_, is_smaller| if is_smaller { step } else { bound });
+        self.def_place(PlaceKind::BoundStep, bound);
+    }
+
+    /// Requests that the simulator rejects the current time point and retries
+    /// with a time step of at most `step` (measured from the last accepted
+    /// time point, see the [`events`](crate::events) module).
+    pub fn reject_step(&mut self, step: Value) {
+        self.call(CallBackKind::SetRetFlag(RetFlag::RejectStep), &[]);
+        self.bound_step(step);
+    }
+
     pub fn implicit_equation(&mut self, kind: ImplicitEquationKind) -> (ImplicitEquation, Value) {
         let equation = self.intern.implicit_equations.push_and_get_key(kind);
         let place = self.dec_place(PlaceKind::CollapseImplicitEquation(equation));
@@ -277,6 +367,18 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
         self.func.ins().phi(&[then_src, else_src])
     }
 
+    pub fn make_multi_select<const N: usize>(
+        &mut self,
+        cond: Value,
+        lower_branch: impl FnMut(&mut Self, bool) -> [Value; N],
+    ) -> [Value; N] {
+        let ((then_bb, mut then_vals), (else_bb, else_vals)) = self.make_cond(cond, lower_branch);
+        for (then_val, else_val) in zip(&mut then_vals, else_vals) {
+            *then_val = self.func.ins().phi(&[(then_bb, *then_val), (else_bb, else_val)]);
+        }
+        then_vals
+    }
+
     pub fn make_cond<T>(
         &mut self,
         cond: Value,
@@ -0,0 +1,315 @@
+//! Lowering of the events in event control statements.
+//!
+//! `initial_step` and `final_step` are derived from the analysis flags passed by
+//! the simulator. Monitored events (`cross`, `above` and `timer`) compare the
+//! current evaluation with the last accepted time point (see [`History`]). They
+//! occur at the first time point after the event and bound the time step so
+//! that this time point lies within the time tolerance of the event.
+//!
+//! Events and the `transition` filter share one meaning of the step bound (the
+//! `bound_step` of OSDI): it is the largest next step measured from the current
+//! evaluation, so `abstime + bound_step` is the time of the next event. The
+//! bound is never negative. An event that lies in the past by more than its
+//! time tolerance instead sets `EVAL_RET_FLAG_REJECT`: the current time point
+//! overshot the event and the simulator should reject it. In that case the
+//! step bound is measured from the last accepted time point, so the retry ends
+//! at the event.
+
+use mir::builder::InstBuilder;
+use mir::{Value, FALSE, F_ONE, F_ZERO, INFINITY, ONE, TRUE, ZERO};
+
+use crate::ctx::LoweringCtx;
+use crate::transition::History;
+use crate::ParamKind;
+
+/// The time tolerance of monitored events without an explicit tolerance.
+const DEFAULT_TIME_TOL: f64 = 1e-12;
+/// The magnitude that `cross` stores for a value that reached zero (see
+/// [`LoweringCtx::zero_side`]).
+const ZERO_SIDE: f64 = f64::MIN_POSITIVE;
+
+impl LoweringCtx<'_, '_> {
+    /// `initial_step` occurs during the operating point that starts every
+    /// analysis. If `phases` are specified, only the listed analyses trigger
+    /// the event.
+    pub(crate) fn initial_step(&mut self, phases: &[String]) -> Value {
+        let is_static = self.analysis("static");
+        self.make_select(
+            is_static,
+            |ctx, is_static| if is_static { ctx.any_analysis(phases) } else { FALSE },
+        )
+    }
+
+    /// OSDI has no way to signal the last step of an analysis. Analyses that
+    /// only consist of their operating point end where they start, so
+    /// `final_step` occurs during their operating point. The end of a
+    /// transient analysis is unknown and `final_step` never occurs there.
+    pub(crate) fn final_step(&mut self, phases: &[String]) -> Value {
+        let is_static = self.analysis("static");
+        self.make_select(is_static, |ctx, is_static| {
+            if !is_static {
+                return FALSE;
+            }
+            let is_tran = ctx.analysis("tran");
+            ctx.make_select(
+                is_tran,
+                |ctx, is_tran| if is_tran { FALSE } else { ctx.any_analysis(phases) },
+            )
+        })
+    }
+
+    fn any_analysis(&mut self, phases: &[String]) -> Value {
+        let (first, rest) = match phases.split_first() {
+            Some(phases) => phases,
+            None => return TRUE,
+        };
+        let mut res = self.analysis(first);
+        for phase in rest {
+            let is_phase = self.analysis(phase);
+            res = self.make_select(res, |_, res| if res { TRUE } else { is_phase });
+        }
+        res
+    }
+
+    /// `cross(val, dir, time_tol, expr_tol)` occurs when `val` crosses zero in
+    /// direction `dir` (rising if positive, falling if negative and both if
+    /// zero). A value within `expr_tol` of zero counts as zero. Otherwise, the
+    /// crossing time is estimated by linear interpolation and the time point is
+    /// rejected if the crossing lies more than `time_tol` in the past.
+    pub(crate) fn cross(
+        &mut self,
+        val: Value,
+        dir: Value,
+        time_tol: Option<Value>,
+        expr_tol: Value,
+    ) -> Value {
+        let time_tol = time_tol.unwrap_or_else(|| self.fconst(DEFAULT_TIME_TOL));
+        let history = self.history();
+        let time = self.use_param(ParamKind::Abstime);
+        let is_transient = self.is_transient();
+        let is_zero = self.is_zero(val, expr_tol);
+        let [prev_val, prev_time, side, occurred] =
+            self.make_multi_select(is_transient, |ctx, tran| {
+                if !tran {
+                    // a value that starts at zero has not crossed it yet
+                    let side =
+                        ctx.make_select(is_zero, |_, is_zero| if is_zero { F_ZERO } else { val });
+                    return [side, time, side, FALSE];
+                }
+
+                let [prev_val, prev_time] = ctx.load_history(history, time);
+                let side = ctx.zero_side(prev_val, val, is_zero, expr_tol);
+                let crossed = ctx.crossed(prev_val, side, dir);
+                ctx.make_cond(crossed, |ctx, crossed| {
+                    if crossed {
+                        ctx.bound_crossing([prev_val, prev_time], [val, time], time_tol, expr_tol)
+                    }
+                });
+                [prev_val, prev_time, side, crossed]
+            });
+
+        self.store_history(history, [prev_val, prev_time], side, time);
+        occurred
+    }
+
+    /// Whether `val` is within `tol` of zero.
+    fn is_zero(&mut self, val: Value, tol: Value) -> Value {
+        let neg_val = self.ins().fneg(val);
+        let abs_val = self.fmax(val, neg_val);
+        self.ins().fle(abs_val, tol)
+    }
+
+    /// The value that `cross` compares and stores instead of `val`. A value that
+    /// reaches zero crosses it on arrival and is replaced by a tiny value on the
+    /// far side of zero. It keeps that value while it stays at zero, so that
+    /// leaving zero towards the same side does not count as a second crossing.
+    /// Only a value that started at zero is stored as exactly zero.
+    fn zero_side(&mut self, prev_val: Value, val: Value, is_zero: Value, expr_tol: Value) -> Value {
+        self.make_select(is_zero, |ctx, is_zero| {
+            if !is_zero {
+                return val;
+            }
+            let zero_side = ctx.fconst(ZERO_SIDE);
+            let prev_tol = ctx.fmax(expr_tol, zero_side);
+            let prev_is_zero = ctx.is_zero(prev_val, prev_tol);
+            ctx.make_select(prev_is_zero, |ctx, prev_is_zero| {
+                if prev_is_zero {
+                    return prev_val;
+                }
+                let was_below = ctx.ins().flt(prev_val, F_ZERO);
+                ctx.make_select(was_below, |ctx, was_below| {
+                    if was_below {
+                        zero_side
+                    } else {
+                        ctx.fconst(-ZERO_SIDE)
+                    }
+                })
+            })
+        })
+    }
+
+    /// `above(val, time_tol, expr_tol)` occurs when `val` rises above zero.
+    /// Unlike `cross` it also occurs during the operating point if `val` is
+    /// not negative.
+    pub(crate) fn above(&mut self, val: Value, time_tol: Option<Value>, expr_tol: Value) -> Value {
+        let crossed = self.cross(val, ONE, time_tol, expr_tol);
+        let is_positive = self.ins().fge(val, F_ZERO);
+        let is_static = self.analysis("static");
+        self.make_select(is_static, |_, is_static| if is_static { is_positive } else { crossed })
+    }
+
+    /// `timer(start, period, time_tol)` occurs at `start` and, if `period` is
+    /// positive, periodically afterwards. Just like the corners of a
+    /// `transition`, the time steps are bounded so that the simulator hits the
+    /// scheduled times.
+    pub(crate) fn timer(
+        &mut self,
+        start: Value,
+        period: Option<Value>,
+        time_tol: Option<Value>,
+    ) -> Value {
+        let time_tol = time_tol.unwrap_or_else(|| self.fconst(DEFAULT_TIME_TOL));
+        let history = self.history();
+        let time = self.use_param(ParamKind::Abstime);
+        let is_transient = self.is_transient();
+        let [prev_time, occurred] = self.make_multi_select(is_transient, |ctx, tran| {
+            if !tran {
+                // events at the start of the simulation occur during the operating point
+                let occurred = ctx.ins().fge(time, start);
+                return [time, occurred];
+            }
+
+            let [_, prev_time] = ctx.load_history(history, time);
+            let next = ctx.next_scheduled_time(start, period, prev_time);
+            let reached = ctx.ins().fge(time, next);
+            ctx.make_cond(reached, |ctx, reached| {
+                if reached {
+                    let late = ctx.ins().fsub(time, next);
+                    let too_late = ctx.ins().fgt(late, time_tol);
+                    ctx.make_cond(too_late, |ctx, too_late| {
+                        if too_late {
+                            // next lies after prev_time so the retry step is positive
+                            let step = ctx.ins().fsub(next, prev_time);
+                            ctx.reject_step(step);
+                        }
+                    });
+                } else {
+                    let step = ctx.ins().fsub(next, time);
+                    ctx.bound_step(step);
+                }
+            });
+            [prev_time, reached]
+        });
+
+        self.store_history(history, [F_ZERO, prev_time], F_ZERO, time);
+        occurred
+    }
+
+    /// The first time after `prev_time` at which a timer with `start` and
+    /// `period` is scheduled.
+    fn next_scheduled_time(
+        &mut self,
+        start: Value,
+        period: Option<Value>,
+        prev_time: Value,
+    ) -> Value {
+        let before_start = self.ins().flt(prev_time, start);
+        self.make_select(before_start, |ctx, before_start| {
+            if before_start {
+                return start;
+            }
+            let period = match period {
+                Some(period) => period,
+                None => return INFINITY,
+            };
+            let is_periodic = ctx.ins().fgt(period, F_ZERO);
+            ctx.make_select(is_periodic, |ctx, is_periodic| {
+                if !is_periodic {
+                    return INFINITY;
+                }
+                let elapsed = ctx.ins().fsub(prev_time, start);
+                let periods = ctx.ins().fdiv(elapsed, period);
+                let periods = ctx.ins().floor(periods);
+                let periods = ctx.ins().fadd(periods, F_ONE);
+                let offset = ctx.ins().fmul(periods, period);
+                ctx.ins().fadd(start, offset)
+            })
+        })
+    }
+
+    /// Whether `val` crossed zero in direction `dir` since `prev_val`. Both values
+    /// are mapped by [`zero_side`](Self::zero_side), so only a value that
+    /// started at zero is exactly zero, and it crosses as soon as it leaves zero.
+    fn crossed(&mut self, prev_val: Value, val: Value, dir: Value) -> Value {
+        let allow_rising = self.ins().ige(dir, ZERO);
+        let rising = self.make_select(allow_rising, |ctx, allow_rising| {
+            if !allow_rising {
+                return FALSE;
+            }
+            let was_below = ctx.ins().fle(prev_val, F_ZERO);
+            ctx.make_select(
+                was_below,
+                |ctx, was_below| if was_below { ctx.ins().fgt(val, F_ZERO) } else { FALSE },
+            )
+        });
+
+        self.make_select(rising, |ctx, rising| {
+            if rising {
+                return TRUE;
+            }
+            let allow_falling = ctx.ins().ile(dir, ZERO);
+            ctx.make_select(allow_falling, |ctx, allow_falling| {
+                if !allow_falling {
+                    return FALSE;
+                }
+                let was_above = ctx.ins().fge(prev_val, F_ZERO);
+                ctx.make_select(was_above, |ctx, was_above| {
+                    if was_above {
+                        ctx.ins().flt(val, F_ZERO)
+                    } else {
+                        FALSE
+                    }
+                })
+            })
+        })
+    }
+
+    /// Rejects the current time point if the zero crossing between the last
+    /// accepted time point and the current evaluation lies more than `time_tol`
+    /// in the past. The retry ends at the crossing. A crossing within `time_tol`
+    /// of the accepted time point (for example because `prev_val` is zero)
+    /// can not be reached by a retry and occurs at the current time point.
+    fn bound_crossing(
+        &mut self,
+        [prev_val, prev_time]: [Value; 2],
+        [val, time]: [Value; 2],
+        time_tol: Value,
+        expr_tol: Value,
+    ) {
+        let within_tol = self.is_zero(val, expr_tol);
+        self.make_cond(within_tol, |ctx, within_tol| {
+            if within_tol {
+                return;
+            }
+            // prev_val and val have different signs so the division is well defined
+            let delta = ctx.ins().fsub(prev_val, val);
+            let fraction = ctx.ins().fdiv(prev_val, delta);
+            let step = ctx.ins().fsub(time, prev_time);
+            let offset = ctx.ins().fmul(step, fraction);
+            let crossing = ctx.ins().fadd(prev_time, offset);
+            let late = ctx.ins().fsub(time, crossing);
+            let too_late = ctx.ins().fgt(late, time_tol);
+            ctx.make_cond(too_late, |ctx, too_late| {
+                if !too_late {
+                    return;
+                }
+                let reachable = ctx.ins().fgt(offset, time_tol);
+                ctx.make_cond(reachable, |ctx, reachable| {
+                    if reachable {
+                        ctx.reject_step(offset);
+                    }
+                });
+            });
+        });
+    }
+}
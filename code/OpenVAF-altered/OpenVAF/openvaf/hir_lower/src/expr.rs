@@ -2,12 +2,12 @@ use hir::builtin::{
     FLICKER_NOISE_NAME, NOISE_TABLE_FILE_NAME, NOISE_TABLE_INLINE_NAME, WHITE_NOISE_NAME,
 };
 use hir::signatures::{
-    ABS_INT, ABS_REAL, BOOL_EQ, DDX_POT, IDTMOD_IC, IDTMOD_IC_MODULUS, IDTMOD_IC_MODULUS_OFFSET,
-    IDTMOD_IC_MODULUS_OFFSET_NATURE, IDTMOD_IC_MODULUS_OFFSET_TOL, IDTMOD_NO_IC, IDT_IC,
-    IDT_IC_ASSERT, IDT_IC_ASSERT_NATURE, IDT_IC_ASSERT_TOL, IDT_NO_IC, INT_EQ, INT_OP,
-    LIMIT_BUILTIN_FUNCTION, MAX_INT, MAX_REAL, NATURE_ACCESS_BRANCH, NATURE_ACCESS_NODES,
-    NATURE_ACCESS_NODE_GND, NATURE_ACCESS_PORT_FLOW, REAL_EQ, REAL_OP, SIMPARAM_DEFAULT,
-    SIMPARAM_NO_DEFAULT, STR_EQ,
+    ABSDELAY_MAX, ABS_INT, ABS_REAL, BOOL_EQ, DDX_POT, IDTMOD_IC, IDTMOD_IC_MODULUS,
+    IDTMOD_IC_MODULUS_OFFSET, IDTMOD_IC_MODULUS_OFFSET_NATURE, IDTMOD_IC_MODULUS_OFFSET_TOL,
+    IDTMOD_NO_IC, IDT_IC, IDT_IC_ASSERT, IDT_IC_ASSERT_NATURE, IDT_IC_ASSERT_TOL, IDT_NO_IC,
+    INT_EQ, INT_OP, LIMIT_BUILTIN_FUNCTION, MAX_INT, MAX_REAL, NATURE_ACCESS_BRANCH,
+    NATURE_ACCESS_NODES, NATURE_ACCESS_NODE_GND, NATURE_ACCESS_PORT_FLOW, REAL_EQ, REAL_OP,
+    SIMPARAM_DEFAULT, SIMPARAM_NO_DEFAULT, SLEW_NEG_MAX, SLEW_NO_MAX, STR_EQ,
 };
 use hir::{Body, BuiltIn, Expr, ExprId, Literal, /*ParamSysFun,*/ Ref, ResolvedFun, Type};
 use mir::builder::InstBuilder;
@@ -21,56 +21,23 @@ use crate::{
     RetFlag, CallBackKind, CurrentKind, IdtKind, ImplicitEquationKind, NoiseTable, ParamKind, PlaceKind,
 };
 
-use std::collections::HashSet;
-use hir_ty::db::HirTyDB;
-use basedb::CliParamDefaultValue;
-use hir::Name;
-use hir_def::db::HirDefDB;
-use hir::diagnostics::FileId;
-use hir_def::ParamId;
-
-fn lookup_default_for(
-    db: &dyn HirDefDB,
-    root_file: FileId,
-    param: ParamId,
-) -> Option<(Name, CliParamDefaultValue)> {
-    db.cli_param_defaults_by_id(root_file).get(&param).cloned()
-}
 
 impl BodyLoweringCtx<'_, '_, '_> {
     pub fn lower_expr(&mut self, expr: ExprId) -> Value {
         let old_loc = self.ctx.get_srcloc();
         self.ctx.set_srcloc(mir::SourceLoc::new(u32::from(expr) as i32 + 1));
-        
-        // BEGIN RDUBI CHANGES
-        // let comparison =  hir::get_param(496_u32);
-        // Hashmap because of Rust things I don't know how to resolve
-        // let mut params_to_leave = HashSet::new();
-        // for p in self.ctx.params_to_leave {
-        //     params_to_leave.insert(hir::get_param(*p));;
-        // }
-
-        let type_db: &dyn HirTyDB = self.ctx.db;
-        // END RDUBI CHANGES
+
         let mut res = match self.body.get_expr(expr) {
             Expr::Read(Ref::Variable(var)) => self.ctx.read_variable(var),
-            Expr::Read(Ref::ParamSysFun(param)) => {
-                self.ctx.use_param(ParamKind::ParamSysFun(param))
-            }
+            Expr::Read(Ref::ParamSysFun(param)) => self.ctx.param_sysfun(param),
             // BEGIN RDUBI CHANGES
-            Expr::Read(Ref::Parameter(param)) => {
-                 if let Some(default) = lookup_default_for(type_db, /* root file id */hir::diagnostics::FileId(7), hir::get_id(param)) {
-                     println!("Omitting parameter {:?}", hir::get_id(param));
-                     match default {
-                         (_, CliParamDefaultValue::Int(i)) => self.ctx.iconst(i),
-                         (_, CliParamDefaultValue::Float(f)) => self.ctx.fconst(f),
-                         _ => self.ctx.use_param(ParamKind::Param(param)),
-                    }
+            Expr::Read(Ref::Parameter(param)) => match self.ctx.elided_param_val(param) {
+                Some(val) => {
+                    self.ctx.intern.elided_params.insert(param);
+                    val
                 }
-                else {
-                    self.ctx.use_param(ParamKind::Param(param))
-                }
-            }
+                None => self.ctx.use_param(ParamKind::Param(param)),
+            },
             // END RDUBI CHANGES
             Expr::Read(Ref::FunctionReturn(fun)) => {
                 self.ctx.use_place(PlaceKind::FunctionReturn(fun))
@@ -636,7 +603,7 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 let fac = self.ctx.fconst(KB / Q);
                 let temp = match args.get(0) {
                     Some(temp) => self.lower_expr(*temp),
-                    None => self.ctx.use_param(ParamKind::Temperature),
+                    None => self.ctx.temperature(),
                 };
 
                 self.ctx.ins().fmul(fac, temp)
@@ -654,8 +621,15 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 };
                 self.ctx.call1(call, &[val])
             }
-            BuiltIn::temperature => self.ctx.use_param(ParamKind::Temperature),
+            BuiltIn::temperature => self.ctx.temperature(),
             BuiltIn::simparam => {
+                let assumed = match self.body.as_literal(args[0]) {
+                    Some(Literal::String(name)) => self.ctx.assumed_simparam(name),
+                    _ => None,
+                };
+                if let Some(val) = assumed {
+                    return self.ctx.fconst(val);
+                }
                 let arg0 = self.lower_expr(args[0]);
                 match_signature! {signature:
                     SIMPARAM_NO_DEFAULT => self.ctx.call1(CallBackKind::SimParam, &[arg0]),
@@ -669,15 +643,22 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 let arg0 = self.lower_expr(args[0]);
                 self.ctx.call1(CallBackKind::SimParamStr, &[arg0])
             }
-            BuiltIn::param_given => self
-                .ctx
-                .use_param(ParamKind::ParamGiven { param: self.body.into_parameter(args[0]) }),
+            BuiltIn::param_given => {
+                let param = self.body.into_parameter(args[0]);
+                // elided parameters are always given
+                if self.ctx.param_default(param).is_some() {
+                    self.ctx.intern.elided_param_given.insert(param);
+                    TRUE
+                } else {
+                    self.ctx.use_param(ParamKind::ParamGiven { param })
+                }
+            }
             BuiltIn::port_connected => {
                 self.ctx.use_param(ParamKind::PortConnected { port: self.body.into_node(args[0]) })
             }
             BuiltIn::bound_step => {
                 let step_size = self.lower_expr(args[0]);
-                self.ctx.def_place(PlaceKind::BoundStep, step_size);
+                self.ctx.bound_step(step_size);
                 GRAVESTONE
             }
 
@@ -728,36 +709,45 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 GRAVESTONE
             },
 
-            /* TODO: absdelay
+            // without implicit equations (for example during parameter initialization)
+            // the delay can not be modelled in any analysis, the input is passed through
+            BuiltIn::absdelay if self.ctx.no_equations => self.lower_expr(args[0]),
             BuiltIn::absdelay => {
                 let arg = self.lower_expr(args[0]);
                 let mut delay = self.lower_expr(args[1]);
-                let (eq1, res) = self.ctx.implicit_equation(ImplicitEquationKind::Absdelay);
-                let (eq2, intermediate) = self.ctx.implicit_equation(ImplicitEquationKind::Absdelay);
                 if signature == ABSDELAY_MAX {
+                    // the delay may change during the simulation but never exceeds max_delay
                     let max_delay = self.lower_expr(args[2]);
-                    let use_delay = self.ctx.ins().fle(delay, max_delay);
-                    delay = self.lower_select_with(use_delay, |_| delay, |_| max_delay);
-                } else {
-                    delay = self.ctx.call1(CallBackKind::StoreDelayTime(eq1), &[delay]);
+                    let too_large = self.ctx.ins().fgt(delay, max_delay);
+                    delay = self.lower_select_with(too_large, |_| max_delay, |_| delay);
                 }
-
-                let mut resist_val = self.ctx.ins().fsub(res, arg);
-                resist_val = self.ctx.ins().fdiv(resist_val, delay);
-                self.ctx.def_resist_residual(resist_val, eq1);
-                self.ctx.def_react_residual(intermediate, eq1);
-
-                let mut resist_val = self.ctx.ins().fsub(res, intermediate);
-                resist_val = self.ctx.ins().fdiv(resist_val, delay);
-                self.ctx.def_resist_residual(resist_val, eq2);
-                let react_val = self.ctx.ins().fdiv(res, F_THREE);
-                self.ctx.def_react_residual(react_val, eq2);
-
-                res
-            }*/
-            BuiltIn::slew | BuiltIn::transition | BuiltIn::limit | BuiltIn::absdelay => {
+                self.lower_absdelay(arg, delay)
+            }
+            // both filters only affect transient analysis
+            BuiltIn::transition | BuiltIn::slew if self.ctx.no_equations => {
                 self.lower_expr(args[0])
             }
+            BuiltIn::transition => {
+                let arg = self.lower_expr(args[0]);
+                let delay = args.get(1).map_or(F_ZERO, |delay| self.lower_expr(*delay));
+                let rise = args.get(2).map_or(F_ZERO, |rise| self.lower_expr(*rise));
+                // the fall time defaults to the rise time, the time tolerance is ignored
+                let fall = args.get(3).map_or(rise, |fall| self.lower_expr(*fall));
+                self.ctx.transition(arg, delay, rise, fall)
+            }
+            // without a maximum slew rate the input is passed through
+            BuiltIn::slew if signature == SLEW_NO_MAX => self.lower_expr(args[0]),
+            BuiltIn::slew => {
+                let arg = self.lower_expr(args[0]);
+                let max_rise = self.lower_expr(args[1]);
+                let max_fall = if signature == SLEW_NEG_MAX {
+                    self.lower_expr(args[2])
+                } else {
+                    self.ctx.ins().fneg(max_rise)
+                };
+                self.ctx.slew(arg, max_rise, max_fall)
+            }
+            BuiltIn::limit => self.lower_expr(args[0]),
 
             _ => unreachable!(),
         }
@@ -825,6 +815,46 @@ impl BodyLoweringCtx<'_, '_, '_> {
         val
     }
 
+    /// Approximates the delay `exp(-sT)` with the second order Padé approximant
+    /// `(1 - sT/2 + (sT)²/12) / (1 + sT/2 + (sT)²/12) = 1 - sT / (1 + sT/2 + (sT)²/12)`.
+    /// Two implicit equations solve `z + T/2 z' + T²/12 z'' = arg` for `z` and `w = T z'`,
+    /// the delayed value is `arg - w`. The approximation is exact in DC, an all-pass
+    /// phase shift in AC and a smoothed delay in transient analysis.
+    fn lower_absdelay(&mut self, arg: Value, delay: Value) -> Value {
+        let (z_equation, z) = self.ctx.implicit_equation(ImplicitEquationKind::Absdelay);
+        let (w_equation, w) = self.ctx.implicit_equation(ImplicitEquationKind::Absdelay);
+
+        let has_delay = self.ctx.ins().fgt(delay, F_ZERO);
+        let [z_resist, z_react, w_resist, w_react] =
+            self.lower_multi_select(has_delay, |ctx, has_delay| {
+                let ctx = ctx.ctx;
+                if has_delay {
+                    // z' = w / T
+                    let z_resist = ctx.ins().fdiv(w, delay);
+                    let z_resist = ctx.ins().fneg(z_resist);
+                    // w' = (12 (arg - z) - 6 w) / T
+                    let twelve = ctx.fconst(12.0);
+                    let six = ctx.fconst(6.0);
+                    let diff = ctx.ins().fsub(z, arg);
+                    let diff = ctx.ins().fmul(twelve, diff);
+                    let damping = ctx.ins().fmul(six, w);
+                    let w_resist = ctx.ins().fadd(diff, damping);
+                    let w_resist = ctx.ins().fdiv(w_resist, delay);
+                    [z_resist, z, w_resist, w]
+                } else {
+                    // without a delay the input is passed through
+                    [ctx.ins().fsub(z, arg), F_ZERO, w, F_ZERO]
+                }
+            });
+
+        self.ctx.def_resist_residual(z_resist, z_equation);
+        self.ctx.def_react_residual(z_react, z_equation);
+        self.ctx.def_resist_residual(w_resist, w_equation);
+        self.ctx.def_react_residual(w_react, w_equation);
+
+        self.ctx.ins().fsub(arg, w)
+    }
+
     pub fn resolved_ty(&self, expr: ExprId) -> Type {
         self.body
             .needs_cast(expr)
@@ -5,10 +5,12 @@ use bitset::HybridBitSet;
 use hir::{
     Branch, BranchWrite, CompilationDB, Module, Node, ParamSysFun, Parameter, Type, Variable,
 };
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use lasso::Rodeo;
 use mir::builder::InstBuilder;
-use mir::{DataFlowGraph, FuncRef, Function, Inst, KnownDerivatives, Param, Unknown, Value};
+use mir::{
+    Const, DataFlowGraph, FuncRef, Function, Inst, KnownDerivatives, Param, Unknown, Value,
+};
 use mir_build::{FunctionBuilder, FunctionBuilderContext, RetBuilder};
 use stdx::packed_option::PackedOption;
 use stdx::{impl_debug_display, impl_idx_from};
@@ -30,20 +32,25 @@ macro_rules! match_signature {
     };
 }
 
+mod assumptions;
 mod body;
 mod callbacks;
 mod ctx;
+mod events;
 mod expr;
 pub mod fmt;
 mod parameters;
 mod state;
 mod stmt;
+mod transition;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ImplicitEquationKind {
     Ddt,
     NoiseSrc,
     Idt(IdtKind),
+    /// One of the two states of the Padé approximation of `absdelay`
+    Absdelay,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -81,6 +88,8 @@ pub enum ParamKind {
     EnableLim,
     PrevState(LimitState),
     NewState(LimitState),
+    /// The value an analog operator stored in its state during the previous evaluation
+    PrevOperatorState(OperatorState),
     Voltage { hi: Node, lo: Option<Node> },
     Current(CurrentKind),
     Temperature,
@@ -110,6 +119,7 @@ impl ParamKind {
                 | ParamKind::HiddenState(_)
                 | ParamKind::PrevState(_)
                 | ParamKind::NewState(_)
+                | ParamKind::PrevOperatorState(_)
                 | ParamKind::EnableLim
         )
     }
@@ -219,6 +229,15 @@ impl_debug_display! {
     match LimitState {LimitState(i) => "lim_state{}", i;}
 }
 
+/// A value that an analog operator (like `transition`) keeps between evaluations.
+/// Operator states are placed after the limit states in the state vector of the simulator.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OperatorState(u32);
+impl_idx_from!(OperatorState(u32));
+impl_debug_display! {
+    match OperatorState {OperatorState(i) => "op_state{}", i;}
+}
+
 /// A mapping between abstractions used in the MIR and the corresponding
 /// information from the HIR. This allows the MIR to remain independent of the frontend/HIR
 #[derive(Debug, PartialEq, Default, Clone)]
@@ -230,6 +249,15 @@ pub struct HirInterner {
     pub tagged_reads: IndexMap<Value, Variable, ahash::RandomState>,
     pub implicit_equations: TiVec<ImplicitEquation, ImplicitEquationKind>,
     pub lim_state: TiMap<LimitState, Value, Vec<(Value, bool)>>,
+    /// The number of states used by analog operators
+    pub operator_states: u32,
+    /// Parameters whose reads were replaced with their elided value
+    pub elided_params: IndexSet<Parameter, ahash::RandomState>,
+    /// Parameters whose `$param_given` calls were folded because they are elided
+    pub elided_param_given: IndexSet<Parameter, ahash::RandomState>,
+    /// Elided parameters that are still exposed to the simulator
+    /// (`--elided-params lock`) and the value they were elided to
+    pub locked_params: IndexMap<Parameter, Const, ahash::RandomState>,
 }
 
 pub type LiveParams<'a> = FilterMap<
@@ -380,6 +408,16 @@ impl HirInterner {
         *entry.or_insert_with(|| func.as_mut().dfg.make_param(len.into()))
     }
 
+    /// The number of entries this module requires in the state vector of the simulator
+    pub fn num_states(&self) -> u32 {
+        self.lim_state.len() as u32 + self.operator_states
+    }
+
+    /// The index of `state` in the state vector of the simulator
+    pub fn operator_state_idx(&self, state: OperatorState) -> u32 {
+        self.lim_state.len() as u32 + u32::from(state)
+    }
+
     pub fn live_params<'a>(
         &'a self,
         dfg: &'a DataFlowGraph,
@@ -464,7 +502,7 @@ impl<'a> MirBuilder<'a> {
         self
     }
 
-    pub fn build(self, literals: &mut Rodeo, params_to_leave: &'a Vec<u32>) -> (Function, HirInterner) {
+    pub fn build(self, literals: &mut Rodeo) -> (Function, HirInterner) {
         let mut func = Function::default();
         let mut interner = HirInterner::default();
 
@@ -482,7 +520,7 @@ impl<'a> MirBuilder<'a> {
         let analog_initial_body = self.module.analog_initial_block(self.db);
         let analog_body = self.module.analog_block(self.db);
 
-        let mut ctx = LoweringCtx::new(self.db, builder, !self.lower_equations, &mut interner, params_to_leave)
+        let mut ctx = LoweringCtx::new(self.db, builder, !self.lower_equations, &mut interner)
             .with_tagged_vars(self.tagged_reads);
         let mut body_ctx =
             BodyLoweringCtx { ctx: &mut ctx, body: analog_initial_body.borrow(), path: &path };
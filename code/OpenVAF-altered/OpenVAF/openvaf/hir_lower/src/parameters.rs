@@ -1,7 +1,7 @@
 use std::f64::NEG_INFINITY;
 use std::mem::replace;
 
-use hir::{CompilationDB, ConstraintValue, ParamConstraint, Parameter, Type};
+use hir::{CompilationDB, ConstraintValue, ParamConstraint, ParamRange, Parameter, Type};
 use lasso::Rodeo;
 use mir::builder::InstBuilder;
 use mir::{Block, FuncRef, Function, Opcode, Value, FALSE, GRAVESTONE, INFINITY};
@@ -44,6 +44,56 @@ impl CmpOps {
     }
 }
 
+/// Calls `invalid` if `val` is not within `range`. Integers are converted to reals
+/// for the comparison.
+fn check_assumed_range(
+    ctx: &mut LoweringCtx,
+    val: Value,
+    ty: &Type,
+    range: &ParamRange,
+    invalid: FuncRef,
+) {
+    let val = if *ty == Type::Integer { ctx.ins().ifcast(val) } else { val };
+    let ops = CmpOps::from_ty(&Type::Real);
+    let invalid_bb = ctx.create_block();
+    let exit = ctx.create_block();
+
+    match *range {
+        ParamRange::Interval { lo, lo_inclusive, hi, hi_inclusive } => {
+            if lo.is_finite() {
+                let lo = ctx.fconst(lo);
+                let is_ok = ctx.ins().binary1(ops.in_bound(lo_inclusive), lo, val);
+                let next_bb = ctx.create_block();
+                ctx.ins().br(is_ok, next_bb, invalid_bb);
+                ctx.switch_to_block(next_bb);
+            }
+            if hi.is_finite() {
+                let hi = ctx.fconst(hi);
+                let is_ok = ctx.ins().binary1(ops.in_bound(hi_inclusive), val, hi);
+                let next_bb = ctx.create_block();
+                ctx.ins().br(is_ok, next_bb, invalid_bb);
+                ctx.switch_to_block(next_bb);
+            }
+            ctx.ins().jump(exit);
+        }
+        ParamRange::OneOf(ref vals) => {
+            for &allowed in vals {
+                let allowed = ctx.fconst(allowed);
+                let is_ok = ctx.ins().binary1(ops.eq, val, allowed);
+                let next_bb = ctx.create_block();
+                ctx.ins().br(is_ok, exit, next_bb);
+                ctx.switch_to_block(next_bb);
+            }
+            ctx.ins().jump(invalid_bb);
+        }
+    }
+
+    ctx.switch_to_block(invalid_bb);
+    ctx.ins().call(invalid, &[]);
+    ctx.ins().jump(exit);
+    ctx.switch_to_block(exit);
+}
+
 impl HirInterner {
     pub fn insert_param_init(
         &mut self,
@@ -63,13 +113,41 @@ impl HirInterner {
 
         let mut ctx = FunctionBuilderContext::default();
         let (builder, term) = FunctionBuilder::edit(func, literals, &mut ctx, false);
-        let placeholder_vec = Vec::new();
-        let mut ctx = LoweringCtx::new(db, builder, true, self, &placeholder_vec);
+        let mut ctx = LoweringCtx::new(db, builder, true, self);
 
         for (i, param) in params.iter().copied().enumerate() {
             let mut param_val = ctx.use_param(ParamKind::Param(param));
             let param_given = ctx.use_param(ParamKind::ParamGiven { param });
 
+            // Elided parameters are always given. Their value was already validated
+            // at compile time, so the only thing left to check at runtime is that
+            // the simulator did not try to set a different value.
+            if let Some(val) = ctx.elided_param_val(param) {
+                let const_val = ctx.dfg().value_def(val).unwrap_const();
+                ctx.intern.locked_params.insert(param, const_val);
+                ctx.dfg_mut().replace_uses(param_val, val);
+                if build_stores {
+                    let ops = CmpOps::from_ty(&param.ty(db));
+                    let invalid =
+                        ctx.dec_callback(CallBackKind::ParamInfo(ParamInfoKind::Invalid, param));
+                    let check_bb = ctx.create_block();
+                    let invalid_bb = ctx.create_block();
+                    let exit = ctx.create_block();
+                    ctx.ins().br(param_given, check_bb, exit);
+                    ctx.switch_to_block(check_bb);
+                    let is_ok = ctx.ins().binary1(ops.eq, param_val, val);
+                    ctx.ins().br(is_ok, exit, invalid_bb);
+                    ctx.switch_to_block(invalid_bb);
+                    ctx.ins().call(invalid, &[]);
+                    ctx.ins().jump(exit);
+                    ctx.switch_to_block(exit);
+                    default_vals[i] = ctx.ins().optbarrier(val);
+                }
+                ctx.def_param(ParamKind::Param(param), val);
+                ctx.def_output(PlaceKind::Param(param), param_val);
+                continue;
+            }
+
             // create a temporary to hold onto the uses
             let new_val = ctx.func.make_param(0u32.into());
             ctx.dfg_mut().replace_uses(param_val, new_val);
@@ -140,6 +218,15 @@ impl HirInterner {
             // let last_inst = builder.func.layout.last_inst(else_src.0).unwrap();
             ctx.ins().with_result(new_val).phi(&[then_src, else_src]);
 
+            // The value of a parameter with an assumed range is still read at runtime.
+            // Code generation may rely on the assumption so values outside of the range
+            // must be rejected.
+            if build_stores {
+                if let Some(range) = ctx.param_range(param) {
+                    check_assumed_range(&mut ctx, new_val, &ty, &range, invalid);
+                }
+            }
+
             // we purposfull insert these reversed here (new val into params and old val into
             // outputs). This ensures that the code generated for other parameters uses the
             // correct value. After code generation is complete we swap these two again
@@ -15,8 +15,7 @@ impl HirInterner {
     ) {
         let mut ctx = FunctionBuilderContext::default();
         let (builder, term) = FunctionBuilder::edit(func, literals, &mut ctx, false);
-        let placeholder_vec = Vec::new();
-        let mut ctx = LoweringCtx::new(db, builder, true, self, &placeholder_vec);
+        let mut ctx = LoweringCtx::new(db, builder, true, self);
         for (kind, param) in ctx.intern.params.clone().iter() {
             if let ParamKind::HiddenState(var) = *kind {
                 if ctx.dfg().value_dead(*param) {
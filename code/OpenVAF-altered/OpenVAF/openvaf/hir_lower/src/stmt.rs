@@ -1,6 +1,9 @@
-use hir::{BranchWrite, Case, CaseCond, ContributeKind, ExprId, Node, Stmt, StmtId, Type};
+use hir::{
+    BranchWrite, BuiltIn, Case, CaseCond, ContributeKind, Event, Expr, ExprId, GlobalEvent, Node,
+    ResolvedFun, Stmt, StmtId, Type,
+};
 use mir::builder::InstBuilder;
-use mir::{Opcode, F_ZERO};
+use mir::{Opcode, Value, F_ZERO, ZERO};
 
 use crate::body::BodyLoweringCtx;
 use crate::{CallBackKind, CurrentKind, ParamKind, PlaceKind};
@@ -17,9 +20,13 @@ impl BodyLoweringCtx<'_, '_, '_> {
             Stmt::Expr(expr) => {
                 self.lower_expr(expr);
             }
-            Stmt::EventControl { body, .. } => {
-                // TODO handle porperly
-                self.lower_stmt(body);
+            Stmt::EventControl { event, body } => {
+                let occurred = self.lower_event(event);
+                self.ctx.make_cond(occurred, |ctx, occurred| {
+                    if occurred {
+                        BodyLoweringCtx { body: self.body, path: self.path, ctx }.lower_stmt(body);
+                    }
+                });
             }
             Stmt::Assignment { lhs, rhs } => {
                 let val_ = self.lower_expr(rhs);
@@ -54,6 +61,45 @@ impl BodyLoweringCtx<'_, '_, '_> {
         }
     }
 
+    /// Returns `true` if `event` occurs during the current evaluation.
+    fn lower_event(&mut self, event: &Event) -> Value {
+        let (fun, args) = match *event {
+            Event::Global { kind: GlobalEvent::InitialStep, ref phases } => {
+                return self.ctx.initial_step(phases)
+            }
+            Event::Global { kind: GlobalEvent::FinalStep, ref phases } => {
+                return self.ctx.final_step(phases)
+            }
+            Event::Monitored(expr) => match self.body.get_expr(expr) {
+                Expr::Call { fun: ResolvedFun::BuiltIn(fun), args } => (fun, args),
+                expr => unreachable!("invalid event {expr:?}"),
+            },
+            _ => unreachable!("unsupported event {event:?}"),
+        };
+
+        let val = self.lower_expr(args[0]);
+        let arg = |s: &mut Self, i: usize| args.get(i).map(|arg| s.lower_expr(*arg));
+        match fun {
+            BuiltIn::cross => {
+                let dir = arg(self, 1).unwrap_or(ZERO);
+                let time_tol = arg(self, 2);
+                let expr_tol = arg(self, 3).unwrap_or(F_ZERO);
+                self.ctx.cross(val, dir, time_tol, expr_tol)
+            }
+            BuiltIn::above => {
+                let time_tol = arg(self, 1);
+                let expr_tol = arg(self, 2).unwrap_or(F_ZERO);
+                self.ctx.above(val, time_tol, expr_tol)
+            }
+            BuiltIn::timer => {
+                let period = arg(self, 1);
+                let time_tol = arg(self, 2);
+                self.ctx.timer(val, period, time_tol)
+            }
+            _ => unreachable!("{fun:?} is not an event function"),
+        }
+    }
+
     fn lower_case(&mut self, discr: ExprId, case_arms: &[Case]) {
         let discr_op = match self.body.expr_type(discr) {
             Type::Real => Opcode::Feq,
@@ -0,0 +1,254 @@
+//! Lowering of the `transition` and `slew` filters.
+//!
+//! Both filters (and the monitored events) depend on the values they produced at
+//! previous time points.
+//! These values are kept in the state vector of the simulator. The simulator
+//! may either pass the states of the last iteration or of the last accepted
+//! time point, so the stored values must remain valid in both cases. Outside of
+//! transient analysis both filters pass their input through unchanged, which
+//! gives them a unit transfer function for AC analysis.
+//!
+//! `transition` bounds the time step so that the next time point lands on the
+//! next corner of its output. The bound has the same meaning as for events (see
+//! the [`events`](crate::events) module): the largest next step measured from
+//! the current time.
+
+use hir::Type;
+use mir::builder::InstBuilder;
+use mir::{Value, FALSE, F_ZERO, INFINITY, TRUE};
+
+use crate::ctx::LoweringCtx;
+use crate::{CallBackKind, OperatorState, ParamKind};
+
+/// The value of an expression at the last accepted time point.
+///
+/// The value and time of the last evaluation are stored in addition to those of
+/// the last accepted time point. Once the simulator advances past the last
+/// evaluation, that evaluation must have been accepted.
+#[derive(Clone, Copy)]
+pub(crate) struct History {
+    accepted_val: OperatorState,
+    accepted_time: OperatorState,
+    last_val: OperatorState,
+    last_time: OperatorState,
+}
+
+impl LoweringCtx<'_, '_> {
+    /// Returns `true` if the simulator is running the analysis `name`.
+    pub(crate) fn analysis(&mut self, name: &str) -> Value {
+        let name = self.sconst(name);
+        let res = self.call1(CallBackKind::Analysis, &[name]);
+        self.insert_cast(res, &Type::Integer, &Type::Bool)
+    }
+
+    /// Returns `true` during the time steps of a transient analysis (but not
+    /// during the calculation of its operating point).
+    pub(crate) fn is_transient(&mut self) -> Value {
+        let is_tran = self.analysis("tran");
+        let is_static = self.analysis("static");
+        self.make_select(is_static, |_, is_static| if is_static { FALSE } else { is_tran })
+    }
+
+    fn store_operator_state(&mut self, state: OperatorState, val: Value) {
+        self.call(CallBackKind::StoreOperatorState(state), &[val]);
+    }
+
+    pub(crate) fn history(&mut self) -> History {
+        History {
+            accepted_val: self.operator_state(),
+            accepted_time: self.operator_state(),
+            last_val: self.operator_state(),
+            last_time: self.operator_state(),
+        }
+    }
+
+    /// Returns the value and the time of the last accepted time point. The
+    /// stored values are only valid during transient analysis.
+    pub(crate) fn load_history(&mut self, history: History, time: Value) -> [Value; 2] {
+        let last_time = self.use_param(ParamKind::PrevOperatorState(history.last_time));
+        let advanced = self.ins().fgt(time, last_time);
+        self.make_multi_select(advanced, |ctx, advanced| {
+            if advanced {
+                let last_val = ctx.use_param(ParamKind::PrevOperatorState(history.last_val));
+                [last_val, last_time]
+            } else {
+                let accepted_val =
+                    ctx.use_param(ParamKind::PrevOperatorState(history.accepted_val));
+                let accepted_time =
+                    ctx.use_param(ParamKind::PrevOperatorState(history.accepted_time));
+                [accepted_val, accepted_time]
+            }
+        })
+    }
+
+    /// Stores the last accepted time point and the value `val` of the current
+    /// evaluation at `time`.
+    pub(crate) fn store_history(
+        &mut self,
+        history: History,
+        [accepted_val, accepted_time]: [Value; 2],
+        val: Value,
+        time: Value,
+    ) {
+        self.store_operator_state(history.accepted_val, accepted_val);
+        self.store_operator_state(history.accepted_time, accepted_time);
+        self.store_operator_state(history.last_val, val);
+        self.store_operator_state(history.last_time, time);
+    }
+
+    fn fmin(&mut self, lhs: Value, rhs: Value) -> Value {
+        let is_smaller = self.ins().flt(lhs, rhs);
+        self.make_select(is_smaller, |_, is_smaller| if is_smaller { lhs } else { rhs })
+    }
+
+    pub(crate) fn fmax(&mut self, lhs: Value, rhs: Value) -> Value {
+        let is_larger = self.ins().fgt(lhs, rhs);
+        self.make_select(is_larger, |_, is_larger| if is_larger { lhs } else { rhs })
+    }
+
+    /// The duration of a transition from `start` to `target`.
+    fn transition_time(&mut self, start: Value, target: Value, rise: Value, fall: Value) -> Value {
+        let is_rising = self.ins().fgt(target, start);
+        self.make_select(is_rising, |_, is_rising| if is_rising { rise } else { fall })
+    }
+
+    /// The value at `time` of a linear ramp from `start` to `target` that
+    /// begins at `begin` and lasts `duration`.
+    fn ramp(
+        &mut self,
+        start: Value,
+        target: Value,
+        begin: Value,
+        duration: Value,
+        time: Value,
+    ) -> Value {
+        let end = self.ins().fadd(begin, duration);
+        let finished = self.ins().fge(time, end);
+        self.make_select(finished, |ctx, finished| {
+            if finished {
+                return target;
+            }
+            let started = ctx.ins().fgt(time, begin);
+            ctx.make_select(started, |ctx, started| {
+                if !started {
+                    return start;
+                }
+                let elapsed = ctx.ins().fsub(time, begin);
+                let progress = ctx.ins().fdiv(elapsed, duration);
+                let delta = ctx.ins().fsub(target, start);
+                let delta = ctx.ins().fmul(delta, progress);
+                ctx.ins().fadd(start, delta)
+            })
+        })
+    }
+
+    /// `transition(arg, delay, rise, fall)` ramps linearly towards the new
+    /// value of `arg` whenever it changes. The ramp starts `delay` after the
+    /// change and takes `rise` (`fall`) seconds. The time steps are bounded so
+    /// that the simulator hits the corners of the ramp. Negative times are
+    /// treated as zero.
+    ///
+    /// The target, the time of the last change and the value at that time are
+    /// kept in the state vector. If the input changes while a transition is in
+    /// progress, the output holds its current value until the new ramp starts.
+    pub(crate) fn transition(
+        &mut self,
+        arg: Value,
+        delay: Value,
+        rise: Value,
+        fall: Value,
+    ) -> Value {
+        let target_state = self.operator_state();
+        let change_state = self.operator_state();
+        let start_state = self.operator_state();
+
+        let is_transient = self.is_transient();
+        let [target, change, start, res] = self.make_multi_select(is_transient, |ctx, tran| {
+            if !tran {
+                let never = ctx.fconst(f64::NEG_INFINITY);
+                return [arg, never, arg, arg];
+            }
+
+            let delay = ctx.fmax(delay, F_ZERO);
+            let rise = ctx.fmax(rise, F_ZERO);
+            let fall = ctx.fmax(fall, F_ZERO);
+
+            let time = ctx.use_param(ParamKind::Abstime);
+            let prev_target = ctx.use_param(ParamKind::PrevOperatorState(target_state));
+            let prev_change = ctx.use_param(ParamKind::PrevOperatorState(change_state));
+            let prev_start = ctx.use_param(ParamKind::PrevOperatorState(start_state));
+
+            let changed = ctx.ins().fne(arg, prev_target);
+            let new_change = ctx.make_select(changed, |ctx, changed| {
+                if changed {
+                    TRUE
+                } else {
+                    // a time step was rejected after the input changed,
+                    // treat the change as if it happened now
+                    ctx.ins().flt(time, prev_change)
+                }
+            });
+            let [target, change, start] = ctx.make_multi_select(new_change, |ctx, new_change| {
+                if !new_change {
+                    return [prev_target, prev_change, prev_start];
+                }
+                let begin = ctx.ins().fadd(prev_change, delay);
+                let duration = ctx.transition_time(prev_start, prev_target, rise, fall);
+                let current = ctx.ramp(prev_start, prev_target, begin, duration, time);
+                [arg, time, current]
+            });
+
+            let begin = ctx.ins().fadd(change, delay);
+            let duration = ctx.transition_time(start, target, rise, fall);
+            let res = ctx.ramp(start, target, begin, duration, time);
+
+            // the next time step should end at the next corner of the ramp
+            let end = ctx.ins().fadd(begin, duration);
+            let before_begin = ctx.ins().flt(time, begin);
+            let corner = ctx.make_select(before_begin, |ctx, before_begin| {
+                if before_begin {
+                    return begin;
+                }
+                let before_end = ctx.ins().flt(time, end);
+                ctx.make_select(before_end, |_, before_end| if before_end { end } else { INFINITY })
+            });
+            let step = ctx.ins().fsub(corner, time);
+            ctx.bound_step(step);
+
+            [target, change, start, res]
+        });
+
+        self.store_operator_state(target_state, target);
+        self.store_operator_state(change_state, change);
+        self.store_operator_state(start_state, start);
+        res
+    }
+
+    /// `slew(arg, max_rise, max_fall)` follows `arg` but its slope is bounded
+    /// by `max_rise` and `max_fall` (which is negative). The slope is measured
+    /// against the last accepted time point.
+    pub(crate) fn slew(&mut self, arg: Value, max_rise: Value, max_fall: Value) -> Value {
+        let history = self.history();
+        let time = self.use_param(ParamKind::Abstime);
+        let is_transient = self.is_transient();
+        let [accepted_val, accepted_time, res] =
+            self.make_multi_select(is_transient, |ctx, tran| {
+                if !tran {
+                    return [arg, time, arg];
+                }
+
+                let [accepted_val, accepted_time] = ctx.load_history(history, time);
+                let elapsed = ctx.ins().fsub(time, accepted_time);
+                let max_delta = ctx.ins().fmul(max_rise, elapsed);
+                let upper = ctx.ins().fadd(accepted_val, max_delta);
+                let min_delta = ctx.ins().fmul(max_fall, elapsed);
+                let lower = ctx.ins().fadd(accepted_val, min_delta);
+                let res = ctx.fmin(arg, upper);
+                let res = ctx.fmax(res, lower);
+                [accepted_val, accepted_time, res]
+            });
+
+        self.store_history(history, [accepted_val, accepted_time], res, time);
+        res
+    }
+}
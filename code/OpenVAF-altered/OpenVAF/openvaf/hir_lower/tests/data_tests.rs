@@ -29,14 +29,15 @@ fn lower(db: &CompilationDB) {
 fn integration_test(dir: &Path) -> Result {
     let name = dir.file_name().unwrap().to_str().unwrap().to_lowercase();
     let main_file = dir.join(format!("{name}.va")).canonicalize().unwrap();
-    let db = CompilationDB::new_fs(AbsPathBuf::assert(main_file), &[], &[], &[]).unwrap();
+    let db = CompilationDB::new_fs(AbsPathBuf::assert(main_file), &[], &[], &[], &[]).unwrap();
     lower(&db);
     Ok(())
 }
 
 fn mir_test(file: &Path) -> Result {
-    let db = CompilationDB::new_fs(AbsPathBuf::assert(file.canonicalize().unwrap()), &[], &[], &[])
-        .unwrap();
+    let db =
+        CompilationDB::new_fs(AbsPathBuf::assert(file.canonicalize().unwrap()), &[], &[], &[], &[])
+            .unwrap();
     assert_eq!(db.compilation_unit().test_diagnostics(&db), "");
 
     let module = db.compilation_unit().modules(&db)[0];
@@ -263,11 +263,11 @@ bultins! {
 
 
     TRANSITION = const {
-        fn TRANSITION_NO_ARGS(Val(Integer)) -> Real;
-        fn TRANSITION_DELAY(Val(Integer),Val(Real)) -> Real;
-        fn TRANSITION_DELAY_RISET(Val(Integer),Val(Real)) -> Real;
-        fn TRANSITION_DELAY_RISET_FALLT(Val(Integer),Val(Real),Val(Real)) -> Real;
-        fn TRANSITION_DELAY_RISET_FALLT_TOL(Val(Integer),Val(Real),Val(Real), Val(Real)) -> Real;
+        fn TRANSITION_NO_ARGS(Val(Real)) -> Real;
+        fn TRANSITION_DELAY(Val(Real),Val(Real)) -> Real;
+        fn TRANSITION_DELAY_RISET(Val(Real),Val(Real),Val(Real)) -> Real;
+        fn TRANSITION_DELAY_RISET_FALLT(Val(Real),Val(Real),Val(Real),Val(Real)) -> Real;
+        fn TRANSITION_DELAY_RISET_FALLT_TOL(Val(Real),Val(Real),Val(Real),Val(Real), Val(Real)) -> Real;
     }
 
 
@@ -276,6 +276,25 @@ bultins! {
         fn LAST_CROSSING_DIRECTION(Val(Real),Val(Integer)) -> Real;
     }
 
+    CROSS = const {
+        fn CROSS_NO_DIRECTION(Val(Real)) -> Void;
+        fn CROSS_DIRECTION(Val(Real),Val(Integer)) -> Void;
+        fn CROSS_TIME_TOL(Val(Real),Val(Integer),Val(Real)) -> Void;
+        fn CROSS_EXPR_TOL(Val(Real),Val(Integer),Val(Real),Val(Real)) -> Void;
+    }
+
+    ABOVE = const {
+        fn ABOVE_NO_TOL(Val(Real)) -> Void;
+        fn ABOVE_TIME_TOL(Val(Real),Val(Real)) -> Void;
+        fn ABOVE_EXPR_TOL(Val(Real),Val(Real),Val(Real)) -> Void;
+    }
+
+    TIMER = const {
+        fn TIMER_ONCE(Val(Real)) -> Void;
+        fn TIMER_PERIOD(Val(Real),Val(Real)) -> Void;
+        fn TIMER_TIME_TOL(Val(Real),Val(Real),Val(Real)) -> Void;
+    }
+
     fn BASIC_IO(Val(Integer)) -> Integer;
 
      FOPEN = {
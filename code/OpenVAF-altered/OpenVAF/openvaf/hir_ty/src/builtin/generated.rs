@@ -116,5 +116,8 @@ const BUILTIN_INFO: [BuiltinInfo; 111usize] = [
     LAST_CROSSING,
     SLEW,
     TRANSITION,
+    CROSS,
+    ABOVE,
+    TIMER,
 ];
 pub(crate) fn builtin_info(builtin: BuiltIn) -> BuiltinInfo { BUILTIN_INFO[builtin as u8 as usize] }
@@ -0,0 +1,242 @@
+//! Compile time validation of elided parameter values.
+//!
+//! A parameter that was elided on the CLI is replaced by a constant during
+//! lowering. Therefore the bounds checks that are usually generated for the
+//! parameter never see the elided value. Instead the value is checked here
+//! against the type and the constant bounds of the parameter declaration.
+//! Bounds that depend on other parameters can not be checked at compile time.
+
+use basedb::diagnostics::{Diagnostic, Label, LabelStyle, Report};
+use basedb::{BaseDB, CliParamDefaultValue, FileId};
+use hir_def::body::{Body, ConstraintKind, ConstraintValue};
+use hir_def::{Expr, ExprId, Literal, Lookup, ParamId, Type};
+use stdx::impl_display;
+use syntax::ast::UnaryOp;
+use syntax::name::Name;
+use syntax::sourcemap::FileSpan;
+use syntax::TextRange;
+
+use crate::db::HirTyDB;
+
+/// Converts an elided value to the type of the parameter.
+/// Integers are promoted to reals and reals without a fractional part are
+/// accepted for integer parameters. Returns `None` if the types are incompatible.
+pub fn coerce_elided_value(
+    value: &CliParamDefaultValue,
+    ty: &Type,
+) -> Option<CliParamDefaultValue> {
+    match (value, ty) {
+        (CliParamDefaultValue::Int(val), Type::Real) => {
+            Some(CliParamDefaultValue::Float(*val as f64))
+        }
+        (CliParamDefaultValue::Float(_), Type::Real)
+        | (CliParamDefaultValue::Int(_), Type::Integer)
+        | (CliParamDefaultValue::Str(_), Type::String)
+        | (CliParamDefaultValue::Range(_), Type::Real | Type::Integer) => Some(value.clone()),
+        (CliParamDefaultValue::Float(val), Type::Integer) => {
+            let int = *val as i32;
+            (int as f64 == *val).then(|| CliParamDefaultValue::Int(int))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElidedValueDiagnostic {
+    TypeMismatch {
+        name: Name,
+        value: CliParamDefaultValue,
+        expected: Type,
+        span: Option<FileSpan>,
+        decl: FileSpan,
+    },
+    OutOfBounds {
+        name: Name,
+        value: f64,
+        span: Option<FileSpan>,
+        constraint: FileSpan,
+        kind: ConstraintKind,
+    },
+}
+
+use ElidedValueDiagnostic::*;
+
+impl_display! {
+    match ElidedValueDiagnostic{
+        TypeMismatch{name, value, expected, ..} => "parameter '{}' of type {} can not be elided to {}", name, expected, DisplayValue(value);
+        OutOfBounds{name, value, ..} => "elided value {} is out of bounds for parameter '{}'", value, name;
+    }
+}
+
+struct DisplayValue<'a>(&'a CliParamDefaultValue);
+
+impl std::fmt::Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            CliParamDefaultValue::Int(val) => write!(f, "the integer {}", val),
+            CliParamDefaultValue::Float(val) => write!(f, "the real {:?}", val),
+            CliParamDefaultValue::Str(val) => write!(f, "the string \"{}\"", val),
+            CliParamDefaultValue::Range(range) => write!(f, "the range {}", range),
+        }
+    }
+}
+
+impl ElidedValueDiagnostic {
+    pub fn collect(db: &dyn HirTyDB, root_file: FileId) -> Vec<ElidedValueDiagnostic> {
+        let defaults = db.cli_param_defaults_by_id(root_file);
+        if defaults.is_empty() {
+            return Vec::new();
+        }
+
+        let parse = db.parse(root_file);
+        let mut res = Vec::new();
+        for (&param, default) in defaults.iter() {
+            let ty = db.param_ty(param);
+            let value = match coerce_elided_value(&default.value, &ty) {
+                Some(value) => value,
+                None => {
+                    // an erroneous declaration has already been reported
+                    if ty != Type::Err {
+                        let loc = param.lookup(db.upcast());
+                        let decl = parse.to_file_span(
+                            loc.ast_ptr(db.upcast()).range(),
+                            &db.sourcemap(root_file),
+                        );
+                        res.push(TypeMismatch {
+                            name: default.name.clone(),
+                            value: default.value.clone(),
+                            expected: ty,
+                            span: default.span,
+                            decl,
+                        })
+                    }
+                    continue;
+                }
+            };
+
+            let value = match value {
+                CliParamDefaultValue::Int(val) => val as f64,
+                CliParamDefaultValue::Float(val) => val,
+                // ranges are checked at runtime
+                CliParamDefaultValue::Str(_) | CliParamDefaultValue::Range(_) => continue,
+            };
+
+            if let Some((kind, range)) = check_bounds(db, param, value) {
+                let constraint = parse.to_file_span(range, &db.sourcemap(root_file));
+                res.push(OutOfBounds {
+                    name: default.name.clone(),
+                    value,
+                    span: default.span,
+                    constraint,
+                    kind,
+                })
+            }
+        }
+
+        // the iteration order of the hashmap is random
+        res.sort_by(|diag1, diag2| diag1.decl().range.start().cmp(&diag2.decl().range.start()));
+        res
+    }
+
+    fn decl(&self) -> FileSpan {
+        match *self {
+            TypeMismatch { decl, .. } => decl,
+            OutOfBounds { constraint, .. } => constraint,
+        }
+    }
+}
+
+/// Checks `value` against all constant bounds of `param`. Returns the violated
+/// constraint (if any).
+fn check_bounds(
+    db: &dyn HirTyDB,
+    param: ParamId,
+    value: f64,
+) -> Option<(ConstraintKind, TextRange)> {
+    let (body, sm, exprs) = db.param_body_with_sourcemap(param);
+    let range = |expr: ExprId| sm.expr_map_back[expr].as_ref().map(|ptr| ptr.range());
+
+    let mut first_from = None;
+    let mut is_from_ok = exprs.bounds.iter().all(|bound| bound.kind != ConstraintKind::From);
+    for bound in exprs.bounds.iter() {
+        let (contains, src) = match bound.val {
+            ConstraintValue::Value(val) => {
+                let contains = const_eval(&body, val).map(|val| val == value);
+                (contains, range(val))
+            }
+            ConstraintValue::Range(bounds) => {
+                let start = const_eval(&body, bounds.start);
+                let end = const_eval(&body, bounds.end);
+                let contains = start.zip(end).map(|(start, end)| {
+                    let lo_ok = if bounds.start_inclusive { start <= value } else { start < value };
+                    let hi_ok = if bounds.end_inclusive { value <= end } else { value < end };
+                    lo_ok && hi_ok
+                });
+                let src = range(bounds.start).zip(range(bounds.end)).map(|(s, e)| s.cover(e));
+                (contains, src)
+            }
+        };
+
+        match (bound.kind, contains, src) {
+            (ConstraintKind::Exclude, Some(true), Some(src)) => {
+                return Some((ConstraintKind::Exclude, src))
+            }
+            // a bound that is not constant might contain the value
+            (ConstraintKind::From, Some(true) | None, _) => is_from_ok = true,
+            (ConstraintKind::From, Some(false), _) => first_from = first_from.or(src),
+            _ => (),
+        }
+    }
+
+    if is_from_ok {
+        None
+    } else {
+        first_from.map(|src| (ConstraintKind::From, src))
+    }
+}
+
+/// Evaluates bounds that only consist of literals
+pub(crate) fn const_eval(body: &Body, expr: ExprId) -> Option<f64> {
+    match body.exprs[expr] {
+        Expr::Literal(Literal::Int(val)) => Some(val.into()),
+        Expr::Literal(Literal::Float(val)) => Some(val.into()),
+        Expr::Literal(Literal::Inf) => Some(f64::INFINITY),
+        Expr::UnaryOp { expr, op: UnaryOp::Neg } => const_eval(body, expr).map(|val| -val),
+        Expr::UnaryOp { expr, op: UnaryOp::Identity } => const_eval(body, expr),
+        _ => None,
+    }
+}
+
+impl Diagnostic for ElidedValueDiagnostic {
+    fn build_report(&self, _root_file: FileId, _db: &dyn BaseDB) -> Report {
+        let (span, decl, decl_msg, elision_msg) = match self {
+            TypeMismatch { span, decl, expected, .. } => {
+                (span, decl, format!("declared as {} here", expected), "invalid type")
+            }
+            OutOfBounds { span, constraint, kind: ConstraintKind::From, .. } => {
+                (span, constraint, "allowed values are declared here".to_owned(), "out of bounds")
+            }
+            OutOfBounds { span, constraint, kind: ConstraintKind::Exclude, .. } => {
+                (span, constraint, "value is excluded here".to_owned(), "out of bounds")
+            }
+        };
+
+        let mut labels = Vec::with_capacity(2);
+        if let Some(span) = span {
+            labels.push(Label {
+                style: LabelStyle::Primary,
+                file_id: span.file,
+                range: span.range.into(),
+                message: elision_msg.to_owned(),
+            });
+        }
+        labels.push(Label {
+            style: if span.is_some() { LabelStyle::Secondary } else { LabelStyle::Primary },
+            file_id: decl.file,
+            range: decl.range.into(),
+            message: decl_msg,
+        });
+
+        Report::error().with_labels(labels).with_message(self.to_string())
+    }
+}
@@ -6,7 +6,7 @@ use ahash::AHashMap;
 use arena::ArenaMap;
 use hir_def::body::Body;
 use hir_def::db::HirDefDB;
-use hir_def::expr::{CaseCond, Literal};
+use hir_def::expr::{CaseCond, Event, Literal};
 use hir_def::nameres::diagnostics::PathResolveError;
 use hir_def::nameres::{NatureAccess, ResolvedPath, ScopeDefItem, ScopeDefItemKind};
 use hir_def::{
@@ -129,6 +129,9 @@ impl Ctx<'_> {
             Stmt::ForLoop { cond, .. } | Stmt::If { cond, .. } | Stmt::WhileLoop { cond, .. } => {
                 self.infere_cond(stmt, cond)
             }
+            Stmt::EventControl { event: Event::Monitored(event), .. } => {
+                self.infere_expr(stmt, event);
+            }
 
             Stmt::Case { discr, ref case_arms } => {
                 if let Some(ty) = self.infere_expr(stmt, discr) {
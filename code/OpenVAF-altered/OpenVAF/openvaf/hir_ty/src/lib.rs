@@ -1,6 +1,7 @@
 pub mod builtin;
 pub mod db;
 pub mod diagnostics;
+pub mod elision;
 pub mod inference;
 pub mod lower;
 pub mod types;
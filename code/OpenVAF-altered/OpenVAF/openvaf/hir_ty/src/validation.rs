@@ -397,6 +397,79 @@ impl Diagnostic for BodyValidationDiagnosticWrapped<'_> {
 
                 res
             }
+            BodyValidationDiagnostic::IgnoredTransitionTolerance { expr } => {
+                let FileSpan { range, file } = self.expr_src(expr);
+                Report::warning()
+                    .with_message("the time tolerance of transition is ignored".to_owned())
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "ignored time tolerance".to_owned(),
+                    }])
+                    .with_notes(vec![
+                        "help: the time steps are bounded so that the corners of the transition are always hit exactly".to_owned(),
+                    ])
+            }
+            BodyValidationDiagnostic::IgnoredFinalStep { stmt } => {
+                let FileSpan { range, file } = self.parse.to_file_span(
+                    self.body_sm.stmt_map_back[stmt].as_ref().unwrap().range(),
+                    self.sm,
+                );
+                Report::warning()
+                    .with_message("final_step never occurs during transient analyses".to_owned())
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "ignored during transient analyses".to_owned(),
+                    }])
+                    .with_notes(vec![
+                        "help: OSDI does not tell the model which time point is the last one of a transient analysis".to_owned(),
+                    ])
+            }
+            BodyValidationDiagnostic::NegativeTransitionTime { expr, name } => {
+                let FileSpan { range, file } = self.expr_src(expr);
+                Report::warning()
+                    .with_message(format!("the {name} of transition is negative"))
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: format!("negative {name}"),
+                    }])
+                    .with_notes(vec![format!("help: a negative {name} is treated as zero")])
+            }
+            BodyValidationDiagnostic::ExpectedEventFunction { expr } => {
+                let FileSpan { range, file } = self.expr_src(expr);
+                Report::error()
+                    .with_message("expected a call to an event function".to_owned())
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "expected event".to_owned(),
+                    }])
+                    .with_notes(vec![
+                        "help: events are either 'initial_step', 'final_step' or a call to 'cross', 'above' or 'timer'".to_owned(),
+                    ])
+            }
+            BodyValidationDiagnostic::UnexpectedEventFunction { expr, func } => {
+                let FileSpan { range, file } = self.expr_src(expr);
+                Report::error()
+                    .with_message(format!(
+                        "event function '{func:?}' can only be used in an event control statement"
+                    ))
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "event function used as an expression".to_owned(),
+                    }])
+                    .with_notes(vec![format!(
+                        "help: use '@({func:?}(..))' to execute a statement when the event occurs"
+                    )])
+            }
             BodyValidationDiagnostic::IncompatibleNatureAccess {
                 ref candidates,
                 access_nature,
@@ -2,6 +2,7 @@ use std::mem::replace;
 
 use ahash::{HashMap, HashSet};
 use hir_def::body::Body;
+use hir_def::expr::{Event, GlobalEvent};
 use hir_def::{
     BranchId, BuiltIn, DefWithBodyId, DisciplineId, Expr, ExprId, FunctionArgLoc, Literal, Lookup,
     NatureId, NodeId, ParamId, Path, Stmt, StmtId, VarId,
@@ -16,6 +17,7 @@ use crate::builtin::{
     TRANSITION_DELAY_RISET_FALLT_TOL,
 };
 use crate::db::HirTyDB;
+use crate::elision::const_eval;
 use crate::inference::{BranchWrite, InferenceResult, ResolvedFun};
 use crate::lower::BranchKind;
 use crate::types::{Signature, Ty};
@@ -79,6 +81,28 @@ pub enum BodyValidationDiagnostic {
         func: BuiltIn,
     },
 
+    IgnoredTransitionTolerance {
+        expr: ExprId,
+    },
+
+    IgnoredFinalStep {
+        stmt: StmtId,
+    },
+
+    NegativeTransitionTime {
+        expr: ExprId,
+        name: &'static str,
+    },
+
+    ExpectedEventFunction {
+        expr: ExprId,
+    },
+
+    UnexpectedEventFunction {
+        expr: ExprId,
+        func: BuiltIn,
+    },
+
     IncompatibleNatureAccess {
         candidates: [Option<(Name, Name)>; 2],
         access_nature: Option<NatureId>,
@@ -214,7 +238,16 @@ impl BodyValidator<'_> {
 
                 return;
             }
-            Stmt::EventControl { body, .. } => {
+            Stmt::EventControl { ref event, body } => {
+                match *event {
+                    Event::Monitored(event) => self.validate_event(event, stmt),
+                    Event::Global { kind: GlobalEvent::FinalStep, ref phases }
+                        if phases.is_empty() || phases.iter().any(|phase| phase == "tran") =>
+                    {
+                        self.diagnostics.push(BodyValidationDiagnostic::IgnoredFinalStep { stmt })
+                    }
+                    _ => (),
+                }
                 let old = replace(&mut self.ctx, BodyCtx::EventControl);
                 self.validate_stmt(body);
                 self.ctx = old;
@@ -280,6 +313,40 @@ impl BodyValidator<'_> {
             .validate_expr(expr)
     }
 
+    /// Monitored events must be a call to an event function. Just like analog
+    /// operators these calls must be executed unconditionally.
+    fn validate_event(&mut self, expr: ExprId, stmt: StmtId) {
+        let body = self.body;
+        let (name, args) = match (&body.exprs[expr], self.infer.resolved_calls.get(&expr)) {
+            (Expr::Call { fun, args, .. }, Some(ResolvedFun::BuiltIn(call)))
+                if call.is_event_fun() =>
+            {
+                (fun, args)
+            }
+            // the call could not be resolved, an error was already reported
+            (Expr::Call { .. }, None) => return,
+            _ => {
+                self.diagnostics.push(BodyValidationDiagnostic::ExpectedEventFunction { expr });
+                self.validate_expr(expr, stmt);
+                return;
+            }
+        };
+
+        if !self.ctx.allow_analog_operator() {
+            let kind = IllegalCtxAccessKind::AnalogOperator {
+                name: name.as_ref().and_then(|p| p.as_ident()).unwrap(),
+                is_standard: true,
+                non_const_dominator: self.non_const_dominator.clone(),
+            };
+            let err = IllegalCtxAccess { kind, ctx: self.ctx, expr };
+            self.diagnostics.push(BodyValidationDiagnostic::IllegalCtxAccess(err));
+        }
+
+        for arg in args {
+            self.validate_expr(*arg, stmt);
+        }
+    }
+
     fn validate_assignment_dst(&mut self, expr: ExprId, stmt: StmtId) {
         ExprValidator { parent: self, cond_diagnostic_sink: None, write: true, stmt }
             .validate_expr(expr)
@@ -585,6 +652,10 @@ impl ExprValidator<'_, '_> {
                     },
                     expr,
                 ),
+            // monitored events are validated separately
+            _ if call.is_event_fun() => {
+                self.report(BodyValidationDiagnostic::UnexpectedEventFunction { expr, func: call })
+            }
             _ => (),
         }
 
@@ -715,8 +786,17 @@ impl ExprValidator<'_, '_> {
                 }
             }
 
+            (BuiltIn::transition, Some(TRANSITION_DELAY_RISET_FALLT_TOL)) => {
+                if let [other_args @ .., const_expr] = args {
+                    args = other_args;
+                    self.validate_const_expr(*const_expr);
+                    self.report(BodyValidationDiagnostic::IgnoredTransitionTolerance {
+                        expr: *const_expr,
+                    });
+                };
+            }
+
             (BuiltIn::absdelay, Some(ABSDELAY_MAX))
-            | (BuiltIn::transition, Some(TRANSITION_DELAY_RISET_FALLT_TOL))
             | (BuiltIn::ddt, Some(DDT_TOL))
             | (BuiltIn::idt | BuiltIn::idtmod, Some(IDT_IC_ASSERT_TOL)) => {
                 if let [other_args @ .., const_expr] = args {
@@ -748,6 +828,18 @@ impl ExprValidator<'_, '_> {
             _ => (),
         }
 
+        if call == BuiltIn::transition {
+            // the input is followed by the delay, the rise time and the fall time
+            for (&arg, name) in args.iter().skip(1).zip(["delay", "rise time", "fall time"]) {
+                if const_eval(self.parent.body, arg).is_some_and(|val| val < 0.0) {
+                    self.report(BodyValidationDiagnostic::NegativeTransitionTime {
+                        expr: arg,
+                        name,
+                    })
+                }
+            }
+        }
+
         for arg in args {
             self.validate_expr(*arg)
         }
@@ -1,16 +1,24 @@
 use mir::{Function, Inst, Value};
 
 use crate::simplify::SimplifyCtx;
+use crate::value_range::{self, ValueRanges};
 
 pub fn inst_combine(func: &mut Function) {
+    inst_combine_with_ranges(func, &ValueRanges::default())
+}
+
+/// Like [`inst_combine`] but additionally folds comparisons that are decided by the
+/// known `ranges` of some values (usually parameters).
+pub fn inst_combine_with_ranges(func: &mut Function, ranges: &ValueRanges) {
     let mut work_list = Vec::new();
+    let mut ranges = ranges.clone();
     let mut ctx = SimplifyCtx::<f64, _>::new(func, |val, _| val);
 
     let mut block_cursor = ctx.func.layout.blocks_cursor();
     while let Some(block) = block_cursor.next(&ctx.func.layout) {
         let mut inst_cursor = ctx.func.layout.block_inst_cursor(block);
         while let Some(inst) = inst_cursor.next(&ctx.func.layout) {
-            if let Some(val) = ctx.simplify_inst(inst) {
+            if let Some(val) = simplify_inst(&mut ctx, &mut ranges, inst) {
                 replace_uses(ctx.func, &mut work_list, inst, val)
             }
         }
@@ -18,13 +26,26 @@ pub fn inst_combine(func: &mut Function) {
 
     while let Some(inst) = work_list.pop() {
         if ctx.func.layout.inst_block(inst).is_some() {
-            if let Some(val) = ctx.simplify_inst(inst) {
+            if let Some(val) = simplify_inst(&mut ctx, &mut ranges, inst) {
                 replace_uses(ctx.func, &mut work_list, inst, val)
             }
         }
     }
 }
 
+fn simplify_inst<M: Fn(Value, &Function) -> Value>(
+    ctx: &mut SimplifyCtx<f64, M>,
+    ranges: &mut ValueRanges,
+    inst: Inst,
+) -> Option<Value> {
+    if !ranges.is_empty() {
+        if let Some(val) = value_range::simplify_inst(ctx.func, ranges, inst) {
+            return Some(val);
+        }
+    }
+    ctx.simplify_inst(inst)
+}
+
 fn replace_uses(func: &mut Function, workque: &mut Vec<Inst>, inst: Inst, replace: Value) {
     let old = func.dfg.first_result(inst);
     for use_ in func.dfg.uses(old) {
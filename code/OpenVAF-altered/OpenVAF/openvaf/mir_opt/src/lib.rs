@@ -7,11 +7,13 @@ mod inst_combine;
 mod simplify;
 mod simplify_cfg;
 mod split_tainted;
+mod value_range;
 
 pub use const_prop::sparse_conditional_constant_propagation;
 pub use dead_code::dead_code_elimination;
 pub use dead_code_aggressive::aggressive_dead_code_elimination;
 pub use global_value_numbering::{ClassId, GVN};
-pub use inst_combine::inst_combine;
+pub use inst_combine::{inst_combine, inst_combine_with_ranges};
 pub use simplify_cfg::{simplify_cfg, simplify_cfg_init, simplify_cfg_no_phi_merge};
 pub use split_tainted::{propagate_direct_taint, propagate_taint};
+pub use value_range::{ValueRange, ValueRanges};
@@ -0,0 +1,260 @@
+//! Ranges of values that are known at compile time.
+//!
+//! Parameters can be assumed to only take values from a certain range. These
+//! ranges are propagated through (monotone) arithmetic so that comparisons
+//! whose result is the same for every value in the range can be folded.
+//! Only instructions whose operands were visited before (in layout order) are
+//! considered, phis are never assigned a range.
+
+use ahash::AHashMap;
+use mir::{Const, Function, Inst, InstructionData, Opcode, Value, ValueDef, FALSE, TRUE};
+
+#[cfg(test)]
+mod tests;
+
+pub type ValueRanges = AHashMap<Value, ValueRange>;
+
+/// The maximum number of elements of a [`ValueRange::OneOf`] created by combining two sets.
+/// Larger results are widened to an interval.
+const MAX_SET_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRange {
+    /// All values between `lo` and `hi`, the bounds may be infinite
+    Interval { lo: f64, lo_inclusive: bool, hi: f64, hi_inclusive: bool },
+    /// Exactly one of these values
+    OneOf(Box<[f64]>),
+}
+
+impl ValueRange {
+    fn bounds(&self) -> (f64, bool, f64, bool) {
+        match *self {
+            ValueRange::Interval { lo, lo_inclusive, hi, hi_inclusive } => {
+                (lo, lo_inclusive, hi, hi_inclusive)
+            }
+            ValueRange::OneOf(ref vals) => {
+                let lo = vals.iter().copied().fold(f64::INFINITY, f64::min);
+                let hi = vals.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                (lo, true, hi, true)
+            }
+        }
+    }
+
+    fn interval(lo: f64, lo_inclusive: bool, hi: f64, hi_inclusive: bool) -> Option<ValueRange> {
+        if lo.is_nan() || hi.is_nan() {
+            return None;
+        }
+        Some(ValueRange::Interval { lo, lo_inclusive, hi, hi_inclusive })
+    }
+
+    fn one_of(mut vals: Vec<f64>) -> Option<ValueRange> {
+        if vals.iter().any(|val| val.is_nan()) {
+            return None;
+        }
+        vals.sort_by(f64::total_cmp);
+        vals.dedup();
+        if vals.len() > MAX_SET_LEN {
+            let (lo, hi) = (vals[0], vals[vals.len() - 1]);
+            return ValueRange::interval(lo, true, hi, true);
+        }
+        Some(ValueRange::OneOf(vals.into_boxed_slice()))
+    }
+
+    fn contains_zero(&self) -> bool {
+        match *self {
+            ValueRange::OneOf(ref vals) => vals.contains(&0.0),
+            _ => {
+                let (lo, lo_inclusive, hi, hi_inclusive) = self.bounds();
+                (lo < 0.0 || lo_inclusive && lo == 0.0) && (0.0 < hi || hi_inclusive && hi == 0.0)
+            }
+        }
+    }
+}
+
+fn range_of(func: &Function, ranges: &ValueRanges, val: Value) -> Option<ValueRange> {
+    match func.dfg.value_def(val) {
+        ValueDef::Const(Const::Float(c)) => Some(ValueRange::OneOf(Box::new([f64::from(c)]))),
+        ValueDef::Const(Const::Int(c)) => Some(ValueRange::OneOf(Box::new([c as f64]))),
+        _ => ranges.get(&val).cloned(),
+    }
+}
+
+/// Folds `inst` if it is a comparison decided by `ranges`. Otherwise the range
+/// of the result of `inst` is derived (if possible) and added to `ranges`.
+pub(crate) fn simplify_inst(
+    func: &Function,
+    ranges: &mut ValueRanges,
+    inst: Inst,
+) -> Option<Value> {
+    let res = match func.dfg.insts[inst] {
+        InstructionData::Unary { opcode, arg } => {
+            let arg = range_of(func, ranges, arg)?;
+            unary(opcode, &arg)?
+        }
+        InstructionData::Binary { opcode, args: [lhs, rhs] } => {
+            let lhs = range_of(func, ranges, lhs)?;
+            let rhs = range_of(func, ranges, rhs)?;
+            if let Some(res) = compare(opcode, &lhs, &rhs) {
+                return Some(if res { TRUE } else { FALSE });
+            }
+            binary(opcode, &lhs, &rhs)?
+        }
+        _ => return None,
+    };
+
+    ranges.insert(func.dfg.first_result(inst), res);
+    None
+}
+
+fn unary(op: Opcode, arg: &ValueRange) -> Option<ValueRange> {
+    let f: fn(f64) -> f64 = match op {
+        Opcode::IFcast | Opcode::OptBarrier => return Some(arg.clone()),
+        Opcode::Fneg | Opcode::Ineg => {
+            return match *arg {
+                ValueRange::Interval { lo, lo_inclusive, hi, hi_inclusive } => {
+                    ValueRange::interval(-hi, hi_inclusive, -lo, lo_inclusive)
+                }
+                ValueRange::OneOf(ref vals) => {
+                    ValueRange::one_of(vals.iter().map(|val| -val).collect())
+                }
+            };
+        }
+        Opcode::Sqrt => f64::sqrt,
+        Opcode::Exp => f64::exp,
+        Opcode::Ln => f64::ln,
+        Opcode::Log => f64::log10,
+        Opcode::Floor => f64::floor,
+        Opcode::Ceil => f64::ceil,
+        _ => return None,
+    };
+
+    // all of these functions are monotonically increasing within their domain,
+    // values outside of the domain produce NaN. The results are rounded so they
+    // may attain the image of an exclusive bound.
+    match *arg {
+        ValueRange::Interval { lo, hi, .. } => ValueRange::interval(f(lo), true, f(hi), true),
+        ValueRange::OneOf(ref vals) => ValueRange::one_of(vals.iter().map(|&val| f(val)).collect()),
+    }
+}
+
+fn binary(op: Opcode, lhs: &ValueRange, rhs: &ValueRange) -> Option<ValueRange> {
+    let f: fn(f64, f64) -> f64 = match op {
+        Opcode::Fadd | Opcode::Iadd => |lhs, rhs| lhs + rhs,
+        Opcode::Fsub | Opcode::Isub => |lhs, rhs| lhs - rhs,
+        Opcode::Fmul | Opcode::Imul => |lhs, rhs| lhs * rhs,
+        Opcode::Fdiv if !rhs.contains_zero() => |lhs, rhs| lhs / rhs,
+        _ => return None,
+    };
+
+    let res = if let (ValueRange::OneOf(lhs), ValueRange::OneOf(rhs)) = (lhs, rhs) {
+        let vals = lhs.iter().flat_map(|&lhs| rhs.iter().map(move |&rhs| f(lhs, rhs)));
+        ValueRange::one_of(vals.collect())?
+    } else {
+        let (l_lo, l_lo_incl, l_hi, l_hi_incl) = lhs.bounds();
+        let (r_lo, r_lo_incl, r_hi, r_hi_incl) = rhs.bounds();
+        // floating point results are rounded and may attain an exclusive bound
+        // (`1.0 + 1e-20 == 1.0`), only integer arithmetic is exact
+        let exact = matches!(op, Opcode::Iadd | Opcode::Isub);
+        match op {
+            Opcode::Fadd | Opcode::Iadd => ValueRange::interval(
+                l_lo + r_lo,
+                !exact || l_lo_incl && r_lo_incl,
+                l_hi + r_hi,
+                !exact || l_hi_incl && r_hi_incl,
+            )?,
+            Opcode::Fsub | Opcode::Isub => ValueRange::interval(
+                l_lo - r_hi,
+                !exact || l_lo_incl && r_hi_incl,
+                l_hi - r_lo,
+                !exact || l_hi_incl && r_lo_incl,
+            )?,
+            // the extrema of a product/quotient are found at the corners, the
+            // inclusive hull is a (conservative) superset of the result
+            _ => {
+                let f: fn(f64, f64) -> f64 =
+                    if matches!(op, Opcode::Fmul | Opcode::Imul) { mul_bounds } else { f };
+                let corners = [f(l_lo, r_lo), f(l_lo, r_hi), f(l_hi, r_lo), f(l_hi, r_hi)];
+                if corners.iter().any(|val| val.is_nan()) {
+                    return None;
+                }
+                let lo = corners.iter().copied().fold(f64::INFINITY, f64::min);
+                let hi = corners.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                ValueRange::interval(lo, true, hi, true)?
+            }
+        }
+    };
+
+    // integer arithmetic wraps on overflow
+    if matches!(op, Opcode::Iadd | Opcode::Isub | Opcode::Imul) {
+        let (lo, _, hi, _) = res.bounds();
+        if lo < i32::MIN as f64 || hi > i32::MAX as f64 {
+            return None;
+        }
+    }
+
+    Some(res)
+}
+
+/// Multiplies two bounds of a range. Infinite bounds are never attained so
+/// `0 * inf` is treated as `0`.
+fn mul_bounds(lhs: f64, rhs: f64) -> f64 {
+    if lhs == 0.0 || rhs == 0.0 {
+        0.0
+    } else {
+        lhs * rhs
+    }
+}
+
+/// Returns the result of the comparison `op` if it's the same for all values in the ranges.
+fn compare(op: Opcode, lhs: &ValueRange, rhs: &ValueRange) -> Option<bool> {
+    let (op, lhs, rhs) = match op {
+        Opcode::Flt | Opcode::Ilt => (Cmp::Lt, lhs, rhs),
+        Opcode::Fle | Opcode::Ile => (Cmp::Le, lhs, rhs),
+        Opcode::Fgt | Opcode::Igt => (Cmp::Lt, rhs, lhs),
+        Opcode::Fge | Opcode::Ige => (Cmp::Le, rhs, lhs),
+        Opcode::Feq | Opcode::Ieq => (Cmp::Eq, lhs, rhs),
+        Opcode::Fne | Opcode::Ine => return compare_ranges(Cmp::Eq, lhs, rhs).map(|eq| !eq),
+        _ => return None,
+    };
+    compare_ranges(op, lhs, rhs)
+}
+
+#[derive(Clone, Copy)]
+enum Cmp {
+    Lt,
+    Le,
+    Eq,
+}
+
+fn compare_ranges(op: Cmp, lhs: &ValueRange, rhs: &ValueRange) -> Option<bool> {
+    if let (ValueRange::OneOf(lhs), ValueRange::OneOf(rhs)) = (lhs, rhs) {
+        let mut results = lhs.iter().flat_map(|&lhs| {
+            rhs.iter().map(move |&rhs| match op {
+                Cmp::Lt => lhs < rhs,
+                Cmp::Le => lhs <= rhs,
+                Cmp::Eq => lhs == rhs,
+            })
+        });
+        let first = results.next()?;
+        return results.all(|res| res == first).then_some(first);
+    }
+
+    let (l_lo, l_lo_incl, l_hi, l_hi_incl) = lhs.bounds();
+    let (r_lo, r_lo_incl, r_hi, r_hi_incl) = rhs.bounds();
+
+    // lhs < rhs (lhs <= rhs) holds for all values
+    let always_lt = l_hi < r_lo || l_hi == r_lo && !(l_hi_incl && r_lo_incl);
+    let always_le = l_hi <= r_lo;
+    // lhs >= rhs (lhs > rhs) holds for all values
+    let always_ge = r_hi <= l_lo;
+    let always_gt = r_hi < l_lo || r_hi == l_lo && !(r_hi_incl && l_lo_incl);
+
+    match op {
+        Cmp::Lt if always_lt => Some(true),
+        Cmp::Lt if always_ge => Some(false),
+        Cmp::Le if always_le => Some(true),
+        Cmp::Le if always_gt => Some(false),
+        Cmp::Eq if always_lt || always_gt => Some(false),
+        _ => None,
+    }
+}
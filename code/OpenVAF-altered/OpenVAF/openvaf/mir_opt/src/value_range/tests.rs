@@ -0,0 +1,101 @@
+use mir::{InstructionData, Opcode, Value, FALSE, TRUE};
+use mir_reader::parse_function;
+
+use crate::{inst_combine_with_ranges, ValueRange, ValueRanges};
+
+/// Runs inst_combine with `v10` in `(0, inf)` and `v11` in `1 | -1` and returns
+/// the arguments of all optbarriers.
+fn check(src: &str) -> Vec<Value> {
+    let (mut func, _) = parse_function(src).unwrap();
+    let mut ranges = ValueRanges::default();
+    let positive = ValueRange::Interval {
+        lo: 0.0,
+        lo_inclusive: false,
+        hi: f64::INFINITY,
+        hi_inclusive: false,
+    };
+    ranges.insert(Value::with_number(10).unwrap(), positive);
+    ranges.insert(Value::with_number(11).unwrap(), ValueRange::OneOf(Box::new([-1.0, 1.0])));
+    inst_combine_with_ranges(&mut func, &ranges);
+
+    let entry = func.layout.entry_block().unwrap();
+    func.layout
+        .block_insts(entry)
+        .filter_map(|inst| match func.dfg.insts[inst] {
+            InstructionData::Unary { opcode: Opcode::OptBarrier, arg } => Some(arg),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn fold_compare() {
+    let raw = r##"
+        function %bar(v10, v11) {
+        block0:
+            v20 = fmul v10, v10
+            v21 = fge v20, v3
+            v22 = flt v10, v3
+            v23 = fmul v11, v11
+            v24 = feq v23, v6
+            v25 = fneg v11
+            v26 = fle v25, v6
+            v27 = flt v10, v6
+            v30 = optbarrier v21
+            v31 = optbarrier v22
+            v32 = optbarrier v24
+            v33 = optbarrier v26
+            v34 = optbarrier v27
+        }
+    "##;
+
+    let v27 = Value::with_number(27).unwrap();
+    assert_eq!(check(raw), vec![TRUE, FALSE, TRUE, TRUE, v27]);
+}
+
+#[test]
+fn no_fold_without_range() {
+    let raw = r##"
+        function %bar(v10, v11, v12) {
+        block0:
+            v20 = fadd v12, v10
+            v21 = flt v3, v20
+            v22 = fdiv v6, v11
+            v23 = fgt v22, v7
+            v24 = fdiv v6, v10
+            v25 = fle v3, v24
+            v30 = optbarrier v21
+            v31 = optbarrier v23
+            v32 = optbarrier v25
+        }
+    "##;
+
+    let v21 = Value::with_number(21).unwrap();
+    let v23 = Value::with_number(23).unwrap();
+    assert_eq!(check(raw), vec![v21, v23, TRUE]);
+}
+
+#[test]
+fn no_fold_rounded_bound() {
+    // v10 + 1.0 and exp(v10) round to 1.0 for tiny v10 so they are not always greater than 1.0
+    let raw = r##"
+        function %bar(v10, v11) {
+        block0:
+            v20 = fadd v10, v6
+            v21 = fgt v20, v6
+            v22 = fge v20, v6
+            v23 = exp v10
+            v24 = fgt v23, v6
+            v25 = fneg v10
+            v26 = flt v25, v3
+            v30 = optbarrier v21
+            v31 = optbarrier v22
+            v32 = optbarrier v24
+            v33 = optbarrier v26
+        }
+    "##;
+
+    let v21 = Value::with_number(21).unwrap();
+    let v24 = Value::with_number(24).unwrap();
+    assert_eq!(check(raw), vec![v21, TRUE, v24, TRUE]);
+}
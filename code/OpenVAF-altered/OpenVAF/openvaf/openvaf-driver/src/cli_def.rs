@@ -43,8 +43,21 @@ pub fn main_command() -> Command {
             dump_json(),
             input(),
             // RDUBI CHANGES
-            param_to_elide(),
+            keep_dynamic(),
             elision_file(),
+            elision_dir(),
+            elision_spice(),
+            elision_model(),
+            elided_params(),
+            elision_report(),
+            binned(),
+            shared_elision(),
+            assume_temperature(),
+            assume_simparam(),
+            verify_elision(),
+            verify_points(),
+            verify_reltol(),
+            verify_abstol(),
             // END RDUBI CHANGES
         ])
         .subcommand_required(false)
@@ -75,8 +88,21 @@ pub const ALLOW: &str = "allow";
 pub const WARN: &str = "warn";
 pub const DENY: &str = "deny";
 // RDUBI changes
-pub const PARAM_TO_LEAVE: &str = "dyn-use-param";
+pub const KEEP_DYNAMIC: &str = "keep-dynamic";
 pub const ELISION_FILE: &str = "elision-file";
+pub const ELISION_DIR: &str = "elision-dir";
+pub const ELIDED_PARAMS: &str = "elided-params";
+pub const ELISION_SPICE: &str = "elision-spice";
+pub const ELISION_MODEL: &str = "elision-model";
+pub const ELISION_REPORT: &str = "elision-report";
+pub const BINNED: &str = "binned";
+pub const SHARED_ELISION: &str = "shared-elision";
+pub const ASSUME_TEMPERATURE: &str = "assume-temperature";
+pub const ASSUME_SIMPARAM: &str = "assume-simparam";
+pub const VERIFY_ELISION: &str = "verify-elision";
+pub const VERIFY_POINTS: &str = "verify-points";
+pub const VERIFY_RELTOL: &str = "verify-reltol";
+pub const VERIFY_ABSTOL: &str = "verify-abstol";
 
 
 fn interface() -> Arg {
@@ -190,23 +216,144 @@ fn input() -> Arg {
 }
 
 // RDUBI CHANGES
-fn param_to_elide() -> Arg {
-    Arg::new(PARAM_TO_LEAVE)
-        .long(PARAM_TO_LEAVE)
-        .value_name("PARAM_TO_LEAVE")
-        .num_args(1..)
+fn keep_dynamic() -> Arg {
+    Arg::new(KEEP_DYNAMIC)
+        .long(KEEP_DYNAMIC)
+        .help("Parameters that are never elided.")
+        .long_help("Comma separated list of parameters that are never elided.\nThese parameters remain settable by the simulator even if an elision file assigns a value to them.")
+        .value_name("PARAMS")
+        .value_delimiter(',')
         .action(ArgAction::Append)
-        .value_parser(clap::value_parser!(u32))
         .required(false)
 }
 
 fn elision_file() -> Arg {
     elision_file_to_path_arg(ELISION_FILE)
         .long(ELISION_FILE)
+        .help("Replace parameters with the constant values from this file.")
+        .long_help("Replace parameters with the constant values from this file.\nEach line has the form 'name = value', comments start with '#' or '//'.\nThe metadata keys 'model' and 'bin' may be given as '# model = NAME'.\nInstead of a value a range can be assumed with 'name in [lo, hi)', 'name > lo' or 'name = a | b'.\nSuch parameters are still read at runtime and setup rejects values outside of the range.\nMultiple files are merged, later files take precedence. Use --elision-dir to compile each\nfile into a separate descriptor instead.")
         .required(false)
         .action(ArgAction::Append)
 }
 
+fn elision_dir() -> Arg {
+    elision_file_to_path_arg(ELISION_DIR)
+        .long(ELISION_DIR)
+        .help("Compile every elision file (*.txt) in this directory into a separate descriptor.")
+        .long_help("Compile every elision file (*.txt) in this directory into a separate descriptor.\nAll descriptors are placed in a single library. Their names are the module name followed\nby the 'model' and 'bin' metadata of the file (or the file name), for example 'bsim4_nch_12'.\nThe source is only analyzed once and modules that no file assigns parameters of are compiled\nonly once. Every other module is compiled for each file because elision changes its code.\nFiles passed with --elision-file are compiled into separate descriptors as well.")
+        .value_name("DIR")
+        .required(false)
+}
+
+fn elision_spice() -> Arg {
+    elision_file_to_path_arg(ELISION_SPICE)
+        .long(ELISION_SPICE)
+        .help("Replace parameters with the values of a SPICE model card.")
+        .long_help("Replace parameters with the values of a SPICE model card from this file.\nThe model is selected with --elision-model. Continuation lines, engineering suffixes\nand expressions referencing '.param' definitions are supported.")
+        .requires(ELISION_MODEL)
+        .required(false)
+}
+
+fn elision_model() -> Arg {
+    Arg::new(ELISION_MODEL)
+        .long(ELISION_MODEL)
+        .help("Name of the model in the --elision-spice file.")
+        .long_help("Name of the model in the --elision-spice file.\nBinned models require the bin to be appended to the name (NAME.BIN).")
+        .value_name("NAME")
+        .requires(ELISION_SPICE)
+        .required(false)
+}
+
+fn elided_params() -> Arg {
+    Arg::new(ELIDED_PARAMS)
+        .long(ELIDED_PARAMS)
+        .help("How elided parameters are exposed to the simulator.")
+        .long_help("How elided parameters are exposed to the simulator:\n\npossible values\n\nlock - keep the parameters without storage, setting them to a different value is an error during setup\ndrop - remove the parameters from the generated model")
+        .value_name("MODE")
+        .value_parser(["lock", "drop"])
+        .hide_possible_values(true)
+        .default_value("lock")
+        .required(false)
+}
+
+fn elision_report() -> Arg {
+    elision_file_to_path_arg(ELISION_REPORT)
+        .long(ELISION_REPORT)
+        .help("Write a JSON report of the effect of parameter elision to this file.")
+        .long_help("Write a JSON report of the effect of parameter elision to this file.\nFor every module the report lists the elided parameters, the resolved $param_given calls,\nthe number of MIR instructions and the size of the instance and model data with and without\nelision and the size of the object code.")
+        .value_name("JSON")
+        .required(false)
+}
+
+fn binned() -> Arg {
+    flag(BINNED, "binned")
+        .help("Combine the elision sets of each module into one geometry binned descriptor.")
+        .long_help("Combine the elision sets of each module into one descriptor that selects a set from the\ninstance geometry. Every set must define 'lmin', 'lmax', 'wmin' and 'wmax'; an instance is\nassigned to the set with lmin <= l < lmax and wmin <= w < wmax. Instances that fit into no\nset are rejected during setup. Requires --elided-params lock.")
+}
+
+fn shared_elision() -> Arg {
+    flag(SHARED_ELISION, "shared-elision")
+        .help("Elide the parameters that all elision sets agree on in a shared base descriptor.")
+        .long_help("Compute the parameters that every elision set assigns the same value and elide them in the\nunspecialized descriptor, which is exported in addition to the descriptors of each set.\nThe descriptor of a set is compiled with the shared and its remaining (set varying)\nparameters, modules without set varying parameters are not specialized.\nThe shared and varying parameters are printed and added to the --elision-report.\nRequires at least two elision sets (--elision-dir, multiple --elision-file or a binned\n--elision-spice).")
+}
+
+fn assume_temperature() -> Arg {
+    Arg::new(ASSUME_TEMPERATURE)
+        .long(ASSUME_TEMPERATURE)
+        .help("Compile for a fixed ambient temperature (in Kelvin).")
+        .long_help("Compile for a fixed ambient temperature (in Kelvin).\n$temperature (and the default of $vt) is replaced with this value.\nSetup of an instance fails if the simulator temperature differs.")
+        .value_name("K")
+        .value_parser(clap::value_parser!(f64))
+        .required(false)
+}
+
+fn assume_simparam() -> Arg {
+    Arg::new(ASSUME_SIMPARAM)
+        .long(ASSUME_SIMPARAM)
+        .help("Compile for fixed values of $simparam, for example 'gmin=1e-12'.")
+        .long_help("Comma separated list of 'name=value' pairs of simulator parameters that are assumed fixed.\n$simparam(\"name\") is replaced with the value, 'mfactor' also fixes $mfactor.\nSetup of an instance fails if the simulator provides a different value.")
+        .value_name("PARAMS")
+        .value_delimiter(',')
+        .action(ArgAction::Append)
+        .required(false)
+}
+
+fn verify_elision() -> Arg {
+    flag(VERIFY_ELISION, "verify-elision")
+        .help("Verify the compiled library against the original module without elision.")
+        .long_help("Additionally compile the module without any elision or assumptions (to <output>.reference.osdi)\nand compare both libraries at randomized operating points and temperatures.\nSetup results, residuals, Jacobian entries, noise densities and opvars must agree within\n--verify-reltol and --verify-abstol. Every mismatch is printed and the exit code is non-zero.\nRequires --elided-params lock and is not supported for --binned.")
+}
+
+fn verify_points() -> Arg {
+    Arg::new(VERIFY_POINTS)
+        .long(VERIFY_POINTS)
+        .help("Number of randomized operating points evaluated by --verify-elision.")
+        .value_name("N")
+        .value_parser(clap::value_parser!(usize))
+        .default_value("100")
+        .required(false)
+}
+
+fn verify_reltol() -> Arg {
+    Arg::new(VERIFY_RELTOL)
+        .long(VERIFY_RELTOL)
+        .help("Relative tolerance of --verify-elision.")
+        .value_name("TOL")
+        .value_parser(clap::value_parser!(f64))
+        .default_value("1e-6")
+        .required(false)
+}
+
+fn verify_abstol() -> Arg {
+    Arg::new(VERIFY_ABSTOL)
+        .long(VERIFY_ABSTOL)
+        .help("Absolute tolerance of --verify-elision.")
+        .value_name("TOL")
+        .value_parser(clap::value_parser!(f64))
+        .default_value("1e-12")
+        .required(false)
+}
+
 fn elision_file_to_path_arg(name: &'static str) -> Arg {
     let parse = |raw: &str| {
         Ok::<Utf8PathBuf, Infallible>(Utf8PathBuf::from(raw).to_owned())
@@ -4,15 +4,18 @@ use std::process::exit;
 use anyhow::{bail, Context, Result};
 use camino::Utf8PathBuf;
 use clap::ArgMatches;
-use openvaf::{builtin_lints, get_target_names, host_triple, AbsPathBuf, LintLevel, OptLevel};
+use openvaf::{
+    builtin_lints, get_target_names, host_triple, AbsPathBuf, ElidedParamMode, LintLevel, OptLevel,
+    SpiceElision,
+};
+use osdi_verify::VerifyOpts;
 use termcolor::{Color, ColorChoice, ColorSpec, WriteColor};
 
 use crate::cli_def::{
     ALLOW, BATCHMODE, CACHE_DIR, CODEGEN, DEFINE, DENY, DRYRUN, DUMPMIR, DUMPUNOPTMIR, DUMPIR, DUMPUNOPTIR, INCLUDE, INPUT, LINTS, OPT_LVL,
-    OUTPUT, SUPPORTED_TARGETS, TARGET, TARGET_CPU, WARN, PARAM_TO_LEAVE, ELISION_FILE,
+    OUTPUT, SUPPORTED_TARGETS, TARGET, TARGET_CPU, WARN, KEEP_DYNAMIC, ELISION_FILE, ELISION_DIR, ELIDED_PARAMS, ELISION_SPICE, ELISION_MODEL, ELISION_REPORT, BINNED, SHARED_ELISION, ASSUME_TEMPERATURE, ASSUME_SIMPARAM, VERIFY_ELISION, VERIFY_POINTS, VERIFY_RELTOL, VERIFY_ABSTOL,
 };
 use crate::{CompilationDestination, Opts};
-use openvaf::elysian::{parse_file, to_cli_defaults};
 
 pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
     if matches.get_flag(LINTS) {
@@ -106,14 +109,57 @@ pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
         matches.get_one(TARGET_CPU).cloned().unwrap_or_else(|| default_cpu.to_owned());
 
     // RDUBI CHANGES
-    let params_to_leave: Vec<u32> = matches.get_many::<u32>(PARAM_TO_LEAVE)
-        .map(|vals| vals.cloned().collect())
-        .unwrap_or_default();
-    let param_defaults = matches.get_one::<Utf8PathBuf>(ELISION_FILE)
-        .and_then(|filename| parse_file(filename).ok())
-        .map_or(vec![], |x| to_cli_defaults(&x));
-    // let param_defaults_map = parse_file(elision_file).unwrap();
-    // let param_defaults = to_cli_defaults(&param_defaults_map);
+    let keep_dynamic = matches
+        .get_many::<String>(KEEP_DYNAMIC)
+        .map_or_else(Vec::new, |values| values.cloned().collect());
+    let elision_files = matches
+        .get_many::<Utf8PathBuf>(ELISION_FILE)
+        .map_or_else(Vec::new, |values| values.cloned().collect());
+    let elision_spice = matches.get_one::<Utf8PathBuf>(ELISION_SPICE).map(|path| SpiceElision {
+        path: path.clone(),
+        model: matches.get_one::<String>(ELISION_MODEL).unwrap().clone(),
+    });
+    let elided_params = match &**matches.get_one::<String>(ELIDED_PARAMS).unwrap() {
+        "lock" => ElidedParamMode::Lock,
+        "drop" => ElidedParamMode::Drop,
+        mode => bail!("unknown elided parameter mode {mode}"),
+    };
+    let binned = matches.get_flag(BINNED);
+    if binned && elided_params == ElidedParamMode::Drop {
+        bail!("--binned requires --elided-params lock");
+    }
+    if binned
+        && elision_files.is_empty()
+        && elision_spice.is_none()
+        && !matches.contains_id(ELISION_DIR)
+    {
+        bail!("--binned requires --elision-dir, --elision-file or --elision-spice");
+    }
+    let shared_elision = matches.get_flag(SHARED_ELISION);
+    if shared_elision
+        && elision_files.len() < 2
+        && elision_spice.is_none()
+        && !matches.contains_id(ELISION_DIR)
+    {
+        bail!("--shared-elision requires --elision-dir, multiple --elision-file or --elision-spice");
+    }
+
+    let assume_simparams = matches.get_many::<String>(ASSUME_SIMPARAM).map_or_else(
+        || Ok(Vec::new()),
+        |values| {
+            values
+                .map(|raw| {
+                    let Some((name, val)) = raw.split_once('=') else {
+                        bail!("expected 'name=value' for --{ASSUME_SIMPARAM} but found '{raw}'");
+                    };
+                    let val = val.trim().parse().with_context(|| {
+                        format!("invalid value for --{ASSUME_SIMPARAM} '{}'", name.trim())
+                    })?;
+                    Ok((name.trim().to_owned(), val))
+                })
+                .collect::<Result<Vec<(String, f64)>>>()
+        },
+    )?;
     // END RDUBI CHANGES
 
     Ok(Opts {
@@ -131,11 +177,51 @@ pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
         dump_ir: matches.get_flag(DUMPIR), 
         dump_unopt_ir: matches.get_flag(DUMPUNOPTIR), 
         dry_run: matches.get_flag(DRYRUN),
-        params_to_leave: vec![],
-        param_defaults,
+        keep_dynamic,
+        param_defaults: vec![],
+        elision_files,
+        elision_dir: matches.get_one::<Utf8PathBuf>(ELISION_DIR).cloned(),
+        elision_spice,
+        elided_params,
+        elision_report: matches.get_one::<Utf8PathBuf>(ELISION_REPORT).cloned(),
+        binned,
+        shared_elision,
+        assume_temperature: matches.get_one::<f64>(ASSUME_TEMPERATURE).copied(),
+        assume_simparams,
     })
 }
 
+/// Returns the options of `--verify-elision` (if enabled). The temperature and
+/// simparams assumed by `opts` are passed to both libraries during verification.
+pub fn matches_to_verify_opts(matches: &ArgMatches, opts: &Opts) -> Result<Option<VerifyOpts>> {
+    if !matches.get_flag(VERIFY_ELISION) {
+        return Ok(None);
+    }
+    if opts.elided_params == ElidedParamMode::Drop {
+        bail!("--{VERIFY_ELISION} requires --elided-params lock");
+    }
+    if opts.binned {
+        bail!("--{VERIFY_ELISION} is not supported for --binned");
+    }
+
+    let mut verify = VerifyOpts {
+        points: *matches.get_one::<usize>(VERIFY_POINTS).unwrap(),
+        reltol: *matches.get_one::<f64>(VERIFY_RELTOL).unwrap(),
+        abstol: *matches.get_one::<f64>(VERIFY_ABSTOL).unwrap(),
+        simparams: opts.assume_simparams.clone(),
+        ..VerifyOpts::default()
+    };
+    if let Some(temperature) = opts.assume_temperature {
+        verify.temperature = (temperature, temperature);
+    }
+    // $mfactor is an instance parameter, not a simparam
+    let mfactor = opts.assume_simparams.iter().rev().find(|(name, _)| name == "mfactor");
+    if let Some((_, mfactor)) = mfactor {
+        verify.params.push(("$mfactor".to_owned(), *mfactor));
+    }
+    Ok(Some(verify))
+}
+
 fn print_lints() {
     let mut stdout = termcolor::StandardStream::stdout(ColorChoice::Auto);
 
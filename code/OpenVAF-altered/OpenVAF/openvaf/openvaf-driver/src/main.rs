@@ -3,16 +3,17 @@ use std::process::exit;
 use std::sync::Mutex;
 
 use anyhow::{bail, Result};
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::ArgMatches;
 use mimalloc::MiMalloc;
+use osdi_verify::VerifyOpts;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 use cli_def::{main_command, INPUT};
 use openvaf::{compile, expand, CompilationDestination, CompilationTermination, Opts};
 
 use crate::cli_def::{DUMP_JSON, PRINT_EXPANSION};
-use crate::cli_process::matches_to_opts;
+use crate::cli_process::{matches_to_opts, matches_to_verify_opts};
 
 mod cli_def;
 mod cli_process;
@@ -58,11 +59,13 @@ pub fn main() {
 }
 
 pub const DATA_ERROR: i32 = 65;
+pub const VERIFY_ERROR: i32 = 1;
 
 fn wrapped_main(matches: ArgMatches) -> Result<i32> {
     let print_expansion = matches.get_flag(PRINT_EXPANSION);
     let dump_json_ = matches.get_flag(DUMP_JSON);
-    let opts = matches_to_opts(matches)?;
+    let opts = matches_to_opts(matches.clone())?;
+    let verify_opts = matches_to_verify_opts(&matches, &opts)?;
     *ARGS.lock().unwrap() = Some(opts.clone());
     if print_expansion {
         let res = match expand(&opts)? {
@@ -80,15 +83,51 @@ fn wrapped_main(matches: ArgMatches) -> Result<i32> {
         // return Ok(res);
     }
 
-    let res = match compile(&opts)? {
+    let lib_file = match compile(&opts)? {
         CompilationTermination::Compiled { lib_file } => {
             if matches!(opts.output, CompilationDestination::Cache { .. }) {
                 println!("{lib_file}");
             }
-            0
+            lib_file
         }
-        CompilationTermination::FatalDiagnostic => DATA_ERROR,
+        CompilationTermination::FatalDiagnostic => return Ok(DATA_ERROR),
     };
 
-    Ok(res)
+    if let Some(verify_opts) = verify_opts {
+        if opts.dry_run {
+            return Ok(0);
+        }
+        return verify_elision(&opts, &lib_file, &verify_opts);
+    }
+
+    Ok(0)
+}
+
+/// Compiles the original module and compares it to the (elided) `lib_file`.
+fn verify_elision(opts: &Opts, lib_file: &Utf8Path, verify_opts: &VerifyOpts) -> Result<i32> {
+    let reference = match compile(&opts.reference_opts())? {
+        CompilationTermination::Compiled { lib_file } => lib_file,
+        CompilationTermination::FatalDiagnostic => return Ok(DATA_ERROR),
+    };
+    // SAFETY: both libraries were just compiled by openvaf
+    let mismatches = unsafe { osdi_verify::verify_libs(&reference, lib_file, verify_opts)? };
+    let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+    if mismatches.is_empty() {
+        stderr.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+        write!(&mut stderr, "Verified")?;
+        stderr.set_color(&ColorSpec::new())?;
+        writeln!(&mut stderr, " {lib_file} against {reference}")?;
+        return Ok(0);
+    }
+
+    for mismatch in &mismatches {
+        stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+        write!(&mut stderr, "mismatch")?;
+        stderr.set_color(ColorSpec::new().set_bold(true))?;
+        write!(&mut stderr, ":")?;
+        stderr.set_color(&ColorSpec::new())?;
+        writeln!(&mut stderr, " {mismatch}")?;
+    }
+    writeln!(&mut stderr, "{} mismatches between {lib_file} and {reference}", mismatches.len())?;
+    Ok(VERIFY_ERROR)
 }
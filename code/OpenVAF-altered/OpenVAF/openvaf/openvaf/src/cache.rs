@@ -1,23 +1,102 @@
 use core::slice;
+use std::collections::BTreeMap;
 use std::mem::{size_of, size_of_val};
 
 use basedb::lints::LintLevel;
-use basedb::{BaseDB, VfsStorage};
+use basedb::{BaseDB, CliParamDefault, CliParamDefaultValue, ParamRange, VfsStorage};
 use hir::CompilationDB;
 
+use crate::variants::Variant;
 use crate::Opts;
 
+fn hash_strs(hash_builder: &mut md5::Context, strs: &[String]) {
+    hash_builder.consume(strs.len().to_ne_bytes());
+    for str in strs {
+        hash_builder.consume(str.len().to_ne_bytes());
+        hash_builder.consume(str)
+    }
+}
+
+/// Hashes the elided parameters. Later entries overwrite earlier ones, so the
+/// resolved set is hashed (sorted by name) instead of the raw list.
+fn hash_param_defaults(hash_builder: &mut md5::Context, defaults: &[CliParamDefault]) {
+    let resolved: BTreeMap<_, _> =
+        defaults.iter().map(|default| (&*default.name, &default.value)).collect();
+
+    hash_builder.consume(resolved.len().to_ne_bytes());
+    for (name, val) in resolved {
+        hash_builder.consume(name.len().to_ne_bytes());
+        hash_builder.consume(name);
+        match val {
+            CliParamDefaultValue::Int(val) => {
+                hash_builder.consume([0u8]);
+                hash_builder.consume(val.to_ne_bytes());
+            }
+            CliParamDefaultValue::Float(val) => {
+                hash_builder.consume([1u8]);
+                hash_builder.consume(val.to_bits().to_ne_bytes());
+            }
+            CliParamDefaultValue::Str(val) => {
+                hash_builder.consume([2u8]);
+                hash_builder.consume(val.len().to_ne_bytes());
+                hash_builder.consume(&**val);
+            }
+            CliParamDefaultValue::Range(ParamRange::Interval {
+                lo,
+                lo_inclusive,
+                hi,
+                hi_inclusive,
+            }) => {
+                hash_builder.consume([3u8, *lo_inclusive as u8, *hi_inclusive as u8]);
+                hash_builder.consume(lo.to_bits().to_ne_bytes());
+                hash_builder.consume(hi.to_bits().to_ne_bytes());
+            }
+            CliParamDefaultValue::Range(ParamRange::OneOf(vals)) => {
+                hash_builder.consume([4u8]);
+                hash_builder.consume(vals.len().to_ne_bytes());
+                for val in vals {
+                    hash_builder.consume(val.to_bits().to_ne_bytes());
+                }
+            }
+        }
+    }
+}
+
 // TODO: use high level hir API instead of low leve database API
-fn hash(db: &CompilationDB, defines: &[String]) -> md5::Digest {
+fn hash(db: &CompilationDB, opts: &Opts, variants: &[Variant]) -> md5::Digest {
     let mut hash_builder = md5::Context::new();
     let cu = db.compilation_unit();
 
     // hash settings
     hash_builder.consume(cu.root_file().0.to_ne_bytes());
+    hash_strs(&mut hash_builder, &opts.defines);
+
+    hash_builder.consume(&opts.target.llvm_target);
+    hash_builder.consume(&opts.target_cpu);
+    hash_builder.consume([opts.opt_lvl as u8]);
+    hash_strs(&mut hash_builder, &opts.codegen_opts);
 
-    hash_builder.consume(defines.len().to_ne_bytes());
-    for def in defines {
-        hash_builder.consume(def)
+    hash_param_defaults(&mut hash_builder, &db.cli_param_defaults(cu.root_file()));
+    hash_builder.consume(variants.len().to_ne_bytes());
+    for variant in variants {
+        hash_builder.consume(variant.name.len().to_ne_bytes());
+        hash_builder.consume(&variant.name);
+        hash_param_defaults(&mut hash_builder, &variant.defaults);
+    }
+    let mut keep_dynamic = opts.keep_dynamic.clone();
+    keep_dynamic.sort_unstable();
+    keep_dynamic.dedup();
+    hash_strs(&mut hash_builder, &keep_dynamic);
+    hash_builder.consume([opts.elided_params as u8, opts.binned as u8, opts.shared_elision as u8]);
+
+    let assumptions = db.sim_assumptions(cu.root_file());
+    hash_builder.consume([assumptions.temperature.is_some() as u8]);
+    hash_builder.consume(assumptions.temperature.unwrap_or(0.0).to_ne_bytes());
+    hash_builder.consume(assumptions.simparams.len().to_ne_bytes());
+    for (name, val) in &assumptions.simparams {
+        hash_builder.consume(name.len().to_ne_bytes());
+        hash_builder.consume(name);
+        hash_builder.consume(val.to_ne_bytes());
     }
 
     hash_builder.consume(env!("CARGO_PKG_VERSION"));
@@ -48,8 +127,89 @@ fn hash(db: &CompilationDB, defines: &[String]) -> md5::Digest {
     hash_builder.compute()
 }
 
-pub fn file_name(db: &CompilationDB, opts: &Opts) -> String {
-    let hash = u128::from_ne_bytes(*hash(db, &opts.defines));
+pub fn file_name(db: &CompilationDB, opts: &Opts, variants: &[Variant]) -> String {
+    let hash = u128::from_ne_bytes(*hash(db, opts, variants));
     let hash = base_n::encode(hash, base_n::CASE_INSENSITIVE);
     format!("{}.osdi", hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use basedb::{BaseDB, CliParamDefault, CliParamDefaultValue};
+    use camino::Utf8PathBuf;
+    use hir::CompilationDB;
+    use llvm::OptLevel;
+    use syntax::name::Name;
+    use target::spec::Target;
+
+    use crate::{CompilationDestination, ElidedParamMode, Opts};
+
+    const RESISTOR: &str = r#"
+`include "disciplines.vams"
+module resistor(inout electrical a, inout electrical c);
+    parameter real r = 1.0;
+    parameter real tc = 0.0;
+    analog I(a, c) <+ V(a, c) / (r * (1 + tc));
+endmodule
+"#;
+
+    fn file_name(defaults: &[(&str, f64)]) -> String {
+        let mut db = CompilationDB::new_virtual(RESISTOR).unwrap();
+        let root_file = db.compilation_unit().root_file();
+        let defaults: Arc<[_]> = defaults
+            .iter()
+            .map(|&(name, val)| CliParamDefault {
+                name: Name::resolve(name),
+                value: CliParamDefaultValue::Float(val),
+                span: None,
+            })
+            .collect();
+        db.set_cli_param_defaults(root_file, defaults);
+
+        let opts = Opts {
+            dry_run: false,
+            defines: Vec::new(),
+            codegen_opts: Vec::new(),
+            lints: Vec::new(),
+            input: Utf8PathBuf::from("/root.va"),
+            output: CompilationDestination::Cache { cache_dir: Utf8PathBuf::from("/cache") },
+            include: Vec::new(),
+            opt_lvl: OptLevel::Aggressive,
+            target: Target::host_target().unwrap(),
+            target_cpu: "native".to_owned(),
+            dump_mir: false,
+            dump_unopt_mir: false,
+            dump_ir: false,
+            dump_unopt_ir: false,
+            keep_dynamic: Vec::new(),
+            param_defaults: Vec::new(),
+            elision_files: Vec::new(),
+            elision_dir: None,
+            elision_spice: None,
+            elided_params: ElidedParamMode::default(),
+            elision_report: None,
+            binned: false,
+            shared_elision: false,
+            assume_temperature: None,
+            assume_simparams: Vec::new(),
+        };
+        super::file_name(&db, &opts, &[])
+    }
+
+    #[test]
+    fn elision_values_change_file_name() {
+        let bin1 = file_name(&[("r", 4.0), ("tc", 0.1)]);
+        let bin2 = file_name(&[("r", 8.0), ("tc", 0.1)]);
+        assert_ne!(bin1, bin2);
+        assert_ne!(bin1, file_name(&[]));
+    }
+
+    #[test]
+    fn elision_order_does_not_change_file_name() {
+        let sorted = file_name(&[("r", 4.0), ("tc", 0.1)]);
+        let reversed = file_name(&[("tc", 0.1), ("r", 4.0)]);
+        assert_eq!(sorted, reversed);
+    }
+}
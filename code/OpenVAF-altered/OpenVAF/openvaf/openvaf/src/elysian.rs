@@ -1,76 +1,667 @@
 // NOTE: pun on "elision", named after an excellent beer brand from Seattle
-use std::collections::HashMap;
-use std::io::{self, BufRead};
-use std::fs::File;
-use camino::Utf8PathBuf;
-use basedb::{CliParamDefault, CliParamDefaultValue};
+
+//! Parsing of elision files.
+//!
+//! An elision file assigns compile time constant values to module parameters.
+//! Every line is one of the following:
+//!
+//! ```text
+//! # model = sky130_fd_pr__pfet_01v8__model     metadata (`model` or `bin`)
+//! # any other comment
+//! // also a comment
+//!
+//! vth0 = 0.42                                  numeric value
+//! toxe = 4.148e-9 # trailing comment
+//! version = "4.5"                              quoted string value
+//!
+//! l in [1.5e-7, 1e-6)                          assumed range
+//! vth0 > 0                                     assumed bound (<, <=, > or >=)
+//! type = 1 | -1                                assumed set of values
+//! ```
+//!
+//! Parameters with an assumed range are not elided. Their value is still read
+//! at runtime but the generated code may rely on the assumption, setup rejects
+//! values outside of the range.
+//!
+//! Metadata may also be written without the leading `#` if its value is
+//! quoted. Parse errors are reported as diagnostics pointing at the offending
+//! line. SPICE model cards can be used as elision sources as well, see [`spice`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use std::{fs, io};
+
+use basedb::diagnostics::{Diagnostic, Label, LabelStyle, Report};
+use basedb::{
+    AbsPathBuf, BaseDB, CliParamDefault, CliParamDefaultValue, FileId, FileReadError, ParamRange,
+    VfsPath,
+};
+use camino::{Utf8Path, Utf8PathBuf};
 use syntax::name::Name;
+use syntax::sourcemap::FileSpan;
+use syntax::{TextRange, TextSize};
+
+pub mod spice;
+
+/// The contents of a single elision file.
+#[derive(Debug, Clone)]
+pub struct ElisionSet {
+    pub path: Utf8PathBuf,
+    pub model: Option<String>,
+    pub bin: Option<String>,
+    pub entries: Vec<CliParamDefault>,
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum NumericValue {
-    Int(i32),
-    Float(f64),
-}
-
-/// Parses a file with lines of the form:
-/// ```text
-/// var_name = value
-/// ```
-/// where value is an integer, float, or scientific notation.
-/// Returns a mapping from variable names to their numeric values.
-pub fn parse_file(path: &Utf8PathBuf) -> io::Result<HashMap<String, NumericValue>> {
-    let file = File::open(&path)?;
-    let reader = io::BufReader::new(file);
-    let mut map = HashMap::new();
-
-    for (i, line) in reader.lines().enumerate() {
-        let line = line?;
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
+pub enum ElisionFileDiagnostic {
+    ReadFailed { path: Utf8PathBuf, err: FileReadError },
+    ExpectedAssignment { span: FileSpan },
+    InvalidName { name: String, span: FileSpan },
+    MissingValue { span: FileSpan },
+    InvalidValue { value: String, span: FileSpan },
+    InvalidRange { value: String, span: FileSpan },
+    UnterminatedString { span: FileSpan },
+    DuplicateMetadata { key: &'static str, span: FileSpan, prev: FileSpan },
+    ExpectedModelName { span: FileSpan },
+    UnterminatedExpression { span: FileSpan },
+    InvalidExpression { span: FileSpan },
+    UndefinedSpiceParam { name: String, span: FileSpan },
+    RecursiveSpiceParam { name: String, span: FileSpan },
+    UnknownModel { path: Utf8PathBuf, model: String },
+    BinnedModel { model: String, bins: Vec<String> },
+    ReadDirFailed { path: Utf8PathBuf, err: io::ErrorKind },
+    EmptyDir { path: Utf8PathBuf },
+    DuplicateVariant { name: String, path: Utf8PathBuf, prev: Utf8PathBuf },
+    MissingBinBound { path: Utf8PathBuf, name: &'static str },
+    SingleSharedSet { sets: usize },
+}
+
+impl Display for ElisionFileDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElisionFileDiagnostic::ReadFailed { path, err } => {
+                write!(f, "failed to read elision file {path}: {err:?}")
+            }
+            ElisionFileDiagnostic::ExpectedAssignment { .. } => {
+                write!(f, "expected an assignment of the form 'name = value'")
+            }
+            ElisionFileDiagnostic::InvalidName { name, .. } => {
+                write!(f, "'{name}' is not a valid parameter name")
+            }
+            ElisionFileDiagnostic::MissingValue { .. } => {
+                write!(f, "expected a value after '='")
+            }
+            ElisionFileDiagnostic::InvalidValue { value, .. } => {
+                write!(f, "cannot parse '{value}' as a number")
+            }
+            ElisionFileDiagnostic::InvalidRange { value, .. } => {
+                write!(f, "cannot parse '{value}' as a range")
+            }
+            ElisionFileDiagnostic::UnterminatedString { .. } => {
+                write!(f, "unterminated string literal")
+            }
+            ElisionFileDiagnostic::DuplicateMetadata { key, .. } => {
+                write!(f, "'{key}' was specified multiple times")
+            }
+            ElisionFileDiagnostic::ExpectedModelName { .. } => {
+                write!(f, "expected a model name and type after '.model'")
+            }
+            ElisionFileDiagnostic::UnterminatedExpression { .. } => {
+                write!(f, "unterminated expression")
+            }
+            ElisionFileDiagnostic::InvalidExpression { .. } => {
+                write!(f, "cannot evaluate expression")
+            }
+            ElisionFileDiagnostic::UndefinedSpiceParam { name, .. } => {
+                write!(f, "'{name}' is not defined by any '.param' statement")
+            }
+            ElisionFileDiagnostic::RecursiveSpiceParam { name, .. } => {
+                write!(f, "the value of '{name}' depends on itself")
+            }
+            ElisionFileDiagnostic::UnknownModel { path, model } => {
+                write!(f, "model '{model}' was not found in {path}")
+            }
+            ElisionFileDiagnostic::BinnedModel { model, .. } => {
+                write!(f, "model '{model}' is binned but no bin was selected")
+            }
+            ElisionFileDiagnostic::ReadDirFailed { path, err } => {
+                write!(f, "failed to read elision directory {path}: {err}")
+            }
+            ElisionFileDiagnostic::EmptyDir { path } => {
+                write!(f, "elision directory {path} contains no elision files")
+            }
+            ElisionFileDiagnostic::DuplicateVariant { name, path, prev } => {
+                write!(f, "{path} and {prev} both produce the descriptor suffix '{name}'")
+            }
+            ElisionFileDiagnostic::MissingBinBound { path, name } => {
+                write!(f, "{path} does not define the bin bound '{name}'")
+            }
+            ElisionFileDiagnostic::SingleSharedSet { sets } => {
+                write!(f, "--shared-elision requires at least two elision sets but found {sets}")
+            }
         }
+    }
+}
+
+impl Diagnostic for ElisionFileDiagnostic {
+    fn build_report(&self, _root_file: FileId, _db: &dyn BaseDB) -> Report {
+        let label = |span: &FileSpan, message: &str| Label {
+            style: LabelStyle::Primary,
+            file_id: span.file,
+            range: span.range.into(),
+            message: message.to_owned(),
+        };
+
+        let report = match self {
+            ElisionFileDiagnostic::ReadFailed { .. } => Report::error(),
+            ElisionFileDiagnostic::ExpectedAssignment { span } => Report::error()
+                .with_labels(vec![label(span, "expected '='")])
+                .with_notes(vec!["help: comments must start with '#' or '//'".to_owned()]),
+            ElisionFileDiagnostic::InvalidName { span, .. } => {
+                Report::error().with_labels(vec![label(span, "invalid name")])
+            }
+            ElisionFileDiagnostic::MissingValue { span } => {
+                Report::error().with_labels(vec![label(span, "value is missing")])
+            }
+            ElisionFileDiagnostic::InvalidValue { span, .. } => Report::error()
+                .with_labels(vec![label(span, "invalid value")])
+                .with_notes(vec!["help: string values must be quoted".to_owned()]),
+            ElisionFileDiagnostic::InvalidRange { span, .. } => {
+                Report::error().with_labels(vec![label(span, "invalid range")]).with_notes(vec![
+                    "help: expected 'name in [lo, hi)', 'name > lo' or 'name = a | b'".to_owned(),
+                ])
+            }
+            ElisionFileDiagnostic::UnterminatedString { span } => {
+                Report::error().with_labels(vec![label(span, "missing closing '\"'")])
+            }
+            ElisionFileDiagnostic::DuplicateMetadata { span, prev, .. } => Report::error()
+                .with_labels(vec![
+                    Label {
+                        style: LabelStyle::Secondary,
+                        file_id: prev.file,
+                        range: prev.range.into(),
+                        message: "first specified here".to_owned(),
+                    },
+                    label(span, "specified again here"),
+                ]),
+            ElisionFileDiagnostic::ExpectedModelName { span } => {
+                Report::error().with_labels(vec![label(span, "expected '.model NAME TYPE'")])
+            }
+            ElisionFileDiagnostic::UnterminatedExpression { span } => Report::error()
+                .with_labels(vec![label(span, "missing closing delimiter")])
+                .with_notes(vec!["help: expressions must not span multiple lines".to_owned()]),
+            ElisionFileDiagnostic::InvalidExpression { span } => {
+                Report::error().with_labels(vec![label(span, "invalid expression")])
+            }
+            ElisionFileDiagnostic::UndefinedSpiceParam { span, .. } => {
+                Report::error().with_labels(vec![label(span, "undefined parameter")])
+            }
+            ElisionFileDiagnostic::RecursiveSpiceParam { span, .. } => {
+                Report::error().with_labels(vec![label(span, "recursive definition")])
+            }
+            ElisionFileDiagnostic::UnknownModel { .. } => Report::error(),
+            ElisionFileDiagnostic::BinnedModel { model, bins } => {
+                let bins: Vec<_> = bins.iter().map(|bin| format!("'{model}.{bin}'")).collect();
+                Report::error().with_notes(vec![format!("help: select one of {}", bins.join(", "))])
+            }
+            ElisionFileDiagnostic::ReadDirFailed { .. } => Report::error(),
+            ElisionFileDiagnostic::EmptyDir { .. } => Report::error()
+                .with_notes(vec!["help: elision files must have the extension '.txt'".to_owned()]),
+            ElisionFileDiagnostic::DuplicateVariant { .. } => Report::error().with_notes(vec![
+                "help: the suffix is derived from the 'model' and 'bin' metadata".to_owned(),
+            ]),
+            ElisionFileDiagnostic::MissingBinBound { .. } => Report::error().with_notes(vec![
+                "help: --binned requires 'lmin', 'lmax', 'wmin' and 'wmax' in every set".to_owned(),
+            ]),
+            ElisionFileDiagnostic::SingleSharedSet { .. } => Report::error().with_notes(vec![
+                "help: a SPICE model only produces multiple sets if it is binned".to_owned(),
+            ]),
+        };
 
-        let parts: Vec<&str> = trimmed.split('=').collect();
-        if parts.len() != 2 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Line {}: expected format 'var = value'", i + 1),
-            ));
+        report.with_message(self.to_string())
+    }
+}
+
+/// Reads an elision file through the VFS of `db` so that diagnostics can
+/// point into the file.
+pub fn read_file(
+    db: &dyn BaseDB,
+    path: &Utf8PathBuf,
+) -> Result<ElisionSet, Vec<ElisionFileDiagnostic>> {
+    let (file, text) = read_text(db, path)?;
+    parse(path.clone(), file, &text)
+}
+
+fn read_text(
+    db: &dyn BaseDB,
+    path: &Utf8PathBuf,
+) -> Result<(FileId, Arc<str>), Vec<ElisionFileDiagnostic>> {
+    let read_failed = |err| vec![ElisionFileDiagnostic::ReadFailed { path: path.clone(), err }];
+    let abs_path = path.canonicalize().map_err(|err| read_failed(FileReadError::Io(err.kind())))?;
+    let file = db.file_id(VfsPath::from(AbsPathBuf::assert(abs_path)));
+    let text = db.file_text(file).map_err(read_failed)?;
+    Ok((file, text))
+}
+
+/// Reads multiple elision files, returns the parsed files or all encountered
+/// diagnostics if any file was invalid.
+pub fn read_files(
+    db: &dyn BaseDB,
+    paths: &[Utf8PathBuf],
+) -> Result<Vec<ElisionSet>, Vec<ElisionFileDiagnostic>> {
+    let mut sets = Vec::with_capacity(paths.len());
+    let mut diagnostics = Vec::new();
+    for path in paths {
+        match read_file(db, path) {
+            Ok(set) => sets.push(set),
+            Err(err) => diagnostics.extend(err),
         }
+    }
 
-        let var = parts[0].trim().to_string();
-        let val_str = parts[1].trim();
+    if diagnostics.is_empty() {
+        Ok(sets)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Lists the elision files (`*.txt`) in `dir` sorted by name.
+pub fn dir_files(dir: &Utf8Path) -> Result<Vec<Utf8PathBuf>, Vec<ElisionFileDiagnostic>> {
+    let read_failed = |err: io::Error| {
+        vec![ElisionFileDiagnostic::ReadDirFailed { path: dir.to_owned(), err: err.kind() }]
+    };
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(read_failed)? {
+        let path = entry.map_err(read_failed)?.path();
+        if let Ok(path) = Utf8PathBuf::try_from(path) {
+            if path.extension() == Some("txt") && path.is_file() {
+                files.push(path)
+            }
+        }
+    }
+
+    if files.is_empty() {
+        return Err(vec![ElisionFileDiagnostic::EmptyDir { path: dir.to_owned() }]);
+    }
+    files.sort_unstable();
+    Ok(files)
+}
 
-        let value = if let Ok(i_val) = val_str.parse::<i32>() {
-            NumericValue::Int(i_val)
-        } else if let Ok(f_val) = val_str.parse::<f64>() {
-            NumericValue::Float(f_val)
+impl ElisionSet {
+    /// A name for this set that can be used as part of an identifier. It is
+    /// derived from the `model` and `bin` metadata (or the file name if both
+    /// are missing).
+    pub fn variant_name(&self) -> String {
+        let name = match (&self.model, &self.bin) {
+            (Some(model), Some(bin)) => format!("{model}_{bin}"),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => self.path.file_stem().unwrap_or_default().to_owned(),
+        };
+        name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+    }
+}
+
+pub fn parse(
+    path: Utf8PathBuf,
+    file: FileId,
+    text: &str,
+) -> Result<ElisionSet, Vec<ElisionFileDiagnostic>> {
+    let mut parser = Parser {
+        file,
+        set: ElisionSet { path, model: None, bin: None, entries: Vec::new() },
+        model_span: None,
+        bin_span: None,
+        diagnostics: Vec::new(),
+    };
+
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        parser.parse_line(line.trim_end_matches(['\n', '\r']), offset);
+        offset += line.len();
+    }
+
+    if parser.diagnostics.is_empty() {
+        Ok(parser.set)
+    } else {
+        Err(parser.diagnostics)
+    }
+}
+
+struct Parser {
+    file: FileId,
+    set: ElisionSet,
+    model_span: Option<FileSpan>,
+    bin_span: Option<FileSpan>,
+    diagnostics: Vec<ElisionFileDiagnostic>,
+}
+
+impl Parser {
+    fn span(&self, start: usize, end: usize) -> FileSpan {
+        let range = TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32));
+        FileSpan { range, file: self.file }
+    }
+
+    fn parse_line(&mut self, line: &str, offset: usize) {
+        let trimmed = line.trim_start();
+        let mut start = offset + line.len() - trimmed.len();
+
+        let (content, is_comment) = if let Some(comment) = trimmed.strip_prefix('#') {
+            start += 1;
+            (comment, true)
+        } else if trimmed.starts_with("//") {
+            return;
         } else {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Line {}: cannot parse value '{}'", i + 1, val_str),
-            ));
+            (trimmed, false)
         };
 
-        map.insert(var, value);
+        let content = strip_trailing_comment(content);
+        if content.trim().is_empty() {
+            return;
+        }
+
+        // comments can only contain metadata
+        let statement = if is_comment {
+            content.split_once('=').map(|(name, value)| (name, Relation::Eq, value))
+        } else {
+            split_statement(content)
+        };
+
+        let (name, relation, value) = match statement {
+            Some(statement) => statement,
+            None if is_comment => return,
+            None => {
+                let span = self.span(start, start + content.trim_end().len());
+                self.diagnostics.push(ElisionFileDiagnostic::ExpectedAssignment { span });
+                return;
+            }
+        };
+
+        let name_start = start + name.len() - name.trim_start().len();
+        let name = name.trim();
+        let name_span = self.span(name_start, name_start + name.len());
+        let value_start = start + content.len() - value.len();
+        let value_start = value_start + value.len() - value.trim_start().len();
+        let value = value.trim();
+        let value_span = self.span(value_start, value_start + value.len());
+
+        let is_metadata = relation == Relation::Eq && matches!(name, "model" | "bin");
+        if is_metadata && (is_comment || value.starts_with('"')) {
+            self.metadata(name, value, value_span);
+            return;
+        }
+
+        if is_comment {
+            return;
+        }
+
+        if !is_valid_name(name) {
+            let diag =
+                ElisionFileDiagnostic::InvalidName { name: name.to_owned(), span: name_span };
+            self.diagnostics.push(diag);
+            return;
+        }
+
+        let value = match relation {
+            Relation::Eq if value.contains('|') && !value.starts_with('"') => {
+                self.one_of(value, value_span)
+            }
+            Relation::Eq => self.value(value, value_span),
+            _ => self.range(relation, value, value_span),
+        };
+        let value = match value {
+            Some(value) => value,
+            None => return,
+        };
+
+        self.set.entries.push(CliParamDefault {
+            name: Name::resolve(name),
+            value,
+            span: Some(self.span(name_start, value_span.range.end().into())),
+        });
+    }
+
+    fn metadata(&mut self, key: &str, value: &str, span: FileSpan) {
+        // metadata values may be left unquoted
+        let value = if value.starts_with('"') {
+            match self.value(value, span) {
+                Some(CliParamDefaultValue::Str(str)) => str.to_string(),
+                _ => return,
+            }
+        } else if value.is_empty() {
+            self.diagnostics.push(ElisionFileDiagnostic::MissingValue { span });
+            return;
+        } else {
+            value.to_owned()
+        };
+
+        let (key, dst, dst_span) = match key {
+            "model" => ("model", &mut self.set.model, &mut self.model_span),
+            _ => ("bin", &mut self.set.bin, &mut self.bin_span),
+        };
+
+        if let Some(prev) = *dst_span {
+            self.diagnostics.push(ElisionFileDiagnostic::DuplicateMetadata { key, span, prev });
+            return;
+        }
+
+        *dst = Some(value);
+        *dst_span = Some(span);
+    }
+
+    fn value(&mut self, value: &str, span: FileSpan) -> Option<CliParamDefaultValue> {
+        if value.is_empty() {
+            self.diagnostics.push(ElisionFileDiagnostic::MissingValue { span });
+            return None;
+        }
+
+        if let Some(str) = value.strip_prefix('"') {
+            return match str.strip_suffix('"') {
+                Some(str) if !str.contains('"') => Some(CliParamDefaultValue::Str(str.into())),
+                _ => {
+                    self.diagnostics.push(ElisionFileDiagnostic::UnterminatedString { span });
+                    None
+                }
+            };
+        }
+
+        if let Ok(val) = value.parse::<i32>() {
+            return Some(CliParamDefaultValue::Int(val));
+        }
+
+        match value.parse::<f64>() {
+            Ok(val) if val.is_finite() => Some(CliParamDefaultValue::Float(val)),
+            _ => {
+                let diag = ElisionFileDiagnostic::InvalidValue { value: value.to_owned(), span };
+                self.diagnostics.push(diag);
+                None
+            }
+        }
+    }
+
+    fn one_of(&mut self, value: &str, span: FileSpan) -> Option<CliParamDefaultValue> {
+        let vals: Option<Vec<f64>> = value.split('|').map(|val| parse_finite(val.trim())).collect();
+        match vals {
+            Some(vals) => Some(CliParamDefaultValue::Range(ParamRange::OneOf(vals))),
+            None => self.invalid_range(value, span),
+        }
+    }
+
+    fn range(
+        &mut self,
+        relation: Relation,
+        value: &str,
+        span: FileSpan,
+    ) -> Option<CliParamDefaultValue> {
+        if value.is_empty() {
+            self.diagnostics.push(ElisionFileDiagnostic::MissingValue { span });
+            return None;
+        }
+
+        let range = match relation {
+            Relation::In => parse_interval(value),
+            _ => parse_finite(value).map(|bound| {
+                let (lo, hi) = (f64::NEG_INFINITY, f64::INFINITY);
+                match relation {
+                    Relation::Lt | Relation::Le => ParamRange::Interval {
+                        lo,
+                        lo_inclusive: false,
+                        hi: bound,
+                        hi_inclusive: relation == Relation::Le,
+                    },
+                    _ => ParamRange::Interval {
+                        lo: bound,
+                        lo_inclusive: relation == Relation::Ge,
+                        hi,
+                        hi_inclusive: false,
+                    },
+                }
+            }),
+        };
+
+        match range {
+            Some(range) => Some(CliParamDefaultValue::Range(range)),
+            None => self.invalid_range(value, span),
+        }
     }
 
+    fn invalid_range(&mut self, value: &str, span: FileSpan) -> Option<CliParamDefaultValue> {
+        let diag = ElisionFileDiagnostic::InvalidRange { value: value.to_owned(), span };
+        self.diagnostics.push(diag);
+        None
+    }
+}
 
-    Ok(map)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relation {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
 }
 
-pub fn to_cli_defaults(
-    params: &HashMap<String, NumericValue>,
-) -> Vec<CliParamDefault> {
-    params
-        .iter()
-        .map(|(name, value)| {
-            let value = match value {
-                NumericValue::Int(v) => CliParamDefaultValue::Int(*v),
-                NumericValue::Float(v) => CliParamDefaultValue::from_float(*v),
+/// Splits a statement at the first relational operator (`=`, `<`, `<=`, `>`,
+/// `>=` or `in`). The returned name is a prefix and the value a suffix of `content`.
+fn split_statement(content: &str) -> Option<(&str, Relation, &str)> {
+    let op = content.find(['=', '<', '>']);
+    let keyword = content.find(|c: char| c.is_ascii_whitespace()).and_then(|start| {
+        let rem = content[start..].trim_start();
+        let rem = rem.strip_prefix("in")?;
+        let is_keyword = rem.starts_with(|c: char| c.is_ascii_whitespace() || c == '[' || c == '(');
+        is_keyword.then_some((start, content.len() - rem.len()))
+    });
+
+    match (op, keyword) {
+        (Some(pos), keyword) if keyword.map_or(true, |(start, _)| pos < start) => {
+            let rem = &content[pos + 1..];
+            let (relation, value) = match (content.as_bytes()[pos], rem.strip_prefix('=')) {
+                (b'<', Some(value)) => (Relation::Le, value),
+                (b'>', Some(value)) => (Relation::Ge, value),
+                (b'<', None) => (Relation::Lt, rem),
+                (b'>', None) => (Relation::Gt, rem),
+                _ => (Relation::Eq, rem),
             };
-            CliParamDefault { name: Name::resolve(name), value }
-        })
-        .collect()
+            Some((&content[..pos], relation, value))
+        }
+        (_, Some((start, end))) => Some((&content[..start], Relation::In, &content[end..])),
+        (None, None) => None,
+    }
+}
+
+/// Parses an interval like `[1e-7, 1e-6)`. Bounds may be `inf` or `-inf`.
+fn parse_interval(value: &str) -> Option<ParamRange> {
+    let (lo_inclusive, rem) = match value.strip_prefix('[') {
+        Some(rem) => (true, rem),
+        None => (false, value.strip_prefix('(')?),
+    };
+    let (hi_inclusive, rem) = match rem.strip_suffix(']') {
+        Some(rem) => (true, rem),
+        None => (false, rem.strip_suffix(')')?),
+    };
+    let (lo, hi) = rem.split_once(',')?;
+    let lo = lo.trim().parse::<f64>().ok().filter(|lo| !lo.is_nan())?;
+    let hi = hi.trim().parse::<f64>().ok().filter(|hi| !hi.is_nan())?;
+    let non_empty = lo < hi || lo == hi && lo_inclusive && hi_inclusive && lo.is_finite();
+    non_empty.then_some(ParamRange::Interval { lo, lo_inclusive, hi, hi_inclusive })
+}
+
+fn parse_finite(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|val| val.is_finite())
+}
+
+/// Removes a trailing `#` or `//` comment that is not part of a string literal
+fn strip_trailing_comment(line: &str) -> &str {
+    let mut in_str = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            '/' if !in_str && line[i..].starts_with("//") => return &line[..i],
+            _ => (),
+        }
+    }
+    line
 }
 
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Collects the defaults of all elision sets into a single list
+pub fn to_cli_defaults(sets: &[ElisionSet]) -> Vec<CliParamDefault> {
+    sets.iter().flat_map(|set| set.entries.iter().cloned()).collect()
+}
+
+/// The entries that all elision sets have in common (see [`shared_elision`]).
+#[derive(Debug, Clone, Default)]
+pub struct SharedElision {
+    /// Entries that assign the same value in every set
+    pub entries: Vec<CliParamDefault>,
+    /// Parameters that are only assigned by some sets or whose value differs
+    /// between the sets, sorted by name
+    pub varying: Vec<Name>,
+}
+
+/// Computes the strict intersection of `sets`. A parameter is shared if every
+/// set assigns it the same value (only the last assignment within a set counts).
+/// The intersection is not a separate compilation step: it is elided in the
+/// generic descriptor and each set is compiled with the intersection plus its
+/// residual (see `Variant::new`).
+pub fn shared_elision(sets: &[ElisionSet]) -> SharedElision {
+    let values: Vec<HashMap<&Name, &CliParamDefaultValue>> = sets
+        .iter()
+        .map(|set| set.entries.iter().map(|entry| (&entry.name, &entry.value)).collect())
+        .collect();
+    let Some((first, rest)) = values.split_first() else {
+        return SharedElision::default();
+    };
+
+    let mut shared = HashSet::new();
+    let mut entries = Vec::new();
+    for entry in sets[0].entries.iter().rev() {
+        let value = first[&entry.name];
+        if shared.contains(&entry.name)
+            || rest.iter().any(|values| values.get(&entry.name) != Some(&value))
+        {
+            continue;
+        }
+        shared.insert(&entry.name);
+        entries.push(entry.clone());
+    }
+    entries.reverse();
+
+    let mut varying: Vec<_> = values
+        .iter()
+        .flat_map(|values| values.keys())
+        .filter(|name| !shared.contains(*name))
+        .map(|&name| name.clone())
+        .collect();
+    varying.sort_unstable();
+    varying.dedup();
+
+    SharedElision { entries, varying }
+}
@@ -0,0 +1,733 @@
+//! Reading SPICE `.model` cards as elision sources.
+//!
+//! Only the subset of the SPICE syntax that is required to extract model
+//! parameters is supported:
+//!
+//! ```text
+//! * comment
+//! .param vth_diff = 10m  tox = 4.1n
+//! .model nfet.1 nmos level = 54 lmin = 1u lmax = 2u
+//! + vth0 = {0.42 + vth_diff} toxe = 'tox * 1.01'   $ trailing comment
+//! ```
+//!
+//! All names are case insensitive and converted to lowercase. Values may use
+//! engineering suffixes (`1meg`, `10u`) and expressions (enclosed in `{}` or
+//! `''`) that reference `.param` definitions. Models with a numeric suffix
+//! (`nfet.1`, `nfet.2`, ...) are treated as bins of the same model. All other
+//! statements (device instances, `.lib`, `.subckt`, ...) are ignored. Errors
+//! are only reported for the selected cards and the `.param` statements they
+//! reference, so a library with cards in unsupported dialects can still be
+//! used.
+
+use std::collections::HashMap;
+
+use basedb::{BaseDB, CliParamDefault, CliParamDefaultValue, FileId};
+use camino::Utf8PathBuf;
+use syntax::name::Name;
+use syntax::sourcemap::FileSpan;
+use syntax::{TextRange, TextSize};
+
+use super::{read_text, ElisionFileDiagnostic, ElisionSet};
+
+/// A model (or a single bin of a model) in a SPICE file that is used as an
+/// elision source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiceElision {
+    pub path: Utf8PathBuf,
+    /// Model name, optionally followed by `.<bin>`
+    pub model: String,
+}
+
+/// A single `.model` card
+#[derive(Debug, Clone)]
+pub struct SpiceModel {
+    pub name: String,
+    pub bin: Option<String>,
+    pub kind: String,
+    pub params: Vec<CliParamDefault>,
+}
+
+/// Reads the elision sets of all bins selected by `src`.
+pub fn read_bins(
+    db: &dyn BaseDB,
+    src: &SpiceElision,
+) -> Result<Vec<ElisionSet>, Vec<ElisionFileDiagnostic>> {
+    let (file, text) = read_text(db, &src.path)?;
+    let models = parse(&src.path, file, &text, &src.model)?;
+    let sets = models
+        .into_iter()
+        .map(|model| ElisionSet {
+            path: src.path.clone(),
+            model: Some(model.name),
+            bin: model.bin,
+            entries: model.params,
+        })
+        .collect();
+    Ok(sets)
+}
+
+/// Reads the elision set of the model selected by `src`. Binned models
+/// require an explicit bin.
+pub fn read_model(
+    db: &dyn BaseDB,
+    src: &SpiceElision,
+) -> Result<ElisionSet, Vec<ElisionFileDiagnostic>> {
+    let mut sets = read_bins(db, src)?;
+    if sets.len() > 1 {
+        let bins = sets.iter().filter_map(|set| set.bin.clone()).collect();
+        return Err(vec![ElisionFileDiagnostic::BinnedModel { model: src.model.clone(), bins }]);
+    }
+    Ok(sets.pop().unwrap())
+}
+
+/// Parses the model cards selected by `model`. That is either the card with
+/// exactly that name or all bins of the model (in declaration order). Only the
+/// selected cards and the `.param` statements they reference are evaluated.
+/// Syntax errors in any other statement are ignored.
+pub fn parse(
+    path: &Utf8PathBuf,
+    file: FileId,
+    text: &str,
+    model: &str,
+) -> Result<Vec<SpiceModel>, Vec<ElisionFileDiagnostic>> {
+    let statements = split_statements(file, text);
+
+    let mut params = HashMap::new();
+    let mut param_errors = Vec::new();
+    let mut cards = Vec::new();
+    let mut invalid_cards = Vec::new();
+    for mut stmt in statements {
+        match stmt.keyword() {
+            Some(".param") => {
+                let stmt_idx = param_errors.len();
+                let valid = assignments(file, &stmt.tokens[1..], &mut stmt.diagnostics);
+                if !stmt.diagnostics.is_empty() {
+                    // references to a broken definition must report its errors
+                    let names = stmt.tokens[1..].windows(2).filter(|pair| pair[1].is("="));
+                    for pair in names {
+                        params.insert(pair[0].text.to_ascii_lowercase(), Param::Invalid(stmt_idx));
+                    }
+                }
+                for assignment in valid {
+                    let name = assignment.name.text.to_ascii_lowercase();
+                    params.insert(name, Param::Pending(assignment, stmt_idx));
+                }
+                param_errors.push(stmt.diagnostics);
+            }
+            Some(_) => match model_header(file, &stmt) {
+                Ok(header) => cards.push((header, stmt)),
+                Err(diag) => invalid_cards.push(diag),
+            },
+            None => (),
+        }
+    }
+
+    let model = model.to_ascii_lowercase();
+    let is_exact = |header: &ModelHeader| header.full_name() == model;
+    let selected: Vec<_> = if cards.iter().any(|(header, _)| is_exact(header)) {
+        cards.into_iter().filter(|(header, _)| is_exact(header)).collect()
+    } else {
+        cards.into_iter().filter(|(header, _)| header.name == model).collect()
+    };
+
+    // the selected model may be one of the cards without a valid name
+    if selected.is_empty() {
+        if invalid_cards.is_empty() {
+            invalid_cards.push(ElisionFileDiagnostic::UnknownModel { path: path.clone(), model });
+        }
+        return Err(invalid_cards);
+    }
+
+    let mut eval = Evaluator { file, params, param_errors, diagnostics: Vec::new() };
+    let models: Vec<_> =
+        selected.into_iter().map(|(header, stmt)| eval.model(header, stmt)).collect();
+
+    if eval.diagnostics.is_empty() {
+        Ok(models)
+    } else {
+        Err(eval.diagnostics)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+impl Token<'_> {
+    fn span(&self, file: FileId) -> FileSpan {
+        span(file, self.start, self.start + self.text.len())
+    }
+
+    fn is(&self, punct: &str) -> bool {
+        self.text == punct
+    }
+}
+
+fn span(file: FileId, start: usize, end: usize) -> FileSpan {
+    let range = TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32));
+    FileSpan { range, file }
+}
+
+/// A statement that may span multiple lines (joined with `+`)
+struct Statement<'a> {
+    tokens: Vec<Token<'a>>,
+    /// Syntax errors, only reported if the statement is used
+    diagnostics: Vec<ElisionFileDiagnostic>,
+}
+
+impl Statement<'_> {
+    fn keyword(&self) -> Option<&'static str> {
+        let first = self.tokens.first()?.text;
+        [".param", ".model"].into_iter().find(|keyword| first.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn split_statements(file: FileId, text: &str) -> Vec<Statement<'_>> {
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let line = strip_comment(line.trim_end_matches(['\n', '\r']));
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('*') {
+            continue;
+        }
+
+        let mut line_start = start + line.len() - trimmed.len();
+        let continuation = trimmed.starts_with('+');
+        let trimmed = if continuation {
+            line_start += 1;
+            &trimmed[1..]
+        } else {
+            trimmed
+        };
+
+        let mut diagnostics = Vec::new();
+        let tokens = tokenize(file, trimmed, line_start, &mut diagnostics);
+        match statements.last_mut() {
+            Some(stmt) if continuation => {
+                stmt.tokens.extend(tokens);
+                stmt.diagnostics.extend(diagnostics);
+            }
+            _ => statements.push(Statement { tokens, diagnostics }),
+        }
+    }
+    statements
+}
+
+/// Removes `$`, `;` and `//` comments
+fn strip_comment(line: &str) -> &str {
+    let mut prev_space = true;
+    for (i, c) in line.char_indices() {
+        match c {
+            '$' if prev_space => return &line[..i],
+            ';' => return &line[..i],
+            '/' if line[i..].starts_with("//") => return &line[..i],
+            _ => (),
+        }
+        prev_space = c.is_whitespace();
+    }
+    line
+}
+
+fn tokenize<'a>(
+    file: FileId,
+    line: &'a str,
+    offset: usize,
+    diagnostics: &mut Vec<ElisionFileDiagnostic>,
+) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let end = match c {
+            _ if c.is_whitespace() => continue,
+            '=' | '(' | ')' | ',' => start + 1,
+            '{' | '\'' => {
+                let close = if c == '{' { '}' } else { '\'' };
+                match line[start + 1..].find(close) {
+                    Some(pos) => {
+                        let end = start + pos + 2;
+                        while chars.next_if(|&(i, _)| i < end).is_some() {}
+                        end
+                    }
+                    None => {
+                        let span = span(file, offset + start, offset + line.len());
+                        diagnostics.push(ElisionFileDiagnostic::UnterminatedExpression { span });
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|&(_, c)| !c.is_whitespace() && !"=(),{}'".contains(c))
+                {
+                    end = i + c.len_utf8();
+                }
+                end
+            }
+        };
+        tokens.push(Token { text: &line[start..end], start: offset + start });
+    }
+    tokens
+}
+
+struct Assignment<'a> {
+    name: Token<'a>,
+    value: Token<'a>,
+}
+
+/// Parses `name = value` pairs, parentheses and commas are ignored
+fn assignments<'a>(
+    file: FileId,
+    tokens: &[Token<'a>],
+    diagnostics: &mut Vec<ElisionFileDiagnostic>,
+) -> Vec<Assignment<'a>> {
+    let mut tokens = tokens.iter().filter(|tok| !(tok.is("(") || tok.is(")") || tok.is(",")));
+    let mut res = Vec::new();
+    while let Some(&name) = tokens.next() {
+        let value = match (tokens.next(), tokens.next()) {
+            (Some(eq), Some(&value)) if eq.is("=") && !value.is("=") => value,
+            (Some(eq), _) if eq.is("=") => {
+                diagnostics.push(ElisionFileDiagnostic::MissingValue { span: eq.span(file) });
+                break;
+            }
+            _ => {
+                let span = name.span(file);
+                diagnostics.push(ElisionFileDiagnostic::ExpectedAssignment { span });
+                break;
+            }
+        };
+
+        if !is_valid_name(name.text) {
+            let diag = ElisionFileDiagnostic::InvalidName {
+                name: name.text.to_owned(),
+                span: name.span(file),
+            };
+            diagnostics.push(diag);
+            continue;
+        }
+        res.push(Assignment { name, value });
+    }
+    res
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct ModelHeader {
+    name: String,
+    bin: Option<String>,
+    kind: String,
+}
+
+impl ModelHeader {
+    fn full_name(&self) -> String {
+        match &self.bin {
+            Some(bin) => format!("{}.{}", self.name, bin),
+            None => self.name.clone(),
+        }
+    }
+}
+
+fn model_header(file: FileId, stmt: &Statement) -> Result<ModelHeader, ElisionFileDiagnostic> {
+    let (name, kind) = match &stmt.tokens[1..] {
+        [name, kind, ..] if is_model_name(name.text) && is_valid_name(kind.text) => {
+            (name.text.to_ascii_lowercase(), kind.text.to_ascii_lowercase())
+        }
+        _ => {
+            let span = stmt.tokens[0].span(file);
+            return Err(ElisionFileDiagnostic::ExpectedModelName { span });
+        }
+    };
+
+    let (name, bin) = match name.rsplit_once('.') {
+        Some((base, bin)) if !bin.is_empty() && bin.chars().all(|c| c.is_ascii_digit()) => {
+            (base.to_owned(), Some(bin.to_owned()))
+        }
+        _ => (name, None),
+    };
+    Ok(ModelHeader { name, bin, kind })
+}
+
+fn is_model_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+enum Param<'a> {
+    /// Not evaluated yet, stores the index of the `.param` statement
+    Pending(Assignment<'a>, usize),
+    /// The definition in this `.param` statement could not be parsed
+    Invalid(usize),
+    InProgress,
+    Done(Option<f64>),
+}
+
+struct Evaluator<'a> {
+    file: FileId,
+    params: HashMap<String, Param<'a>>,
+    /// Syntax errors of each `.param` statement
+    param_errors: Vec<Vec<ElisionFileDiagnostic>>,
+    diagnostics: Vec<ElisionFileDiagnostic>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn model(&mut self, header: ModelHeader, mut stmt: Statement<'a>) -> SpiceModel {
+        self.diagnostics.append(&mut stmt.diagnostics);
+        let params = assignments(self.file, &stmt.tokens[3..], &mut self.diagnostics)
+            .into_iter()
+            .filter_map(|assignment| {
+                let value = self.value(assignment.value)?;
+                let end = assignment.value.start + assignment.value.text.len();
+                Some(CliParamDefault {
+                    name: Name::resolve(&assignment.name.text.to_ascii_lowercase()),
+                    value,
+                    span: Some(span(self.file, assignment.name.start, end)),
+                })
+            })
+            .collect();
+
+        SpiceModel { name: header.name, bin: header.bin, kind: header.kind, params }
+    }
+
+    fn value(&mut self, token: Token<'a>) -> Option<CliParamDefaultValue> {
+        if let Ok(val) = token.text.parse::<i32>() {
+            return Some(CliParamDefaultValue::Int(val));
+        }
+        self.eval(token).map(CliParamDefaultValue::Float)
+    }
+
+    fn eval(&mut self, token: Token<'a>) -> Option<f64> {
+        let src = match token.text.as_bytes()[0] {
+            b'{' | b'\'' => &token.text[1..token.text.len() - 1],
+            _ => token.text,
+        };
+
+        let mut parser =
+            ExprParser { src: src.as_bytes(), pos: 0, token, eval: self, reported: false };
+        let val = parser.expr().and_then(|val| {
+            parser.skip_whitespace();
+            (parser.pos == parser.src.len()).then_some(val)
+        });
+        let reported = parser.reported;
+        match val {
+            Some(val) if val.is_finite() => Some(val),
+            _ if reported => None,
+            _ => {
+                let span = token.span(self.file);
+                self.diagnostics.push(ElisionFileDiagnostic::InvalidExpression { span });
+                None
+            }
+        }
+    }
+
+    /// Evaluates the `.param` called `name`. Returns `Err` for recursive
+    /// definitions and `Ok(None)` if the parameter is undefined or invalid.
+    fn param(&mut self, name: &str) -> Result<Option<f64>, ()> {
+        match self.params.insert(name.to_owned(), Param::InProgress) {
+            Some(Param::Pending(assignment, stmt_idx)) => {
+                self.diagnostics.append(&mut self.param_errors[stmt_idx]);
+                let val = self.eval(assignment.value);
+                self.params.insert(name.to_owned(), Param::Done(val));
+                Ok(val)
+            }
+            Some(Param::Invalid(stmt_idx)) => {
+                self.diagnostics.append(&mut self.param_errors[stmt_idx]);
+                self.params.insert(name.to_owned(), Param::Done(None));
+                Ok(None)
+            }
+            Some(Param::Done(val)) => {
+                self.params.insert(name.to_owned(), Param::Done(val));
+                Ok(val)
+            }
+            Some(Param::InProgress) => Err(()),
+            None => {
+                self.params.remove(name);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Recursive descent parser that directly evaluates an expression
+struct ExprParser<'a, 'e> {
+    src: &'a [u8],
+    pos: usize,
+    token: Token<'e>,
+    eval: &'a mut Evaluator<'e>,
+    /// An invalid parameter reference was already reported
+    reported: bool,
+}
+
+impl ExprParser<'_, '_> {
+    fn skip_whitespace(&mut self) {
+        while self.src.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.src.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_whitespace();
+        if self.src[self.pos..].starts_with(op.as_bytes()) {
+            self.pos += op.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Option<f64> {
+        let mut val = self.term()?;
+        loop {
+            if self.eat("+") {
+                val += self.term()?;
+            } else if self.eat("-") {
+                val -= self.term()?;
+            } else {
+                return Some(val);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut val = self.unary()?;
+        loop {
+            if self.eat("*") {
+                val *= self.unary()?;
+            } else if self.eat("/") {
+                val /= self.unary()?;
+            } else {
+                return Some(val);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        if self.eat("-") {
+            self.unary().map(|val| -val)
+        } else if self.eat("+") {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        if self.eat("**") || self.eat("^") {
+            let exp = self.unary()?;
+            Some(base.powf(exp))
+        } else {
+            Some(base)
+        }
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        match self.peek()? {
+            b'(' => {
+                self.pos += 1;
+                let val = self.expr()?;
+                self.eat(")").then_some(val)
+            }
+            c if c.is_ascii_digit() || c == b'.' => self.number(),
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = self.pos;
+                while self
+                    .src
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_')
+                {
+                    self.pos += 1;
+                }
+                let ident =
+                    String::from_utf8_lossy(&self.src[start..self.pos]).to_ascii_lowercase();
+                if self.eat("(") {
+                    self.call(&ident)
+                } else {
+                    self.reference(ident)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn reference(&mut self, name: String) -> Option<f64> {
+        let span = self.token.span(self.eval.file);
+        self.reported = true;
+        match self.eval.param(&name) {
+            Ok(Some(val)) => {
+                self.reported = false;
+                Some(val)
+            }
+            // the error is reported where the parameter is defined
+            Ok(None) if self.eval.params.contains_key(&name) => None,
+            Ok(None) => {
+                let diag = ElisionFileDiagnostic::UndefinedSpiceParam { name, span };
+                self.eval.diagnostics.push(diag);
+                None
+            }
+            Err(()) => {
+                let diag = ElisionFileDiagnostic::RecursiveSpiceParam { name, span };
+                self.eval.diagnostics.push(diag);
+                None
+            }
+        }
+    }
+
+    fn call(&mut self, func: &str) -> Option<f64> {
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.expr()?);
+                if self.eat(")") {
+                    break;
+                }
+                if !self.eat(",") {
+                    return None;
+                }
+            }
+        }
+
+        let val = match (func, &*args) {
+            ("sqrt", [x]) => x.sqrt(),
+            ("exp", [x]) => x.exp(),
+            ("ln" | "log", [x]) => x.ln(),
+            ("log10", [x]) => x.log10(),
+            ("abs", [x]) => x.abs(),
+            ("min", [x, y]) => x.min(*y),
+            ("max", [x, y]) => x.max(*y),
+            ("pow" | "pwr", [x, y]) => x.powf(*y),
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    /// Parses a number with an optional engineering suffix, trailing letters
+    /// (units) are ignored
+    fn number(&mut self) -> Option<f64> {
+        let start = self.pos;
+        self.digits();
+        if self.src.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            self.digits();
+        }
+        if matches!(self.src.get(self.pos), Some(b'e' | b'E')) {
+            let exp_start = self.pos;
+            self.pos += 1;
+            if matches!(self.src.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.src.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                self.digits();
+            } else {
+                // 1meg
+                self.pos = exp_start;
+            }
+        }
+
+        let mantissa = std::str::from_utf8(&self.src[start..self.pos]).ok()?.parse::<f64>().ok()?;
+        let suffix_start = self.pos;
+        while self.src.get(self.pos).is_some_and(u8::is_ascii_alphabetic) {
+            self.pos += 1;
+        }
+        let suffix = self.src[suffix_start..self.pos].to_ascii_lowercase();
+        Some(mantissa * scale_factor(&suffix))
+    }
+
+    fn digits(&mut self) {
+        while self.src.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+    }
+}
+
+fn scale_factor(suffix: &[u8]) -> f64 {
+    if suffix.starts_with(b"meg") {
+        return 1e6;
+    }
+    if suffix.starts_with(b"mil") {
+        return 25.4e-6;
+    }
+    match suffix.first() {
+        Some(b't') => 1e12,
+        Some(b'g') => 1e9,
+        Some(b'k') => 1e3,
+        Some(b'm') => 1e-3,
+        Some(b'u') => 1e-6,
+        Some(b'n') => 1e-9,
+        Some(b'p') => 1e-12,
+        Some(b'f') => 1e-15,
+        Some(b'a') => 1e-18,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use basedb::{CliParamDefaultValue, FileId};
+    use camino::Utf8PathBuf;
+
+    use super::parse;
+    use crate::elysian::ElisionFileDiagnostic;
+
+    const MODEL_CARDS: &str = r#"
+.param rnom = 2k
+.param rbroken = {1k
+.param rsheet = 100 rmin =
+.model res r r = '2 * rnom'
+.model res_broken r r = 'rnom
+.model
+.model res_sheet r r = {rsheet}
+.model res_missing r r = {rbroken}
+"#;
+
+    fn parse_model(model: &str) -> Result<Vec<f64>, Vec<ElisionFileDiagnostic>> {
+        let models = parse(&Utf8PathBuf::from("/models.lib"), FileId(0), MODEL_CARDS, model)?;
+        let values = models[0]
+            .params
+            .iter()
+            .map(|param| match param.value {
+                CliParamDefaultValue::Float(val) => val,
+                _ => unreachable!(),
+            })
+            .collect();
+        Ok(values)
+    }
+
+    #[test]
+    fn unrelated_syntax_errors() {
+        assert_eq!(parse_model("res"), Ok(vec![4000.0]));
+    }
+
+    #[test]
+    fn referenced_syntax_errors() {
+        let errors = |model| parse_model(model).unwrap_err();
+        assert!(matches!(
+            &*errors("res_broken"),
+            [ElisionFileDiagnostic::UnterminatedExpression { .. }, ..]
+        ));
+        assert!(matches!(&*errors("res_sheet"), [ElisionFileDiagnostic::MissingValue { .. }]));
+        assert!(matches!(
+            &*errors("res_missing"),
+            [ElisionFileDiagnostic::UnterminatedExpression { .. }, ..]
+        ));
+        assert!(matches!(
+            &*errors("res_unknown"),
+            [ElisionFileDiagnostic::ExpectedModelName { .. }]
+        ));
+    }
+}
@@ -1,18 +1,20 @@
 use std::fs::{create_dir_all, remove_file};
 use std::io::Write;
+use std::sync::Arc;
 use std::time::Instant;
-use std::collections::HashMap;
 
 use anyhow::Context;
 use anyhow::Result;
 use basedb::diagnostics::{ConsoleSink, DiagnosticSink};
-use basedb::BaseDB;
+use basedb::{BaseDB, SimAssumptions};
 use camino::Utf8PathBuf;
 use hir::CompilationDB;
+use lasso::Rodeo;
 use linker::link;
 use mir_llvm::LLVMBackend;
 use sim_back::collect_modules;
 use sim_back::{print_module, print_intern};
+use syntax::name::Name;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 pub use basedb::lints::builtin as builtin_lints;
@@ -24,8 +26,14 @@ pub use target::spec::{get_target_names, Target};
 
 mod cache;
 pub mod elysian;
+mod report;
+mod variants;
 
-use basedb::{CliParamDefault, CliParamDefaultValue};
+pub use basedb::{CliParamDefault, CliParamDefaultValue};
+pub use elysian::spice::SpiceElision;
+
+use crate::elysian::{ElisionFileDiagnostic, SharedElision};
+use crate::variants::Variant;
 
 #[derive(Debug, Clone)]
 pub enum CompilationDestination {
@@ -38,6 +46,19 @@ pub enum CompilationTermination {
     FatalDiagnostic,
 }
 
+/// How parameters that were elided are exposed in the generated OSDI descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ElidedParamMode {
+    /// Keep elided parameters in the descriptor. Setting them to a value that
+    /// differs from the elided value results in an error during setup. They
+    /// have no storage or given flag in the instance and model data (unless
+    /// the module is binned).
+    #[default]
+    Lock,
+    /// Remove elided parameters from the descriptor.
+    Drop,
+}
+
 #[derive(Debug, Clone)]
 pub struct Opts {
     pub dry_run: bool,
@@ -55,9 +76,171 @@ pub struct Opts {
     pub dump_ir: bool, 
     pub dump_unopt_ir: bool, 
     // RDUBI line
-    pub params_to_leave: Vec<u32>,
+    /// Parameters that are never elided
+    pub keep_dynamic: Vec<String>,
     pub param_defaults: Vec<CliParamDefault>,
+    pub elision_files: Vec<Utf8PathBuf>,
+    /// Directory of elision files that are compiled into separate descriptors
+    pub elision_dir: Option<Utf8PathBuf>,
+    pub elision_spice: Option<SpiceElision>,
+    pub elided_params: ElidedParamMode,
+    /// Write a JSON report of the effect of elision to this file
+    pub elision_report: Option<Utf8PathBuf>,
+    /// Combine the variants of each module into a single descriptor that
+    /// selects a variant from the `l` and `w` of each instance
+    pub binned: bool,
+    /// Elide the parameters that all elision sets assign the same value in the
+    /// generic module, each set is compiled with these and its remaining
+    /// parameters. Requires at least two sets.
+    pub shared_elision: bool,
+    /// Compile with this (fixed) ambient temperature in Kelvin
+    pub assume_temperature: Option<f64>,
+    /// Compile with these (fixed) values of `$simparam`, `mfactor` fixes `$mfactor`
+    pub assume_simparams: Vec<(String, f64)>,
+}
+
+impl Opts {
+    /// Whether each elision set is compiled into a separate (specialized)
+    /// descriptor (or bin) instead of merging all sets. Multiple elision
+    /// files are merged unless one of these options is given.
+    pub fn compiles_variants(&self) -> bool {
+        self.binned || self.shared_elision || self.elision_dir.is_some()
+    }
+
+    /// The options to compile the original module without any elision or
+    /// assumptions, used as the reference when verifying elided libraries.
+    /// The library is written next to the elided library.
+    pub fn reference_opts(&self) -> Opts {
+        let output = match &self.output {
+            CompilationDestination::Path { lib_file } => {
+                CompilationDestination::Path { lib_file: lib_file.with_extension("reference.osdi") }
+            }
+            cache @ CompilationDestination::Cache { .. } => cache.clone(),
+        };
+        Opts {
+            output,
+            dump_mir: false,
+            dump_unopt_mir: false,
+            dump_ir: false,
+            dump_unopt_ir: false,
+            keep_dynamic: Vec::new(),
+            param_defaults: Vec::new(),
+            elision_files: Vec::new(),
+            elision_dir: None,
+            elision_spice: None,
+            elided_params: ElidedParamMode::Lock,
+            elision_report: None,
+            binned: false,
+            shared_elision: false,
+            assume_temperature: None,
+            assume_simparams: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Reads the elision files (and SPICE model) and stores the parameter defaults
+/// they contain (in addition to `opts.param_defaults`) in the database.
+///
+/// If multiple elision sets are compiled into one library (see
+/// [`Opts::compiles_variants`]) only `opts.param_defaults` (and the entries
+/// shared by all sets if `opts.shared_elision` is set) is stored and one
+/// [`Variant`] is returned per set instead. Returns `None` if any elision
+/// source could not be parsed.
+fn set_param_defaults(
+    db: &mut CompilationDB,
+    opts: &Opts,
+) -> Option<(Vec<Variant>, Option<SharedElision>)> {
+    let root_file = db.compilation_unit().root_file();
+    let dynamic_params: Arc<[_]> =
+        opts.keep_dynamic.iter().map(|name| Name::resolve(name.trim())).collect();
+    db.set_cli_dynamic_params(root_file, dynamic_params);
+    let assumptions = SimAssumptions {
+        temperature: opts.assume_temperature,
+        simparams: opts
+            .assume_simparams
+            .iter()
+            .map(|(name, val)| (name.as_str().into(), *val))
+            .collect(),
+    };
+    db.set_sim_assumptions(root_file, Arc::new(assumptions));
+
+    let report = |db: &CompilationDB, diagnostics: Vec<ElisionFileDiagnostic>| {
+        let mut sink = ConsoleSink::new(db);
+        sink.add_diagnostics(&diagnostics, root_file, db);
+        sink.summary(&opts.input.file_name().unwrap());
+        None
+    };
+
+    let mut files = opts.elision_files.clone();
+    if let Some(dir) = &opts.elision_dir {
+        match elysian::dir_files(dir) {
+            Ok(dir_files) => files.extend(dir_files),
+            Err(diagnostics) => return report(db, diagnostics),
+        }
+    }
+
+    if files.is_empty() && opts.elision_spice.is_none() {
+        return Some((Vec::new(), None));
+    }
+
+    let variants = opts.compiles_variants();
+    let mut sets = elysian::read_files(db, &files);
+    if let Some(spice) = &opts.elision_spice {
+        // every bin of a binned model becomes a separate variant
+        let spice_sets = if variants {
+            elysian::spice::read_bins(db, spice)
+        } else {
+            elysian::spice::read_model(db, spice).map(|set| vec![set])
+        };
+        sets = match (sets, spice_sets) {
+            (Ok(mut sets), Ok(spice_sets)) => {
+                sets.extend(spice_sets);
+                Ok(sets)
+            }
+            (Ok(_), Err(diagnostics)) => Err(diagnostics),
+            (Err(mut diagnostics), res) => {
+                diagnostics.extend(res.err().into_iter().flatten());
+                Err(diagnostics)
+            }
+        };
+    }
+
+    let sets = match sets {
+        Ok(sets) => sets,
+        Err(diagnostics) => return report(db, diagnostics),
+    };
+
+    if variants {
+        if opts.shared_elision && sets.len() < 2 {
+            let diagnostic = ElisionFileDiagnostic::SingleSharedSet { sets: sets.len() };
+            return report(db, vec![diagnostic]);
+        }
+        let shared = opts.shared_elision.then(|| {
+            let mut shared = elysian::shared_elision(&sets);
+            if opts.binned {
+                shared.entries.retain(|entry| !variants::is_bin_bound(&entry.name));
+                shared.varying.retain(|name| !variants::is_bin_bound(name));
+            }
+            shared
+        });
+        let mut base = opts.param_defaults.clone();
+        if let Some(shared) = &shared {
+            base.extend(shared.entries.iter().cloned());
+            db.set_cli_param_defaults(root_file, base.as_slice().into());
+        }
+        return match Variant::new(sets, &base, opts.binned) {
+            Ok(variants) => Some((variants, shared)),
+            Err(diagnostics) => report(db, diagnostics),
+        };
+    }
+
+    let defaults: Arc<[_]> =
+        opts.param_defaults.iter().cloned().chain(elysian::to_cli_defaults(&sets)).collect();
+    db.set_cli_param_defaults(root_file, defaults);
+    Some((Vec::new(), None))
 }
+
 // pub fn dump_json(opts: &Opts) -> Result<CompilationTermination> {
 //     let input =
 //         opts.input.canonicalize().with_context(|| format!("failed to resolve {}", opts.input))?;
@@ -165,19 +348,46 @@ pub fn expand(opts: &Opts) -> Result<CompilationTermination> {
     Ok(CompilationTermination::Compiled { lib_file: Utf8PathBuf::default() })
 }
 
+/// Prints how many parameters are shared by all elision sets and which
+/// parameters vary between them.
+fn print_shared_elision(shared: &SharedElision, num_sets: usize) -> Result<()> {
+    let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+    stderr.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(&mut stderr, "Shared")?;
+    stderr.set_color(&ColorSpec::new())?;
+    writeln!(
+        &mut stderr,
+        " {} parameters between {num_sets} elision sets, {} vary: {}",
+        shared.entries.len(),
+        shared.varying.len(),
+        shared.varying.iter().map(|name| &**name).collect::<Vec<_>>().join(", ")
+    )?;
+    Ok(())
+}
+
 pub fn compile(opts: &Opts) -> Result<CompilationTermination> {
     let start = Instant::now();
 
     let input =
         opts.input.canonicalize().with_context(|| format!("failed to resolve {}", opts.input))?;
     let input = AbsPathBuf::assert(input);
-    let db = CompilationDB::new_fs(input, &opts.include, &opts.defines, &opts.lints, &opts.param_defaults)?;
+    let mut db = CompilationDB::new_fs(input, &opts.include, &opts.defines, &opts.lints, &opts.param_defaults)?;
+    let (variants, shared) = if let Some(res) = set_param_defaults(&mut db, opts) {
+        res
+    } else {
+        return Ok(CompilationTermination::FatalDiagnostic);
+    };
+    if let Some(shared) = &shared {
+        print_shared_elision(shared, variants.len())?;
+    }
 
     let lib_file = match &opts.output {
         CompilationDestination::Cache { cache_dir } => {
-            let file_name = cache::file_name(&db, opts);
+            let file_name = cache::file_name(&db, opts, &variants);
             let lib_file = cache_dir.join(file_name);
-            if cfg!(not(debug_assertions)) && lib_file.exists() {
+            // the report requires the MIR so a cached library can not be used
+            if cfg!(not(debug_assertions)) && lib_file.exists() && opts.elision_report.is_none()
+            {
                 return Ok(CompilationTermination::Compiled { lib_file });
             }
             create_dir_all(cache_dir).context("failed to create cache directory")?;
@@ -186,17 +396,59 @@ pub fn compile(opts: &Opts) -> Result<CompilationTermination> {
         CompilationDestination::Path { lib_file } => lib_file.clone(),
     };
 
-    let modules = if let Some(modules) = collect_modules(&db, false, &mut ConsoleSink::new(&db)) {
+    let mut modules = if let Some(modules) = collect_modules(&db, false, &mut ConsoleSink::new(&db))
+    {
         modules
     } else {
         return Ok(CompilationTermination::FatalDiagnostic);
     };
-
+    let specializations =
+        if let Some(res) = variants::specialize(&mut db, &modules, &variants, opts.elided_params) {
+            res
+        } else {
+            return Ok(CompilationTermination::FatalDiagnostic);
+        };
     let back = LLVMBackend::new(&opts.codegen_opts, &opts.target, opts.target_cpu.clone(), &[]);
+    let baseline = opts
+        .elision_report
+        .as_ref()
+        .map(|_| report::baseline_stats(&mut db, &modules, &opts.target, &back));
+    if opts.elided_params == ElidedParamMode::Drop {
+        modules.iter_mut().for_each(|module| module.remove_elided_params(&db));
+    }
+
     if opts.dry_run {
         return Ok(CompilationTermination::Compiled { lib_file });
     }
-    let (paths, compiled_modules, literals) = osdi::compile(&db, &modules, &lib_file, &opts.target, &back, true, opts.opt_lvl, opts.dump_mir, opts.dump_unopt_mir, opts.dump_ir, opts.dump_unopt_ir, &opts.params_to_leave);
+    let mut literals = Rodeo::new();
+    let compiled_modules = variants::build(
+        &mut db,
+        &modules,
+        &specializations,
+        &variants,
+        &mut literals,
+        opts.binned || opts.shared_elision,
+        opts.dump_unopt_mir,
+        opts.dump_mir,
+    );
+    let binned = if opts.binned {
+        variants::binned_devices(&db, &modules, &specializations, &variants, &compiled_modules)?
+    } else {
+        Vec::new()
+    };
+    let (paths, compiled_modules, literals) = osdi::compile_variants(
+        &db,
+        compiled_modules,
+        &binned,
+        literals,
+        &lib_file,
+        &opts.target,
+        &back,
+        true,
+        opts.opt_lvl,
+        opts.dump_ir,
+        opts.dump_unopt_ir,
+    );
 
     // Dump MIR of compiled modules
     if opts.dump_mir || opts.dump_unopt_mir {
@@ -210,24 +462,48 @@ pub fn compile(opts: &Opts) -> Result<CompilationTermination> {
         }
         println!("");
 
-        for(module, cmodule) in modules.iter().zip(compiled_modules.iter()) {
-            print_module("  ", &db, &module, &cmodule.dae_system, &cmodule.init);
+        for variant in compiled_modules.iter() {
+            let cmodule = &variant.module;
+            print_module("  ", &db, cmodule.info, &cmodule.dae_system, &cmodule.init);
             println!("");
 
-            println!("Model setup HIR interner of {}", module.module.name(&db));
+            println!("Model setup HIR interner of {}", variant.name);
             print_intern("  ", &db, &cmodule.model_param_intern);
             println!("");
 
-            println!("Instance setup HIR interner of {}", module.module.name(&db));
+            println!("Instance setup HIR interner of {}", variant.name);
             print_intern("  ", &db, &cmodule.init.intern);
             println!("");
 
-            println!("Evaluation HIR interner of {}", module.module.name(&db));
+            println!("Evaluation HIR interner of {}", variant.name);
             print_intern("  ", &db, &cmodule.intern);
             println!("");
         }
     }
 
+    let module_reports: Vec<_> = baseline
+        .map(|baseline| {
+            let sizes = osdi::data_sizes(&db, &compiled_modules, &binned, &opts.target, &back);
+            compiled_modules
+                .iter()
+                .enumerate()
+                .map(|(i, variant)| {
+                    let module = modules
+                        .iter()
+                        .position(|module| module.module == variant.module.info.module)
+                        .unwrap();
+                    report::ModuleReport::new(
+                        &db,
+                        variant,
+                        baseline[module],
+                        sizes[i],
+                        &paths[i * 4..i * 4 + 4],
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
     // TODO configure linker
     link(None, &opts.target, lib_file.as_ref(), |linker| {
         for path in &paths {
@@ -235,6 +511,10 @@ pub fn compile(opts: &Opts) -> Result<CompilationTermination> {
         }
     })?;
 
+    if let Some(report_file) = &opts.elision_report {
+        report::write(report_file, &module_reports, shared.as_ref(), &lib_file)?;
+    }
+
     for obj_file in paths {
         remove_file(obj_file).context("failed to delete intermediate compile artifact")?;
     }
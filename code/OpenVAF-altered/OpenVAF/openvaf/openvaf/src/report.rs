@@ -0,0 +1,194 @@
+//! Report of the effect of parameter elision (`--elision-report`).
+//!
+//! The MIR of every module is built a second time without any elisions to
+//! obtain a baseline that the elided MIR and the size of the instance and
+//! model data are compared against. With
+//! `--shared-elision` the report also lists the shared and the varying parameters.
+
+use std::fmt::{Display, Write};
+use std::fs;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use basedb::BaseDB;
+use camino::{Utf8Path, Utf8PathBuf};
+use hir::CompilationDB;
+use lasso::Rodeo;
+use mir_llvm::LLVMBackend;
+use osdi::{DataSizes, ModuleVariant};
+use sim_back::stats::ModuleStats;
+use sim_back::{CompiledModule, ModuleInfo};
+use target::spec::Target;
+
+use crate::elysian::SharedElision;
+
+pub(crate) struct ModuleReport {
+    name: String,
+    elided_params: Vec<String>,
+    resolved_param_given: Vec<String>,
+    before: ModuleStats,
+    after: ModuleStats,
+    sizes_before: DataSizes,
+    sizes_after: DataSizes,
+    object_size: u64,
+}
+
+impl ModuleReport {
+    pub(crate) fn new(
+        db: &CompilationDB,
+        variant: &ModuleVariant,
+        (before, sizes_before): (ModuleStats, DataSizes),
+        sizes_after: DataSizes,
+        objects: &[Utf8PathBuf],
+    ) -> ModuleReport {
+        let module = &variant.module;
+        let interns = [&module.intern, &module.init.intern, &module.model_param_intern];
+        let mut elided_params = Vec::new();
+        let mut resolved_param_given = Vec::new();
+        for intern in interns {
+            elided_params.extend(intern.elided_params.iter().map(|param| param.name(db)));
+            resolved_param_given
+                .extend(intern.elided_param_given.iter().map(|param| param.name(db)));
+        }
+        elided_params.sort_unstable();
+        elided_params.dedup();
+        resolved_param_given.sort_unstable();
+        resolved_param_given.dedup();
+
+        let object_size =
+            objects.iter().filter_map(|path| fs::metadata(path).ok()).map(|m| m.len());
+
+        ModuleReport {
+            name: variant.name.clone(),
+            elided_params,
+            resolved_param_given,
+            before,
+            after: ModuleStats::new(module),
+            sizes_before,
+            sizes_after,
+            object_size: object_size.sum(),
+        }
+    }
+}
+
+/// Builds the MIR and the data layout of all modules without elisions
+pub(crate) fn baseline_stats(
+    db: &mut CompilationDB,
+    modules: &[ModuleInfo],
+    target: &Target,
+    back: &LLVMBackend,
+) -> Vec<(ModuleStats, DataSizes)> {
+    let root_file = db.compilation_unit().root_file();
+    let defaults = db.cli_param_defaults(root_file);
+    db.set_cli_param_defaults(root_file, Arc::new([]));
+
+    let mut literals = Rodeo::new();
+    let variants: Vec<_> = modules
+        .iter()
+        .map(|module| ModuleVariant {
+            module: CompiledModule::new(db, module, &mut literals, false, false),
+            name: module.module.name(db),
+        })
+        .collect();
+    let sizes = osdi::data_sizes(db, &variants, &[], target, back);
+    let stats =
+        variants.iter().map(|variant| ModuleStats::new(&variant.module)).zip(sizes).collect();
+
+    db.set_cli_param_defaults(root_file, defaults);
+    stats
+}
+
+pub(crate) fn write(
+    path: &Utf8Path,
+    modules: &[ModuleReport],
+    shared: Option<&SharedElision>,
+    lib_file: &Utf8Path,
+) -> Result<()> {
+    let lib_size = fs::metadata(lib_file).map_or(0, |m| m.len());
+
+    let mut dst = String::new();
+    dst.push_str("{\n  \"modules\": [");
+    for (i, module) in modules.iter().enumerate() {
+        if i != 0 {
+            dst.push(',');
+        }
+        let (before, after) = (&module.before, &module.after);
+        let _ = write!(
+            dst,
+            "
+    {{
+      \"name\": {},
+      \"elided_params\": {},
+      \"resolved_param_given\": {},
+      \"instructions\": {{
+        \"eval\": {},
+        \"instance_setup\": {},
+        \"model_setup\": {}
+      }},
+      \"init_cache_slots\": {},
+      \"instance_size\": {},
+      \"model_size\": {},
+      \"jacobian_entries\": {},
+      \"constant_jacobian_entries\": {},
+      \"object_code_size\": {}
+    }}",
+            json_str(&module.name),
+            json_str_list(&module.elided_params),
+            json_str_list(&module.resolved_param_given),
+            json_diff(before.eval_insts, after.eval_insts),
+            json_diff(before.instance_setup_insts, after.instance_setup_insts),
+            json_diff(before.model_setup_insts, after.model_setup_insts),
+            json_diff(before.cache_slots, after.cache_slots),
+            json_diff(module.sizes_before.instance, module.sizes_after.instance),
+            json_diff(module.sizes_before.model, module.sizes_after.model),
+            json_diff(before.jacobian_entries, after.jacobian_entries),
+            json_diff(before.const_jacobian_entries, after.const_jacobian_entries),
+            module.object_size,
+        );
+    }
+    dst.push_str("\n  ],");
+    if let Some(shared) = shared {
+        let shared_params: Vec<_> =
+            shared.entries.iter().map(|entry| entry.name.to_string()).collect();
+        let varying_params: Vec<_> = shared.varying.iter().map(|name| name.to_string()).collect();
+        let _ = write!(
+            dst,
+            "
+  \"shared_elision\": {{
+    \"shared_params\": {},
+    \"varying_params\": {}
+  }},",
+            json_str_list(&shared_params),
+            json_str_list(&varying_params),
+        );
+    }
+    let _ = write!(dst, "\n  \"library_size\": {lib_size}\n}}\n");
+
+    fs::write(path, dst).with_context(|| format!("failed to write elision report {path}"))
+}
+
+fn json_diff(before: impl Display, after: impl Display) -> String {
+    format!("{{ \"before\": {before}, \"after\": {after} }}")
+}
+
+fn json_str_list(strs: &[String]) -> String {
+    let strs: Vec<_> = strs.iter().map(|str| json_str(str)).collect();
+    format!("[{}]", strs.join(", "))
+}
+
+fn json_str(str: &str) -> String {
+    let mut res = String::with_capacity(str.len() + 2);
+    res.push('"');
+    for c in str.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(res, "\\u{:04x}", c as u32);
+            }
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
@@ -0,0 +1,245 @@
+//! Compilation of multiple elision sets into a single library (`--elision-dir`).
+//!
+//! Every elision set produces a specialized copy of the modules whose
+//! parameters it elides. These copies are exported as separate OSDI
+//! descriptors named `<module>_<variant>`. Modules that are not affected by
+//! any set are only compiled (and exported) once.
+//!
+//! With `--binned` the specializations of a module are instead combined into
+//! a single descriptor that selects a specialization from the `l` and `w` of
+//! each instance (see [`osdi::BinnedDevice`]).
+//!
+//! With `--shared-elision` the parameters that all sets assign the same value
+//! are elided in the generic descriptor, which is exported as well. Only the
+//! modules whose parameters vary between the sets are specialized. These are
+//! lowered and code generated for each set (with the shared and the residual
+//! elisions of the set), only the frontend queries are shared.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use basedb::diagnostics::ConsoleSink;
+use basedb::{BaseDB, CliParamDefault, CliParamDefaultValue};
+use camino::Utf8PathBuf;
+use hir::{CompilationDB, Parameter};
+use lasso::Rodeo;
+use osdi::{Bin, BinnedDevice, ModuleVariant};
+use sim_back::{CompiledModule, ModuleInfo};
+
+use crate::elysian::{ElisionFileDiagnostic, ElisionSet};
+use crate::ElidedParamMode;
+
+/// The entries of an elision set that define the bounds of a bin. They are
+/// not parameters and are removed from the defaults of binned variants.
+const BIN_BOUNDS: [&str; 4] = ["lmin", "lmax", "wmin", "wmax"];
+
+pub(crate) fn is_bin_bound(name: &str) -> bool {
+    BIN_BOUNDS.iter().any(|bound| name.eq_ignore_ascii_case(bound))
+}
+
+pub(crate) struct Variant {
+    pub name: String,
+    pub path: Utf8PathBuf,
+    /// All parameter defaults (including those shared by all variants)
+    pub defaults: Arc<[CliParamDefault]>,
+    /// `(lmin, lmax)` and `(wmin, wmax)` if the variants are binned
+    pub bounds: Option<((f64, f64), (f64, f64))>,
+}
+
+impl Variant {
+    /// Creates one variant for each of the `sets`. If `binned` is set the
+    /// bounds of each bin are read (and removed) from the `lmin`, `lmax`,
+    /// `wmin` and `wmax` entries of the set. Entries that `base` already
+    /// assigns the same value are dropped so that each variant only contains
+    /// its residual.
+    pub(crate) fn new(
+        sets: Vec<ElisionSet>,
+        base: &[CliParamDefault],
+        binned: bool,
+    ) -> Result<Vec<Variant>, Vec<ElisionFileDiagnostic>> {
+        let mut names: HashMap<String, Utf8PathBuf> = HashMap::with_capacity(sets.len());
+        let mut diagnostics = Vec::new();
+        let mut variants = Vec::with_capacity(sets.len());
+        for set in sets {
+            let name = set.variant_name();
+            if let Some(prev) = names.insert(name.clone(), set.path.clone()) {
+                diagnostics.push(ElisionFileDiagnostic::DuplicateVariant {
+                    name,
+                    path: set.path,
+                    prev,
+                });
+                continue;
+            }
+            let mut bound = |name| {
+                let value = set
+                    .entries
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.name.eq_ignore_ascii_case(name))
+                    .and_then(|entry| match entry.value {
+                        CliParamDefaultValue::Int(value) => Some(value as f64),
+                        CliParamDefaultValue::Float(value) => Some(value),
+                        CliParamDefaultValue::Str(_) | CliParamDefaultValue::Range(_) => None,
+                    });
+                if value.is_none() {
+                    let path = set.path.clone();
+                    diagnostics.push(ElisionFileDiagnostic::MissingBinBound { path, name });
+                }
+                value.unwrap_or(f64::NAN)
+            };
+            let bounds =
+                binned.then(|| ((bound("lmin"), bound("lmax")), (bound("wmin"), bound("wmax"))));
+            let residual = set.entries.into_iter().filter(|entry| {
+                let prev = base.iter().rev().find(|prev| prev.name == entry.name);
+                prev.map_or(true, |prev| prev.value != entry.value)
+                    && !(binned && is_bin_bound(&entry.name))
+            });
+            let defaults = base.iter().cloned().chain(residual).collect();
+            variants.push(Variant { name, path: set.path, defaults, bounds })
+        }
+
+        if diagnostics.is_empty() {
+            Ok(variants)
+        } else {
+            Err(diagnostics)
+        }
+    }
+}
+
+/// A module specialized for one variant
+pub(crate) struct Specialization {
+    pub variant: usize,
+    pub name: String,
+    pub info: ModuleInfo,
+}
+
+/// Returns whether `param` is elided or restricted to an assumed range
+fn is_specialized(db: &CompilationDB, param: Parameter) -> bool {
+    param.is_elided(db) || param.assumed_range(db).is_some()
+}
+
+/// Determines which modules are affected by each variant and reports the
+/// elision diagnostics of all variants. Returns `None` if any of them were
+/// errors.
+pub(crate) fn specialize(
+    db: &mut CompilationDB,
+    modules: &[ModuleInfo],
+    variants: &[Variant],
+    mode: ElidedParamMode,
+) -> Option<Vec<Specialization>> {
+    let cu = db.compilation_unit();
+    let root_file = cu.root_file();
+    let base = db.cli_param_defaults(root_file);
+    let base_elided: Vec<Vec<Parameter>> = modules
+        .iter()
+        .map(|module| {
+            module.params.keys().copied().filter(|&param| is_specialized(db, param)).collect()
+        })
+        .collect();
+
+    let mut res = Vec::new();
+    let mut failed = false;
+    for (i, variant) in variants.iter().enumerate() {
+        db.set_cli_param_defaults(root_file, variant.defaults.clone());
+        let db = &*db;
+        let mut sink = ConsoleSink::new(db);
+        cu.elision_diagnostics(db, &mut sink);
+        failed |= sink.summary(&variant.path.file_name().unwrap_or(variant.name.as_str()));
+
+        for (module, base_elided) in modules.iter().zip(&base_elided) {
+            let affected = module
+                .params
+                .keys()
+                .any(|&param| is_specialized(db, param) && !base_elided.contains(&param));
+            if !affected {
+                continue;
+            }
+            let mut info = module.clone();
+            if mode == ElidedParamMode::Drop {
+                info.remove_elided_params(db);
+            }
+            let name = format!("{}_{}", module.module.name(db), variant.name);
+            res.push(Specialization { variant: i, name, info });
+        }
+    }
+
+    db.set_cli_param_defaults(root_file, base);
+    (!failed).then_some(res)
+}
+
+/// Builds the MIR of all modules that are not specialized (with the shared
+/// elisions) and of all specializations (with the elisions of their variant).
+/// Specialized modules are only built without specialization if `include_generic` is set.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build<'a>(
+    db: &mut CompilationDB,
+    modules: &'a [ModuleInfo],
+    specializations: &'a [Specialization],
+    variants: &[Variant],
+    literals: &mut Rodeo,
+    include_generic: bool,
+    dump_unopt_mir: bool,
+    dump_mir: bool,
+) -> Vec<ModuleVariant<'a>> {
+    let root_file = db.compilation_unit().root_file();
+    let mut res: Vec<_> = modules
+        .iter()
+        .filter(|module| {
+            include_generic || specializations.iter().all(|spec| spec.info.module != module.module)
+        })
+        .map(|module| ModuleVariant {
+            module: CompiledModule::new(db, module, literals, dump_unopt_mir, dump_mir),
+            name: module.module.name(db),
+        })
+        .collect();
+
+    if specializations.is_empty() {
+        return res;
+    }
+
+    let base = db.cli_param_defaults(root_file);
+    let mut current = None;
+    for spec in specializations {
+        if current != Some(spec.variant) {
+            db.set_cli_param_defaults(root_file, variants[spec.variant].defaults.clone());
+            current = Some(spec.variant);
+        }
+        res.push(ModuleVariant {
+            module: CompiledModule::new(db, &spec.info, literals, dump_unopt_mir, dump_mir),
+            name: spec.name.clone(),
+        });
+    }
+    db.set_cli_param_defaults(root_file, base);
+    res
+}
+
+/// Combines the specializations of each module into a binned device that
+/// replaces the unspecialized module. Expects the modules in the order
+/// produced by [`build`] with `binned` set.
+pub(crate) fn binned_devices(
+    db: &CompilationDB,
+    modules: &[ModuleInfo],
+    specializations: &[Specialization],
+    variants: &[Variant],
+    compiled: &[ModuleVariant],
+) -> Result<Vec<BinnedDevice>, osdi::BinnedDeviceError> {
+    let mut devices = Vec::new();
+    for (generic, module) in modules.iter().enumerate() {
+        let bins: Vec<_> = specializations
+            .iter()
+            .enumerate()
+            .filter(|(_, spec)| spec.info.module == module.module)
+            .map(|(i, spec)| {
+                let (l, w) = variants[spec.variant].bounds.expect("variants are binned");
+                Bin { variant: modules.len() + i, l, w }
+            })
+            .collect();
+        if bins.is_empty() {
+            continue;
+        }
+        let device = BinnedDevice { name: module.module.name(db), generic, bins };
+        device.check(db, compiled)?;
+        devices.push(device);
+    }
+    Ok(devices)
+}
@@ -1,23 +1,43 @@
 use std::f64::consts;
+use std::ffi::CStr;
 use std::path::Path;
 
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use expect_test::expect_file;
 use float_cmp::assert_approx_eq;
 use llvm::OptLevel;
 use mini_harness::{harness, Result};
-use openvaf::{CompilationDestination, CompilationTermination};
-use stdx::{ignore_dev_tests, openvaf_test_data, project_root};
+use openvaf::{
+    CliParamDefault, CliParamDefaultValue, CompilationDestination, CompilationTermination,
+    ElidedParamMode, LintLevel, SpiceElision,
+};
+use osdi_verify::load::{
+    load_osdi_lib, EvalFlags, EvalRetFlags, OsdiDescriptor, ParamValue, SimParams,
+};
+use osdi_verify::{Difference, Mismatch, ParamSetting, VerifyOpts};
+use stdx::{format_to, ignore_dev_tests, openvaf_test_data, project_root, write_test_module};
+use syntax::name::Name;
 use target::spec::Target;
 
-use crate::load::{load_osdi_lib, EvalFlags, OsdiDescriptor};
-use crate::mock_sim::{MockSimulation, ALPHA};
+use crate::mock_sim::{MockDevice, MockInstance, MockSimulation, ALPHA, HANDLE};
 
-mod load;
 mod mock_sim;
 
 fn compile_and_load(root_file: &Utf8Path) -> &'static OsdiDescriptor {
-    let openvaf_opts = openvaf::Opts {
+    compile_and_load_elided(root_file, Vec::new(), ElidedParamMode::default())
+}
+
+fn compile_and_load_elided(
+    root_file: &Utf8Path,
+    param_defaults: Vec<CliParamDefault>,
+    elided_params: ElidedParamMode,
+) -> &'static OsdiDescriptor {
+    let opts = openvaf::Opts { param_defaults, elided_params, ..default_opts(root_file) };
+    compile_and_load_opts(&opts)
+}
+
+fn default_opts(root_file: &Utf8Path) -> openvaf::Opts {
+    openvaf::Opts {
         defines: Vec::new(),
         codegen_opts: Vec::new(),
         lints: Vec::new(),
@@ -32,18 +52,40 @@ fn compile_and_load(root_file: &Utf8Path) -> &'static OsdiDescriptor {
         dump_unopt_mir: false, 
         dump_ir: false, 
         dump_unopt_ir: false, 
-    };
+        keep_dynamic: Vec::new(),
+        param_defaults: Vec::new(),
+        elision_files: Vec::new(),
+        elision_dir: None,
+        elision_spice: None,
+        elided_params: ElidedParamMode::default(),
+        elision_report: None,
+        binned: false,
+        shared_elision: false,
+        assume_temperature: None,
+        assume_simparams: Vec::new(),
+    }
+}
 
-    let res = openvaf::compile(&openvaf_opts).unwrap();
-    let lib_file = match res {
+fn compile_and_load_opts(opts: &openvaf::Opts) -> &'static OsdiDescriptor {
+    let libs = compile_and_load_lib(opts);
+    assert_eq!(libs.len(), 1);
+    &libs[0]
+}
+
+fn compile_and_load_lib(opts: &openvaf::Opts) -> &'static [OsdiDescriptor] {
+    let lib_file = compile_lib(opts);
+    unsafe { load_osdi_lib(&lib_file).unwrap() }
+}
+
+fn compile_lib(opts: &openvaf::Opts) -> Utf8PathBuf {
+    let root_file = &opts.input;
+    let res = openvaf::compile(opts).unwrap();
+    match res {
         CompilationTermination::Compiled { lib_file } => lib_file,
         CompilationTermination::FatalDiagnostic => {
             panic!("openvaf: compilation of {root_file} failed");
         }
-    };
-    let libs = unsafe { load_osdi_lib(&lib_file).unwrap() };
-    assert_eq!(libs.len(), 1);
-    &libs[0]
+    }
 }
 
 // fn integration_test(dir: &str) -> Result {
@@ -70,9 +112,9 @@ fn test_descriptor(main_file: &Path) -> Result<&'static OsdiDescriptor> {
     let test_dir = openvaf_test_data("osdi");
     expect_file![test_dir.join(format!("{name}.snap"))].assert_eq(&expect);
     let default_model = desc.new_model();
-    default_model.process_params()?;
-    let mut instance = default_model.new_instance();
-    instance.process_params(&default_model, desc.num_terminals, 300.0)?;
+    default_model.process_params(HANDLE, &mut SimParams::default())?;
+    let instance = default_model.new_instance();
+    instance.process_params(&default_model, HANDLE, 300.0, &mut SimParams::default())?;
     Ok(desc)
 }
 
@@ -146,11 +188,11 @@ fn test_limit() -> Result<()> {
     // compile model and setup simulation
     let desc = test_descriptor(&openvaf_test_data("osdi").join("diode_lim.va"))?;
     let model = desc.new_model();
-    model.set_real_param(1, IS);
-    model.set_real_param(5, CJ0);
-    model.process_params()?;
+    model.write_param(1, ParamValue::Real(IS));
+    model.write_param(5, ParamValue::Real(CJ0));
+    model.process_params(HANDLE, &mut SimParams::default())?;
     let mut instance = model.new_instance();
-    let mut sim = instance.mock_simulation(&model, desc.num_terminals, 300.0)?;
+    let mut sim = instance.mock_simulation(&model, 300.0)?;
 
     instance.eval(&model, &mut sim, EvalFlags::INIT_LIM | EvalFlags::ENABLE_LIM);
     instance.load_dae(&model, &mut sim);
@@ -162,11 +204,13 @@ fn test_limit() -> Result<()> {
     sim.next_iter();
     sim.set_voltage("A", 2.0 * vcrit);
     instance.eval(&model, &mut sim, EvalFlags::ENABLE_LIM);
+    // SPICE pnjlim steps logarithmically from the previous (limited) voltage
+    let vd_lim = vcrit + VT * f64::ln(1.0 + vcrit / VT);
     instance.load_dae(&model, &mut sim);
-    check_dae_equations(&sim, 1.5 * vcrit, 2.0 * vcrit);
+    check_dae_equations(&sim, vd_lim, 2.0 * vcrit);
     sim.clear();
     instance.load_spice(&model, &mut sim);
-    check_spice_equations(&sim, 1.5 * vcrit, 2.0 * vcrit);
+    check_spice_equations(&sim, vd_lim, 2.0 * vcrit);
     Ok(())
 }
 
@@ -195,12 +239,12 @@ fn test_noise() -> Result<()> {
     // compile model and setup simulation
     let desc = test_descriptor(&openvaf_test_data("osdi").join("noise.va"))?;
     let model = desc.new_model();
-    model.set_real_param(0, MFACTOR);
-    model.set_real_param(1, PWR);
-    model.set_real_param(2, EXP);
-    model.process_params()?;
+    model.write_param(0, ParamValue::Real(MFACTOR));
+    model.write_param(1, ParamValue::Real(PWR));
+    model.write_param(2, ParamValue::Real(EXP));
+    model.process_params(HANDLE, &mut SimParams::default())?;
     let mut instance = model.new_instance();
-    let mut sim = instance.mock_simulation(&model, desc.num_terminals, 300.0)?;
+    let mut sim = instance.mock_simulation(&model, 300.0)?;
 
     sim.set_voltage("a", V_AC);
     instance.eval(&model, &mut sim, EvalFlags::empty());
@@ -219,8 +263,911 @@ fn test_noise() -> Result<()> {
     Ok(())
 }
 
+/// Evaluates a two terminal device with the model parameters `params` at
+/// `V(a, c) = 1` and checks that it conducts `g`.
+fn assert_conductance(desc: &'static OsdiDescriptor, params: &[(&str, f64)], g: f64) -> Result {
+    let mut device = MockDevice::new(desc, params, 300.0)?;
+    device.eval(EvalFlags::empty(), &[("a", 1.0)]);
+    assert_approx_eq!(device.sim.read_jacobian("a", "a").0, g);
+    assert_approx_eq!(device.sim.read_residual("a").0, g);
+    Ok(())
+}
+
+const ELIDED_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module elided_resistor(inout electrical a, inout electrical c);
+    parameter real r = 1.0 from (0:inf);
+    analog I(a, c) <+ V(a, c) / r;
+endmodule
+"#;
+
+/// Elision defaults must be resolved against the actual root file of the
+/// compilation unit. Prepend a varying number of user include files to
+/// ensure that the root file id does not matter.
+fn test_elision_root_file(num_includes: usize) -> Result {
+    const R: f64 = 4.0;
+
+    let mut files = Vec::new();
+    let mut src = String::new();
+    for i in 0..num_includes {
+        let header = format!("header{i}.vams");
+        format_to!(src, "`include \"{header}\"\n");
+        files.push((header, format!("`define ELISION_HEADER{i} {i}\n")));
+    }
+    src.push_str(ELIDED_RESISTOR);
+    files.push(("elided_resistor.va".to_owned(), src));
+    let files: Vec<_> = files.iter().map(|(path, src)| (&**path, &**src)).collect();
+    let Some(dir) = write_test_module(&format!("openvaf_elision_{num_includes}"), &files)? else {
+        return Ok(());
+    };
+    let root_file = dir.join("elided_resistor.va");
+
+    let elision = CliParamDefault {
+        name: Name::resolve("r"),
+        value: CliParamDefaultValue::Float(R),
+        span: None,
+    };
+    let desc = compile_and_load_elided(&root_file, vec![elision], ElidedParamMode::default());
+    // the default (r = 1) would produce a conductance of 1
+    assert_conductance(desc, &[], 1.0 / R)
+}
+
+const PARAM_GIVEN_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module param_given_resistor(inout electrical a, inout electrical c);
+    parameter real r = 1.0 from (0:inf);
+    parameter real g = $param_given(r) ? 1.0 / r : 0.5;
+    analog I(a, c) <+ V(a, c) * ($param_given(r) ? g : 0.1);
+endmodule
+"#;
+
+/// Elided parameters must be treated as given both during eval and model setup.
+fn test_elision_param_given() -> Result {
+    const R: f64 = 4.0;
+
+    let Some(dir) = write_test_module(
+        "openvaf_elision_param_given",
+        &[("param_given_resistor.va", PARAM_GIVEN_RESISTOR)],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("param_given_resistor.va");
+
+    let elision = CliParamDefault {
+        name: Name::resolve("r"),
+        value: CliParamDefaultValue::Int(R as i32),
+        span: None,
+    };
+    let desc = compile_and_load_elided(&root_file, vec![elision], ElidedParamMode::default());
+    assert_conductance(desc, &[], 1.0 / R)
+}
+
+/// Elided parameters are either removed from the descriptor or setting them
+/// to a different value is an error.
+fn test_elided_param_mode(mode: ElidedParamMode) -> Result {
+    let Some(dir) = write_test_module(
+        &format!("openvaf_elided_param_mode_{mode:?}"),
+        &[("elided_resistor.va", ELIDED_RESISTOR)],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("elided_resistor.va");
+
+    let elision = CliParamDefault {
+        name: Name::resolve("r"),
+        value: CliParamDefaultValue::Float(4.0),
+        span: None,
+    };
+    let desc = compile_and_load_elided(&root_file, vec![elision], mode);
+    let param = desc.param_id("r");
+    match mode {
+        ElidedParamMode::Drop => assert_eq!(param, None),
+        ElidedParamMode::Lock => {
+            let param = param.expect("elided parameter was removed");
+            let model = desc.new_model();
+            model.write_param(param, ParamValue::Real(4.0));
+            model.process_params(HANDLE, &mut SimParams::default())?;
+
+            let model = desc.new_model();
+            model.write_param(param, ParamValue::Real(5.0));
+            assert!(model.process_params(HANDLE, &mut SimParams::default()).is_err());
+        }
+    }
+    Ok(())
+}
+
+const RESISTOR_MODEL_CARDS: &str = r#"* binned resistor
+.param rnom = 2k
+.param rmax = {rnom * 10   $ unused and invalid
+.MODEL res.1 r (R = '2 * rnom') $ 4 kOhm
+.model res.2 r
++ r = {sqrt(16meg) / 1k}
+.model res.3 r r = undefined_param
+"#;
+
+/// Parameters can be elided with the values of a (binned) SPICE model card
+fn test_elision_spice(bin: u32) -> Result {
+    let Some(dir) = write_test_module(
+        &format!("openvaf_elision_spice_{bin}"),
+        &[("elided_resistor.va", ELIDED_RESISTOR), ("resistor.lib", RESISTOR_MODEL_CARDS)],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("elided_resistor.va");
+    let model_file = dir.join("resistor.lib");
+
+    let r = if bin == 1 { 4000.0 } else { 4.0 };
+    let elision_spice = SpiceElision { path: model_file, model: format!("RES.{bin}") };
+    let opts = openvaf::Opts { elision_spice: Some(elision_spice), ..default_opts(&root_file) };
+    let desc = compile_and_load_opts(&opts);
+    assert_conductance(desc, &[], 1.0 / r)
+}
+
+/// The elision report lists the folded parameters and the MIR and data sizes
+/// with and without elision
+fn test_elision_report() -> Result {
+    let Some(dir) = write_test_module(
+        "openvaf_elision_report",
+        &[("param_given_resistor.va", PARAM_GIVEN_RESISTOR)],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("param_given_resistor.va");
+    let report_file = dir.join("report.json");
+
+    let elision = CliParamDefault {
+        name: Name::resolve("r"),
+        value: CliParamDefaultValue::Float(4.0),
+        span: None,
+    };
+    let opts = openvaf::Opts {
+        param_defaults: vec![elision],
+        elision_report: Some(report_file.clone()),
+        ..default_opts(&root_file)
+    };
+    compile_and_load_opts(&opts);
+
+    let report = std::fs::read_to_string(&report_file)?;
+    assert!(report.contains("\"name\": \"param_given_resistor\""), "{report}");
+    assert!(report.contains("\"elided_params\": [\"r\"]"), "{report}");
+    assert!(report.contains("\"resolved_param_given\": [\"r\"]"), "{report}");
+    assert!(report.contains("\"library_size\""), "{report}");
+    // the selected conductance no longer needs to be cached in the instance
+    let (before, after) = report_diff(&report, "instance_size");
+    assert!(after < before, "{report}");
+    // the locked model parameter has no storage in the model
+    let (before, after) = report_diff(&report, "model_size");
+    assert!(after < before, "{report}");
+    Ok(())
+}
+
+const SCALED_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module scaled_resistor(inout electrical a, inout electrical c);
+    (*type="instance"*) parameter real m = 1.0 from (0:inf);
+    parameter real r = 1.0 from (0:inf);
+    analog I(a, c) <+ m * V(a, c) / r;
+endmodule
+"#;
+
+/// Locked instance and model parameters are removed from the instance and
+/// model data but can still be read and set to their elided value.
+fn test_locked_param_layout() -> Result {
+    let Some(dir) =
+        write_test_module("openvaf_locked_layout", &[("scaled_resistor.va", SCALED_RESISTOR)])?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("scaled_resistor.va");
+    let report_file = dir.join("report.json");
+
+    let elision = |name, value| CliParamDefault {
+        name: Name::resolve(name),
+        value: CliParamDefaultValue::Float(value),
+        span: None,
+    };
+    let opts = openvaf::Opts {
+        param_defaults: vec![elision("m", 2.0), elision("r", 4.0)],
+        elision_report: Some(report_file.clone()),
+        ..default_opts(&root_file)
+    };
+    let desc = compile_and_load_opts(&opts);
+
+    let report = std::fs::read_to_string(&report_file)?;
+    assert!(report.contains("\"elided_params\": [\"m\", \"r\"]"), "{report}");
+    let (before, after) = report_diff(&report, "instance_size");
+    assert!(after < before, "{report}");
+    let (before, after) = report_diff(&report, "model_size");
+    assert!(after < before, "{report}");
+
+    let m = desc.param_id("m").unwrap();
+    let r = desc.param_id("r").unwrap();
+    assert!(m < desc.num_instance_params && r >= desc.num_instance_params);
+
+    let model = desc.new_model();
+    assert_eq!(model.read_param(m), Some(ParamValue::Real(2.0)));
+    assert_eq!(model.read_param(r), Some(ParamValue::Real(4.0)));
+    model.write_param(m, ParamValue::Real(2.0));
+    model.write_param(r, ParamValue::Real(4.0));
+    model.process_params(HANDLE, &mut SimParams::default())?;
+    let mut instance = model.new_instance();
+    instance.write_param(&model, m, ParamValue::Real(2.0));
+    let mut sim = instance.mock_simulation(&model, 300.0)?;
+    sim.set_voltage("a", 1.0);
+    instance.eval(&model, &mut sim, EvalFlags::empty());
+    instance.load_dae(&model, &mut sim);
+    assert_approx_eq!(sim.read_residual("a").0, 2.0 / 4.0);
+
+    // a different value is reported by the setup of the model or instance it was set for
+    let invalid = desc.new_model();
+    invalid.write_param(r, ParamValue::Real(8.0));
+    let valid = desc.new_model();
+    valid.process_params(HANDLE, &mut SimParams::default())?;
+    assert!(invalid.process_params(HANDLE, &mut SimParams::default()).is_err());
+    let instance = model.new_instance();
+    instance.write_param(&model, m, ParamValue::Real(3.0));
+    assert!(instance.process_params(&model, HANDLE, 300.0, &mut SimParams::default()).is_err());
+    Ok(())
+}
+
+/// Reads the sizes before and after elision of `key` from an elision report
+fn report_diff(report: &str, key: &str) -> (u64, u64) {
+    let start = format!("\"{key}\": {{ \"before\": ");
+    let diff = &report[report.find(&start).unwrap() + start.len()..];
+    let (before, diff) = diff.split_once(", \"after\": ").unwrap();
+    let (after, _) = diff.split_once(' ').unwrap();
+    (before.parse().unwrap(), after.parse().unwrap())
+}
+
+const RESISTOR_AND_CONDUCTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module elided_resistor(inout electrical a, inout electrical c);
+    parameter real r = 1.0 from (0:inf);
+    analog I(a, c) <+ V(a, c) / r;
+endmodule
+
+module conductor(inout electrical a, inout electrical c);
+    parameter real g = 1.0;
+    analog I(a, c) <+ V(a, c) * g;
+endmodule
+"#;
+
+/// Every file in the elision directory produces a specialized descriptor,
+/// modules that are not affected by any file are only compiled once.
+/// Multiple elision files are merged instead.
+fn test_elision_dir() -> Result {
+    let Some(dir) = write_test_module(
+        "openvaf_elision_dir",
+        &[
+            ("resistor_and_conductor.va", RESISTOR_AND_CONDUCTOR),
+            ("bins/res_bin_1.txt", "# model = res\n# bin = 1\nr = 4\n"),
+            ("bins/res_bin_2.txt", "# model = res\n# bin = 2\nr = 8\n"),
+            ("bins/summary.csv", "bin,output_file\n"),
+            ("conductance.txt", "g = 2\n"),
+        ],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("resistor_and_conductor.va");
+    let elision_dir = dir.join("bins");
+
+    let opts = openvaf::Opts { elision_dir: Some(elision_dir), ..default_opts(&root_file) };
+    let descriptors = compile_and_load_lib(&opts);
+    let names: Vec<_> = descriptors
+        .iter()
+        .map(|desc| unsafe { CStr::from_ptr(desc.name) }.to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["conductor", "elided_resistor_res_1", "elided_resistor_res_2"]);
+
+    for (desc, r) in descriptors[1..].iter().zip([4.0, 8.0]) {
+        assert_conductance(desc, &[], 1.0 / r)?;
+    }
+
+    let opts = openvaf::Opts {
+        output: CompilationDestination::Path { lib_file: dir.join("merged.osdi") },
+        elision_files: vec![dir.join("bins/res_bin_1.txt"), dir.join("conductance.txt")],
+        ..default_opts(&root_file)
+    };
+    let descriptors = compile_and_load_lib(&opts);
+    assert_eq!(descriptors.len(), 2);
+    for desc in descriptors {
+        let g = match unsafe { CStr::from_ptr(desc.name) }.to_str().unwrap() {
+            "elided_resistor" => 0.25,
+            "conductor" => 2.0,
+            name => panic!("unexpected descriptor {name}"),
+        };
+        assert_conductance(desc, &[], g)?;
+    }
+    Ok(())
+}
+
+const SHUNTED_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module shunted_resistor(inout electrical a, inout electrical c);
+    parameter real r = 1.0 from (0:inf);
+    parameter real g = 1.0;
+    analog I(a, c) <+ V(a, c) / r + V(a, c) * g;
+endmodule
+"#;
+
+/// The parameters all sets agree on are elided in the generic descriptor,
+/// the descriptor of each set additionally elides its remaining parameters.
+/// A single set has no meaningful intersection and is rejected.
+fn test_shared_elision() -> Result {
+    let Some(dir) = write_test_module(
+        "openvaf_shared_elision",
+        &[
+            ("shunted_resistor.va", SHUNTED_RESISTOR),
+            ("bins/res_bin_1.txt", "# model = res\n# bin = 1\nr = 4\ng = 0.5\n"),
+            ("bins/res_bin_2.txt", "# model = res\n# bin = 2\ng = 5e-1\nr = 8\n"),
+        ],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("shunted_resistor.va");
+    let elision_dir = dir.join("bins");
+    let report_file = dir.join("report.json");
+
+    let opts = openvaf::Opts {
+        elision_dir: Some(elision_dir),
+        shared_elision: true,
+        elision_report: Some(report_file.clone()),
+        ..default_opts(&root_file)
+    };
+    let descriptors = compile_and_load_lib(&opts);
+    let names: Vec<_> = descriptors
+        .iter()
+        .map(|desc| unsafe { CStr::from_ptr(desc.name) }.to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["shunted_resistor", "shunted_resistor_res_1", "shunted_resistor_res_2"]);
+
+    let generic = &descriptors[0];
+    let g = generic.param_id("g").unwrap();
+    let model = generic.new_model();
+    model.write_param(g, ParamValue::Real(1.0));
+    assert!(
+        model.process_params(HANDLE, &mut SimParams::default()).is_err(),
+        "g is elided in the shared descriptor"
+    );
+
+    // r is only elided by the descriptors of the sets
+    for (i, (desc, r)) in descriptors.iter().zip([2.0, 4.0, 8.0]).enumerate() {
+        let params: &[_] = if i == 0 { &[("r", r)] } else { &[] };
+        assert_conductance(desc, params, 1.0 / r + 0.5)?;
+    }
+
+    let report = std::fs::read_to_string(&report_file)?;
+    assert!(report.contains("\"shared_params\": [\"g\"]"), "{report}");
+    assert!(report.contains("\"varying_params\": [\"r\"]"), "{report}");
+
+    let opts = openvaf::Opts {
+        elision_files: vec![dir.join("bins/res_bin_1.txt")],
+        shared_elision: true,
+        ..default_opts(&root_file)
+    };
+    assert!(matches!(openvaf::compile(&opts)?, CompilationTermination::FatalDiagnostic));
+    Ok(())
+}
+
+const GEOMETRY_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module geometry_resistor(inout electrical a, inout electrical c);
+    parameter real r = 1.0 from (0:inf);
+    (*type="instance"*) parameter real l = 1e-6 from (0:inf);
+    (*type="instance"*) parameter real w = 1e-6 from (0:inf);
+    analog I(a, c) <+ V(a, c) / r;
+endmodule
+"#;
+
+/// A binned device selects the elision set from the geometry of the instance
+/// and rejects geometries outside of all bins. The bin bounds are not
+/// resolved as parameters.
+fn test_binned() -> Result {
+    let Some(dir) = write_test_module(
+        "openvaf_binned",
+        &[
+            ("geometry_resistor.va", GEOMETRY_RESISTOR),
+            (
+                "bins/res_bin_1.txt",
+                "# model = res\n# bin = 1\nr = 4\nlmin = 0\nlmax = 2e-6\nwmin = 0\nwmax = 1\n",
+            ),
+            (
+                "bins/res_bin_2.txt",
+                "# model = res\n# bin = 2\nr = 8\nlmin = 2e-6\nlmax = 1e-5\nwmin = 0\nwmax = 1\n",
+            ),
+        ],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("geometry_resistor.va");
+    let elision_dir = dir.join("bins");
+
+    let opts = openvaf::Opts {
+        elision_dir: Some(elision_dir),
+        binned: true,
+        lints: vec![("elision_mismatch".to_owned(), LintLevel::Deny)],
+        ..default_opts(&root_file)
+    };
+    let desc = compile_and_load_opts(&opts);
+    let name = unsafe { CStr::from_ptr(desc.name) }.to_str().unwrap();
+    assert_eq!(name, "geometry_resistor");
+
+    for (len, r) in [(1e-6, 4.0), (2e-6, 8.0), (5e-6, 8.0)] {
+        assert_conductance(desc, &[("l", len)], 1.0 / r)?;
+    }
+
+    let l = desc.param_id("l").unwrap();
+    let model = desc.new_model();
+    model.write_param(l, ParamValue::Real(2e-5));
+    model.process_params(HANDLE, &mut SimParams::default())?;
+    let instance = model.new_instance();
+    assert!(instance.process_params(&model, HANDLE, 300.0, &mut SimParams::default()).is_err());
+    Ok(())
+}
+
+const CLAMPED_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module clamped_resistor(inout electrical a, inout electrical c);
+    parameter real r = 4.0 from (0:inf);
+    analog I(a, c) <+ V(a, c) / max(r, 2.0);
+endmodule
+"#;
+
+/// Parameters with an assumed range are still read at runtime, values outside
+/// of the range are rejected during setup. Comparisons decided by the range are folded.
+fn test_assumed_range() -> Result {
+    let Some(dir) = write_test_module(
+        "openvaf_assumed_range",
+        &[
+            ("clamped_resistor.va", CLAMPED_RESISTOR),
+            ("assumptions.txt", "r > 2 # max(r, 2) is always r\n"),
+        ],
+    )?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("clamped_resistor.va");
+    let elision_file = dir.join("assumptions.txt");
+    let report_file = dir.join("report.json");
+
+    let opts = openvaf::Opts {
+        elision_files: vec![elision_file],
+        elision_report: Some(report_file.clone()),
+        ..default_opts(&root_file)
+    };
+    let desc = compile_and_load_opts(&opts);
+    let param = desc.param_id("r").expect("parameters with an assumed range are not removed");
+
+    // the baseline of the report is built without the assumption and still contains `max`
+    let report = std::fs::read_to_string(&report_file)?;
+    assert!(report.contains("\"elided_params\": []"), "{report}");
+    let (eval_before, eval_after) = report_diff(&report, "eval");
+    let (setup_before, setup_after) = report_diff(&report, "instance_setup");
+    assert!(eval_after + setup_after < eval_before + setup_before, "{report}");
+
+    for r in [4.0, 8.0] {
+        assert_conductance(desc, &[("r", r)], 1.0 / r)?;
+    }
+
+    // 1 satisfies the declared range (0:inf) but not the assumption, 2 lies on its open bound
+    for r in [1.0, 2.0] {
+        let model = desc.new_model();
+        model.write_param(param, ParamValue::Real(r));
+        assert!(model.process_params(HANDLE, &mut SimParams::default()).is_err());
+    }
+    Ok(())
+}
+
+const THERMAL_RESISTOR: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module thermal_resistor(inout electrical a, inout electrical c);
+    parameter real r = 2.0 from (0:inf);
+    parameter real tc = 0.01;
+    analog begin
+        I(a, c) <+ V(a, c) / (r * (1 + tc * ($temperature - 300)));
+        I(a, c) <+ V(a, c) * $simparam("gmin", 0.0);
+    end
+endmodule
+"#;
+
+/// The temperature and simparams assumed during compilation are constants,
+/// setup fails if the simulator temperature or a simparam differs.
+fn test_sim_assumptions() -> Result {
+    let Some(dir) =
+        write_test_module("openvaf_sim_assumptions", &[("thermal_resistor.va", THERMAL_RESISTOR)])?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("thermal_resistor.va");
+
+    let opts = openvaf::Opts {
+        assume_temperature: Some(350.0),
+        assume_simparams: vec![("gmin".to_owned(), 0.5)],
+        ..default_opts(&root_file)
+    };
+    let desc = compile_and_load_opts(&opts);
+    // the assumed values are recorded in the descriptor so that simulators can check their settings
+    let (temperature, simparams) = desc.sim_assumptions()?;
+    assert_eq!(temperature, Some(350.0));
+    assert_eq!(simparams, [("gmin", 0.5)]);
+
+    let mut device = MockDevice::new(desc, &[("r", 4.0)], 350.0)?;
+    device.eval(EvalFlags::empty(), &[("a", 1.0)]);
+    let conductance = 1.0 / (4.0 * 1.5) + 0.5;
+    assert_approx_eq!(device.sim.read_jacobian("a", "a").0, conductance);
+    assert_approx_eq!(device.sim.read_residual("a").0, conductance);
+
+    let model = &device.model;
+    let instance = model.new_instance();
+    assert!(instance.process_params(model, HANDLE, 300.0, &mut SimParams::default()).is_err());
+    let instance = model.new_instance();
+    let mut sim_params = SimParams::new(&[("gmin".to_owned(), 0.5)]);
+    instance.process_params(model, HANDLE, 350.0, &mut sim_params)?;
+    let instance = model.new_instance();
+    let mut sim_params = SimParams::new(&[("gmin".to_owned(), 1.0)]);
+    assert!(instance.process_params(model, HANDLE, 350.0, &mut sim_params).is_err());
+    Ok(())
+}
+
+const JUNCTION: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module junction(inout electrical a, inout electrical c);
+    electrical x;
+    parameter real rs = 1.0 from (0:inf);
+    parameter real is = 1e-14 from (0:inf);
+    parameter real cj = 1e-12 from [0:inf);
+    (*desc="junction conductance"*) real gd;
+    analog begin
+        I(a, x) <+ V(a, x) / rs;
+        I(a, x) <+ white_noise(4 * `P_K * $temperature / rs, "thermal");
+        I(x, c) <+ is * (limexp(V(x, c) / $vt) - 1) + ddt(cj * V(x, c));
+        gd = is / $vt * limexp(V(x, c) / $vt);
+    end
+endmodule
+"#;
+
+/// An elided library computes the same device as the original module. A
+/// library that elides a different value is rejected.
+fn test_verify_elision() -> Result {
+    let Some(dir) = write_test_module("openvaf_verify_elision", &[("junction.va", JUNCTION)])?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("junction.va");
+    let elide_rs = |rs| {
+        vec![CliParamDefault {
+            name: Name::resolve("rs"),
+            value: CliParamDefaultValue::Float(rs),
+            span: None,
+        }]
+    };
+
+    let opts = openvaf::Opts {
+        param_defaults: elide_rs(2.0),
+        assume_temperature: Some(300.0),
+        ..default_opts(&root_file)
+    };
+    let lib_file = compile_lib(&opts);
+    let reference = compile_lib(&opts.reference_opts());
+    let verify = VerifyOpts { points: 20, temperature: (300.0, 300.0), ..VerifyOpts::default() };
+    let mismatches = unsafe { osdi_verify::verify_libs(&reference, &lib_file, &verify)? };
+    assert!(mismatches.is_empty(), "{mismatches:#?}");
+
+    let other = openvaf::Opts {
+        param_defaults: elide_rs(4.0),
+        output: CompilationDestination::Path { lib_file: dir.join("junction_4.osdi") },
+        ..default_opts(&root_file)
+    };
+    let other = compile_lib(&other);
+    let mismatches = unsafe { osdi_verify::verify_libs(&other, &lib_file, &verify)? };
+    assert!(
+        matches!(
+            mismatches[..],
+            [Mismatch { difference: Difference::Failure { stage: "setup_model", .. }, .. }]
+        ),
+        "{mismatches:#?}"
+    );
+    // the value elided by the candidate is copied to the reference, which elides a different one
+    assert_eq!(mismatches[0].params, [("rs".to_owned(), ParamSetting::Real(2.0))]);
+    Ok(())
+}
+
+/// A library that computes a different device is reported with the quantity
+/// that differs, the bias point and the parameters of the models.
+fn test_verify_perturbation() -> Result {
+    let perturb_resist = JUNCTION.replace("V(a, x) / rs;", "1.001 * V(a, x) / rs;");
+    let perturb_opvar = JUNCTION.replace("gd = is", "gd = 1.001 * is");
+    let Some(dir) = write_test_module(
+        "openvaf_verify_perturbation",
+        &[
+            ("junction.va", JUNCTION),
+            ("junction_resist.va", &perturb_resist),
+            ("junction_opvar.va", &perturb_opvar),
+        ],
+    )?
+    else {
+        return Ok(());
+    };
+    let reference = compile_lib(&default_opts(&dir.join("junction.va")));
+    let verify = VerifyOpts {
+        points: 5,
+        temperature: (300.0, 300.0),
+        params: vec![("is".to_owned(), 1e-12)],
+        ..VerifyOpts::default()
+    };
+    let check_point = |mismatch: &Mismatch| {
+        assert_eq!(mismatch.point.temperature, Some(300.0), "{mismatch}");
+        let nodes: Vec<_> = mismatch.point.voltages.iter().map(|(node, _)| &**node).collect();
+        assert!(nodes.contains(&"a") && nodes.contains(&"c"), "{mismatch}");
+        assert_eq!(mismatch.params, [("is".to_owned(), ParamSetting::Real(1e-12))]);
+    };
+    let quantity = |mismatch: &Mismatch| match &mismatch.difference {
+        Difference::Value { quantity, .. } => quantity.clone(),
+        _ => panic!("unexpected mismatch {mismatch}"),
+    };
+
+    let candidate = compile_lib(&default_opts(&dir.join("junction_resist.va")));
+    let mismatches = unsafe { osdi_verify::verify_libs(&reference, &candidate, &verify)? };
+    mismatches.iter().for_each(check_point);
+    let quantities: Vec<_> = mismatches.iter().map(quantity).collect();
+    assert!(quantities.contains(&"resistive residual of a".to_owned()), "{quantities:?}");
+    assert!(quantities.contains(&"resistive Jacobian entry (a, a)".to_owned()), "{quantities:?}");
+    assert!(!quantities.iter().any(|quantity| quantity.starts_with("opvar")), "{quantities:?}");
+
+    let candidate = compile_lib(&default_opts(&dir.join("junction_opvar.va")));
+    let mismatches = unsafe { osdi_verify::verify_libs(&reference, &candidate, &verify)? };
+    assert!(!mismatches.is_empty());
+    mismatches.iter().for_each(check_point);
+    for mismatch in &mismatches {
+        assert_eq!(quantity(mismatch), "opvar gd");
+    }
+    Ok(())
+}
+
+const ABSDELAY_LINE: &str = r#"
+`include "constants.vams"
+`include "disciplines.vams"
+
+module absdelay_line(inout electrical a, inout electrical c);
+    parameter real td = 0.5 from [0:inf);
+    analog I(c) <+ absdelay(V(a), td);
+endmodule
+"#;
+
+/// `absdelay` is approximated by two implicit equations that pass the input
+/// through unchanged in DC.
+fn test_absdelay() -> Result {
+    let Some(dir) = write_test_module("openvaf_absdelay", &[("absdelay_line.va", ABSDELAY_LINE)])?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("absdelay_line.va");
+
+    let desc = compile_and_load_opts(&default_opts(&root_file));
+    let mut device = MockDevice::new(desc, &[], 300.0)?;
+
+    // z = 1 and w = T z' = 0.5 for an input of 1
+    let voltages = [("a", 1.0), ("implicit_equation_0", 1.0), ("implicit_equation_1", 0.5)];
+    device.eval(EvalFlags::empty(), &voltages);
+    let sim = &device.sim;
+
+    assert_approx_eq!(sim.read_residual("implicit_equation_0").0, -1.0);
+    assert_approx_eq!(sim.read_residual("implicit_equation_0").1, 1.0);
+    assert_approx_eq!(sim.read_residual("implicit_equation_1").0, 6.0);
+    assert_approx_eq!(sim.read_residual("implicit_equation_1").1, 0.5);
+    assert_approx_eq!(sim.read_residual("c").0, 0.5);
+
+    assert_approx_eq!(sim.read_jacobian("implicit_equation_0", "implicit_equation_1").0, -2.0);
+    assert_approx_eq!(sim.read_jacobian("implicit_equation_0", "implicit_equation_0").1, 1.0);
+    assert_approx_eq!(sim.read_jacobian("implicit_equation_1", "implicit_equation_0").0, 24.0);
+    assert_approx_eq!(sim.read_jacobian("implicit_equation_1", "a").0, -24.0);
+    assert_approx_eq!(sim.read_jacobian("implicit_equation_1", "implicit_equation_1").0, 12.0);
+    assert_approx_eq!(sim.read_jacobian("implicit_equation_1", "implicit_equation_1").1, 1.0);
+    assert_approx_eq!(sim.read_jacobian("c", "implicit_equation_1").0, -1.0);
+    assert_approx_eq!(sim.read_jacobian("c", "a").0, 1.0);
+    Ok(())
+}
+
+const ANALOG_FILTERS: &str = r#"
+`include "disciplines.vams"
+module analog_filters(a, out1, out2);
+    inout a, out1, out2;
+    electrical a, out1, out2;
+    analog begin
+        I(out1) <+ transition(V(a) > 0.5 ? 1.0 : 0.0, 1n, 2n, 4n);
+        I(out2) <+ slew(V(a), 1G, -1G);
+    end
+endmodule
+"#;
+
+/// `transition` and `slew` pass their input through during the operating point
+/// and remember their history in the state vector during transient analysis.
+fn test_analog_filters() -> Result {
+    let Some(dir) =
+        write_test_module("openvaf_analog_filters", &[("analog_filters.va", ANALOG_FILTERS)])?
+    else {
+        return Ok(());
+    };
+    let root_file = dir.join("analog_filters.va");
+
+    let desc = compile_and_load_opts(&default_opts(&root_file));
+    let mut device = MockDevice::new(desc, &[], 300.0)?;
+
+    const OUTPUTS: [&str; 2] = ["out1", "out2"];
+    let op = EvalFlags::ANALYSIS_DC | EvalFlags::ANALYSIS_STATIC;
+    let tran = EvalFlags::ANALYSIS_TRAN;
+
+    // the operating point passes the input through
+    let ([transition, slew], _, bound) = device.step(op, 0.0, &[("a", 0.0)], OUTPUTS);
+    assert_approx_eq!(transition, 0.0);
+    assert_approx_eq!(slew, 0.0);
+    assert_eq!(bound, f64::INFINITY);
+
+    // the input steps at 1ns, the ramp starts after the delay of 1ns. The next
+    // time point should hit the start of the ramp
+    let ([transition, slew], _, bound) = device.step(tran, 1e-9, &[("a", 2.0)], OUTPUTS);
+    assert_approx_eq!(transition, 0.0);
+    assert_approx_eq!(slew, 1.0);
+    assert_approx_eq!(bound, 1e-9);
+
+    // halfway through the rise time, the next corner is the end of the ramp at 4ns
+    let ([transition, slew], _, bound) = device.step(tran, 3e-9, &[("a", 2.0)], OUTPUTS);
+    assert_approx_eq!(transition, 0.5);
+    assert_approx_eq!(slew, 2.0);
+    assert_approx_eq!(bound, 1e-9);
+
+    // the ramp is finished, nothing bounds the step
+    let ([transition, slew], _, bound) = device.step(tran, 5e-9, &[("a", 2.0)], OUTPUTS);
+    assert_approx_eq!(transition, 1.0);
+    assert_approx_eq!(slew, 2.0);
+    assert_eq!(bound, f64::INFINITY);
+
+    // the input falls back, the fall time is 4ns
+    let ([transition, slew], _, bound) = device.step(tran, 6e-9, &[("a", 0.0)], OUTPUTS);
+    assert_approx_eq!(transition, 1.0);
+    assert_approx_eq!(slew, 1.0);
+    assert_approx_eq!(bound, 1e-9);
+
+    // the time point at 6ns is rejected and retried at 5.5ns. The input already
+    // changed during the rejected evaluation, so the change moves to 5.5ns and
+    // the ramp starts at 6.5ns
+    let ([transition, slew], _, bound) = device.step(tran, 5.5e-9, &[("a", 0.0)], OUTPUTS);
+    assert_approx_eq!(transition, 1.0);
+    assert_approx_eq!(slew, 1.5);
+    assert_approx_eq!(bound, 1e-9);
+
+    let ([transition, slew], _, bound) = device.step(tran, 9e-9, &[("a", 0.0)], OUTPUTS);
+    assert_approx_eq!(transition, 0.375);
+    assert_approx_eq!(slew, 0.0);
+    assert_approx_eq!(bound, 1.5e-9);
+    Ok(())
+}
+
+const EVENTS: &str = r#"
+`include "disciplines.vams"
+module events(a, out1, out2, out3);
+    inout a, out1, out2, out3;
+    electrical a, out1, out2, out3;
+    real crossed, ticked, initial;
+    analog begin
+        crossed = 0;
+        ticked = 0;
+        initial = 0;
+        @(cross(V(a) - 0.5, 1)) crossed = 1;
+        @(timer(2n)) ticked = 1;
+        @(initial_step) initial = 1;
+        I(out1) <+ crossed;
+        I(out2) <+ ticked;
+        I(out3) <+ initial;
+    end
+endmodule
+"#;
+
+/// Event control statements only execute their body when the event occurs.
+fn test_events() -> Result {
+    let Some(dir) = write_test_module("openvaf_events", &[("events.va", EVENTS)])? else {
+        return Ok(());
+    };
+    let root_file = dir.join("events.va");
+
+    let desc = compile_and_load_opts(&default_opts(&root_file));
+    let mut device = MockDevice::new(desc, &[], 300.0)?;
+
+    const OUTPUTS: [&str; 3] = ["out1", "out2", "out3"];
+    let op = EvalFlags::ANALYSIS_DC | EvalFlags::ANALYSIS_STATIC;
+    let tran = EvalFlags::ANALYSIS_TRAN;
+
+    // only the initial step occurs during the operating point
+    let (res, flags, bound) = device.step(op, 0.0, &[("a", 0.0)], OUTPUTS);
+    assert_eq!(res, [0.0, 0.0, 1.0]);
+    assert_eq!(flags, EvalRetFlags::empty());
+    assert_eq!(bound, f64::INFINITY);
+
+    // the step is bounded so that the next time point hits the timer at 2ns
+    let (res, flags, bound) = device.step(tran, 1e-9, &[("a", 0.0)], OUTPUTS);
+    assert_eq!(res, [0.0, 0.0, 0.0]);
+    assert_eq!(flags, EvalRetFlags::empty());
+    assert_approx_eq!(bound, 1e-9);
+
+    // the input rises past the threshold and the timer expires. The crossing at
+    // 1.5ns lies in the past, the time point is rejected and the retry should
+    // end 0.5ns after the last accepted time point (1ns)
+    let (res, flags, bound) = device.step(tran, 2e-9, &[("a", 1.0)], OUTPUTS);
+    assert_eq!(res, [1.0, 1.0, 0.0]);
+    assert_eq!(flags, EvalRetFlags::EVAL_RET_FLAG_REJECT);
+    assert_approx_eq!(bound, 0.5e-9);
+
+    let (res, flags, bound) = device.step(tran, 3e-9, &[("a", 1.0)], OUTPUTS);
+    assert_eq!(res, [0.0, 0.0, 0.0]);
+    assert_eq!(flags, EvalRetFlags::empty());
+    assert_eq!(bound, f64::INFINITY);
+
+    // falling crossings are ignored
+    let (res, flags, _) = device.step(tran, 4e-9, &[("a", 0.0)], OUTPUTS);
+    assert_eq!(res, [0.0, 0.0, 0.0]);
+    assert_eq!(flags, EvalRetFlags::empty());
+
+    // a value that reaches exactly zero crosses on arrival and not again when it leaves zero
+    let (res, flags, _) = device.step(tran, 5e-9, &[("a", 0.5)], OUTPUTS);
+    assert_eq!(res, [1.0, 0.0, 0.0]);
+    assert_eq!(flags, EvalRetFlags::empty());
+    let (res, _, _) = device.step(tran, 6e-9, &[("a", 1.0)], OUTPUTS);
+    assert_eq!(res, [0.0, 0.0, 0.0]);
+
+    // a value that leaves zero after falling onto it crosses. The crossing lies
+    // at the accepted time point, so it can not be hit by a retry
+    let (res, _, _) = device.step(tran, 7e-9, &[("a", 0.5)], OUTPUTS);
+    assert_eq!(res, [0.0, 0.0, 0.0]);
+    let (res, flags, bound) = device.step(tran, 8e-9, &[("a", 1.0)], OUTPUTS);
+    assert_eq!(res, [1.0, 0.0, 0.0]);
+    assert_eq!(flags, EvalRetFlags::empty());
+    assert_eq!(bound, f64::INFINITY);
+    Ok(())
+}
+
 harness! {
     // TODO: run this in CI, somehow this test is flakey tough regarding the linker invocation (and really slow)
     Test::from_dir("integration", &integration_test, &ignore_dev_tests, &project_root().join("integration_tests")),
-    [Test::new("$limit", &test_limit),Test::new("noise", &test_noise)]
+    [Test::new("$limit", &test_limit),Test::new("noise", &test_noise)],
+    Test::from_list("elision_root_file", &test_elision_root_file, &|_| false, &[0, 1, 3]),
+    Test::new("elision_param_given", &test_elision_param_given),
+    Test::from_list("elided_param_mode", &test_elided_param_mode, &|_| false, &[ElidedParamMode::Lock, ElidedParamMode::Drop]),
+    Test::from_list("elision_spice", &test_elision_spice, &|_| false, &[1, 2]),
+    Test::new("elision_report", &test_elision_report),
+    Test::new("locked_param_layout", &test_locked_param_layout),
+    Test::new("elision_dir", &test_elision_dir),
+    Test::new("shared_elision", &test_shared_elision),
+    Test::new("binned", &test_binned),
+    Test::new("assumed_range", &test_assumed_range),
+    Test::new("sim_assumptions", &test_sim_assumptions),
+    Test::new("verify_elision", &test_verify_elision),
+    Test::new("verify_perturbation", &test_verify_perturbation),
+    Test::new("absdelay", &test_absdelay),
+    Test::new("analog_filters", &test_analog_filters),
+    Test::new("events", &test_events)
 }
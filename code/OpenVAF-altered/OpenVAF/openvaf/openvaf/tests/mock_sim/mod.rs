@@ -1,17 +1,19 @@
 use std::cell::UnsafeCell;
+use std::ffi::CStr;
 use std::mem::swap;
 use std::ptr;
 
 use anyhow::Result;
 use indexmap::IndexSet;
 use libc::c_void;
+use osdi_verify::load::{
+    osdi_str, EvalFlags, EvalRetFlags, OsdiDescriptor, OsdiInstance, OsdiModel, OsdiSimInfo,
+    OsdiSimParas, ParamValue, SimParams,
+};
 use stdx::iter::zip;
 
 pub const ALPHA: f64 = 0.172;
-
-use crate::load::{
-    osdi_str, EvalFlags, EvalRetFlags, OsdiInstance, OsdiModel, OsdiSimInfo, OsdiSimParas,
-};
+pub const HANDLE: &CStr = c"foo";
 
 #[derive(Debug, Default)]
 pub struct MockSimulation {
@@ -25,6 +27,7 @@ pub struct MockSimulation {
     pub state_1: Vec<f64>,
     pub state_2: Vec<f64>,
     pub noise_dense: Vec<f64>,
+    pub abstime: f64,
 }
 impl MockSimulation {
     fn new() -> MockSimulation {
@@ -47,6 +50,7 @@ impl MockSimulation {
             state_1: Vec::new(),
             state_2: Vec::new(),
             noise_dense: Vec::new(),
+            abstime: 0.0,
         }
     }
 
@@ -119,14 +123,76 @@ impl MockSimulation {
     }
 }
 
-impl OsdiInstance {
-    pub(super) fn mock_simulation(
-        &mut self,
-        model: &OsdiModel,
-        connected_terminals: u32,
+/// A model with a single instance that is evaluated inside a [`MockSimulation`].
+pub struct MockDevice {
+    pub model: OsdiModel,
+    pub instance: OsdiInstance,
+    pub sim: MockSimulation,
+}
+
+impl MockDevice {
+    /// Sets up a model of `desc` with the real parameters `params` and an instance
+    /// of it at the temperature `temp`.
+    pub fn new(
+        desc: &'static OsdiDescriptor,
+        params: &[(&str, f64)],
         temp: f64,
-    ) -> Result<MockSimulation> {
-        let mut internal_nodes = self.process_params(model, connected_terminals, temp)?;
+    ) -> Result<MockDevice> {
+        let model = desc.new_model();
+        for &(name, val) in params {
+            let param = desc.param_id(name).unwrap_or_else(|| panic!("unknown parameter {name}"));
+            model.write_param(param, ParamValue::Real(val));
+        }
+        model.process_params(HANDLE, &mut SimParams::default())?;
+        let mut instance = model.new_instance();
+        let sim = instance.mock_simulation(&model, temp)?;
+        Ok(MockDevice { model, instance, sim })
+    }
+
+    /// Sets the node `voltages` and evaluates and loads the DAE system.
+    pub fn eval(&mut self, flags: EvalFlags, voltages: &[(&str, f64)]) -> EvalRetFlags {
+        for &(node, voltage) in voltages {
+            self.sim.set_voltage(node, voltage);
+        }
+        let ret_flags = self.instance.eval(&self.model, &mut self.sim, flags);
+        self.instance.load_dae(&self.model, &mut self.sim);
+        ret_flags
+    }
+
+    /// Evaluates the time point `abstime` and returns the resistive residuals of
+    /// the `outputs`, the return flags and the step bound. Afterwards the time
+    /// point is accepted and the states are advanced to the next iteration.
+    pub fn step<const N: usize>(
+        &mut self,
+        flags: EvalFlags,
+        abstime: f64,
+        voltages: &[(&str, f64)],
+        outputs: [&str; N],
+    ) -> ([f64; N], EvalRetFlags, f64) {
+        self.sim.abstime = abstime;
+        let ret_flags = self.eval(flags, voltages);
+        let res = outputs.map(|node| self.sim.read_residual(node).0);
+        self.sim.next_iter();
+        (res, ret_flags, self.instance.bound_step())
+    }
+}
+
+/// Evaluates an instance inside a [`MockSimulation`] with all terminals connected.
+pub trait MockInstance {
+    fn mock_simulation(&mut self, model: &OsdiModel, temp: f64) -> Result<MockSimulation>;
+    fn load_spice(&self, model: &OsdiModel, sim: &mut MockSimulation);
+    fn load_noise(&self, model: &OsdiModel, sim: &mut MockSimulation, freq: f64);
+    fn load_dae(&self, model: &OsdiModel, sim: &mut MockSimulation);
+    fn eval(&self, model: &OsdiModel, sim: &mut MockSimulation, flags: EvalFlags) -> EvalRetFlags;
+    /// The step bound of the last evaluation (infinite if the module never bounds the step).
+    fn bound_step(&self) -> f64;
+}
+
+impl MockInstance for OsdiInstance {
+    fn mock_simulation(&mut self, model: &OsdiModel, temp: f64) -> Result<MockSimulation> {
+        let connected_terminals = self.descriptor.num_terminals;
+        let mut internal_nodes =
+            self.process_params(model, HANDLE, temp, &mut SimParams::default())?;
         let mut sim = MockSimulation::new();
         // create internal nodes
         let terminals: Vec<_> = self.descriptor.nodes()[..connected_terminals as usize]
@@ -166,22 +232,23 @@ impl OsdiInstance {
             let row = node_mapping[entry.nodes.node_2 as usize].get();
             let i = sim.get_jacobian_entry(row, column);
             ptr_resist.set(sim.jacobian_resist[i].get());
-            if entry.react_ptr_off != u32::MAX {
-                let data = self.data as *mut u8;
-                unsafe {
-                    let react_ptr_ptr: *mut *mut f64 =
-                        data.add(entry.react_ptr_off as usize).cast();
-                    *react_ptr_ptr = sim.jacobian_react[i].get();
-                }
-            }
+            // SAFETY: the jacobian of the simulation is leaked
+            unsafe { self.set_matrix_ptr_react(entry, sim.jacobian_react[i].get()) };
         }
         sim.state_1.resize(self.descriptor.num_states as usize, 0.0);
         sim.state_2.resize(self.descriptor.num_states as usize, 0.0);
+        for state in 0..self.descriptor.num_states {
+            unsafe {
+                let data = self.data as *mut u8;
+                let state_idx: *mut u32 = data.add(self.descriptor.state_idx_off as usize).cast();
+                *state_idx.add(state as usize) = state;
+            }
+        }
         sim.noise_dense.resize(self.descriptor.num_noise_src as usize, 0.0);
         Ok(sim)
     }
 
-    pub fn load_spice(&self, model: &OsdiModel, sim: &mut MockSimulation) {
+    fn load_spice(&self, model: &OsdiModel, sim: &mut MockSimulation) {
         self.descriptor.load_spice_rhs_tran(
             self.data,
             model.data,
@@ -192,11 +259,11 @@ impl OsdiInstance {
         self.descriptor.load_jacobian_tran(self.data, self.data, ALPHA);
     }
 
-    pub fn load_noise(&self, model: &OsdiModel, sim: &mut MockSimulation, freq: f64) {
+    fn load_noise(&self, model: &OsdiModel, sim: &mut MockSimulation, freq: f64) {
         self.descriptor.load_noise(self.data, model.data, freq, sim.noise_dense.as_mut_ptr())
     }
 
-    pub fn load_dae(&self, model: &OsdiModel, sim: &mut MockSimulation) {
+    fn load_dae(&self, model: &OsdiModel, sim: &mut MockSimulation) {
         self.descriptor.load_residual_resist(
             self.data,
             model.data,
@@ -218,7 +285,8 @@ impl OsdiInstance {
         self.descriptor.load_jacobian_resist(self.data, model.data);
         self.descriptor.load_jacobian_react(self.data, model.data, 1.0);
     }
-    pub fn eval(
+
+    fn eval(
         &self,
         model: &OsdiModel,
         sim: &mut MockSimulation,
@@ -240,18 +308,26 @@ impl OsdiInstance {
         };
         let mut sim_info = OsdiSimInfo {
             paras: sim_params,
-            abstime: 0.0,
+            abstime: sim.abstime,
             prev_solve: sim.solve.as_ptr() as *mut f64,
             prev_state: sim.state_1.as_mut_ptr(),
             next_state: sim.state_2.as_mut_ptr(),
             flags: flags.bits(),
         };
         let flags = self.descriptor.eval(
-            b"foo\0".as_ptr() as *mut c_void,
+            HANDLE.as_ptr() as *mut c_void,
             self.data,
             model.data,
             &mut sim_info,
         );
         EvalRetFlags::from_bits(flags).unwrap()
     }
+
+    fn bound_step(&self) -> f64 {
+        let offset = self.descriptor.bound_step_offset;
+        if offset == u32::MAX {
+            return f64::INFINITY;
+        }
+        unsafe { self.data.cast::<u8>().add(offset as usize).cast::<f64>().read() }
+    }
 }
@@ -50,6 +50,7 @@
 #define EVAL_RET_FLAG_FATAL 2
 #define EVAL_RET_FLAG_FINISH 4
 #define EVAL_RET_FLAG_STOP 8
+#define EVAL_RET_FLAG_REJECT 16
 
 
 #define LOG_LVL_MASK 7
@@ -63,6 +64,8 @@
 
 #define INIT_ERR_OUT_OF_BOUNDS 1
 
+#define OSDI_SIM_ASSUMPTIONS_VERSION 1
+
 
 
 typedef struct OsdiLimFunction {
@@ -138,6 +141,15 @@ typedef struct OsdiNoiseSource {
   OsdiNodePair nodes;
 }OsdiNoiseSource;
 
+typedef struct OsdiSimAssumptions {
+  uint32_t version;
+  bool has_temperature;
+  double temperature;
+  uint32_t num_simparams;
+  char **simparam_names;
+  double *simparam_values;
+}OsdiSimAssumptions;
+
 typedef struct OsdiDescriptor {
   char *name;
 
@@ -202,6 +214,7 @@ typedef struct OsdiDescriptor {
   OsdiNodePair* inputs;
   void (*load_jacobian_with_offset_resist)(void *inst, void* model, size_t offset);
   void (*load_jacobian_with_offset_react)(void *inst, void* model, size_t offset);
+  OsdiSimAssumptions *sim_assumptions;
 }OsdiDescriptor;
 
 
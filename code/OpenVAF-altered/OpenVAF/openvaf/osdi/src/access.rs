@@ -6,6 +6,7 @@ use llvm::{
 };
 
 use crate::compilation_unit::OsdiCompilationUnit;
+use crate::locked::LockedParams;
 use crate::metadata::osdi_0_4::{ACCESS_FLAG_INSTANCE, ACCESS_FLAG_SET};
 
 impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
@@ -20,7 +21,8 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
 
     pub fn access_function(&self) -> &'ll llvm::Value {
         let llfunc = self.access_function_prototype();
-        let OsdiCompilationUnit { inst_data, model_data, cx, .. } = &self;
+        let OsdiCompilationUnit { inst_data, model_data, cx, module, .. } = &self;
+        let locked = LockedParams::new(cx, module, inst_data, model_data);
 
         unsafe {
             let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
@@ -65,10 +67,15 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             // it returns a pointer into the parameter storage in instance structure
             LLVMPositionBuilderAtEnd(llbuilder, inst_bb);
 
+            // validate the value the simulator wrote to a locked parameter since the last call
+            if let Some(locked) = &locked {
+                locked.check(cx, llfunc, llbuilder);
+            }
+
             // create switch statement, based on param_id, default block is opvar_bb
             // number of cases obtained from inst_data
             let switch_inst =
-                LLVMBuildSwitch(llbuilder, param_id, opvar_bb, inst_data.params.len() as u32);
+                LLVMBuildSwitch(llbuilder, param_id, opvar_bb, inst_data.num_params() as u32);
 
             // build cases, one for each instance parameter
             // assumes osdi ids of instance parameters are 0..inst_data.params.len()
@@ -106,18 +113,30 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 LLVMPositionBuilderAtEnd(llbuilder, ret);
                 LLVMBuildRet(llbuilder, ptr);
             }
+
+            // build cases for the locked instance parameters (without storage)
+            if let Some(locked) = &locked {
+                for &param in locked.instance_params() {
+                    let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+                    LLVMPositionBuilderAtEnd(llbuilder, bb);
+                    LLVMAddCase(switch_inst, cx.const_unsigned_int(param.id), bb);
+                    locked.access(cx, llfunc, param, write_flag_set, inst, llbuilder);
+                }
+            }
             
             //
             // start building model params access block
             LLVMPositionBuilderAtEnd(llbuilder, model_bb);
+
+            if let Some(locked) = &locked {
+                locked.check(cx, llfunc, llbuilder);
+            }
             
             // create switch statement, based on param_id, default block is opvar_bb
-            // number of cases: is the number of cases ok? 
-            // should it be inst_data.params.len()+model_data.params.len()
             let switch_model =
                 LLVMBuildSwitch(
                     llbuilder, param_id, opvar_bb, 
-                    inst_data.params.len() as u32 + model_data.params.len() as u32
+                    inst_data.num_params() as u32 + model_data.num_params() as u32
                 );
 
             // build cases, one for each instance parameter
@@ -150,13 +169,13 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
 
             // build cases, one for each model parameter
-            // assumes osdi ids of model parameters start with inst_data.params.len()
+            // assumes osdi ids of model parameters start with inst_data.num_params()
             for param_idx in 0..model_data.params.len() {
                 // create building block bb
                 let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
                 LLVMPositionBuilderAtEnd(llbuilder, bb);
                 // construct case constant, add case with building block bb
-                let case = cx.const_unsigned_int((inst_data.params.len() + param_idx) as u32);
+                let case = cx.const_unsigned_int((inst_data.num_params() + param_idx) as u32);
                 LLVMAddCase(switch_model, case, bb);
 
                 // build code for getting the pointer to 
@@ -176,6 +195,16 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 LLVMPositionBuilderAtEnd(llbuilder, ret);
                 LLVMBuildRet(llbuilder, ptr);
             }
+
+            // build cases for the locked parameters (without storage)
+            if let Some(locked) = &locked {
+                for &param in &locked.params {
+                    let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+                    LLVMPositionBuilderAtEnd(llbuilder, bb);
+                    LLVMAddCase(switch_model, cx.const_unsigned_int(param.id), bb);
+                    locked.access(cx, llfunc, param, write_flag_set, model, llbuilder);
+                }
+            }
             
             // null pointer constant
             let null_ptr = cx.const_null_ptr();
@@ -188,7 +217,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 LLVMBuildSwitch(llbuilder, param_id, err_exit, inst_data.opvars.len() as u32);
 
             // build cases, one for each opvar
-            // assumes osdi ids of opvars start with model_data.params.len() + inst_data.params.len()
+            // assumes osdi ids of opvars start with model_data.num_params() + inst_data.num_params()
             for opvar_idx in 0..inst_data.opvars.len() {
                 // get inst_data, model_data, and cx
                 let OsdiCompilationUnit { inst_data, model_data, cx, .. } = &self;
@@ -197,7 +226,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 LLVMPositionBuilderAtEnd(llbuilder, bb);
                 // construct case constant, add case with building block bb
                 let case = cx.const_unsigned_int(
-                    (model_data.params.len() + inst_data.params.len() + opvar_idx) as u32,
+                    (model_data.num_params() + inst_data.num_params() + opvar_idx) as u32,
                 );
                 LLVMAddCase(switch_opvar, case, bb);
 
@@ -227,7 +256,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
         let name = &format!("given_flag_instance_{}", &self.module.sym);
         let llfunc = cx.declare_int_c_fn(name, fun_ty);
         
-        let OsdiCompilationUnit { inst_data, cx, .. } = &self;
+        let OsdiCompilationUnit { inst_data, model_data, cx, module, .. } = &self;
         
         unsafe {
             let zero = cx.const_int(0);
@@ -249,7 +278,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             // create switch statement, based on param_id, default block is opvar_bb
             // number of cases obtained from inst_data
             let switch_inst =
-                LLVMBuildSwitch(llbuilder, param_id, not_found, inst_data.params.len() as u32);
+                LLVMBuildSwitch(llbuilder, param_id, not_found, inst_data.num_params() as u32);
 
             // build cases, one for each instance parameter
             // assumes osdi ids of instance parameters are 0..inst_data.params.len()
@@ -268,6 +297,16 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 // Return value
                 LLVMBuildRet(llbuilder, is_given);
             }
+
+            // locked parameters are always given (they were elided to a value)
+            if let Some(locked) = LockedParams::new(cx, module, inst_data, model_data) {
+                let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+                for param in locked.instance_params() {
+                    LLVMAddCase(switch_inst, cx.const_unsigned_int(param.id), bb);
+                }
+                LLVMPositionBuilderAtEnd(llbuilder, bb);
+                LLVMBuildRet(llbuilder, one);
+            }
             
             // build not_found block
             LLVMPositionBuilderAtEnd(llbuilder, not_found);
@@ -280,7 +319,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
     }
 
     pub fn given_flag_model(&self) -> &'ll llvm::Value {
-        let OsdiCompilationUnit { inst_data, model_data, cx, .. } = &self;
+        let OsdiCompilationUnit { inst_data, model_data, cx, module, .. } = &self;
         let args_ = [cx.ty_ptr(), cx.ty_int()];
         let fun_ty = cx.ty_func(&args_, cx.ty_int());
         let name = &format!("given_flag_model_{}", self.module.sym);
@@ -307,7 +346,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             // number of cases obtained from inst_data
             let switch_inst = LLVMBuildSwitch(
                 llbuilder, param_id, not_found, 
-                (model_data.params.len() + inst_data.params.len()) as u32
+                (model_data.num_params() + inst_data.num_params()) as u32
             );
 
             // build cases, one for each instance parameter
@@ -329,13 +368,13 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
 
             // build cases, one for each model parameter
-            // assumes osdi ids of model parameters start with inst_data.params.len()
+            // assumes osdi ids of model parameters start with inst_data.num_params()
             for param_idx in 0..model_data.params.len() {
                 // create building block bb
                 let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
                 LLVMPositionBuilderAtEnd(llbuilder, bb);
                 // construct case constant, add case with building block bb
-                let case = cx.const_unsigned_int((inst_data.params.len() + param_idx) as u32);
+                let case = cx.const_unsigned_int((inst_data.num_params() + param_idx) as u32);
                 LLVMAddCase(switch_inst, case, bb);
                 
                 // Build code for checking the parameter given flag
@@ -345,6 +384,16 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 // Return value
                 LLVMBuildRet(llbuilder, is_given);
             }
+
+            // locked parameters are always given (they were elided to a value)
+            if let Some(locked) = LockedParams::new(cx, module, inst_data, model_data) {
+                let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+                for param in &locked.params {
+                    LLVMAddCase(switch_inst, cx.const_unsigned_int(param.id), bb);
+                }
+                LLVMPositionBuilderAtEnd(llbuilder, bb);
+                LLVMBuildRet(llbuilder, one);
+            }
             
             // build not_found block
             LLVMPositionBuilderAtEnd(llbuilder, not_found);
@@ -0,0 +1,907 @@
+//! Devices that dispatch to one of several specializations (bins) of a module
+//! based on the instance geometry.
+//!
+//! The instance data of a binned device starts with the instance data of the
+//! unspecialized module. This is the only part the simulator accesses (all
+//! offsets in the descriptor point into it). It is followed by the index of the
+//! selected bin and storage for the instance data of that bin. The model data
+//! contains the model data of the unspecialized module followed by a copy for
+//! every bin. All copies have the same layout because elided parameters are
+//! kept in the descriptor of every bin and the bins keep their storage (locked
+//! parameters are only dropped from the data of modules that are not binned),
+//! this is asserted when the device is generated.
+//!
+//! `setup_instance` selects the bin with `lmin <= l < lmax` and `wmin <= w < wmax`
+//! and calls the setup function of that bin. `eval` copies the node mapping,
+//! jacobian pointers and state indices (which the simulator only writes to the
+//! unspecialized instance data) into the bin, evaluates the bin and copies the
+//! residuals back. All other functions dispatch to the selected bin.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::iter::once;
+use std::ptr;
+
+use hir::{CompilationDB, Parameter, Type};
+use llvm::IntPredicate::{IntNE, IntUGE};
+use llvm::RealPredicate::{RealOGE, RealOLT};
+use llvm::{
+    struct_element_types, LLVMABISizeOfType, LLVMAddCase, LLVMAppendBasicBlockInContext,
+    LLVMBuildAlloca, LLVMBuildAnd, LLVMBuildBr, LLVMBuildCall2, LLVMBuildCondBr, LLVMBuildFCmp,
+    LLVMBuildGEP2, LLVMBuildICmp, LLVMBuildLoad2, LLVMBuildOr, LLVMBuildRet, LLVMBuildRetVoid,
+    LLVMBuildSelect, LLVMBuildStore, LLVMBuildStructGEP2, LLVMBuildSwitch,
+    LLVMCreateBuilderInContext, LLVMDisposeBuilder, LLVMGetParam, LLVMPositionBuilderAtEnd,
+    TargetData, UNNAMED,
+};
+use mir::{strip_optbarrier, F_ZERO};
+use mir_llvm::CodegenCx;
+use sim_back::dae::{MatrixEntryId, SimUnknown};
+use sim_back::node_collapse::CollapsePair;
+use sim_back::CompiledModule;
+use typed_index_collections::TiVec;
+
+use crate::compilation_unit::OsdiCompilationUnit;
+use crate::inst_data::{
+    live_builtin_params, OsdiInstanceData, OsdiInstanceParam, COLLAPSED, JACOBIAN_PTR_REACT,
+    JACOBIAN_PTR_RESIST, NODE_MAPPING, PARAM_GIVEN, STATE_IDX,
+};
+use crate::metadata::osdi_0_4::{OsdiDescriptor, EVAL_RET_FLAG_FATAL};
+use crate::ModuleVariant;
+
+/// Fields of the binned instance data (after the unspecialized instance data)
+const BIN_IDX: u32 = 1;
+const BIN_DATA: u32 = 2;
+
+/// A device that selects one of several specializations of a module based on
+/// the `l` and `w` parameters of an instance.
+#[derive(Debug, Clone)]
+pub struct BinnedDevice {
+    /// name of the OSDI descriptor
+    pub name: String,
+    /// index of the unspecialized module in the compiled variants
+    pub generic: usize,
+    pub bins: Vec<Bin>,
+}
+
+impl BinnedDevice {
+    /// The indices of the unspecialized module and of all bins
+    pub fn variants(&self) -> impl Iterator<Item = usize> + '_ {
+        once(self.generic).chain(self.bins.iter().map(|bin| bin.variant))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bin {
+    /// index of the specialized module in the compiled variants
+    pub variant: usize,
+    /// `(lmin, lmax)`
+    pub l: (f64, f64),
+    /// `(wmin, wmax)`
+    pub w: (f64, f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinnedDeviceError {
+    MissingGeometry { device: String, param: &'static str },
+    IncompatibleBin { device: String, bin: String, reason: &'static str },
+}
+
+impl Display for BinnedDeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinnedDeviceError::MissingGeometry { device, param } => {
+                write!(f, "binned device '{device}' requires a real parameter '{param}'")
+            }
+            BinnedDeviceError::IncompatibleBin { device, bin, reason } => {
+                write!(f, "bin '{bin}' of binned device '{device}' {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BinnedDeviceError {}
+
+impl BinnedDevice {
+    /// Checks that the module has geometry parameters and that the
+    /// unspecialized module can dispatch to every bin.
+    pub fn check(
+        &self,
+        db: &CompilationDB,
+        modules: &[ModuleVariant],
+    ) -> Result<(), BinnedDeviceError> {
+        let generic = &modules[self.generic].module;
+        for param in ["l", "w"] {
+            if geometry_param(db, generic, param).is_none() {
+                return Err(BinnedDeviceError::MissingGeometry {
+                    device: self.name.clone(),
+                    param,
+                });
+            }
+        }
+
+        for bin in &self.bins {
+            let variant = &modules[bin.variant];
+            if let Err(reason) = BinMap::new(generic, &variant.module) {
+                return Err(BinnedDeviceError::IncompatibleBin {
+                    device: self.name.clone(),
+                    bin: variant.name.clone(),
+                    reason,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generates the functions of the device and returns its descriptor.
+    /// `generic` is the descriptor of the unspecialized module and
+    /// `descriptors` contains the descriptors of all bins.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn descriptor<'a, 'b, 'll>(
+        &'a self,
+        db: &CompilationDB,
+        modules: &[ModuleVariant],
+        units: &'a [OsdiCompilationUnit<'a, 'b, 'll>],
+        generic: OsdiDescriptor<'ll>,
+        descriptors: &'a [Option<OsdiDescriptor<'ll>>],
+        target_data: &TargetData,
+    ) -> OsdiDescriptor<'ll> {
+        let generic_module = &modules[self.generic].module;
+        let generic_unit = &units[self.generic];
+        let cx = generic_unit.cx;
+
+        let bins: Vec<_> = self
+            .bins
+            .iter()
+            .map(|bin| {
+                let unit = &units[bin.variant];
+                assert!(
+                    unsafe { same_data_layout(generic_unit, unit) },
+                    "bin {} has a different data layout than {}",
+                    modules[bin.variant].name,
+                    self.name
+                );
+                BinCodegen {
+                    bin,
+                    unit,
+                    descriptor: descriptors[bin.variant].as_ref().unwrap(),
+                    map: BinMap::new(generic_module, &modules[bin.variant].module)
+                        .unwrap_or_else(|reason| unreachable!("bin {reason}")),
+                }
+            })
+            .collect();
+
+        let bin_size = bins
+            .iter()
+            .map(|bin| unsafe { LLVMABISizeOfType(target_data, bin.unit.inst_data.ty) })
+            .max()
+            .unwrap_or(0);
+        let sym = format!("{}_binned", generic_unit.module.sym);
+        let bin_data = cx.ty_array(cx.ty_aint(64), ((bin_size + 7) / 8) as u32);
+        let inst_ty = cx.ty_struct(
+            &format!("osdi_inst_data_{sym}"),
+            &[generic_unit.inst_data.ty, cx.ty_int(), bin_data],
+        );
+        let model_data = generic_unit.model_data.ty;
+        let model_ty = cx.ty_struct(
+            &format!("osdi_model_data_{sym}"),
+            &[model_data, cx.ty_array(model_data, bins.len() as u32)],
+        );
+
+        let codegen = BinnedCodegen { cx, generic: generic_unit, bins, inst_ty, model_ty, sym };
+        let l = geometry_param(db, generic_module, "l").unwrap();
+        let w = geometry_param(db, generic_module, "w").unwrap();
+
+        let ptr = cx.ty_ptr();
+        let f64 = cx.ty_double();
+        unsafe {
+            OsdiDescriptor {
+                name: self.name.clone(),
+                instance_size: LLVMABISizeOfType(target_data, inst_ty) as u32,
+                model_size: LLVMABISizeOfType(target_data, model_ty) as u32,
+                access: codegen.access(generic.access, generic.num_params),
+                setup_model: codegen.setup_model(generic.setup_model),
+                setup_instance: codegen.setup_instance(l, w),
+                eval: codegen.eval(),
+                load_noise: codegen.load_noise(),
+                load_residual_resist: codegen
+                    .dispatch("load_residual_resist", &[ptr], |it| it.load_residual_resist),
+                load_residual_react: codegen
+                    .dispatch("load_residual_react", &[ptr], |it| it.load_residual_react),
+                load_limit_rhs_resist: codegen
+                    .dispatch("load_lim_rhs_resist", &[ptr], |it| it.load_limit_rhs_resist),
+                load_limit_rhs_react: codegen
+                    .dispatch("load_lim_rhs_react", &[ptr], |it| it.load_limit_rhs_react),
+                load_spice_rhs_dc: codegen
+                    .dispatch("load_spice_rhs_dc", &[ptr, ptr], |it| it.load_spice_rhs_dc),
+                load_spice_rhs_tran: codegen.dispatch(
+                    "load_spice_rhs_tran",
+                    &[ptr, ptr, f64],
+                    |it| it.load_spice_rhs_tran,
+                ),
+                load_jacobian_resist: codegen
+                    .dispatch("load_jacobian_resist", &[], |it| it.load_jacobian_resist),
+                load_jacobian_react: codegen
+                    .dispatch("load_jacobian_react", &[f64], |it| it.load_jacobian_react),
+                load_jacobian_tran: codegen
+                    .dispatch("load_jacobian_tran", &[f64], |it| it.load_jacobian_tran),
+                load_jacobian_with_offset_resist: codegen.dispatch(
+                    "load_jacobian_with_offset_resist",
+                    &[cx.ty_size()],
+                    |it| it.load_jacobian_with_offset_resist,
+                ),
+                load_jacobian_with_offset_react: codegen.dispatch(
+                    "load_jacobian_with_offset_react",
+                    &[cx.ty_size()],
+                    |it| it.load_jacobian_with_offset_react,
+                ),
+                write_jacobian_array_resist: codegen.write_jacobian_array(false),
+                write_jacobian_array_react: codegen.write_jacobian_array(true),
+                ..generic
+            }
+        }
+    }
+}
+
+/// Whether the parameters of a bin are stored like those of the unspecialized
+/// module. The parameters are copied per position into the instance data of
+/// the bin and `setup_model` copies the model data as a whole.
+unsafe fn same_data_layout(generic: &OsdiCompilationUnit, bin: &OsdiCompilationUnit) -> bool {
+    let generic_fields = struct_element_types(generic.model_data.ty);
+    let bin_fields = struct_element_types(bin.model_data.ty);
+    generic.model_data.params.keys().eq(bin.model_data.params.keys())
+        && generic.inst_data.params.keys().eq(bin.inst_data.params.keys())
+        && generic_fields.len() == bin_fields.len()
+        && generic_fields.iter().zip(bin_fields.iter()).all(|(&lhs, &rhs)| ptr::eq(lhs, rhs))
+}
+
+fn geometry_param(db: &CompilationDB, module: &CompiledModule, name: &str) -> Option<Parameter> {
+    module
+        .info
+        .params
+        .iter()
+        .find(|(param, info)| info.name.eq_ignore_ascii_case(name) && param.ty(db) == Type::Real)
+        .map(|(param, _)| *param)
+}
+
+/// How the unknowns, jacobian entries, collapsible node pairs and noise
+/// sources of a bin correspond to those of the unspecialized module
+struct BinMap {
+    unknowns: TiVec<SimUnknown, SimUnknown>,
+    jacobian: TiVec<MatrixEntryId, MatrixEntryId>,
+    collapsible: Vec<(CollapsePair, CollapsePair)>,
+    noise: Vec<usize>,
+}
+
+impl BinMap {
+    fn new(generic: &CompiledModule, bin: &CompiledModule) -> Result<BinMap, &'static str> {
+        let params = |module: &CompiledModule<'_>| {
+            let params: Vec<_> =
+                module.info.params.iter().map(|(param, info)| (*param, info.is_instance)).collect();
+            let builtins: Vec<_> =
+                live_builtin_params(&module.intern, &module.eval, &module.init).collect();
+            let aliases: Vec<_> = module.info.sys_fun_alias.keys().copied().collect();
+            (params, builtins, aliases)
+        };
+        if params(generic) != params(bin) {
+            return Err("has different parameters (binned devices require --elided-params lock)");
+        }
+        if !generic.info.op_vars.keys().eq(bin.info.op_vars.keys()) {
+            return Err("has different operating point variables");
+        }
+        if generic.intern.lim_state.len() != bin.intern.lim_state.len()
+            || generic.intern.operator_states != bin.intern.operator_states
+        {
+            return Err("has a different number of states");
+        }
+
+        let unknowns: TiVec<SimUnknown, SimUnknown> = bin
+            .dae_system
+            .unknowns
+            .iter()
+            .map(|unknown| generic.dae_system.unknowns.index(unknown))
+            .collect::<Option<_>>()
+            .ok_or("has nodes that the unspecialized module does not have")?;
+
+        let entries: HashMap<_, _> = generic
+            .dae_system
+            .jacobian
+            .iter_enumerated()
+            .map(|(id, entry)| ((entry.row, entry.col), id))
+            .collect();
+        // the jacobian pointers of the reactive entries are assigned before
+        // the optimization barriers are removed, the values afterwards
+        let covers = |generic_val, bin_val| {
+            (generic_val != F_ZERO || bin_val == F_ZERO)
+                && (strip_optbarrier(&generic.eval, generic_val) != F_ZERO
+                    || strip_optbarrier(&bin.eval, bin_val) == F_ZERO)
+        };
+        let jacobian = bin
+            .dae_system
+            .jacobian
+            .iter()
+            .map(|entry| {
+                let id = *entries.get(&(unknowns[entry.row], unknowns[entry.col]))?;
+                let generic_entry = &generic.dae_system.jacobian[id];
+                let covered = covers(generic_entry.resist, entry.resist)
+                    && covers(generic_entry.react, entry.react);
+                covered.then_some(id)
+            })
+            .collect::<Option<_>>()
+            .ok_or("has jacobian entries that the unspecialized module does not have")?;
+
+        let pairs: HashMap<_, _> =
+            generic.node_collapse.pairs().map(|(pair, hi, lo)| ((hi, lo), pair)).collect();
+        let collapsible = bin
+            .node_collapse
+            .pairs()
+            .map(|(pair, hi, lo)| {
+                let generic_pair = *pairs.get(&(unknowns[hi], lo.map(|lo| unknowns[lo])))?;
+                Some((pair, generic_pair))
+            })
+            .collect::<Option<_>>()
+            .ok_or("has collapsible nodes that the unspecialized module does not have")?;
+
+        let noise = bin
+            .dae_system
+            .noise_sources
+            .iter()
+            .map(|src| generic.dae_system.noise_sources.iter().position(|it| it.name == src.name))
+            .collect::<Option<_>>()
+            .ok_or("has noise sources that the unspecialized module does not have")?;
+
+        Ok(BinMap { unknowns, jacobian, collapsible, noise })
+    }
+}
+
+struct BinCodegen<'a, 'b, 'll> {
+    bin: &'a Bin,
+    unit: &'a OsdiCompilationUnit<'a, 'b, 'll>,
+    descriptor: &'a OsdiDescriptor<'ll>,
+    map: BinMap,
+}
+
+struct BinnedCodegen<'a, 'b, 'll> {
+    cx: &'a CodegenCx<'b, 'll>,
+    generic: &'a OsdiCompilationUnit<'a, 'b, 'll>,
+    bins: Vec<BinCodegen<'a, 'b, 'll>>,
+    inst_ty: &'ll llvm::Type,
+    model_ty: &'ll llvm::Type,
+    sym: String,
+}
+
+impl<'a, 'b, 'll> BinnedCodegen<'a, 'b, 'll> {
+    fn declare_fn(
+        &self,
+        name: &str,
+        args: &[&'ll llvm::Type],
+        ret: &'ll llvm::Type,
+    ) -> &'ll llvm::Value {
+        let fun_ty = self.cx.ty_func(args, ret);
+        self.cx.declare_int_c_fn(&format!("{name}_{}", self.sym), fun_ty)
+    }
+
+    unsafe fn bin_inst(
+        &self,
+        inst: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) -> &'ll llvm::Value {
+        LLVMBuildStructGEP2(llbuilder, self.inst_ty, inst, BIN_DATA, UNNAMED)
+    }
+
+    unsafe fn bin_model(
+        &self,
+        bin: usize,
+        model: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let indices =
+            [cx.const_unsigned_int(0), cx.const_unsigned_int(1), cx.const_unsigned_int(bin as u32)];
+        LLVMBuildGEP2(llbuilder, self.model_ty, model, indices.as_ptr(), 3, UNNAMED)
+    }
+
+    /// Branches to a new block for every bin based on the bin index stored in
+    /// `inst`. `default` is used if no bin was selected.
+    unsafe fn switch_bin(
+        &self,
+        llfunc: &'ll llvm::Value,
+        inst: &'ll llvm::Value,
+        default: &'ll llvm::BasicBlock,
+        llbuilder: &llvm::Builder<'ll>,
+    ) -> Vec<&'ll llvm::BasicBlock> {
+        let cx = self.cx;
+        let idx = LLVMBuildStructGEP2(llbuilder, self.inst_ty, inst, BIN_IDX, UNNAMED);
+        let idx = LLVMBuildLoad2(llbuilder, cx.ty_int(), idx, UNNAMED);
+        let switch = LLVMBuildSwitch(llbuilder, idx, default, self.bins.len() as u32);
+        (0..self.bins.len())
+            .map(|i| {
+                let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+                LLVMAddCase(switch, cx.const_unsigned_int(i as u32), bb);
+                bb
+            })
+            .collect()
+    }
+
+    /// `access` of the unspecialized module is used for parameters, operating
+    /// point variables are read from the selected bin.
+    unsafe fn access(&self, generic_access: &'ll llvm::Value, num_params: u32) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let (ptr, int) = (cx.ty_ptr(), cx.ty_int());
+        let args = [ptr, ptr, int, int];
+        let fun_ty = cx.ty_func(&args, ptr);
+        let llfunc = self.declare_fn("access", &args, ptr);
+
+        let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let param_bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let opvar_bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let err_exit = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+        LLVMPositionBuilderAtEnd(llbuilder, entry);
+
+        let params: Vec<_> = (0..4).map(|i| LLVMGetParam(llfunc, i)).collect();
+        let is_opvar =
+            LLVMBuildICmp(llbuilder, IntUGE, params[2], cx.const_unsigned_int(num_params), UNNAMED);
+        LLVMBuildCondBr(llbuilder, is_opvar, opvar_bb, param_bb);
+
+        LLVMPositionBuilderAtEnd(llbuilder, param_bb);
+        let res = LLVMBuildCall2(llbuilder, fun_ty, generic_access, params.as_ptr(), 4, UNNAMED);
+        LLVMBuildRet(llbuilder, res);
+
+        LLVMPositionBuilderAtEnd(llbuilder, opvar_bb);
+        let bin_bbs = self.switch_bin(llfunc, params[0], err_exit, llbuilder);
+        for (i, (bin, bb)) in self.bins.iter().zip(bin_bbs).enumerate() {
+            LLVMPositionBuilderAtEnd(llbuilder, bb);
+            let args = [
+                self.bin_inst(params[0], llbuilder),
+                self.bin_model(i, params[1], llbuilder),
+                params[2],
+                params[3],
+            ];
+            let res =
+                LLVMBuildCall2(llbuilder, fun_ty, bin.descriptor.access, args.as_ptr(), 4, UNNAMED);
+            LLVMBuildRet(llbuilder, res);
+        }
+
+        LLVMPositionBuilderAtEnd(llbuilder, err_exit);
+        LLVMBuildRet(llbuilder, cx.const_null_ptr());
+        LLVMDisposeBuilder(llbuilder);
+        llfunc
+    }
+
+    /// Sets up the unspecialized model and copies the result into every bin
+    /// before calling the model setup of the bin.
+    unsafe fn setup_model(&self, generic_setup: &'ll llvm::Value) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let tys = self.generic.tys;
+        let ptr = cx.ty_ptr();
+        let args = [ptr, ptr, ptr, ptr];
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let llfunc = self.declare_fn("setup_model", &args, cx.ty_void());
+
+        let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let exit = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+        LLVMPositionBuilderAtEnd(llbuilder, entry);
+
+        let params: Vec<_> = (0..4).map(|i| LLVMGetParam(llfunc, i)).collect();
+        let (model, res) = (params[1], params[3]);
+        let flags_ptr = LLVMBuildStructGEP2(llbuilder, tys.osdi_init_info, res, 0, UNNAMED);
+        let num_errors = LLVMBuildStructGEP2(llbuilder, tys.osdi_init_info, res, 1, UNNAMED);
+
+        LLVMBuildCall2(llbuilder, fun_ty, generic_setup, params.as_ptr(), 4, UNNAMED);
+        let mut flags = LLVMBuildLoad2(llbuilder, cx.ty_int(), flags_ptr, UNNAMED);
+        for (i, bin) in self.bins.iter().enumerate() {
+            // stop at the first error, otherwise the error list would be overwritten
+            let next = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+            let errors = LLVMBuildLoad2(llbuilder, cx.ty_int(), num_errors, UNNAMED);
+            let has_errors = LLVMBuildICmp(llbuilder, IntNE, errors, cx.const_int(0), UNNAMED);
+            LLVMBuildCondBr(llbuilder, has_errors, exit, next);
+            LLVMPositionBuilderAtEnd(llbuilder, next);
+
+            let bin_model = self.bin_model(i, model, llbuilder);
+            let data = LLVMBuildLoad2(llbuilder, self.generic.model_data.ty, model, UNNAMED);
+            LLVMBuildStore(llbuilder, data, bin_model);
+            let args = [params[0], bin_model, params[2], res];
+            LLVMBuildCall2(
+                llbuilder,
+                fun_ty,
+                bin.descriptor.setup_model,
+                args.as_ptr(),
+                4,
+                UNNAMED,
+            );
+            let bin_flags = LLVMBuildLoad2(llbuilder, cx.ty_int(), flags_ptr, UNNAMED);
+            flags = LLVMBuildOr(llbuilder, flags, bin_flags, UNNAMED);
+        }
+        LLVMBuildStore(llbuilder, flags, flags_ptr);
+        LLVMBuildBr(llbuilder, exit);
+
+        LLVMPositionBuilderAtEnd(llbuilder, exit);
+        LLVMBuildRetVoid(llbuilder);
+        LLVMDisposeBuilder(llbuilder);
+        llfunc
+    }
+
+    /// Reads the value of a geometry parameter (the instance value if it was
+    /// given and the model value otherwise). Returns the value and the OSDI
+    /// id of the parameter.
+    unsafe fn read_geometry(
+        &self,
+        param: Parameter,
+        inst: &'ll llvm::Value,
+        model: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) -> (&'ll llvm::Value, u32) {
+        let OsdiCompilationUnit { inst_data, model_data, cx, .. } = self.generic;
+        if let Some(pos) = inst_data.params.get_index_of(&OsdiInstanceParam::User(param)) {
+            let pos = pos as u32;
+            let is_given = inst_data.is_nth_param_given(cx, pos, inst, llbuilder);
+            let inst_val = inst_data.read_nth_param(pos, inst, llbuilder);
+            let model_val = model_data.read_nth_inst_param(inst_data, pos, model, llbuilder);
+            let val = LLVMBuildSelect(llbuilder, is_given, inst_val, model_val, UNNAMED);
+            (val, pos)
+        } else {
+            let pos = model_data.params.get_index_of(&param).unwrap();
+            let (ptr, ty) = model_data.param_ptr(param, model, llbuilder).unwrap();
+            let val = LLVMBuildLoad2(llbuilder, ty, ptr, UNNAMED);
+            (val, (inst_data.num_params() + pos) as u32)
+        }
+    }
+
+    /// Selects the bin from the instance geometry, copies the instance
+    /// parameters into the bin and sets up the bin. An instance that does not
+    /// fit into any bin is reported as an invalid value of `l`.
+    unsafe fn setup_instance(&self, l: Parameter, w: Parameter) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let OsdiCompilationUnit { inst_data, tys, .. } = self.generic;
+        let ptr = cx.ty_ptr();
+        let args = [ptr, ptr, ptr, cx.ty_double(), cx.ty_int(), ptr, ptr];
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let llfunc = self.declare_fn("setup_instance", &args, cx.ty_void());
+
+        let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+        LLVMPositionBuilderAtEnd(llbuilder, entry);
+
+        let params: Vec<_> = (0..7).map(|i| LLVMGetParam(llfunc, i)).collect();
+        let (inst, model, res) = (params[1], params[2], params[6]);
+        let err_cap = LLVMBuildAlloca(llbuilder, cx.ty_int(), UNNAMED);
+
+        let (l, l_id) = self.read_geometry(l, inst, model, llbuilder);
+        let (w, _) = self.read_geometry(w, inst, model, llbuilder);
+
+        let in_range = |val, (min, max): (f64, f64)| {
+            let lower = LLVMBuildFCmp(llbuilder, RealOGE, val, cx.const_real(min), UNNAMED);
+            let upper = LLVMBuildFCmp(llbuilder, RealOLT, val, cx.const_real(max), UNNAMED);
+            LLVMBuildAnd(llbuilder, lower, upper, UNNAMED)
+        };
+
+        for (i, bin) in self.bins.iter().enumerate() {
+            let selected = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+            let next = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+            let fits =
+                LLVMBuildAnd(llbuilder, in_range(l, bin.bin.l), in_range(w, bin.bin.w), UNNAMED);
+            LLVMBuildCondBr(llbuilder, fits, selected, next);
+
+            LLVMPositionBuilderAtEnd(llbuilder, selected);
+            let bin_data = &bin.unit.inst_data;
+            let bin_inst = self.bin_inst(inst, llbuilder);
+            let bin_model = self.bin_model(i, model, llbuilder);
+            let idx = LLVMBuildStructGEP2(llbuilder, self.inst_ty, inst, BIN_IDX, UNNAMED);
+            LLVMBuildStore(llbuilder, cx.const_unsigned_int(i as u32), idx);
+
+            let src = LLVMBuildStructGEP2(llbuilder, inst_data.ty, inst, PARAM_GIVEN, UNNAMED);
+            let dst = LLVMBuildStructGEP2(llbuilder, bin_data.ty, bin_inst, PARAM_GIVEN, UNNAMED);
+            copy(inst_data.param_given, src, dst, llbuilder);
+            for pos in 0..inst_data.params.len() as u32 {
+                let val = inst_data.read_nth_param(pos, inst, llbuilder);
+                bin_data.store_nth_param(pos, bin_inst, val, llbuilder);
+            }
+
+            let args = [params[0], bin_inst, bin_model, params[3], params[4], params[5], res];
+            LLVMBuildCall2(
+                llbuilder,
+                fun_ty,
+                bin.descriptor.setup_instance,
+                args.as_ptr(),
+                7,
+                UNNAMED,
+            );
+
+            // make the resolved parameters visible to the simulator
+            for pos in 0..inst_data.params.len() as u32 {
+                let val = bin_data.read_nth_param(pos, bin_inst, llbuilder);
+                inst_data.store_nth_param(pos, inst, val, llbuilder);
+            }
+            for &(bin_pair, pair) in &bin.map.collapsible {
+                let src =
+                    static_elem_ptr(bin_data, COLLAPSED, bin_pair.into(), bin_inst, llbuilder, cx);
+                let dst = static_elem_ptr(inst_data, COLLAPSED, pair.into(), inst, llbuilder, cx);
+                copy(cx.ty_c_bool(), src, dst, llbuilder);
+            }
+            LLVMBuildRetVoid(llbuilder);
+
+            LLVMPositionBuilderAtEnd(llbuilder, next);
+        }
+
+        let flags = LLVMBuildStructGEP2(llbuilder, tys.osdi_init_info, res, 0, UNNAMED);
+        let err_len = LLVMBuildStructGEP2(llbuilder, tys.osdi_init_info, res, 1, UNNAMED);
+        let err_ptr = LLVMBuildStructGEP2(llbuilder, tys.osdi_init_info, res, 2, UNNAMED);
+        let zero = cx.const_unsigned_int(0);
+        LLVMBuildStore(llbuilder, cx.const_null_ptr(), err_ptr);
+        LLVMBuildStore(llbuilder, zero, err_len);
+        LLVMBuildStore(llbuilder, zero, err_cap);
+        LLVMBuildStore(llbuilder, zero, flags);
+        let (err_ty, err_fun) = OsdiCompilationUnit::invalid_param_err(cx);
+        let args = [err_ptr, err_len, err_cap, cx.const_unsigned_int(l_id)];
+        LLVMBuildCall2(llbuilder, err_ty, err_fun, args.as_ptr(), 4, UNNAMED);
+        LLVMBuildRetVoid(llbuilder);
+
+        LLVMDisposeBuilder(llbuilder);
+        llfunc
+    }
+
+    unsafe fn eval(&self) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let inst_data = &self.generic.inst_data;
+        let ptr = cx.ty_ptr();
+        let args = [ptr, ptr, ptr, ptr];
+        let fun_ty = cx.ty_func(&args, cx.ty_int());
+        let llfunc = self.declare_fn("eval", &args, cx.ty_int());
+
+        let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let err_exit = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+        LLVMPositionBuilderAtEnd(llbuilder, entry);
+
+        let params: Vec<_> = (0..4).map(|i| LLVMGetParam(llfunc, i)).collect();
+        let (inst, model) = (params[1], params[2]);
+        let bin_bbs = self.switch_bin(llfunc, inst, err_exit, llbuilder);
+        for (i, (bin, bb)) in self.bins.iter().zip(bin_bbs).enumerate() {
+            LLVMPositionBuilderAtEnd(llbuilder, bb);
+            let bin_data = &bin.unit.inst_data;
+            let bin_inst = self.bin_inst(inst, llbuilder);
+            let bin_model = self.bin_model(i, model, llbuilder);
+
+            let sync = |field, ty, bin_idx: u32, idx: u32| {
+                let src = static_elem_ptr(inst_data, field, idx, inst, llbuilder, cx);
+                let dst = static_elem_ptr(bin_data, field, bin_idx, bin_inst, llbuilder, cx);
+                copy(ty, src, dst, llbuilder);
+            };
+            for (unknown, &generic_unknown) in bin.map.unknowns.iter_enumerated() {
+                sync(NODE_MAPPING, cx.ty_int(), unknown.into(), generic_unknown.into());
+            }
+            for (entry, &generic_entry) in bin.map.jacobian.iter_enumerated() {
+                sync(JACOBIAN_PTR_RESIST, ptr, entry.into(), generic_entry.into());
+                if let Some(off) = bin_data.jacobian[entry].react_off.expand() {
+                    let generic_off = inst_data.jacobian[generic_entry]
+                        .react_off
+                        .expect("BinMap only maps reactive entries to reactive entries");
+                    sync(JACOBIAN_PTR_REACT, ptr, off.into(), generic_off.into());
+                }
+            }
+            for state in 0..bin.unit.module.intern.num_states() {
+                sync(STATE_IDX, cx.ty_int(), state, state);
+            }
+
+            let args = [params[0], bin_inst, bin_model, params[3]];
+            let ret_flags =
+                LLVMBuildCall2(llbuilder, fun_ty, bin.descriptor.eval, args.as_ptr(), 4, UNNAMED);
+
+            // the simulator may read the residuals directly from the instance data
+            let zero = cx.const_real(0.0);
+            for (unknown, &generic_unknown) in bin.map.unknowns.iter_enumerated() {
+                for (reactive, lim_rhs) in
+                    [(false, false), (true, false), (false, true), (true, true)]
+                {
+                    let dst =
+                        inst_data.residual_ptr(generic_unknown, inst, llbuilder, reactive, lim_rhs);
+                    if let Some(dst) = dst {
+                        let val = bin_data
+                            .residual_ptr(unknown, bin_inst, llbuilder, reactive, lim_rhs)
+                            .map_or(zero, |src| {
+                                LLVMBuildLoad2(llbuilder, cx.ty_double(), src, UNNAMED)
+                            });
+                        LLVMBuildStore(llbuilder, val, dst);
+                    }
+                }
+            }
+            if let Some(dst) = inst_data.bound_step_ptr(inst, llbuilder) {
+                let val = bin_data
+                    .bound_step_ptr(bin_inst, llbuilder)
+                    .map_or(cx.const_real(f64::INFINITY), |src| {
+                        LLVMBuildLoad2(llbuilder, cx.ty_double(), src, UNNAMED)
+                    });
+                LLVMBuildStore(llbuilder, val, dst);
+            }
+            LLVMBuildRet(llbuilder, ret_flags);
+        }
+
+        LLVMPositionBuilderAtEnd(llbuilder, err_exit);
+        LLVMBuildRet(llbuilder, cx.const_unsigned_int(EVAL_RET_FLAG_FATAL));
+        LLVMDisposeBuilder(llbuilder);
+        llfunc
+    }
+
+    /// Builds a function that calls the function `fun` of the selected bin
+    /// with the bin instance and model followed by the remaining arguments.
+    unsafe fn dispatch(
+        &self,
+        name: &str,
+        extra_args: &[&'ll llvm::Type],
+        fun: impl Fn(&OsdiDescriptor<'ll>) -> &'ll llvm::Value,
+    ) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let mut args = vec![cx.ty_ptr(), cx.ty_ptr()];
+        args.extend_from_slice(extra_args);
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let llfunc = self.declare_fn(name, &args, cx.ty_void());
+
+        let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let exit = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+        LLVMPositionBuilderAtEnd(llbuilder, entry);
+
+        let mut params: Vec<_> = (0..args.len() as u32).map(|i| LLVMGetParam(llfunc, i)).collect();
+        let (inst, model) = (params[0], params[1]);
+        let bin_bbs = self.switch_bin(llfunc, inst, exit, llbuilder);
+        for (i, (bin, bb)) in self.bins.iter().zip(bin_bbs).enumerate() {
+            LLVMPositionBuilderAtEnd(llbuilder, bb);
+            params[0] = self.bin_inst(inst, llbuilder);
+            params[1] = self.bin_model(i, model, llbuilder);
+            let fun = fun(bin.descriptor);
+            LLVMBuildCall2(llbuilder, fun_ty, fun, params.as_ptr(), params.len() as u32, UNNAMED);
+            LLVMBuildBr(llbuilder, exit);
+        }
+
+        LLVMPositionBuilderAtEnd(llbuilder, exit);
+        LLVMBuildRetVoid(llbuilder);
+        LLVMDisposeBuilder(llbuilder);
+        llfunc
+    }
+
+    /// Lets the selected bin write its noise sources into a temporary array
+    /// and copies them to the positions of the unspecialized module.
+    unsafe fn load_noise(&self) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let ptr = cx.ty_ptr();
+        let args = [ptr, ptr, cx.ty_double(), ptr];
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let llfunc = self.declare_fn("load_noise", &args, cx.ty_void());
+        let num_noise = self.generic.module.dae_system.noise_sources.len();
+
+        self.scatter(llfunc, fun_ty, 3, num_noise, |bin| {
+            (bin.descriptor.load_noise, bin.map.noise.clone())
+        });
+        llfunc
+    }
+
+    /// Lets the selected bin write its jacobian entries into a temporary array
+    /// and copies them to the positions of the unspecialized module.
+    unsafe fn write_jacobian_array(&self, reactive: bool) -> &'ll llvm::Value {
+        let cx = self.cx;
+        let ptr = cx.ty_ptr();
+        let args = [ptr, ptr, ptr];
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let name =
+            if reactive { "write_jacobian_array_react" } else { "write_jacobian_array_resist" };
+        let llfunc = self.declare_fn(name, &args, cx.ty_void());
+
+        // entries that are always zero are not written to the array
+        let positions = |data: &OsdiInstanceData<'ll>| {
+            let mut pos = 0;
+            data.jacobian
+                .iter()
+                .map(|entry| {
+                    let present = if reactive { entry.react } else { entry.resist }.is_some();
+                    pos += present as usize;
+                    present.then_some(pos - 1)
+                })
+                .collect::<TiVec<MatrixEntryId, _>>()
+        };
+        let generic_positions = positions(&self.generic.inst_data);
+        let num_entries = generic_positions.iter().flatten().count();
+
+        self.scatter(llfunc, fun_ty, 2, num_entries, |bin| {
+            let fun = if reactive {
+                bin.descriptor.write_jacobian_array_react
+            } else {
+                bin.descriptor.write_jacobian_array_resist
+            };
+            let map = positions(&bin.unit.inst_data)
+                .iter_enumerated()
+                .filter(|(_, pos)| pos.is_some())
+                .map(|(entry, _)| generic_positions[bin.map.jacobian[entry]].unwrap())
+                .collect();
+            (fun, map)
+        });
+        llfunc
+    }
+
+    /// Builds the body of a function whose argument `dst_arg` is an array of
+    /// `len` doubles. The function of the selected bin is called with a
+    /// temporary array instead. Element `i` of the temporary array is copied
+    /// to the position `map[i]` of the destination, all other elements of the
+    /// destination are zeroed.
+    unsafe fn scatter(
+        &self,
+        llfunc: &'ll llvm::Value,
+        fun_ty: &'ll llvm::Type,
+        dst_arg: u32,
+        len: usize,
+        bin_fun: impl Fn(&BinCodegen<'a, 'b, 'll>) -> (&'ll llvm::Value, Vec<usize>),
+    ) {
+        let cx = self.cx;
+        let num_args = dst_arg + 1;
+        let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let exit = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+        LLVMPositionBuilderAtEnd(llbuilder, entry);
+
+        let mut params: Vec<_> = (0..num_args).map(|i| LLVMGetParam(llfunc, i)).collect();
+        let (inst, model, dst) = (params[0], params[1], params[dst_arg as usize]);
+        let tmp_ty = cx.ty_array(cx.ty_double(), len as u32);
+        let tmp = LLVMBuildAlloca(llbuilder, tmp_ty, UNNAMED);
+        let elem_ptr = |arr, i: usize| {
+            let indices = [cx.const_unsigned_int(0), cx.const_unsigned_int(i as u32)];
+            LLVMBuildGEP2(llbuilder, tmp_ty, arr, indices.as_ptr(), 2, UNNAMED)
+        };
+
+        let bin_bbs = self.switch_bin(llfunc, inst, exit, llbuilder);
+        for (i, (bin, bb)) in self.bins.iter().zip(bin_bbs).enumerate() {
+            LLVMPositionBuilderAtEnd(llbuilder, bb);
+            let (fun, map) = bin_fun(bin);
+            params[0] = self.bin_inst(inst, llbuilder);
+            params[1] = self.bin_model(i, model, llbuilder);
+            params[dst_arg as usize] = tmp;
+            LLVMBuildCall2(llbuilder, fun_ty, fun, params.as_ptr(), num_args, UNNAMED);
+
+            let mut vals = vec![cx.const_real(0.0); len];
+            for (src, &dst) in map.iter().enumerate() {
+                vals[dst] = LLVMBuildLoad2(llbuilder, cx.ty_double(), elem_ptr(tmp, src), UNNAMED);
+            }
+            for (i, val) in vals.into_iter().enumerate() {
+                LLVMBuildStore(llbuilder, val, elem_ptr(dst, i));
+            }
+            LLVMBuildBr(llbuilder, exit);
+        }
+
+        LLVMPositionBuilderAtEnd(llbuilder, exit);
+        LLVMBuildRetVoid(llbuilder);
+        LLVMDisposeBuilder(llbuilder);
+    }
+}
+
+unsafe fn copy<'ll>(
+    ty: &'ll llvm::Type,
+    src: &'ll llvm::Value,
+    dst: &'ll llvm::Value,
+    llbuilder: &llvm::Builder<'ll>,
+) {
+    let val = LLVMBuildLoad2(llbuilder, ty, src, UNNAMED);
+    LLVMBuildStore(llbuilder, val, dst);
+}
+
+/// Pointer to element `idx` of the array stored in the static instance data
+/// field `field`
+unsafe fn static_elem_ptr<'ll>(
+    data: &OsdiInstanceData<'ll>,
+    field: u32,
+    idx: u32,
+    inst: &'ll llvm::Value,
+    llbuilder: &llvm::Builder<'ll>,
+    cx: &CodegenCx<'_, 'll>,
+) -> &'ll llvm::Value {
+    let arr_ty = match field {
+        JACOBIAN_PTR_RESIST => data.jacobian_ptr,
+        JACOBIAN_PTR_REACT => data.jacobian_ptr_react,
+        NODE_MAPPING => data.node_mapping,
+        COLLAPSED => data.collapsed,
+        STATE_IDX => data.state_idx,
+        _ => unreachable!("field {field} is not an array"),
+    };
+    let ptr = LLVMBuildStructGEP2(llbuilder, data.ty, inst, field, UNNAMED);
+    let indices = [cx.const_int(0), cx.const_unsigned_int(idx)];
+    LLVMBuildGEP2(llbuilder, arr_ty, ptr, indices.as_ptr(), 2, UNNAMED)
+}
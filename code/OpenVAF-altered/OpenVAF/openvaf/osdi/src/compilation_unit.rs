@@ -1,6 +1,8 @@
-use hir::CompilationDB;
+use ahash::RandomState;
+use hir::{CompilationDB, Parameter};
 use hir_lower::fmt::{DisplayKind, FmtArg, FmtArgKind};
 use hir_lower::{CallBackKind, RetFlag, HirInterner};
+use indexmap::IndexMap;
 use lasso::Rodeo;
 use llvm::Linkage;
 use llvm::{
@@ -10,7 +12,7 @@ use llvm::{
     LLVMGetParam, LLVMIsDeclaration, LLVMPositionBuilderAtEnd, LLVMSetLinkage,
     LLVMSetUnnamedAddress, UnnamedAddr, UNNAMED,
 };
-use mir::{FuncRef, Function};
+use mir::{Const, FuncRef, Function};
 use mir_llvm::{CallbackFun, CodegenCx, LLVMBackend, ModuleLlvm, BuiltCallbackFun};
 use sim_back::dae::DaeSystem;
 use sim_back::init::Initialization;
@@ -26,7 +28,7 @@ use crate::metadata::osdi_0_4::{
 };
 use crate::metadata::OsdiLimFunction;
 use crate::model_data::OsdiModelData;
-use crate::{lltype, OsdiLimId};
+use crate::{lltype, ModuleVariant, OsdiLimId};
 
 pub fn new_codegen<'a, 'll>(
     back: &'a LLVMBackend,
@@ -113,16 +115,30 @@ pub struct OsdiModule<'a> {
     pub model_param_intern: &'a HirInterner,
     pub lim_table: &'a TiSet<OsdiLimId, OsdiLimFunction>,
     pub node_collapse: &'a NodeCollapse,
+    /// Elided parameters that are exposed to the simulator but have no storage
+    /// in the instance and model data (see [`crate::locked`])
+    pub locked_params: IndexMap<Parameter, Const, RandomState>,
+    /// name of the OSDI descriptor
+    pub name: &'a str,
     pub sym: String,
 }
 
 impl<'a> OsdiModule<'a> {
+    /// Modules of a binned device (`binned`) keep the storage of locked
+    /// parameters so all of them share the layout of the unspecialized module.
     pub fn new(
         db: &'a CompilationDB,
-        module: &'a CompiledModule,
+        module: &'a ModuleVariant,
         lim_table: &'a TiSet<OsdiLimId, OsdiLimFunction>,
+        binned: bool,
     ) -> Self {
-        let sym = base_n::encode(module.info.module.uuid(db) as u128, base_n::CASE_INSENSITIVE);
+        let ModuleVariant { module, name } = module;
+        let mut sym =
+            base_n::encode(module.info.module.uuid(db) as u128, base_n::CASE_INSENSITIVE);
+        if *name != module.info.module.name(db) {
+            sym.push('_');
+            sym.push_str(name);
+        }
         let CompiledModule {
             info,
             dae_system,
@@ -133,7 +149,10 @@ impl<'a> OsdiModule<'a> {
             model_param_intern,
             node_collapse,
         } = module;
+        let locked_params =
+            if binned { IndexMap::default() } else { model_param_intern.locked_params.clone() };
         OsdiModule {
+            name,
             sym,
             lim_table,
             info,
@@ -144,6 +163,7 @@ impl<'a> OsdiModule<'a> {
             model_param_setup,
             model_param_intern,
             node_collapse,
+            locked_params,
         }
     }
 }
@@ -219,6 +239,7 @@ pub fn general_callbacks<'ll>(
                 | CallBackKind::CollapseHint(_, _)
                 | CallBackKind::BuiltinLimit { .. }
                 | CallBackKind::StoreLimit(_)
+                | CallBackKind::StoreOperatorState(_)
                 | CallBackKind::LimDiscontinuity
                 | CallBackKind::Analysis
                 | CallBackKind::NoiseTable(_)
@@ -249,6 +270,12 @@ pub fn general_callbacks<'ll>(
                             .cx
                             .get_func_by_name("set_ret_flag_stop")
                             .expect("stdlib function set_ret_flag_stop is missing")
+                    } else if *flag==RetFlag::RejectStep {
+                        // Reject
+                        builder
+                            .cx
+                            .get_func_by_name("set_ret_flag_reject")
+                            .expect("stdlib function set_ret_flag_reject is missing")
                     } else {
                         panic!("Unsupported RetFlag encountered.");
                     };
@@ -126,8 +126,8 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
         };
 
-        let state_idx: TiVec<LimitState, _> = (0..intern.lim_state.len())
-            .map(|i| unsafe { inst_data.read_state_idx(cx, i.into(), instance, builder.llbuilder) })
+        let state_idx: TiVec<LimitState, _> = (0..intern.lim_state.len() as u32)
+            .map(|i| unsafe { inst_data.read_state_idx(cx, i, instance, builder.llbuilder) })
             .collect();
 
         let true_ = cx.const_bool(true);
@@ -225,8 +225,12 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                             LLVMBuildAnd(builder.llbuilder, is_not_dc, is_not_ic, UNNAMED)
                         }
                         ParamKind::PrevState(state) => {
-                            let idx =
-                                inst_data.read_state_idx(cx, state, instance, builder.llbuilder);
+                            let idx = inst_data.read_state_idx(
+                                cx,
+                                state.into(),
+                                instance,
+                                builder.llbuilder,
+                            );
                             return MemLoc {
                                 ptr: prev_state,
                                 ptr_ty: cx.ty_double(),
@@ -236,8 +240,12 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                             .into();
                         }
                         ParamKind::NewState(state) => {
-                            let idx =
-                                inst_data.read_state_idx(cx, state, instance, builder.llbuilder);
+                            let idx = inst_data.read_state_idx(
+                                cx,
+                                state.into(),
+                                instance,
+                                builder.llbuilder,
+                            );
 
                             return MemLoc {
                                 ptr: next_state,
@@ -247,6 +255,21 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                             }
                             .into();
                         }
+                        ParamKind::PrevOperatorState(state) => {
+                            let idx = inst_data.read_state_idx(
+                                cx,
+                                intern.operator_state_idx(state),
+                                instance,
+                                builder.llbuilder,
+                            );
+                            return MemLoc {
+                                ptr: prev_state,
+                                ptr_ty: cx.ty_double(),
+                                ty: cx.ty_double(),
+                                indices: vec![idx].into_boxed_slice(),
+                            }
+                            .into();
+                        }
                         ParamKind::EnableLim => {
                             is_flag_set_mem(cx, ENABLE_LIM, &flags, builder.llbuilder)
                         }
@@ -289,6 +312,28 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                         num_state: 0,
                     })
                 }
+                CallBackKind::StoreOperatorState(state) => {
+                    let fun = builder
+                        .cx
+                        .get_func_by_name("store_state")
+                        .expect("stdlib function store_state is missing");
+                    let fun_ty =
+                        cx.ty_func(&[cx.ty_ptr(), cx.ty_int(), cx.ty_double()], cx.ty_void());
+                    let idx = unsafe {
+                        inst_data.read_state_idx(
+                            cx,
+                            intern.operator_state_idx(state),
+                            instance,
+                            builder.llbuilder,
+                        )
+                    };
+                    CallbackFun::Prebuilt(BuiltCallbackFun {
+                        fun_ty,
+                        fun,
+                        state: Box::new([sim_info, idx]),
+                        num_state: 0,
+                    })
+                }
                 CallBackKind::LimDiscontinuity => {
                     let fun = builder
                         .cx
@@ -1,6 +1,6 @@
 use ahash::RandomState;
 use hir::{CompilationDB, ParamSysFun, Parameter, Variable};
-use hir_lower::{HirInterner, LimitState, ParamKind, PlaceKind};
+use hir_lower::{HirInterner, ParamKind, PlaceKind};
 use indexmap::IndexMap;
 use llvm::{
     IntPredicate, LLVMBuildFAdd, LLVMBuildFSub, LLVMBuildGEP2, LLVMBuildICmp, LLVMBuildIntCast2,
@@ -10,7 +10,7 @@ use llvm::{
 use mir::{strip_optbarrier, Const, Function, Param, ValueDef, F_ZERO};
 use mir_llvm::{CodegenCx, MemLoc};
 use sim_back::dae::{self, MatrixEntryId, SimUnknown};
-use sim_back::init::CacheSlot;
+use sim_back::init::{CacheSlot, Initialization};
 use stdx::packed_option::PackedOption;
 use stdx::{impl_debug_display, impl_idx_from};
 use typed_index_collections::TiVec;
@@ -25,6 +25,19 @@ pub enum OsdiInstanceParam {
     User(Parameter),
 }
 
+/// The builtin instance parameters (like `$mfactor`) that are used by a module
+pub fn live_builtin_params<'a>(
+    intern: &'a HirInterner,
+    eval: &'a Function,
+    init: &'a Initialization,
+) -> impl Iterator<Item = ParamSysFun> + 'a {
+    ParamSysFun::iter().filter(move |&param| {
+        let is_live =
+            |intern: &HirInterner, func| intern.is_param_live(func, &ParamKind::ParamSysFun(param));
+        is_live(intern, eval) || is_live(&init.intern, &init.func)
+    })
+}
+
 pub const NUM_CONST_FIELDS: u32 = 8;
 pub const PARAM_GIVEN: u32 = 0;
 pub const JACOBIAN_PTR_RESIST: u32 = 1;
@@ -38,7 +51,7 @@ pub const STATE_IDX: u32 = 7;
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum EvalOutput {
     Calculated(EvalOutputSlot),
-    Const(Const, PackedOption<EvalOutputSlot>),
+    Const(Const),
     Param(Param),
     Cache(CacheSlot),
 }
@@ -50,7 +63,6 @@ impl EvalOutput {
         module: &OsdiModule<'_>,
         val: mir::Value,
         eval_outputs: &mut TiMap<EvalOutputSlot, mir::Value, &'ll llvm::Type>,
-        requires_slot: bool,
         ty: &'ll llvm::Type,
     ) -> EvalOutput {
         match module.eval.dfg.value_def(val) {
@@ -71,10 +83,9 @@ impl EvalOutput {
                     return EvalOutput::Cache(slot.into());
                 }
             }
-            ValueDef::Const(const_val) => {
-                let slot = requires_slot.then(|| eval_outputs.insert_full(val, ty).0);
-                return EvalOutput::Const(const_val, slot.into());
-            }
+            // constants are never stored in the instance data, even if they are
+            // accessed by pointer (opvars)
+            ValueDef::Const(const_val) => return EvalOutput::Const(const_val),
             ValueDef::Invalid => unreachable!(),
         }
 
@@ -139,7 +150,7 @@ impl MatrixEntry {
             if val == F_ZERO {
                 None
             } else {
-                Some(EvalOutput::new(module, val, slots, false, ty_real))
+                Some(EvalOutput::new(module, val, slots, ty_real))
             }
         };
         let react_off = if entry.react == F_ZERO {
@@ -172,7 +183,7 @@ impl NoiseSource {
     ) -> NoiseSource {
         let mut get_output = |mut val| {
             val = strip_optbarrier(module.eval, val);
-            EvalOutput::new(module, val, slots, false, ty_real)
+            EvalOutput::new(module, val, slots, ty_real)
         };
         let args = match source.kind {
             dae::NoiseSourceKind::WhiteNoise { pwr } => [get_output(pwr), EvalOutput::NONE],
@@ -201,6 +212,9 @@ pub struct OsdiInstanceData<'ll> {
 
     // llvm types for dynamic instance data struct fields
     pub params: IndexMap<OsdiInstanceParam, &'ll llvm::Type, RandomState>,
+    /// instance parameters without storage, their OSDI ids follow `params`
+    /// (see [`crate::locked`])
+    pub locked_params: IndexMap<Parameter, Const, RandomState>,
     pub eval_outputs: TiMap<EvalOutputSlot, mir::Value, &'ll llvm::Type>,
     pub cache_slots: TiVec<CacheSlot, &'ll llvm::Type>,
 
@@ -216,24 +230,25 @@ impl<'ll> OsdiInstanceData<'ll> {
         let ty_f64 = cx.ty_double();
         let ty_u32 = cx.ty_int();
 
-        let builtin_inst_params = ParamSysFun::iter().filter_map(|param| {
-            let is_live = |intern: &HirInterner, func| {
-                intern.is_param_live(func, &ParamKind::ParamSysFun(param))
-            };
-            let is_live = is_live(module.intern, module.eval)
-                || is_live(&module.init.intern, &module.init.func);
-            is_live.then_some((OsdiInstanceParam::Builtin(param), ty_f64))
-        });
+        let builtin_inst_params = live_builtin_params(module.intern, module.eval, module.init)
+            .map(|param| (OsdiInstanceParam::Builtin(param), ty_f64));
         let alias_inst_params = module
             .info
             .sys_fun_alias
             .keys()
             .map(|param| (OsdiInstanceParam::Builtin(*param), ty_f64));
         let user_inst_params = module.info.params.iter().filter_map(|(param, info)| {
-            info.is_instance.then(|| (OsdiInstanceParam::User(*param), lltype(&param.ty(db), cx)))
+            (info.is_instance && !module.locked_params.contains_key(param))
+                .then(|| (OsdiInstanceParam::User(*param), lltype(&param.ty(db), cx)))
         });
         let params: IndexMap<_, _, _> =
             builtin_inst_params.chain(alias_inst_params).chain(user_inst_params).collect();
+        let locked_params: IndexMap<_, _, _> = module
+            .locked_params
+            .iter()
+            .filter(|(param, _)| module.info.params[*param].is_instance)
+            .map(|(param, val)| (*param, *val))
+            .collect();
 
         let mut eval_outputs = TiMap::default();
         let opvars = module
@@ -243,7 +258,7 @@ impl<'ll> OsdiInstanceData<'ll> {
             .map(|var| {
                 let val = module.intern.outputs[&PlaceKind::Var(*var)].unwrap_unchecked();
                 let ty = lltype(&var.ty(db), cx);
-                let pos = EvalOutput::new(module, val, &mut eval_outputs, true, ty);
+                let pos = EvalOutput::new(module, val, &mut eval_outputs, ty);
                 (*var, pos)
             })
             .collect();
@@ -284,7 +299,7 @@ impl<'ll> OsdiInstanceData<'ll> {
         let cache_slots: TiVec<_, _> =
             module.init.cache_slots.raw.values().map(|ty| lltype(ty, cx)).collect();
 
-        let state_idx = cx.ty_array(cx.ty_int(), module.intern.lim_state.len() as u32);
+        let state_idx = cx.ty_array(cx.ty_int(), module.intern.num_states());
         let static_fields: [_; NUM_CONST_FIELDS as usize] = [
             param_given,
             jacobian_ptr,
@@ -316,6 +331,7 @@ impl<'ll> OsdiInstanceData<'ll> {
             state_idx,
             collapsed,
             params,
+            locked_params,
             eval_outputs,
             cache_slots,
             residual,
@@ -326,6 +342,11 @@ impl<'ll> OsdiInstanceData<'ll> {
         }
     }
 
+    /// The number of instance parameters in the descriptor
+    pub fn num_params(&self) -> usize {
+        self.params.len() + self.locked_params.len()
+    }
+
     pub unsafe fn store_bound_step(
         &self,
         ptr: &'ll llvm::Value,
@@ -336,6 +357,15 @@ impl<'ll> OsdiInstanceData<'ll> {
         }
     }
 
+    pub unsafe fn bound_step_ptr(
+        &self,
+        ptr: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) -> Option<&'ll llvm::Value> {
+        let (ptr, _) = self.eval_output_slot_ptr(llbuilder, ptr, self.bound_step?);
+        Some(ptr)
+    }
+
     pub fn bound_step_elem(&self) -> Option<u32> {
         let elem = self.eval_output_slot_elem(self.bound_step?);
         Some(elem)
@@ -603,13 +633,13 @@ impl<'ll> OsdiInstanceData<'ll> {
     pub unsafe fn read_state_idx(
         &self,
         cx: &CodegenCx<'_, 'll>,
-        idx: LimitState,
+        idx: u32,
         ptr: &'ll llvm::Value,
         llbuilder: &llvm::Builder<'ll>,
     ) -> &'ll llvm::Value {
         let ptr = LLVMBuildStructGEP2(llbuilder, self.ty, ptr, STATE_IDX, UNNAMED);
         let zero = cx.const_int(0);
-        let state = cx.const_unsigned_int(idx.into());
+        let state = cx.const_unsigned_int(idx);
         let ptr = LLVMBuildGEP2(llbuilder, self.state_idx, ptr, [zero, state].as_ptr(), 2, UNNAMED);
         LLVMBuildLoad2(llbuilder, cx.ty_int(), ptr, UNNAMED)
     }
@@ -640,6 +670,27 @@ impl<'ll> OsdiInstanceData<'ll> {
         Some(val)
     }
 
+    /// Pointer to the slot that stores the residual (or limit rhs) of `node`.
+    /// Returns `None` if the residual is always zero.
+    pub unsafe fn residual_ptr(
+        &self,
+        node: SimUnknown,
+        ptr: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+        reactive: bool,
+        lim_rhs: bool,
+    ) -> Option<&'ll llvm::Value> {
+        let residual = &self.residual[node];
+        let slot = match (reactive, lim_rhs) {
+            (false, false) => residual.resist,
+            (true, false) => residual.react,
+            (false, true) => residual.resist_lim_rhs,
+            (true, true) => residual.react_lim_rhs,
+        };
+        let (ptr, _) = self.eval_output_slot_ptr(llbuilder, ptr, slot.expand()?);
+        Some(ptr)
+    }
+
     pub unsafe fn store_lim_rhs(
         &self,
         node: SimUnknown,
@@ -915,7 +966,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             EvalOutput::Calculated(slot) => {
                 inst_data.eval_output_slot_ptr(llbuilder, inst_ptr, slot)
             }
-            EvalOutput::Const(val, _) => {
+            EvalOutput::Const(val) => {
                 return cx.const_val(&val);
             }
             EvalOutput::Param(param) => {
@@ -952,6 +1003,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                     | ParamKind::EnableLim
                     | ParamKind::PrevState(_)
                     | ParamKind::NewState(_)
+                    | ParamKind::PrevOperatorState(_)
                     | ParamKind::ImplicitUnknown(_) => unreachable!(),
                 }
             }
@@ -987,10 +1039,10 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             EvalOutput::Calculated(slot) => {
                 inst_data.eval_output_slot_ptr(llbuilder, inst_ptr, slot)
             }
-            EvalOutput::Const(val, slot) => {
-                let (ptr, ty) = inst_data.eval_output_slot_ptr(llbuilder, inst_ptr, slot.unwrap());
-                LLVMBuildStore(llbuilder, cx.const_val(&val), ptr);
-                (ptr, ty)
+            EvalOutput::Const(val) => {
+                let val = cx.const_val(&val);
+                let ty = cx.val_ty(val);
+                (cx.global_const(ty, val), ty)
             }
             EvalOutput::Param(param) => {
                 let intern = &module.intern;
@@ -1026,6 +1078,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                     | ParamKind::EnableLim
                     | ParamKind::PrevState(_)
                     | ParamKind::NewState(_)
+                    | ParamKind::PrevOperatorState(_)
                     | ParamKind::ImplicitUnknown(_) => unreachable!(),
                 }
             }
@@ -13,17 +13,22 @@ use typed_index_collections::TiVec;
 use typed_indexmap::TiSet;
 
 use std::ffi::{CStr, CString};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
+pub use crate::binned::{Bin, BinnedDevice, BinnedDeviceError};
 use crate::compilation_unit::{new_codegen, OsdiCompilationUnit, OsdiModule};
+use crate::inst_data::OsdiInstanceData;
 use crate::metadata::osdi_0_4::OsdiTys;
-use crate::metadata::OsdiLimFunction;
+use crate::metadata::{sim_assumptions, OsdiLimFunction};
+use crate::model_data::OsdiModelData;
 
 mod access;
+mod binned;
 mod bitfield;
 mod compilation_unit;
 mod inst_data;
+mod locked;
 mod metadata;
 mod model_data;
 
@@ -34,6 +39,63 @@ mod setup;
 
 const OSDI_VERSION: (u32, u32) = (0, 4);
 
+/// A compiled module and the name of the OSDI descriptor it is exported as.
+/// A library may contain multiple variants of the same module that were
+/// compiled with different elided parameters.
+pub struct ModuleVariant<'a> {
+    pub module: CompiledModule<'a>,
+    pub name: String,
+}
+
+/// The size in bytes of the instance and model data of a module, as reported
+/// by `instance_size` and `model_size` of its OSDI descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataSizes {
+    pub instance: u64,
+    pub model: u64,
+}
+
+/// Computes the layout of the instance and model data of each of the `modules`
+/// without generating any code. `binned` must match the devices passed to
+/// [`compile_variants`].
+pub fn data_sizes(
+    db: &CompilationDB,
+    modules: &[ModuleVariant],
+    binned: &[BinnedDevice],
+    target: &Target,
+    back: &LLVMBackend,
+) -> Vec<DataSizes> {
+    // the limit functions only affect the generated code and not the layout
+    let lim_table = TiSet::default();
+    let literals = Rodeo::new();
+    let llmod = unsafe { back.new_module("data_sizes", OptLevel::None).unwrap() };
+    let cx = unsafe { back.new_ctx(&literals, &llmod) };
+    let target_data = unsafe {
+        let src = CString::new(target.data_layout.clone()).unwrap();
+        llvm::LLVMCreateTargetData(src.as_ptr())
+    };
+
+    let binned: HashSet<_> = binned.iter().flat_map(BinnedDevice::variants).collect();
+    let sizes = modules
+        .iter()
+        .enumerate()
+        .map(|(i, module)| {
+            let module = OsdiModule::new(db, module, &lim_table, binned.contains(&i));
+            let inst_data = OsdiInstanceData::new(db, &module, &cx);
+            let model_data = OsdiModelData::new(db, &module, &cx, &inst_data);
+            unsafe {
+                DataSizes {
+                    instance: LLVMABISizeOfType(target_data, inst_data.ty),
+                    model: LLVMABISizeOfType(target_data, model_data.ty),
+                }
+            }
+        })
+        .collect();
+
+    unsafe { LLVMDisposeTargetData(target_data) };
+    sizes
+}
+
 pub fn compile<'a>(
     db: &'a CompilationDB,
     modules: &'a [ModuleInfo],
@@ -46,23 +108,57 @@ pub fn compile<'a>(
     dump_unopt_mir: bool, 
     dump_ir: bool, 
     dump_unopt_ir: bool, 
-    params_to_leave: &'a Vec<u32>,
 ) -> (Vec<Utf8PathBuf>, Vec<CompiledModule<'a>>, Rodeo) {
     let mut literals = Rodeo::new();
-    let mut lim_table = TiSet::default();
-    let mnames: Vec<_> = modules.iter().map(|m| {m.module.name(db)}).collect();
-    let modules: Vec<_> = modules
+    let modules = modules
         .iter()
-        .map(|module| {
-            let mir = CompiledModule::new(db, module, &mut literals, dump_unopt_mir, dump_mir, params_to_leave);
-            for cb in mir.intern.callbacks.iter() {
-                if let CallBackKind::BuiltinLimit { name, num_args } = *cb {
-                    lim_table.ensure(OsdiLimFunction { name, num_args: num_args - 2 });
-                }
-            }
-            mir
+        .map(|module| ModuleVariant {
+            module: CompiledModule::new(db, module, &mut literals, dump_unopt_mir, dump_mir),
+            name: module.module.name(db),
         })
         .collect();
+    let (paths, modules, literals) = compile_variants(
+        db,
+        modules,
+        &[],
+        literals,
+        dst,
+        target,
+        back,
+        emit,
+        opt_lvl,
+        dump_ir,
+        dump_unopt_ir,
+    );
+    (paths, modules.into_iter().map(|variant| variant.module).collect(), literals)
+}
+
+/// Generates an OSDI library with one descriptor for each of the `modules`.
+/// The descriptor of the unspecialized module of a `binned` device is
+/// replaced with the descriptor of the device.
+/// The MIR of the modules must have been built with `literals`.
+pub fn compile_variants<'a>(
+    db: &CompilationDB,
+    modules: Vec<ModuleVariant<'a>>,
+    binned: &[BinnedDevice],
+    mut literals: Rodeo,
+    dst: &Utf8Path,
+    target: &Target,
+    back: &LLVMBackend,
+    emit: bool,
+    opt_lvl: OptLevel,
+    dump_ir: bool, 
+    dump_unopt_ir: bool, 
+) -> (Vec<Utf8PathBuf>, Vec<ModuleVariant<'a>>, Rodeo) {
+    let mut lim_table = TiSet::default();
+    let mnames: Vec<_> = modules.iter().map(|m| m.name.clone()).collect();
+    for module in &modules {
+        for cb in module.module.intern.callbacks.iter() {
+            if let CallBackKind::BuiltinLimit { name, num_args } = *cb {
+                lim_table.ensure(OsdiLimFunction { name, num_args: num_args - 2 });
+            }
+        }
+    }
 
     let name = dst.file_stem().expect("destination is a file").to_owned();
         
@@ -81,14 +177,17 @@ pub fn compile<'a>(
 
     let compiled_modules = modules;
 
+    let binned_variants: HashSet<_> = binned.iter().flat_map(BinnedDevice::variants).collect();
     let osdi_modules: Vec<_> = compiled_modules
         .iter()
-        .map(|module| {
-            let unit = OsdiModule::new(db, module, &lim_table);
+        .enumerate()
+        .map(|(i, module)| {
+            let unit = OsdiModule::new(db, module, &lim_table, binned_variants.contains(&i));
             unit.intern_names(&mut literals, db);
             unit
         })
         .collect();
+
     
     let db = db.snapshot();
 
@@ -229,15 +328,36 @@ pub fn compile<'a>(
         let cx = new_codegen(back, &llmod, &literals);
         let tys = OsdiTys::new(&cx, target_data);
 
-        let descriptors: Vec<_> = osdi_modules
+        let cguints: Vec<_> = osdi_modules
+            .iter()
+            .map(|module| OsdiCompilationUnit::new(&db, module, &cx, &tys, false))
+            .collect();
+        let sim_assumptions = sim_assumptions(&cx, &tys, &db);
+        let mut descriptors: Vec<_> = cguints
+            .iter()
+            .map(|cguint| Some(cguint.descriptor(target_data, &db, sim_assumptions)))
+            .collect();
+        for device in binned {
+            let generic = descriptors[device.generic].take().unwrap();
+            let descriptor = device.descriptor(
+                &db,
+                &compiled_modules,
+                &cguints,
+                generic,
+                &descriptors,
+                target_data,
+            );
+            descriptors[device.generic] = Some(descriptor);
+        }
+        let descriptors: Vec<_> = descriptors
             .iter()
-            .map(|module| {
-                let cguint = OsdiCompilationUnit::new(&db, module, &cx, &tys, false);
-                let descriptor = cguint.descriptor(target_data, &db);
-                descriptor.to_ll_val(&cx, &tys)
-            })
+            .map(|descriptor| descriptor.as_ref().unwrap().to_ll_val(&cx, &tys))
             .collect();
 
+        for module in &osdi_modules {
+            locked::define_lock_state(&cx, module);
+        }
+
         cx.export_array("OSDI_DESCRIPTORS", tys.osdi_descriptor, &descriptors, true, false);
         cx.export_val(
             "OSDI_NUM_DESCRIPTORS",
@@ -329,7 +449,7 @@ pub fn compile<'a>(
 
 impl OsdiModule<'_> {
     fn intern_names(&self, literals: &mut Rodeo, db: &CompilationDB) {
-        literals.get_or_intern(&*self.info.module.name(db));
+        literals.get_or_intern(self.name);
         self.intern_node_strs(literals, db);
         literals.get_or_intern_static("Multiplier (Verilog-A $mfactor)");
         literals.get_or_intern_static("deg");
@@ -0,0 +1,251 @@
+//! Parameters that were elided with `--elided-params lock` remain in the
+//! descriptor but have neither storage nor a given flag in the instance or
+//! model data. Their OSDI ids follow the stored parameters of the same kind
+//! (instance or model).
+//!
+//! When the simulator sets a locked parameter, `access` returns a pointer to a
+//! scratch slot and records which parameter was set (and for which instance or
+//! model). The scratch slot is compared with the elided value by the next call
+//! to `access` or to a setup function. The first parameter that was set to a
+//! different value is reported as invalid by the setup function of the
+//! instance or model it was set for.
+//!
+//! The scratch slot and the records are a single global per module (so the
+//! size of the instance and model data does not grow). Parameters of a module
+//! must therefore not be set from multiple threads at the same time.
+
+use llvm::IntPredicate::{IntEQ, IntNE};
+use llvm::RealPredicate::RealOEQ;
+use llvm::{
+    LLVMAddCase, LLVMAppendBasicBlockInContext, LLVMBuildAnd, LLVMBuildBr, LLVMBuildCall2,
+    LLVMBuildCondBr, LLVMBuildFCmp, LLVMBuildICmp, LLVMBuildLoad2, LLVMBuildRet, LLVMBuildStore,
+    LLVMBuildStructGEP2, LLVMBuildSub, LLVMBuildSwitch, LLVMConstNull, LLVMPositionBuilderAtEnd,
+    LLVMSetInitializer, LLVMSetLinkage, Linkage, UNNAMED,
+};
+use mir::Const;
+use mir_llvm::CodegenCx;
+
+use crate::compilation_unit::{OsdiCompilationUnit, OsdiModule};
+use crate::inst_data::OsdiInstanceData;
+use crate::model_data::OsdiModelData;
+
+/// The slot a locked parameter is written to. A double is large enough to hold
+/// a value of any parameter type.
+const SCRATCH: u32 = 0;
+/// The instance or model data the pending parameter was set for
+const PENDING_DATA: u32 = 1;
+/// `id + 1` of the locked parameter that was set last (0 if none)
+const PENDING: u32 = 2;
+/// The instance or model data the invalid parameter was set for
+const INVALID_DATA: u32 = 3;
+/// `id + 1` of the first locked parameter that was set to a different value (0 if none)
+const INVALID: u32 = 4;
+
+fn lock_state_ty<'ll>(cx: &CodegenCx<'_, 'll>) -> &'ll llvm::Type {
+    let fields = [cx.ty_double(), cx.ty_ptr(), cx.ty_int(), cx.ty_ptr(), cx.ty_int()];
+    cx.ty_struct("osdi_lock_state", &fields)
+}
+
+/// Declares the lock state of `module`. It is defined (and zero initialized)
+/// in the main object by [`define_lock_state`].
+fn declare_lock_state<'ll>(cx: &CodegenCx<'_, 'll>, module: &OsdiModule) -> &'ll llvm::Value {
+    let name = format!("lock_state_{}", module.sym);
+    if let Some(global) = cx.get_declared_value(&name) {
+        return global;
+    }
+    let global = cx.define_global(&name, lock_state_ty(cx)).unwrap();
+    unsafe { LLVMSetLinkage(global, Linkage::ExternalLinkage) };
+    global
+}
+
+/// Defines the lock state of `module` if it has locked parameters
+pub fn define_lock_state(cx: &CodegenCx<'_, '_>, module: &OsdiModule) {
+    if !module.locked_params.is_empty() {
+        let global = declare_lock_state(cx, module);
+        unsafe { LLVMSetInitializer(global, LLVMConstNull(lock_state_ty(cx))) };
+    }
+}
+
+/// A locked parameter: its OSDI id and the value it was elided to
+#[derive(Clone, Copy, Debug)]
+pub struct LockedParam {
+    pub id: u32,
+    pub val: Const,
+}
+
+/// The locked parameters of a module
+pub struct LockedParams<'ll> {
+    /// the locked instance parameters followed by the locked model parameters
+    pub params: Vec<LockedParam>,
+    num_instance_params: usize,
+    state: &'ll llvm::Value,
+    state_ty: &'ll llvm::Type,
+}
+
+impl<'ll> LockedParams<'ll> {
+    pub fn new(
+        cx: &CodegenCx<'_, 'll>,
+        module: &OsdiModule,
+        inst_data: &OsdiInstanceData<'ll>,
+        model_data: &OsdiModelData<'ll>,
+    ) -> Option<LockedParams<'ll>> {
+        if module.locked_params.is_empty() {
+            return None;
+        }
+
+        let inst_offset = inst_data.params.len();
+        let model_offset = inst_data.num_params() + model_data.params.len();
+        let inst_params = inst_data
+            .locked_params
+            .values()
+            .enumerate()
+            .map(|(i, &val)| LockedParam { id: (inst_offset + i) as u32, val });
+        let model_params = model_data
+            .locked_params
+            .values()
+            .enumerate()
+            .map(|(i, &val)| LockedParam { id: (model_offset + i) as u32, val });
+        Some(LockedParams {
+            params: inst_params.chain(model_params).collect(),
+            num_instance_params: inst_data.locked_params.len(),
+            state: declare_lock_state(cx, module),
+            state_ty: lock_state_ty(cx),
+        })
+    }
+
+    /// The locked instance parameters
+    pub fn instance_params(&self) -> &[LockedParam] {
+        &self.params[..self.num_instance_params]
+    }
+
+    unsafe fn field_ptr(&self, field: u32, llbuilder: &llvm::Builder<'ll>) -> &'ll llvm::Value {
+        LLVMBuildStructGEP2(llbuilder, self.state_ty, self.state, field, UNNAMED)
+    }
+
+    /// Builds the `access` case of the locked parameter `param` for the
+    /// instance or model data `ptr`. If the parameter is set the elided value
+    /// is stored in the scratch slot and a pointer to it is returned.
+    /// Otherwise a pointer to a constant holding the elided value is returned.
+    pub unsafe fn access(
+        &self,
+        cx: &CodegenCx<'_, 'll>,
+        llfunc: &'ll llvm::Value,
+        param: LockedParam,
+        write_flag_set: &'ll llvm::Value,
+        ptr: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) {
+        let write = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let read = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let val = cx.const_val(&param.val);
+        LLVMBuildCondBr(llbuilder, write_flag_set, write, read);
+
+        LLVMPositionBuilderAtEnd(llbuilder, write);
+        let scratch = self.field_ptr(SCRATCH, llbuilder);
+        LLVMBuildStore(llbuilder, val, scratch);
+        LLVMBuildStore(llbuilder, ptr, self.field_ptr(PENDING_DATA, llbuilder));
+        let pending = cx.const_unsigned_int(param.id + 1);
+        LLVMBuildStore(llbuilder, pending, self.field_ptr(PENDING, llbuilder));
+        LLVMBuildRet(llbuilder, scratch);
+
+        LLVMPositionBuilderAtEnd(llbuilder, read);
+        LLVMBuildRet(llbuilder, cx.global_const(cx.val_ty(val), val));
+    }
+
+    /// Compares the scratch slot with the elided value of the locked parameter
+    /// that was set last and records the parameter if they differ. The builder
+    /// is positioned at a new block afterwards.
+    pub unsafe fn check(
+        &self,
+        cx: &CodegenCx<'_, 'll>,
+        llfunc: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) {
+        let mismatch = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let record = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let clear = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let done = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+
+        let scratch = self.field_ptr(SCRATCH, llbuilder);
+        let pending_ptr = self.field_ptr(PENDING, llbuilder);
+        let pending = LLVMBuildLoad2(llbuilder, cx.ty_int(), pending_ptr, UNNAMED);
+        let switch = LLVMBuildSwitch(llbuilder, pending, done, self.params.len() as u32);
+        for param in &self.params {
+            let bb = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+            LLVMAddCase(switch, cx.const_unsigned_int(param.id + 1), bb);
+            LLVMPositionBuilderAtEnd(llbuilder, bb);
+
+            let expected = cx.const_val(&param.val);
+            let val = LLVMBuildLoad2(llbuilder, cx.val_ty(expected), scratch, UNNAMED);
+            let is_eq = match param.val {
+                Const::Float(_) => LLVMBuildFCmp(llbuilder, RealOEQ, val, expected, UNNAMED),
+                Const::Int(_) | Const::Bool(_) => {
+                    LLVMBuildICmp(llbuilder, IntEQ, val, expected, UNNAMED)
+                }
+                Const::Str(_) => {
+                    let (fun_ty, fun) = cx.intrinsic("strcmp").unwrap();
+                    let args = [val, expected];
+                    let res = LLVMBuildCall2(llbuilder, fun_ty, fun, args.as_ptr(), 2, UNNAMED);
+                    LLVMBuildICmp(llbuilder, IntEQ, res, cx.const_int(0), UNNAMED)
+                }
+            };
+            LLVMBuildCondBr(llbuilder, is_eq, clear, mismatch);
+        }
+
+        // only the first mismatch is recorded until it is reported
+        LLVMPositionBuilderAtEnd(llbuilder, mismatch);
+        let invalid_ptr = self.field_ptr(INVALID, llbuilder);
+        let invalid = LLVMBuildLoad2(llbuilder, cx.ty_int(), invalid_ptr, UNNAMED);
+        let is_first = LLVMBuildICmp(llbuilder, IntEQ, invalid, cx.const_int(0), UNNAMED);
+        LLVMBuildCondBr(llbuilder, is_first, record, clear);
+
+        LLVMPositionBuilderAtEnd(llbuilder, record);
+        LLVMBuildStore(llbuilder, pending, invalid_ptr);
+        let pending_data_ptr = self.field_ptr(PENDING_DATA, llbuilder);
+        let pending_data = LLVMBuildLoad2(llbuilder, cx.ty_ptr(), pending_data_ptr, UNNAMED);
+        LLVMBuildStore(llbuilder, pending_data, self.field_ptr(INVALID_DATA, llbuilder));
+        LLVMBuildBr(llbuilder, clear);
+
+        LLVMPositionBuilderAtEnd(llbuilder, clear);
+        LLVMBuildStore(llbuilder, cx.const_int(0), pending_ptr);
+        LLVMBuildBr(llbuilder, done);
+
+        LLVMPositionBuilderAtEnd(llbuilder, done);
+    }
+
+    /// Reports the first locked parameter that was set to a value other than
+    /// its elided value for the instance or model data `ptr` as invalid (`err`
+    /// is the error list of the setup function). The builder is positioned at
+    /// a new block afterwards.
+    pub unsafe fn report(
+        &self,
+        cx: &CodegenCx<'_, 'll>,
+        llfunc: &'ll llvm::Value,
+        ptr: &'ll llvm::Value,
+        err: [&'ll llvm::Value; 3],
+        llbuilder: &llvm::Builder<'ll>,
+    ) {
+        self.check(cx, llfunc, llbuilder);
+
+        let report = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let done = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+        let invalid_ptr = self.field_ptr(INVALID, llbuilder);
+        let invalid = LLVMBuildLoad2(llbuilder, cx.ty_int(), invalid_ptr, UNNAMED);
+        let is_invalid = LLVMBuildICmp(llbuilder, IntNE, invalid, cx.const_int(0), UNNAMED);
+        let invalid_data_ptr = self.field_ptr(INVALID_DATA, llbuilder);
+        let invalid_data = LLVMBuildLoad2(llbuilder, cx.ty_ptr(), invalid_data_ptr, UNNAMED);
+        let is_data = LLVMBuildICmp(llbuilder, IntEQ, invalid_data, ptr, UNNAMED);
+        let is_invalid = LLVMBuildAnd(llbuilder, is_invalid, is_data, UNNAMED);
+        LLVMBuildCondBr(llbuilder, is_invalid, report, done);
+
+        LLVMPositionBuilderAtEnd(llbuilder, report);
+        let id = LLVMBuildSub(llbuilder, invalid, cx.const_int(1), UNNAMED);
+        let (err_ty, err_fun) = OsdiCompilationUnit::invalid_param_err(cx);
+        let args = [err[0], err[1], err[2], id];
+        LLVMBuildCall2(llbuilder, err_ty, err_fun, args.as_ptr(), 4, UNNAMED);
+        LLVMBuildStore(llbuilder, cx.const_int(0), invalid_ptr);
+        LLVMBuildBr(llbuilder, done);
+
+        LLVMPositionBuilderAtEnd(llbuilder, done);
+    }
+}
@@ -1,6 +1,6 @@
 use std::iter::once;
 
-use hir::{CompilationDB, ParamSysFun, Type};
+use hir::{CompilationDB, ParamSysFun, Parameter, Type};
 use hir_lower::CurrentKind;
 use lasso::{Rodeo, Spur};
 use llvm::{LLVMABISizeOfType, LLVMOffsetOfElement, TargetData};
@@ -17,9 +17,9 @@ use crate::inst_data::{
 use crate::load::JacobianLoadType;
 use crate::metadata::osdi_0_4::{
     OsdiDescriptor, OsdiJacobianEntry, OsdiNode, OsdiNodePair, OsdiNoiseSource, OsdiParamOpvar,
-    OsdiTys, JACOBIAN_ENTRY_REACT, JACOBIAN_ENTRY_REACT_CONST, JACOBIAN_ENTRY_RESIST,
-    JACOBIAN_ENTRY_RESIST_CONST, PARA_KIND_INST, PARA_KIND_MODEL, PARA_KIND_OPVAR, PARA_TY_INT,
-    PARA_TY_REAL, PARA_TY_STR,
+    OsdiSimAssumptions, OsdiTys, JACOBIAN_ENTRY_REACT, JACOBIAN_ENTRY_REACT_CONST,
+    JACOBIAN_ENTRY_RESIST, JACOBIAN_ENTRY_RESIST_CONST, OSDI_SIM_ASSUMPTIONS_VERSION,
+    PARA_KIND_INST, PARA_KIND_MODEL, PARA_KIND_OPVAR, PARA_TY_INT, PARA_TY_REAL, PARA_TY_STR,
 };
 use crate::ty_len;
 
@@ -43,6 +43,27 @@ impl OsdiLimFunction {
     }
 }
 
+/// Builds the table of simulator settings assumed during compilation
+/// (`--assume-temperature`/`--assume-simparam`) that every descriptor points to.
+pub fn sim_assumptions<'ll>(
+    cx: &CodegenCx<'_, 'll>,
+    tys: &'ll OsdiTys,
+    db: &CompilationDB,
+) -> &'ll llvm::Value {
+    let assumptions = db.compilation_unit().sim_assumptions(db);
+    let simparams = assumptions.resolved_simparams();
+    let table = OsdiSimAssumptions {
+        version: OSDI_SIM_ASSUMPTIONS_VERSION,
+        has_temperature: assumptions.temperature.is_some(),
+        temperature: assumptions.temperature.unwrap_or(0.0),
+        num_simparams: simparams.len() as u32,
+        simparam_names: simparams.iter().map(|(name, _)| (*name).to_owned()).collect(),
+        simparam_values: simparams.iter().map(|&(_, val)| val).collect(),
+    };
+    let val = table.to_ll_val(cx, tys);
+    cx.const_arr_ptr(tys.osdi_sim_assumptions, &[val])
+}
+
 impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
     pub fn param_opvar(&self) -> Vec<OsdiParamOpvar> {
         let OsdiCompilationUnit { inst_data, model_data, module, .. } = self;
@@ -55,6 +76,22 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
         }
 
+        let user_param = |param: &Parameter, kind: u32| {
+            let param_info = &module.info.params[param];
+            let ty = param.ty(self.db);
+            OsdiParamOpvar {
+                name: once(&param_info.name)
+                    .chain(&*param_info.alias)
+                    .map(SmolStr::to_string)
+                    .collect(),
+                num_alias: param_info.alias.len() as u32,
+                description: param_info.description.clone(),
+                units: param_info.unit.clone(),
+                flags: para_ty_flags(&ty) | kind,
+                len: ty_len(&ty).unwrap_or(0),
+            }
+        };
+
         let inst_params = inst_data.params.keys().map(|param| match param {
             OsdiInstanceParam::Builtin(builtin) => {
                 let mut name = vec![format!("${builtin:?}")];
@@ -77,45 +114,18 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                     len: 0,
                 }
             }
-            OsdiInstanceParam::User(param) => {
-                let param_info = &module.info.params[param];
-                let ty = param.ty(self.db);
-
-                let flags = para_ty_flags(&ty) | PARA_KIND_INST;
-                OsdiParamOpvar {
-                    name: once(&param_info.name)
-                        .chain(&*param_info.alias)
-                        .map(SmolStr::to_string)
-                        .collect(),
-                    num_alias: param_info.alias.len() as u32,
-                    description: param_info.description.clone(),
-                    units: param_info.unit.clone(),
-                    flags,
-                    len: ty_len(&ty).unwrap_or(0),
-                }
-            }
+            OsdiInstanceParam::User(param) => user_param(param, PARA_KIND_INST),
         });
+        // locked parameters follow the stored parameters of the same kind
+        let inst_params = inst_params
+            .chain(inst_data.locked_params.keys().map(|param| user_param(param, PARA_KIND_INST)));
 
-        let model_params = model_data.params.keys().filter_map(|param| {
-            let param_info = &module.info.params[param];
-            if param_info.is_instance {
-                return None;
-            }
-            let ty = param.ty(self.db);
-            let flags = para_ty_flags(&ty) | PARA_KIND_MODEL;
-            let param_opvar = OsdiParamOpvar {
-                name: once(&param_info.name)
-                    .chain(&*param_info.alias)
-                    .map(SmolStr::to_string)
-                    .collect(),
-                num_alias: param_info.alias.len() as u32,
-                description: param_info.description.clone(),
-                units: param_info.unit.clone(),
-                flags,
-                len: ty_len(&ty).unwrap_or(0),
-            };
-            Some(param_opvar)
-        });
+        let model_params = model_data
+            .params
+            .keys()
+            .chain(model_data.locked_params.keys())
+            .filter(|param| !module.info.params[*param].is_instance)
+            .map(|param| user_param(param, PARA_KIND_MODEL));
 
         let opvars = inst_data.opvars.keys().map(|opvar| {
             let opvar_info = &module.info.op_vars[opvar];
@@ -245,6 +255,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
         &self,
         target_data: &llvm::TargetData,
         db: &CompilationDB,
+        sim_assumptions: &'ll llvm::Value,
     ) -> OsdiDescriptor<'ll> {
         let collapsible = self.collapsible();
         let inputs = self.inputs();
@@ -279,7 +290,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 .collect();
 
             OsdiDescriptor {
-                name: module.info.module.name(db),
+                name: module.name.to_owned(),
                 num_nodes: module.dae_system.unknowns.len() as u32,
                 num_terminals: module.info.module.ports(db).len() as u32,
                 nodes: self.nodes(target_data, db),
@@ -294,8 +305,8 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 num_noise_src: noise_sources.len() as u32,
                 noise_sources,
 
-                num_params: model_data.params.len() as u32 + inst_data.params.len() as u32,
-                num_instance_params: inst_data.params.len() as u32,
+                num_params: (model_data.num_params() + inst_data.num_params()) as u32,
+                num_instance_params: inst_data.num_params() as u32,
                 num_opvars: inst_data.opvars.len() as u32,
                 param_opvar: self.param_opvar(),
 
@@ -316,7 +327,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 load_jacobian_resist: self.load_jacobian(JacobianLoadType::Resist, false),
                 load_jacobian_react: self.load_jacobian(JacobianLoadType::React, false),
                 load_jacobian_tran: self.load_jacobian(JacobianLoadType::Tran, false),
-                num_states: self.module.intern.lim_state.len() as u32,
+                num_states: self.module.intern.num_states(),
                 load_limit_rhs_resist: self.load_lim_rhs(false),
                 load_limit_rhs_react: self.load_lim_rhs(true),
                 given_flag_model: self.given_flag_model(), 
@@ -329,6 +340,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 inputs: inputs, 
                 load_jacobian_with_offset_resist: self.load_jacobian(JacobianLoadType::Resist, true),
                 load_jacobian_with_offset_react: self.load_jacobian(JacobianLoadType::React, true),
+                sim_assumptions,
             }
         }
     }
@@ -63,6 +63,7 @@ pub const EVAL_RET_FLAG_LIM: u32 = 1;
 pub const EVAL_RET_FLAG_FATAL: u32 = 2;
 pub const EVAL_RET_FLAG_FINISH: u32 = 4;
 pub const EVAL_RET_FLAG_STOP: u32 = 8;
+pub const EVAL_RET_FLAG_REJECT: u32 = 16;
 pub const LOG_LVL_MASK: u32 = 7;
 pub const LOG_LVL_DEBUG: u32 = 0;
 pub const LOG_LVL_DISPLAY: u32 = 1;
@@ -72,6 +73,7 @@ pub const LOG_LVL_ERR: u32 = 4;
 pub const LOG_LVL_FATAL: u32 = 5;
 pub const LOG_FMT_ERR: u32 = 16;
 pub const INIT_ERR_OUT_OF_BOUNDS: u32 = 1;
+pub const OSDI_SIM_ASSUMPTIONS_VERSION: u32 = 1;
 
 pub struct OsdiLimFunction<'ll> {
     pub name: String,
@@ -292,6 +294,46 @@ impl OsdiTyBuilder<'_, '_, '_> {
         self.osdi_noise_source = Some(ty);
     }
 }
+pub struct OsdiSimAssumptions {
+    pub version: u32,
+    pub has_temperature: bool,
+    pub temperature: f64,
+    pub num_simparams: u32,
+    pub simparam_names: Vec<String>,
+    pub simparam_values: Vec<f64>,
+}
+impl OsdiSimAssumptions {
+    pub fn to_ll_val<'ll>(&self, ctx: &CodegenCx<'_, 'll>, tys: &'ll OsdiTys) -> &'ll llvm::Value {
+        let arr_4: Vec<_> =
+            self.simparam_names.iter().map(|it| ctx.const_str_uninterned(it)).collect();
+        let arr_5: Vec<_> = self.simparam_values.iter().map(|it| ctx.const_real(*it)).collect();
+        let fields = [
+            ctx.const_unsigned_int(self.version),
+            ctx.const_c_bool(self.has_temperature),
+            ctx.const_real(self.temperature),
+            ctx.const_unsigned_int(self.num_simparams),
+            ctx.const_arr_ptr(ctx.ty_ptr(), &arr_4),
+            ctx.const_arr_ptr(ctx.ty_double(), &arr_5),
+        ];
+        let ty = tys.osdi_sim_assumptions;
+        ctx.const_struct(ty, &fields)
+    }
+}
+impl OsdiTyBuilder<'_, '_, '_> {
+    fn osdi_sim_assumptions(&mut self) {
+        let ctx = self.ctx;
+        let fields = [
+            ctx.ty_int(),
+            ctx.ty_c_bool(),
+            ctx.ty_double(),
+            ctx.ty_int(),
+            ctx.ty_ptr(),
+            ctx.ty_ptr(),
+        ];
+        let ty = ctx.ty_struct("OsdiSimAssumptions", &fields);
+        self.osdi_sim_assumptions = Some(ty);
+    }
+}
 pub struct OsdiDescriptor<'ll> {
     pub name: String,
     pub num_nodes: u32,
@@ -339,6 +381,7 @@ pub struct OsdiDescriptor<'ll> {
     pub inputs: Vec<OsdiNodePair>,
     pub load_jacobian_with_offset_resist: &'ll llvm::Value,
     pub load_jacobian_with_offset_react: &'ll llvm::Value,
+    pub sim_assumptions: &'ll llvm::Value,
 }
 impl<'ll> OsdiDescriptor<'ll> {
     pub fn to_ll_val(&self, ctx: &CodegenCx<'_, 'll>, tys: &'ll OsdiTys) -> &'ll llvm::Value {
@@ -395,6 +438,7 @@ impl<'ll> OsdiDescriptor<'ll> {
             ctx.const_arr_ptr(tys.osdi_node_pair, &arr_43),
             self.load_jacobian_with_offset_resist,
             self.load_jacobian_with_offset_react,
+            self.sim_assumptions,
         ];
         let ty = tys.osdi_descriptor;
         ctx.const_struct(ty, &fields)
@@ -450,6 +494,7 @@ impl OsdiTyBuilder<'_, '_, '_> {
             ctx.ty_ptr(),
             ctx.ty_ptr(),
             ctx.ty_ptr(),
+            ctx.ty_ptr(),
         ];
         let ty = ctx.ty_struct("OsdiDescriptor", &fields);
         self.osdi_descriptor = Some(ty);
@@ -468,6 +513,7 @@ pub struct OsdiTys<'ll> {
     pub osdi_node: &'ll llvm::Type,
     pub osdi_param_opvar: &'ll llvm::Type,
     pub osdi_noise_source: &'ll llvm::Type,
+    pub osdi_sim_assumptions: &'ll llvm::Type,
     pub osdi_descriptor: &'ll llvm::Type,
 }
 impl<'ll> OsdiTys<'ll> {
@@ -486,6 +532,7 @@ impl<'ll> OsdiTys<'ll> {
             osdi_node: None,
             osdi_param_opvar: None,
             osdi_noise_source: None,
+            osdi_sim_assumptions: None,
             osdi_descriptor: None,
         };
         builder.osdi_lim_function();
@@ -499,6 +546,7 @@ impl<'ll> OsdiTys<'ll> {
         builder.osdi_node();
         builder.osdi_param_opvar();
         builder.osdi_noise_source();
+        builder.osdi_sim_assumptions();
         builder.osdi_descriptor();
         builder.finish()
     }
@@ -517,6 +565,7 @@ struct OsdiTyBuilder<'a, 'b, 'll> {
     osdi_node: Option<&'ll llvm::Type>,
     osdi_param_opvar: Option<&'ll llvm::Type>,
     osdi_noise_source: Option<&'ll llvm::Type>,
+    osdi_sim_assumptions: Option<&'ll llvm::Type>,
     osdi_descriptor: Option<&'ll llvm::Type>,
 }
 impl<'ll> OsdiTyBuilder<'_, '_, 'll> {
@@ -533,6 +582,7 @@ impl<'ll> OsdiTyBuilder<'_, '_, 'll> {
             osdi_node: self.osdi_node.unwrap(),
             osdi_param_opvar: self.osdi_param_opvar.unwrap(),
             osdi_noise_source: self.osdi_noise_source.unwrap(),
+            osdi_sim_assumptions: self.osdi_sim_assumptions.unwrap(),
             osdi_descriptor: self.osdi_descriptor.unwrap(),
         }
     }
@@ -2,6 +2,7 @@ use ahash::RandomState;
 use hir::{CompilationDB, Parameter};
 use indexmap::IndexMap;
 use llvm::{LLVMBuildLoad2, LLVMBuildStore, LLVMBuildStructGEP2, Value, UNNAMED};
+use mir::Const;
 use mir_llvm::{CodegenCx, MemLoc};
 
 use crate::compilation_unit::OsdiModule;
@@ -13,6 +14,9 @@ const NUM_CONST_FIELDS: u32 = 1;
 pub struct OsdiModelData<'ll> {
     pub param_given: &'ll llvm::Type,
     pub params: IndexMap<Parameter, &'ll llvm::Type, RandomState>,
+    /// model parameters without storage, their OSDI ids follow `params`
+    /// (see [`crate::locked`])
+    pub locked_params: IndexMap<Parameter, Const, RandomState>,
     pub ty: &'ll llvm::Type,
 }
 
@@ -27,15 +31,21 @@ impl<'ll> OsdiModelData<'ll> {
         let params: IndexMap<_, _, _> = cgunit
             .info
             .params
-            .keys()
-            .filter_map(|param| {
-                if inst_params.contains_key(&OsdiInstanceParam::User(*param)) {
+            .iter()
+            .filter_map(|(param, info)| {
+                if info.is_instance || cgunit.locked_params.contains_key(param) {
                     None
                 } else {
                     Some((*param, lltype(&param.ty(db), cx)))
                 }
             })
             .collect();
+        let locked_params: IndexMap<_, _, _> = cgunit
+            .locked_params
+            .iter()
+            .filter(|(param, _)| !cgunit.info.params[*param].is_instance)
+            .map(|(param, val)| (*param, *val))
+            .collect();
 
         let param_given = bitfield::arr_ty((inst_params.len() + params.len()) as u32, cx);
 
@@ -47,7 +57,21 @@ impl<'ll> OsdiModelData<'ll> {
         let name = format!("osdi_model_data_{name}");
         let ty = cx.ty_struct(&name, &fields);
 
-        OsdiModelData { param_given, params, ty }
+        OsdiModelData { param_given, params, locked_params, ty }
+    }
+
+    /// The number of model parameters in the descriptor
+    pub fn num_params(&self) -> usize {
+        self.params.len() + self.locked_params.len()
+    }
+
+    /// The OSDI id of the model parameter `param`
+    pub fn param_id(&self, inst_data: &OsdiInstanceData<'ll>, param: Parameter) -> u32 {
+        let idx = match self.params.get_index_of(&param) {
+            Some(idx) => idx,
+            None => self.params.len() + self.locked_params.get_index_of(&param).unwrap(),
+        };
+        (inst_data.num_params() + idx) as u32
     }
 
     pub fn nth_param_loc(
@@ -1,4 +1,7 @@
-use hir_lower::{CallBackKind, ParamInfoKind, ParamKind, PlaceKind};
+use ahash::RandomState;
+use hir::Parameter;
+use hir_lower::{CallBackKind, HirInterner, ParamInfoKind, ParamKind, PlaceKind};
+use indexmap::IndexMap;
 
 use llvm::IntPredicate::IntSLT;
 use llvm::{
@@ -6,12 +9,13 @@ use llvm::{
     LLVMCreateBuilderInContext, LLVMDisposeBuilder, LLVMGetParam, LLVMPositionBuilderAtEnd,
     UNNAMED,
 };
-use mir::ControlFlowGraph;
+use mir::{Const, ControlFlowGraph};
 use mir_llvm::{Builder, BuilderVal, BuiltCallbackFun, CallbackFun, CodegenCx, InlineCallbackBuilder};
 use sim_back::SimUnknownKind;
 
 use crate::compilation_unit::{general_callbacks, OsdiCompilationUnit};
 use crate::inst_data::OsdiInstanceParam;
+use crate::locked::LockedParams;
 
 impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
     fn mark_collapsed(&self) -> (&'ll llvm::Value, &'ll llvm::Type) {
@@ -38,7 +42,7 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
         (llfunc, fn_type)
     }
 
-    fn invalid_param_err(cx: &CodegenCx<'_, 'll>) -> (&'ll llvm::Type, &'ll llvm::Value) {
+    pub(crate) fn invalid_param_err(cx: &CodegenCx<'_, 'll>) -> (&'ll llvm::Type, &'ll llvm::Value) {
         let val = cx
             .get_func_by_name("push_invalid_param_err")
             .expect("stdlib function push_invalid_param_err is missing");
@@ -48,6 +52,24 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
         (ty, val)
     }
 
+    /// Locked parameters are never given, so the setup functions see the value
+    /// they were elided to
+    fn lock_params(
+        cx: &CodegenCx<'_, 'll>,
+        intern: &HirInterner,
+        locked_params: &IndexMap<Parameter, Const, RandomState>,
+        builder: &mut Builder<'_, '_, 'll>,
+    ) {
+        for (&param, val) in locked_params {
+            if let Some(dst) = intern.params.index(&ParamKind::Param(param)) {
+                builder.params[dst] = BuilderVal::Eager(cx.const_val(val));
+            }
+            if let Some(dst) = intern.params.index(&ParamKind::ParamGiven { param }) {
+                builder.params[dst] = BuilderVal::Eager(cx.const_bool(false));
+            }
+        }
+    }
+
     pub fn setup_model_prototype(&self) -> &'ll llvm::Value {
         let cx = &self.cx;
         let name = &format!("setup_model_{}", &self.module.sym);
@@ -115,6 +137,9 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
         }
 
+        Self::lock_params(cx, intern, &inst_data.locked_params, &mut builder);
+        Self::lock_params(cx, intern, &model_data.locked_params, &mut builder);
+
         let res = unsafe { llvm::LLVMGetParam(llfunc, 3) };
 
         let err_cap = unsafe { builder.alloca(cx.ty_int()) };
@@ -144,9 +169,8 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
         for (call_id, call) in intern.callbacks.iter_enumerated() {
             if let CallBackKind::ParamInfo(ParamInfoKind::Invalid, param) = call {
                 if !self.module.info.params[param].is_instance {
-                    let id =
-                        model_data.params.get_index_of(param).unwrap() + inst_data.params.len();
-                    let err_param = cx.const_unsigned_int(id as u32);
+                    let id = model_data.param_id(inst_data, *param);
+                    let err_param = cx.const_unsigned_int(id);
                     let cb = CallbackFun::Prebuilt(BuiltCallbackFun {
                         fun_ty: invalid_param_err.0,
                         fun: invalid_param_err.1,
@@ -186,6 +210,10 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
         }
 
         builder.select_bb(exit_bb);
+        if let Some(locked) = LockedParams::new(cx, self.module, inst_data, model_data) {
+            let err = [err_ptr, err_len, err_cap];
+            unsafe { locked.report(cx, builder.fun, model, err, builder.llbuilder) };
+        }
         unsafe { 
             builder.ret_void() 
         }
@@ -292,6 +320,9 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
         }
 
+        Self::lock_params(cx, intern, &inst_data.locked_params, &mut builder);
+        Self::lock_params(cx, intern, &model_data.locked_params, &mut builder);
+
         if let Some(dst) = intern.params.index(&ParamKind::Temperature) {
             builder.params[dst] = BuilderVal::Eager(temperature)
         }
@@ -430,6 +461,11 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
             }
         }
 
+        if let Some(locked) = LockedParams::new(cx, module, inst_data, model_data) {
+            let err = [err_ptr, err_len, err_cap];
+            unsafe { locked.report(cx, builder.fun, instance, err, builder.llbuilder) };
+        }
+
         unsafe { 
             builder.ret_void() 
         }
@@ -179,12 +179,19 @@ void set_ret_flag_finish(int *flags) { *flags |= EVAL_RET_FLAG_FINISH; }
 
 void set_ret_flag_stop(int *flags) { *flags |= EVAL_RET_FLAG_STOP; }
 
+void set_ret_flag_reject(int *flags) { *flags |= EVAL_RET_FLAG_REJECT; }
+
 double store_lim(void *sim_info_, int idx, double val) {
   OsdiSimInfo *sim_info = (OsdiSimInfo *)sim_info_;
   sim_info->next_state[idx] = val;
   return val;
 }
 
+void store_state(void *sim_info_, int idx, double val) {
+  OsdiSimInfo *sim_info = (OsdiSimInfo *)sim_info_;
+  sim_info->next_state[idx] = val;
+}
+
 int analysis(void *sim_info_, char *name) {
   OsdiSimInfo *sim_info = (OsdiSimInfo *)sim_info_;
   uint32_t flags = sim_info->flags;
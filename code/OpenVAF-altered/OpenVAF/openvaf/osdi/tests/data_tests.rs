@@ -13,12 +13,24 @@ use target::spec::Target;
 
 fn test_compile(root_file: &Path) {
     let root_file = AbsPathBuf::assert(root_file.canonicalize().unwrap());
-    let db = CompilationDB::new_fs(root_file, &[], &[], &[]).unwrap();
+    let db = CompilationDB::new_fs(root_file, &[], &[], &[], &[]).unwrap();
     let modules = collect_modules(&db, false, &mut ConsoleSink::new(&db)).unwrap();
     let target = Target::host_target().unwrap();
     let back = LLVMBackend::new(&[], &target, "native".to_owned(), &[]);
     let emit = !stdx::IS_CI;
-    osdi::compile(&db, &modules, Utf8Path::new("foo.o"), &target, &back, emit, OptLevel::None, false, false, false, false);
+    osdi::compile(
+        &db,
+        &modules,
+        Utf8Path::new("foo.o"),
+        &target,
+        &back,
+        emit,
+        OptLevel::None,
+        false,
+        false,
+        false,
+        false,
+    );
 }
 
 fn integration_test(dir: &Path) -> Result {
@@ -0,0 +1,538 @@
+//! Differential verification of compiled OSDI libraries.
+//!
+//! Parameter elision and the other compile time assumptions specialize a module
+//! for fixed values. A specialized descriptor must still compute the same
+//! device as the original module does for these values. [`verify_libs`] checks
+//! this by evaluating the descriptors of a specialized (candidate) library side
+//! by side with the descriptors of the original (reference) library at
+//! randomized operating points and temperatures. Everything a simulator
+//! observes is compared: setup failures, residuals, Jacobian entries, noise
+//! densities and operating point variables.
+//!
+//! The candidate model is set up first. Every (model or instance) parameter
+//! whose value then differs from the default of the reference is copied to the
+//! reference before it is set up. Parameters elided to their default value are
+//! not copied, so they are not considered given by the reference.
+
+use std::ffi::CStr;
+use std::fmt::{self, Display};
+
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+
+use crate::load::{
+    load_osdi_lib, OsdiDescriptor, OsdiInstance, OsdiModel, ParamValue, SimParams,
+    EVAL_RET_FLAG_FATAL, PARA_KIND_MASK, PARA_KIND_OPVAR,
+};
+use crate::system::DenseSystem;
+
+pub mod load;
+mod system;
+
+#[derive(Debug, Clone)]
+pub struct VerifyOpts {
+    /// The number of (randomized) operating points evaluated per descriptor
+    pub points: usize,
+    pub reltol: f64,
+    pub abstol: f64,
+    /// Node voltages are drawn uniformly from `[-max_voltage, max_voltage]`
+    pub max_voltage: f64,
+    /// Temperatures (in Kelvin) are drawn uniformly from this (inclusive) range
+    pub temperature: (f64, f64),
+    /// The frequency at which noise densities are compared
+    pub noise_freq: f64,
+    /// `$simparam` values passed to both libraries
+    pub simparams: Vec<(String, f64)>,
+    /// Parameters set to the same value for both models before the setup
+    pub params: Vec<(String, f64)>,
+    pub seed: u64,
+    /// Stop verifying a descriptor after this many mismatches
+    pub max_mismatches: usize,
+}
+
+impl Default for VerifyOpts {
+    fn default() -> VerifyOpts {
+        VerifyOpts {
+            points: 100,
+            reltol: 1e-6,
+            abstol: 1e-12,
+            max_voltage: 1.0,
+            temperature: (250.0, 400.0),
+            noise_freq: 1e3,
+            simparams: Vec::new(),
+            params: Vec::new(),
+            seed: 0x6f70656e766166,
+            max_mismatches: 10,
+        }
+    }
+}
+
+impl VerifyOpts {
+    fn is_close(&self, reference: f64, candidate: f64) -> bool {
+        if reference == candidate || reference.is_nan() && candidate.is_nan() {
+            return true;
+        }
+        let tol = self.abstol + self.reltol * reference.abs().max(candidate.abs());
+        (reference - candidate).abs() <= tol
+    }
+}
+
+/// The conditions under which a [`Mismatch`] occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiasPoint {
+    /// `None` if the mismatch occurred during the model setup
+    pub temperature: Option<f64>,
+    /// The voltages of all nodes, empty if the mismatch occurred during setup
+    pub voltages: Vec<(String, f64)>,
+}
+
+impl Display for BiasPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(temperature) = self.temperature else {
+            return write!(f, "during the model setup");
+        };
+        write!(f, "at T = {temperature} K")?;
+        for (node, voltage) in &self.voltages {
+            write!(f, ", V({node}) = {voltage}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    /// `quantity` (for example the residual of a node) differs
+    Value { quantity: String, reference: f64, candidate: f64 },
+    /// `stage` failed for only one of the descriptors
+    Failure { stage: &'static str, reference: Option<String>, candidate: Option<String> },
+    /// Different internal nodes remain after collapsing
+    Nodes { reference: Vec<String>, candidate: Vec<String> },
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Value { quantity, reference, candidate } => {
+                write!(f, "{quantity} is {candidate:e} instead of {reference:e}")
+            }
+            Difference::Failure { stage, reference: Some(err), candidate: None } => {
+                write!(f, "{stage} succeeds but fails for the original: {err}")
+            }
+            Difference::Failure { stage, candidate: Some(err), .. } => {
+                write!(f, "{stage} fails but succeeds for the original: {err}")
+            }
+            Difference::Failure { stage, .. } => write!(f, "{stage} differs"),
+            Difference::Nodes { reference, candidate } => write!(
+                f,
+                "the nodes are [{}] instead of [{}]",
+                candidate.join(", "),
+                reference.join(", ")
+            ),
+        }
+    }
+}
+
+/// The value of a parameter of the models a [`Mismatch`] occurred for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamSetting {
+    Real(f64),
+    Int(i32),
+    Str(String),
+}
+
+impl Display for ParamSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamSetting::Real(val) => write!(f, "{val}"),
+            ParamSetting::Int(val) => write!(f, "{val}"),
+            ParamSetting::Str(val) => write!(f, "\"{val}\""),
+        }
+    }
+}
+
+/// A difference between a descriptor and the descriptor of the original module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub descriptor: String,
+    pub difference: Difference,
+    pub point: BiasPoint,
+    /// The parameters that were set for both models ([`VerifyOpts::params`]) and
+    /// those copied from the candidate because they differ from the reference defaults
+    pub params: Vec<(String, ParamSetting)>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} {}", self.descriptor, self.difference, self.point)?;
+        for (i, (name, val)) in self.params.iter().enumerate() {
+            let sep = if i == 0 { " with" } else { "," };
+            write!(f, "{sep} {name} = {val}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Verifies that each descriptor of the library `candidate` computes the same
+/// device as the corresponding descriptor of the library `reference`. A
+/// reference descriptor corresponds to all candidate descriptors with the
+/// same name or whose name starts with its name followed by an underscore
+/// (the names of the descriptors of elision sets).
+///
+/// # Safety
+///
+/// Both libraries are loaded (and never unloaded), they must be valid OSDI
+/// libraries compiled by OpenVAF.
+pub unsafe fn verify_libs(
+    reference: &Utf8Path,
+    candidate: &Utf8Path,
+    opts: &VerifyOpts,
+) -> Result<Vec<Mismatch>> {
+    let references = load_osdi_lib(reference)?;
+    let candidates = load_osdi_lib(candidate)?;
+    let reference_lib = reference;
+
+    let mut mismatches = Vec::new();
+    for candidate in candidates {
+        let name = candidate.name();
+        let reference = references
+            .iter()
+            .filter(|reference| {
+                let prefix = reference.name();
+                name == prefix
+                    || name.strip_prefix(prefix).is_some_and(|suffix| suffix.starts_with('_'))
+            })
+            .max_by_key(|reference| reference.name().len());
+        let Some(reference) = reference else {
+            bail!("{reference_lib}: no descriptor corresponds to '{name}'")
+        };
+        let mut verifier = Verifier::new(reference, candidate, opts);
+        verifier.run();
+        mismatches.append(&mut verifier.mismatches);
+    }
+    Ok(mismatches)
+}
+
+const REFERENCE: &CStr = c"reference";
+const CANDIDATE: &CStr = c"candidate";
+
+/// A small xorshift* generator, the sweep only needs to be reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // the state of xorshift must not be zero
+        Rng(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    fn uniform(&mut self, lo: f64, hi: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        lo + (hi - lo) * unit
+    }
+}
+
+struct Verifier<'a> {
+    reference: &'static OsdiDescriptor,
+    candidate: &'static OsdiDescriptor,
+    opts: &'a VerifyOpts,
+    sim_params: SimParams,
+    rng: Rng,
+    mismatches: Vec<Mismatch>,
+    /// See [`Mismatch::params`]
+    params: Vec<(String, ParamSetting)>,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(
+        reference: &'static OsdiDescriptor,
+        candidate: &'static OsdiDescriptor,
+        opts: &'a VerifyOpts,
+    ) -> Verifier<'a> {
+        Verifier {
+            reference,
+            candidate,
+            opts,
+            sim_params: SimParams::new(&opts.simparams),
+            rng: Rng::new(opts.seed),
+            mismatches: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, difference: Difference, point: &BiasPoint) {
+        self.mismatches.push(Mismatch {
+            descriptor: self.candidate.name().to_owned(),
+            difference,
+            point: point.clone(),
+            params: self.params.clone(),
+        })
+    }
+
+    /// Records that the parameter `name` was set to `val` for the reference model
+    fn record_param(&mut self, name: &str, val: ParamSetting) {
+        match self.params.iter_mut().find(|(param, _)| param == name) {
+            Some((_, dst)) => *dst = val,
+            None => self.params.push((name.to_owned(), val)),
+        }
+    }
+
+    fn compare(
+        &mut self,
+        quantity: impl FnOnce() -> String,
+        reference: f64,
+        candidate: f64,
+        point: &BiasPoint,
+    ) {
+        if !self.opts.is_close(reference, candidate) {
+            let quantity = quantity();
+            self.report(Difference::Value { quantity, reference, candidate }, point)
+        }
+    }
+
+    /// Reports a failure of `stage` if it failed for only one descriptor.
+    /// Returns whether both succeeded.
+    fn check_stage<T>(
+        &mut self,
+        stage: &'static str,
+        reference: &Result<T>,
+        candidate: &Result<T>,
+        point: &BiasPoint,
+    ) -> bool {
+        match (reference, candidate) {
+            (Ok(_), Ok(_)) => return true,
+            (Err(_), Err(_)) => (),
+            _ => {
+                let err = |res: &Result<T>| res.as_ref().err().map(|err| err.to_string());
+                let difference = Difference::Failure {
+                    stage,
+                    reference: err(reference),
+                    candidate: err(candidate),
+                };
+                self.report(difference, point)
+            }
+        }
+        false
+    }
+
+    fn done(&self) -> bool {
+        self.mismatches.len() >= self.opts.max_mismatches
+    }
+
+    fn run(&mut self) {
+        let reference = self.reference.new_model();
+        let candidate = self.candidate.new_model();
+        let opts = self.opts;
+        for (name, val) in &opts.params {
+            for model in [&reference, &candidate] {
+                if let Some(param) = model.descriptor.param_id(name) {
+                    model.write_param(param, ParamValue::Real(*val));
+                }
+            }
+            self.record_param(name, ParamSetting::Real(*val));
+        }
+
+        let point = BiasPoint { temperature: None, voltages: Vec::new() };
+        let candidate_res = candidate.process_params(CANDIDATE, &mut self.sim_params);
+        let mut reference_res = reference.process_params(REFERENCE, &mut self.sim_params);
+        if candidate_res.is_ok()
+            && reference_res.is_ok()
+            && self.copy_params(&reference, &candidate)
+        {
+            reference_res = reference.process_params(REFERENCE, &mut self.sim_params);
+        }
+        if !self.check_stage("setup_model", &reference_res, &candidate_res, &point) {
+            return;
+        }
+
+        for _ in 0..self.opts.points {
+            if self.done() {
+                break;
+            }
+            let (lo, hi) = self.opts.temperature;
+            let temperature = self.rng.uniform(lo, hi);
+            self.verify_point(&reference, &candidate, temperature);
+        }
+    }
+
+    /// Copies all parameters from the candidate model that differ from the
+    /// reference model. Returns whether any parameter was copied.
+    fn copy_params(&mut self, reference: &OsdiModel, candidate: &OsdiModel) -> bool {
+        let mut changed = false;
+        for param in 0..self.candidate.num_params {
+            let Some(val) = candidate.read_param(param) else { continue };
+            let name = self.candidate.param_name(param);
+            let Some(ref_param) = self.reference.param_id(name) else { continue };
+            let differs = match (reference.read_param(ref_param), val) {
+                // SAFETY: OSDI strings are valid null terminated strings
+                (Some(ParamValue::Str(old)), ParamValue::Str(new)) => unsafe {
+                    CStr::from_ptr(old) != CStr::from_ptr(new)
+                },
+                (old, new) => old != Some(new),
+            };
+            if differs {
+                reference.write_param(ref_param, val);
+                let val = match val {
+                    ParamValue::Real(val) => ParamSetting::Real(val),
+                    ParamValue::Int(val) => ParamSetting::Int(val),
+                    // SAFETY: OSDI strings are valid null terminated strings
+                    ParamValue::Str(val) => unsafe {
+                        ParamSetting::Str(CStr::from_ptr(val).to_string_lossy().into_owned())
+                    },
+                };
+                self.record_param(name, val);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn verify_point(&mut self, reference: &OsdiModel, candidate: &OsdiModel, temperature: f64) {
+        let mut point = BiasPoint { temperature: Some(temperature), voltages: Vec::new() };
+        let ref_inst = reference.new_instance();
+        let cand_inst = candidate.new_instance();
+        let ref_res =
+            ref_inst.process_params(reference, REFERENCE, temperature, &mut self.sim_params);
+        let cand_res =
+            cand_inst.process_params(candidate, CANDIDATE, temperature, &mut self.sim_params);
+        if !self.check_stage("setup_instance", &ref_res, &cand_res, &point) {
+            return;
+        }
+
+        let mut ref_sys = DenseSystem::new(&ref_inst, &ref_res.unwrap());
+        let mut cand_sys = DenseSystem::new(&cand_inst, &cand_res.unwrap());
+        let mut ref_nodes = ref_sys.nodes.clone();
+        let mut cand_nodes = cand_sys.nodes.clone();
+        ref_nodes.sort_unstable();
+        cand_nodes.sort_unstable();
+        if ref_nodes != cand_nodes {
+            let names = |nodes: &[&str]| nodes[1..].iter().map(|node| node.to_string()).collect();
+            let difference = Difference::Nodes {
+                reference: names(&ref_sys.nodes),
+                candidate: names(&cand_sys.nodes),
+            };
+            self.report(difference, &point);
+            return;
+        }
+
+        // the node sets are equal so every candidate node also exists in the reference
+        let node_map: Vec<_> =
+            cand_sys.nodes.iter().map(|node| ref_sys.node(node).unwrap()).collect();
+        for (node, &ref_node) in node_map.iter().enumerate().skip(1) {
+            let voltage = self.rng.uniform(-self.opts.max_voltage, self.opts.max_voltage);
+            cand_sys.set_voltage(node, voltage);
+            ref_sys.set_voltage(ref_node, voltage);
+            point.voltages.push((cand_sys.nodes[node].to_owned(), voltage));
+        }
+
+        let freq = self.opts.noise_freq;
+        let ref_flags = ref_sys.eval(&ref_inst, reference, REFERENCE, &mut self.sim_params, freq);
+        let cand_flags =
+            cand_sys.eval(&cand_inst, candidate, CANDIDATE, &mut self.sim_params, freq);
+        let fatal = |flags: u32| {
+            if flags & EVAL_RET_FLAG_FATAL != 0 {
+                bail!("Verilog-A $fatal was called")
+            }
+            Ok(())
+        };
+        if !self.check_stage("eval", &fatal(ref_flags), &fatal(cand_flags), &point) {
+            return;
+        }
+
+        for (node, &ref_node) in node_map.iter().enumerate().skip(1) {
+            let name = cand_sys.nodes[node];
+            self.compare(
+                || format!("resistive residual of {name}"),
+                ref_sys.residual_resist[ref_node],
+                cand_sys.residual_resist[node],
+                &point,
+            );
+            self.compare(
+                || format!("reactive residual of {name}"),
+                ref_sys.residual_react[ref_node],
+                cand_sys.residual_react[node],
+                &point,
+            );
+        }
+
+        for (row, &ref_row) in node_map.iter().enumerate().skip(1) {
+            for (column, &ref_column) in node_map.iter().enumerate().skip(1) {
+                let (ref_resist, ref_react) = ref_sys.jacobian(ref_row, ref_column);
+                let (cand_resist, cand_react) = cand_sys.jacobian(row, column);
+                let entry = || format!("({}, {})", cand_sys.nodes[row], cand_sys.nodes[column]);
+                let resist = || format!("resistive Jacobian entry {}", entry());
+                self.compare(resist, ref_resist, cand_resist, &point);
+                let react = || format!("reactive Jacobian entry {}", entry());
+                self.compare(react, ref_react, cand_react, &point);
+            }
+        }
+
+        self.compare_noise(&ref_sys, &cand_sys, &point);
+        self.compare_opvars(&ref_inst, reference, &cand_inst, candidate, &point);
+    }
+
+    /// Compares the noise densities of all sources with the same name. The
+    /// sources without a name are compared in order.
+    fn compare_noise(
+        &mut self,
+        reference: &DenseSystem,
+        candidate: &DenseSystem,
+        point: &BiasPoint,
+    ) {
+        let src_name = |descriptor: &OsdiDescriptor, i: usize| {
+            // SAFETY: the descriptor is assumed valid
+            unsafe { load::osdi_str(descriptor.noise()[i].name) }
+        };
+        let mut used = vec![false; reference.noise.len()];
+        for (i, &cand_val) in candidate.noise.iter().enumerate() {
+            let name = src_name(self.candidate, i);
+            let ref_src = (0..reference.noise.len())
+                .find(|&j| !used[j] && src_name(self.reference, j) == name);
+            let ref_val = match ref_src {
+                Some(j) => {
+                    used[j] = true;
+                    reference.noise[j]
+                }
+                None => 0.0,
+            };
+            let quantity = || format!("noise density of source '{name}'");
+            self.compare(quantity, ref_val, cand_val, point);
+        }
+        for (j, &ref_val) in reference.noise.iter().enumerate() {
+            if !used[j] {
+                let quantity =
+                    || format!("noise density of source '{}'", src_name(self.reference, j));
+                self.compare(quantity, ref_val, 0.0, point);
+            }
+        }
+    }
+
+    fn compare_opvars(
+        &mut self,
+        ref_inst: &OsdiInstance,
+        reference: &OsdiModel,
+        cand_inst: &OsdiInstance,
+        candidate: &OsdiModel,
+        point: &BiasPoint,
+    ) {
+        let num_params = self.candidate.num_params;
+        for opvar in num_params..num_params + self.candidate.num_opvars {
+            let info = &self.candidate.params()[opvar as usize];
+            if info.flags & PARA_KIND_MASK != PARA_KIND_OPVAR {
+                continue;
+            }
+            let name = self.candidate.param_name(opvar);
+            let Some(ref_opvar) = self.reference.param_id(name) else { continue };
+            let Some(cand_val) = cand_inst.read_opvar(candidate, opvar) else { continue };
+            let Some(ref_val) = ref_inst.read_opvar(reference, ref_opvar) else { continue };
+            self.compare(|| format!("opvar {name}"), ref_val, cand_val, point);
+        }
+    }
+}
@@ -1,6 +1,9 @@
+//! A minimal OSDI host that loads compiled libraries and manages the model and
+//! instance data of their descriptors.
+
 use std::alloc::{alloc_zeroed, handle_alloc_error, Layout};
 use std::cell::Cell;
-use std::ffi::{c_char, CStr};
+use std::ffi::{c_char, CStr, CString};
 use std::fmt::Debug;
 use std::mem::{align_of, swap};
 use std::panic::catch_unwind;
@@ -12,8 +15,6 @@ use bitflags::bitflags;
 use camino::Utf8Path;
 use libc::c_void;
 use libloading::Library;
-use stdx::format_to;
-use stdx::iter::zip;
 
 #[allow(warnings)]
 mod osdi_0_4;
@@ -21,6 +22,11 @@ mod osdi_0_4;
 pub use osdi_0_4::*;
 
 impl OsdiDescriptor {
+    pub fn name(&self) -> &'static str {
+        // SAFETY: the descriptor is assumed valid
+        unsafe { osdi_str(self.name) }
+    }
+
     pub fn nodes(&self) -> &[OsdiNode] {
         // # SAFETY: OsdiDescriptor can only be constructed from FFI and is assumed to contain
         // valid data
@@ -30,24 +36,55 @@ impl OsdiDescriptor {
     pub fn params(&self) -> &[OsdiParamOpvar] {
         // # SAFETY: OsdiDescriptor can only be constructed from FFI and is assumed to contain
         // valid data
-        unsafe { slice::from_raw_parts(self.param_opvar, self.num_params as usize) }
+        unsafe {
+            let len = self.num_params + self.num_opvars;
+            slice::from_raw_parts(self.param_opvar, len as usize)
+        }
+    }
+
+    pub fn param_name(&self, id: u32) -> &'static str {
+        // SAFETY: the descriptor is assumed valid
+        unsafe { osdi_str(*self.params()[id as usize].name) }
+    }
+
+    pub fn param_id(&self, name: &str) -> Option<u32> {
+        let pos = (0..self.params().len() as u32).position(|id| self.param_name(id) == name);
+        pos.map(|pos| pos as u32)
     }
 
     pub fn collapsible(&self) -> &[OsdiNodePair] {
-        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        // SAFETY: the descriptor is assumed valid
         unsafe { slice::from_raw_parts(self.collapsible, self.num_collapsible as usize) }
     }
 
     pub fn noise(&self) -> &[OsdiNoiseSource] {
-        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        // SAFETY: the descriptor is assumed valid
         unsafe { slice::from_raw_parts(self.noise_sources, self.num_noise_src as usize) }
     }
 
     pub fn matrix_entries(&self) -> &[OsdiJacobianEntry] {
-        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        // SAFETY: the descriptor is assumed valid
         unsafe { slice::from_raw_parts(self.jacobian_entries, self.num_jacobian_entries as usize) }
     }
 
+    /// The temperature and `$simparam` values the library was compiled for.
+    pub fn sim_assumptions(&self) -> Result<SimAssumptions> {
+        // SAFETY: the descriptor is assumed valid
+        unsafe {
+            let table = &*self.sim_assumptions;
+            if table.version < OSDI_SIM_ASSUMPTIONS_VERSION {
+                bail!("unsupported sim assumptions version {}", table.version)
+            }
+            let temperature = table.has_temperature.then_some(table.temperature);
+            let len = table.num_simparams as usize;
+            let names = slice::from_raw_parts(table.simparam_names, len);
+            let values = slice::from_raw_parts(table.simparam_values, len);
+            let simparams =
+                names.iter().zip(values).map(|(&name, &val)| (osdi_str(name), val)).collect();
+            Ok((temperature, simparams))
+        }
+    }
+
     pub fn check_init_result(&self, res: OsdiInitInfo) -> Result<()> {
         if (res.flags & EVAL_RET_FLAG_FATAL) != 0 {
             bail!("Verilog-A $fatal was called")
@@ -62,12 +99,13 @@ impl OsdiDescriptor {
                 match err.code {
                     INIT_ERR_OUT_OF_BOUNDS => {
                         let param = unsafe { err.payload.parameter_id };
-                        let param = unsafe { *self.params()[param as usize].name };
-                        let param = unsafe { osdi_str(param) };
-                        format_to!(msg, "value supplied for parameter '{param}' is out of bounds\n")
+                        let param = self.param_name(param);
+                        msg.push_str(&format!(
+                            "value supplied for parameter '{param}' is out of bounds\n"
+                        ))
                     }
 
-                    code => format_to!(msg, "unknown error: {code}\n"),
+                    code => msg.push_str(&format!("unknown error: {code}\n")),
                 }
             }
 
@@ -94,7 +132,10 @@ impl Drop for OsdiInitInfo {
     }
 }
 
-pub(super) unsafe fn osdi_str(raw: *mut c_char) -> &'static str {
+/// # Safety
+///
+/// `raw` must point to a nul terminated string that is never freed.
+pub unsafe fn osdi_str(raw: *mut c_char) -> &'static str {
     CStr::from_ptr(raw).to_str().expect("All OSDI strings must be encoded in UTF-8")
 }
 
@@ -103,7 +144,7 @@ type max_align_t = u128;
 const MAX_ALIGN: usize = align_of::<max_align_t>();
 
 fn aligned_size(size: usize) -> usize {
-    (size + (MAX_ALIGN - 1)) / MAX_ALIGN
+    size.div_ceil(MAX_ALIGN)
 }
 
 fn max_align_layout(size: usize) -> Layout {
@@ -136,6 +177,51 @@ unsafe fn dealloc(ptr: *mut c_void, size: usize) {
     std::alloc::dealloc(ptr as *mut u8, layout)
 }
 
+/// The `$simparam` values passed to the model during setup and eval.
+pub struct SimParams {
+    _names: Vec<CString>,
+    names: Vec<*mut c_char>,
+    vals: Vec<f64>,
+    names_str: [*mut c_char; 1],
+}
+
+impl SimParams {
+    pub fn new(params: &[(String, f64)]) -> SimParams {
+        let owned: Vec<_> = params
+            .iter()
+            .map(|(name, _)| CString::new(name.as_str()).expect("simparam names contain no nul"))
+            .collect();
+        // both arrays are terminated by a null pointer
+        let mut names: Vec<_> = owned.iter().map(|name| name.as_ptr() as *mut c_char).collect();
+        names.push(ptr::null_mut());
+        let vals = params.iter().map(|(_, val)| *val).collect();
+        SimParams { _names: owned, names, vals, names_str: [ptr::null_mut()] }
+    }
+
+    pub fn raw(&mut self) -> OsdiSimParas {
+        OsdiSimParas {
+            names: self.names.as_mut_ptr(),
+            vals: self.vals.as_mut_ptr(),
+            names_str: self.names_str.as_mut_ptr(),
+            vals_str: ptr::null_mut(),
+        }
+    }
+}
+
+impl Default for SimParams {
+    fn default() -> SimParams {
+        SimParams::new(&[])
+    }
+}
+
+/// The value of a (scalar) parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamValue {
+    Real(f64),
+    Int(i32),
+    Str(*mut c_char),
+}
+
 pub struct OsdiModel {
     pub descriptor: &'static OsdiDescriptor,
     pub data: *mut c_void,
@@ -149,17 +235,11 @@ impl Drop for OsdiModel {
 }
 
 impl OsdiModel {
-    pub fn process_params(&self) -> Result<()> {
-        let mut sim_params = OsdiSimParas {
-            names: &mut ptr::null_mut(),
-            vals: ptr::null_mut(),
-            names_str: &mut ptr::null_mut(),
-            vals_str: ptr::null_mut(),
-        };
-
+    pub fn process_params(&self, handle: &CStr, sim_params: &mut SimParams) -> Result<()> {
+        let mut sim_params = sim_params.raw();
         let mut res = OsdiInitInfo { flags: 0, num_errors: 0, errors: ptr::null_mut() };
         self.descriptor.setup_model(
-            b"foo\0".as_ptr() as *mut c_void,
+            handle.as_ptr() as *mut c_void,
             self.data,
             &mut sim_params,
             &mut res,
@@ -167,13 +247,32 @@ impl OsdiModel {
         self.descriptor.check_init_result(res)
     }
 
-    pub fn set_real_param(&self, param: u32, val: f64) {
-        let ptr = self.descriptor.access(ptr::null_mut(), self.data, param, ACCESS_FLAG_SET);
-        let ptr = ptr as *mut f64;
+    /// Reads the value of the model (or model level default of the instance)
+    /// parameter `param`. Returns `None` for array parameters.
+    pub fn read_param(&self, param: u32) -> Option<ParamValue> {
+        let info = &self.descriptor.params()[param as usize];
+        if info.len != 0 {
+            return None;
+        }
+        let ptr = self.descriptor.access(ptr::null_mut(), self.data, param, ACCESS_FLAG_READ);
         if ptr.is_null() {
-            unreachable!("invalid parameter access")
+            return None;
         }
-        unsafe { ptr.write(val) };
+        // SAFETY: access returns a pointer to a value of the parameters type
+        let val = unsafe {
+            match info.flags & PARA_TY_MASK {
+                PARA_TY_REAL => ParamValue::Real(*(ptr as *const f64)),
+                PARA_TY_INT => ParamValue::Int(*(ptr as *const i32)),
+                _ => ParamValue::Str(*(ptr as *const *mut c_char)),
+            }
+        };
+        Some(val)
+    }
+
+    pub fn write_param(&self, param: u32, val: ParamValue) {
+        let ptr = self.descriptor.access(ptr::null_mut(), self.data, param, ACCESS_FLAG_SET);
+        // SAFETY: access returns a pointer to a value of the parameters type
+        unsafe { write_param(ptr, val) }
     }
 
     pub fn new_instance(&self) -> OsdiInstance {
@@ -184,6 +283,20 @@ impl OsdiModel {
     }
 }
 
+/// # Safety
+///
+/// `ptr` must be null or point to a value of the type of `val`.
+unsafe fn write_param(ptr: *mut c_void, val: ParamValue) {
+    if ptr.is_null() {
+        unreachable!("invalid parameter access")
+    }
+    match val {
+        ParamValue::Real(val) => (ptr as *mut f64).write(val),
+        ParamValue::Int(val) => (ptr as *mut i32).write(val),
+        ParamValue::Str(val) => (ptr as *mut *mut c_char).write(val),
+    }
+}
+
 pub struct OsdiInstance {
     pub descriptor: &'static OsdiDescriptor,
     pub data: *mut c_void,
@@ -206,6 +319,18 @@ impl OsdiInstance {
         }
     }
 
+    /// # Safety
+    ///
+    /// `dst` must remain valid as long as the reactive Jacobian of the instance is loaded.
+    pub unsafe fn set_matrix_ptr_react(&self, entry: &OsdiJacobianEntry, dst: *mut f64) {
+        if entry.react_ptr_off == u32::MAX {
+            return;
+        }
+        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        let ptr: *mut *mut f64 = (self.data as *mut u8).add(entry.react_ptr_off as usize).cast();
+        ptr.write(dst)
+    }
+
     pub fn node_mapping(&self) -> &[Cell<u32>] {
         let ptr = self.data as *mut u8;
         // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
@@ -224,7 +349,10 @@ impl OsdiInstance {
         }
     }
 
-    pub fn collapse_nodes(&self, connected_terminals: u32) -> Vec<u32> {
+    /// Collapses the nodes of the instance (all terminals are connected) and
+    /// returns the (descriptor) index of each internal node that remains.
+    pub fn collapse_nodes(&self) -> Vec<u32> {
+        let connected_terminals = self.descriptor.num_terminals;
         let collapsed = self.collapsed();
         let node_mapping = self.node_mapping();
         let collapsible = self.descriptor.collapsible();
@@ -236,7 +364,7 @@ impl OsdiInstance {
             node_mapping.set(node as u32)
         }
 
-        for (candidate, is_collapsed) in zip(collapsible, collapsed) {
+        for (candidate, is_collapsed) in collapsible.iter().zip(collapsed) {
             if !is_collapsed {
                 continue;
             }
@@ -282,45 +410,58 @@ impl OsdiInstance {
         back_map
     }
 
+    /// Runs the instance setup with all terminals connected and returns the
+    /// internal nodes that were not collapsed (see [`OsdiInstance::collapse_nodes`]).
     pub fn process_params(
-        &mut self,
+        &self,
         model: &OsdiModel,
-        connected_terminals: u32,
+        handle: &CStr,
         temp: f64,
+        sim_params: &mut SimParams,
     ) -> Result<Vec<u32>> {
-        let mut sim_params = OsdiSimParas {
-            names: &mut ptr::null_mut(),
-            vals: ptr::null_mut(),
-            names_str: &mut ptr::null_mut(),
-            vals_str: ptr::null_mut(),
-        };
-
+        let mut sim_params = sim_params.raw();
         let mut res = OsdiInitInfo { flags: 0, num_errors: 0, errors: ptr::null_mut() };
         self.descriptor.setup_instance(
-            b"foo\0".as_ptr() as *mut c_void,
+            handle.as_ptr() as *mut c_void,
             self.data,
             model.data,
             temp,
-            connected_terminals,
+            self.descriptor.num_terminals,
             &mut sim_params,
             &mut res,
         );
         self.descriptor.check_init_result(res)?;
-        let internal_nodes = self.collapse_nodes(connected_terminals);
-        Ok(internal_nodes)
-    }
-
-    // pub fn set_real_param(&mut self, param: u32, val: f64) {
-    //     let ptr =
-    //         unsafe { self.descriptor.access(ptr::null_mut(), self.data, param, ACCESS_FLAG_SET) };
-    //     let ptr = ptr as *mut f64;
-    //     if ptr.is_null() {
-    //         unreachable!("invalid parameter access")
-    //     }
-    //     unsafe { ptr.write(val) };
-    // }
+        Ok(self.collapse_nodes())
+    }
+
+    /// Sets the instance parameter `param` (overriding the model level default).
+    pub fn write_param(&self, model: &OsdiModel, param: u32, val: ParamValue) {
+        let flags = ACCESS_FLAG_SET | ACCESS_FLAG_INSTANCE;
+        let ptr = self.descriptor.access(self.data, model.data, param, flags);
+        // SAFETY: access returns a pointer to a value of the parameters type
+        unsafe { write_param(ptr, val) }
+    }
+
+    /// Reads the operating point variable `opvar`. Returns `None` if it's not real valued.
+    pub fn read_opvar(&self, model: &OsdiModel, opvar: u32) -> Option<f64> {
+        let info = &self.descriptor.params()[opvar as usize];
+        if info.len != 0 || info.flags & PARA_TY_MASK != PARA_TY_REAL {
+            return None;
+        }
+        let ptr = self.descriptor.access(self.data, model.data, opvar, ACCESS_FLAG_INSTANCE);
+        if ptr.is_null() {
+            return None;
+        }
+        // SAFETY: access returns a pointer to a value of the opvars type
+        Some(unsafe { *(ptr as *const f64) })
+    }
 }
 
+/// Loads the OSDI library at `path`. The library is never unloaded.
+///
+/// # Safety
+///
+/// Loading a library runs arbitrary code, `path` must point to a valid OSDI library.
 pub unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<&'static [OsdiDescriptor]> {
     let lib = Library::new(path)?;
     let lib = Box::leak(Box::new(lib));
@@ -329,7 +470,7 @@ pub unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<&'static [OsdiDescriptor]
     let minor_version: &u32 = *lib.get(b"OSDI_VERSION_MINOR\0")?;
 
     if *major_version != 0 || *minor_version != 4 {
-        bail!("invalid version v{major_version}.{minor_version}",);
+        bail!("{path}: invalid version v{major_version}.{minor_version}",);
     }
 
     let num_descriptors: &u32 = *lib.get(b"OSDI_NUM_DESCRIPTORS\0")?;
@@ -359,6 +500,9 @@ pub unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<&'static [OsdiDescriptor]
     Ok(descriptors)
 }
 
+/// The temperature and `$simparam` values a library was compiled for.
+pub type SimAssumptions = (Option<f64>, Vec<(&'static str, f64)>);
+
 unsafe extern "C" fn osdi_log(handle: *mut c_void, msg: *const c_char, lvl: u32) {
     let _ = catch_unwind(|| osdi_log_impl(handle, msg, lvl));
 }
@@ -371,7 +515,7 @@ unsafe extern "C" fn osdi_pnjlim(
     vt: f64,
     vcrit: f64,
 ) -> f64 {
-    if let Ok((res, check_)) = catch_unwind(|| osdi_pnjlim_impl(init, vnew, vold, vt, vcrit)) {
+    if let Ok((res, check_)) = catch_unwind(|| pnjlim(init, vnew, vold, vt, vcrit)) {
         if check_ {
             *check = true;
         }
@@ -381,12 +525,22 @@ unsafe extern "C" fn osdi_pnjlim(
     }
 }
 
-// an incorrect implementation of pnjlim that makes testing easy
-fn osdi_pnjlim_impl(init: bool, vnew: f64, vold: f64, vt: f64, vcrit: f64) -> (f64, bool) {
+/// The junction voltage limiting of SPICE (`DEVpnjlim`).
+fn pnjlim(init: bool, vnew: f64, vold: f64, vt: f64, vcrit: f64) -> (f64, bool) {
     if init {
-        (vcrit, true)
-    } else if vnew > vcrit && (vnew - vold).abs() > vt + vt {
-        (vold + (vnew - vold) / 2.0, true)
+        return (vcrit, true);
+    }
+    if vnew > vcrit && (vnew - vold).abs() > vt + vt {
+        if vold > 0.0 {
+            let arg = 1.0 + (vnew - vold) / vt;
+            if arg > 0.0 {
+                (vold + vt * arg.ln(), true)
+            } else {
+                (vcrit, true)
+            }
+        } else {
+            (vt * (vnew / vt).ln(), true)
+        }
     } else {
         (vnew, false)
     }
@@ -399,16 +553,16 @@ unsafe fn osdi_log_impl(handle: *mut c_void, msg: *const c_char, lvl: u32) {
 
     if (lvl & LOG_FMT_ERR) == 0 {
         match lvl & LOG_LVL_MASK {
-            LOG_LVL_DEBUG => println!("debug {instance} - {msg}"),
-            LOG_LVL_DISPLAY => println!("display {instance} - {msg}"),
-            LOG_LVL_INFO => println!("info {instance} - {msg}"),
-            LOG_LVL_WARN => println!("warn {instance} - {msg}"),
-            LOG_LVL_ERR => println!("err {instance} - {msg}"),
-            LOG_LVL_FATAL => println!("fatal {instance} - FATAL {msg}"),
-            _ => println!("{instance} - UNKNOWN_LOG_LVL {msg}"),
+            LOG_LVL_DEBUG => log::debug!("{instance} - {msg}"),
+            LOG_LVL_DISPLAY => log::info!("display {instance} - {msg}"),
+            LOG_LVL_INFO => log::info!("{instance} - {msg}"),
+            LOG_LVL_WARN => log::warn!("{instance} - {msg}"),
+            LOG_LVL_ERR => log::error!("{instance} - {msg}"),
+            LOG_LVL_FATAL => log::error!("{instance} - FATAL {msg}"),
+            _ => log::warn!("{instance} - UNKNOWN_LOG_LVL {msg}"),
         }
     } else {
-        println!("{instance} - failed to format\"{msg}\"")
+        log::error!("{instance} - failed to format\"{msg}\"")
     }
 }
 
@@ -426,7 +580,7 @@ impl Debug for OsdiDescriptor {
         }
 
         unsafe {
-            for param in self.params() {
+            for param in &self.params()[..self.num_params as usize] {
                 assert_eq!(param.len, 0);
                 w!("param ");
                 for i in 0..=param.num_alias {
@@ -550,5 +704,6 @@ bitflags! {
         const EVAL_RET_FLAG_FATAL = EVAL_RET_FLAG_FATAL;
         const EVAL_RET_FLAG_FINISH = EVAL_RET_FLAG_FINISH;
         const EVAL_RET_FLAG_STOP = EVAL_RET_FLAG_STOP;
+        const EVAL_RET_FLAG_REJECT = EVAL_RET_FLAG_REJECT;
     }
 }
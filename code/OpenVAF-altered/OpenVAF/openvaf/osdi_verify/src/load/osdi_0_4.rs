@@ -40,6 +40,7 @@ pub const EVAL_RET_FLAG_LIM: u32 = 1;
 pub const EVAL_RET_FLAG_FATAL: u32 = 2;
 pub const EVAL_RET_FLAG_FINISH: u32 = 4;
 pub const EVAL_RET_FLAG_STOP: u32 = 8;
+pub const EVAL_RET_FLAG_REJECT: u32 = 16;
 pub const LOG_LVL_MASK: u32 = 7;
 pub const LOG_LVL_DEBUG: u32 = 0;
 pub const LOG_LVL_DISPLAY: u32 = 1;
@@ -49,6 +50,7 @@ pub const LOG_LVL_ERR: u32 = 4;
 pub const LOG_LVL_FATAL: u32 = 5;
 pub const LOG_FMT_ERR: u32 = 16;
 pub const INIT_ERR_OUT_OF_BOUNDS: u32 = 1;
+pub const OSDI_SIM_ASSUMPTIONS_VERSION: u32 = 1;
 
 #[repr(C)]
 pub struct OsdiLimFunction {
@@ -124,6 +126,15 @@ pub struct OsdiNoiseSource {
     pub nodes: OsdiNodePair,
 }
 #[repr(C)]
+pub struct OsdiSimAssumptions {
+    pub version: u32,
+    pub has_temperature: bool,
+    pub temperature: f64,
+    pub num_simparams: u32,
+    pub simparam_names: *mut *mut c_char,
+    pub simparam_values: *mut f64,
+}
+#[repr(C)]
 #[non_exhaustive]
 pub struct OsdiDescriptor {
     pub name: *mut c_char,
@@ -173,6 +184,7 @@ pub struct OsdiDescriptor {
     pub inputs: *mut OsdiNodePair,
     pub load_jacobian_with_offset_resist: fn(*mut c_void, *mut c_void, usize),
     pub load_jacobian_with_offset_react: fn(*mut c_void, *mut c_void, usize),
+    pub sim_assumptions: *mut OsdiSimAssumptions,
 }
 impl OsdiDescriptor {
     pub fn access(
@@ -0,0 +1,128 @@
+use std::ffi::CStr;
+
+use libc::c_void;
+
+use crate::load::{
+    osdi_str, OsdiInstance, OsdiModel, OsdiSimInfo, SimParams, CALC_NOISE, CALC_OP,
+    CALC_REACT_JACOBIAN, CALC_REACT_RESIDUAL, CALC_RESIST_JACOBIAN, CALC_RESIST_RESIDUAL,
+};
+
+/// The (dense) equation system of a single instance. The unknowns are the
+/// terminals and the internal nodes that were not collapsed, index 0 is ground.
+pub(crate) struct DenseSystem {
+    pub nodes: Vec<&'static str>,
+    solve: Vec<f64>,
+    pub residual_resist: Vec<f64>,
+    pub residual_react: Vec<f64>,
+    /// Row major, rows are the residuals and columns the unknowns
+    pub jacobian_resist: Box<[f64]>,
+    pub jacobian_react: Box<[f64]>,
+    pub noise: Vec<f64>,
+    prev_state: Vec<f64>,
+    next_state: Vec<f64>,
+}
+
+impl DenseSystem {
+    /// Creates the system for `inst` and connects the nodes and matrix entries
+    /// of the instance to it. `internal_nodes` are the internal nodes
+    /// remaining after collapsing.
+    pub fn new(inst: &OsdiInstance, internal_nodes: &[u32]) -> DenseSystem {
+        let descriptor = inst.descriptor;
+        let terminals = &descriptor.nodes()[..descriptor.num_terminals as usize];
+        let internal_nodes = internal_nodes.iter().map(|&node| &descriptor.nodes()[node as usize]);
+        let mut nodes = vec!["gnd"];
+        // SAFETY: the descriptor is assumed valid
+        nodes.extend(
+            terminals.iter().chain(internal_nodes).map(|node| unsafe { osdi_str(node.name) }),
+        );
+
+        let len = nodes.len();
+        let mut system = DenseSystem {
+            nodes,
+            solve: vec![0.0; len],
+            residual_resist: vec![0.0; len],
+            residual_react: vec![0.0; len],
+            jacobian_resist: vec![0.0; len * len].into_boxed_slice(),
+            jacobian_react: vec![0.0; len * len].into_boxed_slice(),
+            noise: vec![0.0; descriptor.num_noise_src as usize],
+            prev_state: vec![0.0; descriptor.num_states as usize],
+            next_state: vec![0.0; descriptor.num_states as usize],
+        };
+
+        // the simulator index of a node is its position in the system
+        let node_mapping = inst.node_mapping();
+        for node in node_mapping {
+            let idx = node.get();
+            node.set(if idx == u32::MAX { 0 } else { idx + 1 });
+        }
+
+        for (entry, ptr_resist) in descriptor.matrix_entries().iter().zip(inst.matrix_ptrs_resist())
+        {
+            let row = node_mapping[entry.nodes.node_1 as usize].get() as usize;
+            let column = node_mapping[entry.nodes.node_2 as usize].get() as usize;
+            let i = row * len + column;
+            ptr_resist.set(&mut system.jacobian_resist[i]);
+            // SAFETY: the boxed Jacobian does not move and the system outlives the evaluations
+            unsafe { inst.set_matrix_ptr_react(entry, &mut system.jacobian_react[i]) };
+        }
+
+        system
+    }
+
+    pub fn node(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| *node == name)
+    }
+
+    pub fn set_voltage(&mut self, node: usize, voltage: f64) {
+        self.solve[node] = voltage
+    }
+
+    pub fn jacobian(&self, row: usize, column: usize) -> (f64, f64) {
+        let i = row * self.nodes.len() + column;
+        (self.jacobian_resist[i], self.jacobian_react[i])
+    }
+
+    /// Evaluates `inst` at the current operating point and loads the
+    /// residuals, Jacobian and noise densities (at `freq`) into the system.
+    /// Returns the flags returned by `eval`.
+    pub fn eval(
+        &mut self,
+        inst: &OsdiInstance,
+        model: &OsdiModel,
+        handle: &CStr,
+        sim_params: &mut SimParams,
+        freq: f64,
+    ) -> u32 {
+        self.residual_resist.fill(0.0);
+        self.residual_react.fill(0.0);
+        self.jacobian_resist.fill(0.0);
+        self.jacobian_react.fill(0.0);
+        self.noise.fill(0.0);
+        self.prev_state.fill(0.0);
+
+        let flags = CALC_RESIST_RESIDUAL
+            | CALC_REACT_RESIDUAL
+            | CALC_RESIST_JACOBIAN
+            | CALC_REACT_JACOBIAN
+            | CALC_NOISE
+            | CALC_OP;
+        let mut sim_info = OsdiSimInfo {
+            paras: sim_params.raw(),
+            abstime: 0.0,
+            prev_solve: self.solve.as_mut_ptr(),
+            prev_state: self.prev_state.as_mut_ptr(),
+            next_state: self.next_state.as_mut_ptr(),
+            flags,
+        };
+        let descriptor = inst.descriptor;
+        let ret_flags =
+            descriptor.eval(handle.as_ptr() as *mut c_void, inst.data, model.data, &mut sim_info);
+
+        descriptor.load_residual_resist(inst.data, model.data, self.residual_resist.as_mut_ptr());
+        descriptor.load_residual_react(inst.data, model.data, self.residual_react.as_mut_ptr());
+        descriptor.load_jacobian_resist(inst.data, model.data);
+        descriptor.load_jacobian_react(inst.data, model.data, 1.0);
+        descriptor.load_noise(inst.data, model.data, freq, self.noise.as_mut_ptr());
+        ret_flags
+    }
+}
@@ -1,12 +1,12 @@
 use bitset::{BitSet, SparseBitMatrix};
-use hir::CompilationDB;
-use hir_lower::{HirInterner, MirBuilder, PlaceKind};
+use hir::{CompilationDB, ParamRange};
+use hir_lower::{HirInterner, MirBuilder, ParamKind, PlaceKind};
 use lasso::Rodeo;
 use mir::{Block, ControlFlowGraph, DominatorTree, Function, Inst, Value};
 use mir_opt::{
-    aggressive_dead_code_elimination, dead_code_elimination, inst_combine, propagate_direct_taint,
-    propagate_taint, simplify_cfg, simplify_cfg_no_phi_merge,
-    sparse_conditional_constant_propagation, GVN,
+    aggressive_dead_code_elimination, dead_code_elimination, inst_combine_with_ranges,
+    propagate_direct_taint, propagate_taint, simplify_cfg, simplify_cfg_no_phi_merge,
+    sparse_conditional_constant_propagation, ValueRange, ValueRanges, GVN,
 };
 use stdx::packed_option::PackedOption;
 
@@ -22,6 +22,9 @@ pub(crate) struct Context<'a> {
     pub(crate) output_values: BitSet<Value>,
     pub(crate) op_dependent_insts: BitSet<Inst>,
     pub(crate) op_dependent_vals: Vec<Value>,
+    /// Ranges of the parameters that are assumed to only take certain values.
+    /// Setup rejects any other value so these can be used to optimize eval.
+    pub(crate) assumed_ranges: ValueRanges,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -32,7 +35,7 @@ pub enum OptimiziationStage {
 }
 
 impl<'a> Context<'a> {
-    pub fn new(db: &'a CompilationDB, literals: &mut Rodeo, module: &'a ModuleInfo, params_to_leave: &'a Vec<u32>) -> Self {
+    pub fn new(db: &'a CompilationDB, literals: &mut Rodeo, module: &'a ModuleInfo) -> Self {
         let (mut func, mut intern) = MirBuilder::new(
             db,
             module.module,
@@ -48,10 +51,27 @@ impl<'a> Context<'a> {
         )
         .with_equations()
         .with_tagged_writes()
-        .build(literals, params_to_leave);
+        .build(literals);
         // TODO hidden state
         intern.insert_var_init(db, &mut func, literals);
 
+        let assumed_ranges = intern
+            .params
+            .iter()
+            .filter_map(|(kind, &val)| match *kind {
+                ParamKind::Param(param) => {
+                    let range = match param.assumed_range(db)? {
+                        ParamRange::Interval { lo, lo_inclusive, hi, hi_inclusive } => {
+                            ValueRange::Interval { lo, lo_inclusive, hi, hi_inclusive }
+                        }
+                        ParamRange::OneOf(vals) => ValueRange::OneOf(vals.into_boxed_slice()),
+                    };
+                    Some((val, range))
+                }
+                _ => None,
+            })
+            .collect();
+
         Context {
             output_values: BitSet::new_empty(func.dfg.num_values()),
             func,
@@ -62,6 +82,7 @@ impl<'a> Context<'a> {
             module,
             op_dependent_insts: BitSet::new_empty(0),
             op_dependent_vals: Vec::new(),
+            assumed_ranges,
         }
     }
 
@@ -70,7 +91,7 @@ impl<'a> Context<'a> {
             dead_code_elimination(&mut self.func, &self.output_values);
         }
         sparse_conditional_constant_propagation(&mut self.func, &self.cfg);
-        inst_combine(&mut self.func);
+        inst_combine_with_ranges(&mut self.func, &self.assumed_ranges);
         if stage == OptimiziationStage::Final {
             simplify_cfg(&mut self.func, &mut self.cfg);
         } else {
@@ -555,13 +555,21 @@ impl<'a> Builder<'a> {
         }
     }
 
+    /// Returns `$mfactor`, which is a constant if it was assumed on the CLI.
+    fn mfactor(&mut self) -> Value {
+        match self.db.compilation_unit().sim_assumptions(self.db).mfactor() {
+            Some(mfactor) => self.cursor.func.dfg.fconst(mfactor.into()),
+            None => self
+                .intern
+                .ensure_param(&mut self.cursor, ParamKind::ParamSysFun(ParamSysFun::mfactor)),
+        }
+    }
+
     fn current_branch(
         &mut self, 
         BranchInfo { current_src, .. }: &BranchInfo,
     ) -> Contribution {
-        let mfactor = self
-            .intern
-            .ensure_param(&mut self.cursor, ParamKind::ParamSysFun(ParamSysFun::mfactor));
+        let mfactor = self.mfactor();
         let mut noise = Vec::with_capacity(current_src.noise.len());
         let current_noise = current_src.noise.iter().map(|src| {
             let mut src = src.clone();
@@ -584,9 +592,7 @@ impl<'a> Builder<'a> {
         &mut self, 
         BranchInfo { voltage_src, .. }: &BranchInfo,
     ) -> Contribution {
-        let mfactor = self
-            .intern
-            .ensure_param(&mut self.cursor, ParamKind::ParamSysFun(ParamSysFun::mfactor));
+        let mfactor = self.mfactor();
         let mut noise = Vec::with_capacity(voltage_src.noise.len());
         let voltage_noise = voltage_src.noise.iter().map(|src| {
             let mut src = src.clone();
@@ -658,9 +664,7 @@ impl<'a> Builder<'a> {
         // Scale noise 
         // Must do this after all phi commands
         // because all phi commands must be listed at block beginning
-        let mfactor = self
-            .intern
-            .ensure_param(&mut self.cursor, ParamKind::ParamSysFun(ParamSysFun::mfactor));
+        let mfactor = self.mfactor();
         for ii in 0..voltage_src.noise.len() + current_src.noise.len() {
             if ii < voltage_src.noise.len() {
                 // Voltage noise
@@ -734,9 +738,7 @@ impl<'a> Builder<'a> {
     /// multiply each residual and matrix entry with mfactor and ensure it has
     /// a optbarrier
     pub(super) fn ensure_optbarriers(&mut self) {
-        let mfactor = self
-            .intern
-            .ensure_param(&mut self.cursor, ParamKind::ParamSysFun(ParamSysFun::mfactor));
+        let mfactor = self.mfactor();
         let mut ensure_optbarrier = |mut val, is_kirchoff_law| {
             val = self.cursor.ins().ensure_optbarrier(val);
             if is_kirchoff_law && val != F_ZERO {
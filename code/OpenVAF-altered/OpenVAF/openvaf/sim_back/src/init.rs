@@ -6,7 +6,7 @@ use indexmap::IndexMap;
 use mir::builder::InstBuilder;
 use mir::cursor::{Cursor, FuncCursor};
 use mir::{
-    strip_optbarrier, Block, ControlFlowGraph, DominatorTree, FuncRef, Function, Inst,
+    strip_optbarrier, Block, Const, ControlFlowGraph, DominatorTree, FuncRef, Function, Inst,
     InstructionData, Opcode, Value, FALSE,
 };
 use mir_opt::{aggressive_dead_code_elimination, simplify_cfg, simplify_cfg_init, ClassId, GVN};
@@ -272,6 +272,23 @@ impl<'a> Builder<'a> {
                     return None;
                 }
 
+                // values that became constant during specialization (for example a
+                // variable assigned from elided parameters) do not need a cache slot
+                let new_val = self.val_map[&val];
+                let stripped = strip_optbarrier(&self.init.func, new_val);
+                let const_val = self.init.func.dfg.value_def(stripped).as_const();
+                if let Some(const_val) = const_val.filter(|c| !matches!(c, Const::Bool(_))) {
+                    let values = &mut self.func.dfg.values;
+                    match const_val {
+                        Const::Float(c) => values.fconst_at(c, val),
+                        Const::Int(c) => values.iconst_at(c, val),
+                        Const::Str(c) => values.sconst_at(c, val),
+                        Const::Bool(_) => unreachable!(),
+                    }
+                    values.set_tag(val, None);
+                    return None;
+                }
+
                 let ty = if let Some(tag) = self.func.dfg.tag(val) {
                     let idx = usize::from(tag);
                     let place = self.intern.outputs.get_index(idx).unwrap().0;
@@ -291,7 +308,6 @@ impl<'a> Builder<'a> {
                     .unwrap();
                 let cache_slot = ensure_cache_slot(Some(old_inst), idx, ty);
 
-                let new_val = self.val_map[&val];
                 let (new_inst, _) = self.init.func.dfg.value_def(new_val).unwrap_result();
 
                 self.func
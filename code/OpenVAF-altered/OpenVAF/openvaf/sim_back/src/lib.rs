@@ -19,6 +19,7 @@ pub mod init;
 mod module_info;
 pub mod node_collapse;
 mod noise;
+pub mod stats;
 mod topology;
 
 mod util;
@@ -156,10 +157,9 @@ impl<'a> CompiledModule<'a> {
         literals: &mut Rodeo,
         dump_unopt_mir: bool, 
         dump_mir: bool, 
-        params_to_leave: &'a Vec<u32>,
     ) -> CompiledModule<'a> {
         // Build MIR for the module
-        let mut cx = Context::new(db, literals, module, params_to_leave);
+        let mut cx = Context::new(db, literals, module);
 
         if dump_unopt_mir {
             println!("Unoptimized MIR (no DAE) of {}", module.module.name(db));
@@ -209,6 +209,8 @@ impl<'a> CompiledModule<'a> {
             .collect();
         // Add initialization of instance parameters
         init.intern.insert_param_init(db, &mut init.func, literals, false, true, &inst_params);
+        // Abort setup if the simulator disagrees with the inputs assumed during compilation
+        init.intern.insert_assumption_checks(db, &mut init.func, literals);
         
         // Model setup MIR
         let mut model_param_setup = Function::default();
@@ -40,6 +40,7 @@ pub fn collect_modules(
     Some(res)
 }
 
+#[derive(Clone)]
 pub struct ModuleInfo {
     pub module: Module,
     pub params: IndexMap<Parameter, ParamInfo, ahash::RandomState>,
@@ -195,6 +196,12 @@ impl ModuleInfo {
 
         ModuleInfo { module, params, op_vars, sys_fun_alias }
     }
+
+    /// Removes all parameters that were elided on the CLI so they are not
+    /// exposed to the simulator.
+    pub fn remove_elided_params(&mut self, db: &CompilationDB) {
+        self.params.retain(|param, _| !param.is_elided(db));
+    }
 }
 
 struct IllegalAttr {
@@ -0,0 +1,41 @@
+use mir::Function;
+
+use crate::CompiledModule;
+
+/// Size of the MIR generated for a module. Used to judge the effect of
+/// optimizations like parameter elision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModuleStats {
+    pub eval_insts: usize,
+    pub instance_setup_insts: usize,
+    pub model_setup_insts: usize,
+    pub cache_slots: usize,
+    pub jacobian_entries: usize,
+    /// Jacobian entries whose resistive and reactive parts are both constant
+    pub const_jacobian_entries: usize,
+}
+
+impl ModuleStats {
+    pub fn new(module: &CompiledModule) -> ModuleStats {
+        let is_const = |val| module.eval.dfg.value_def(val).as_const().is_some();
+        let const_jacobian_entries = module
+            .dae_system
+            .jacobian
+            .iter()
+            .filter(|entry| is_const(entry.resist) && is_const(entry.react))
+            .count();
+
+        ModuleStats {
+            eval_insts: num_insts(&module.eval),
+            instance_setup_insts: num_insts(&module.init.func),
+            model_setup_insts: num_insts(&module.model_param_setup),
+            cache_slots: module.init.cache_slots.len(),
+            jacobian_entries: module.dae_system.jacobian.len(),
+            const_jacobian_entries,
+        }
+    }
+}
+
+fn num_insts(func: &Function) -> usize {
+    func.layout.blocks().map(|bb| func.layout.block_insts(bb).count()).sum()
+}
@@ -0,0 +1,26 @@
+from setuptools import setup
+from setuptools_rust import RustExtension, Binding
+
+
+extension = RustExtension(
+    "melange.melange",
+    path="melange/melange_py/Cargo.toml",
+    rust_version=">=1.64",
+    binding=Binding.NoBinding,
+    debug=False,
+)
+
+
+setup(
+    name="melange",
+    version="0.0.0",
+    author="DSPOM",
+    author_email="dspom@protonmail.com",
+    description="An experimental circuit simulator based on OpenVAF",
+    license="GPL-3",
+    python_requires=">=3.8",
+    packages=["melange"],
+    # rust extensions are not zip safe, just like C-extensions.
+    zip_safe=False,
+    rust_extensions=[extension],
+)
@@ -24,7 +24,7 @@ const ANALOG_OPERATORS: [&str; 17] = [
     "transition",
 ];
 
-const UNSUPPORTED: [&str; 50] = [
+const UNSUPPORTED: [&str; 48] = [
     "simprobe",
     "analog_node_alias",
     "analog_port_alias",
@@ -39,8 +39,6 @@ const UNSUPPORTED: [&str; 50] = [
     "laplace_zd",
     "laplace_zp",
     "last_crossing",
-    "slew",
-    "transition",
     "fclose",
     "fopen",
     "fdisplay",
@@ -79,6 +77,8 @@ const UNSUPPORTED: [&str; 50] = [
 
 const ANALOG_OPERATORS_SYSFUN: [&str; 1] = ["$limit"];
 
+const EVENT_FUNS: [&str; 3] = ["cross", "above", "timer"];
+
 const ANALYSIS_FUNS: [&str; 6] =
     ["analysis", "ac_stim", "noise_table", "noise_table_log", "white_noise", "flicker_noise"];
 
@@ -209,6 +209,7 @@ fn generate_builtins() {
         .chain(ANALYSIS_FUNS)
         .chain(ANALOG_OPERATORS_SYSFUN)
         .chain(ANALOG_OPERATORS)
+        .chain(EVENT_FUNS)
         .map(|builtin| {
             let is_sysfun = builtin.starts_with('$');
 
@@ -229,6 +230,7 @@ fn generate_builtins() {
 
     let analysis_funs = ANALYSIS_FUNS.into_iter().map(|op| format_ident!("{}", op));
     let analog_operators = ANALOG_OPERATORS.into_iter().map(|op| format_ident!("{}", op));
+    let event_funs = EVENT_FUNS.into_iter().map(|fun| format_ident!("{}", fun));
     let unsupported = UNSUPPORTED.into_iter().map(|op| format_ident!("{}", op));
     let analog_operators_sysfun =
         ANALOG_OPERATORS_SYSFUN.into_iter().map(|op| format_ident!("{}", &op[1..]));
@@ -289,6 +291,14 @@ fn generate_builtins() {
                     _ => false
                 }
             }
+
+            #[allow(clippy::match_like_matches_macro)]
+            pub fn is_event_fun(self)->bool{
+                match self{
+                    #(BuiltIn::#event_funs)|* =>true,
+                    _ => false
+                }
+            }
         }
 
         pub fn insert_builtin_scope(dst: &mut IndexMap<Name, ScopeDefItem, RandomState>){
@@ -23,6 +23,8 @@ fn gen_osdi_structs() {
     let osdi_src_dir = project_root().join("openvaf").join("osdi").join("src").join("metadata");
     let osdi_test_dir = project_root().join("openvaf").join("openvaf").join("tests").join("load");
     let melange_src_dir = project_root().join("melange").join("core").join("src").join("veriloga");
+    let verify_src_dir =
+        project_root().join("openvaf").join("osdi_verify").join("src").join("load");
 
     for header in &headers {
         let res = HeaderParser { header, res: ParseResults::default(), off: 0 }.run();
@@ -65,6 +67,7 @@ fn gen_osdi_structs() {
 
         ensure_file_contents(&melange_src_dir.join(&file_name), &file_string);
         ensure_file_contents(&osdi_test_dir.join(&file_name), &file_string);
+        ensure_file_contents(&verify_src_dir.join(&file_name), &file_string);
     }
 }
 
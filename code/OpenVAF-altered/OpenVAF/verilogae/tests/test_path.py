@@ -0,0 +1,21 @@
+from pathlib import Path
+import shutil
+import tempfile
+import verilogae
+
+# paths may be passed as str or pathlib.Path and may contain non ascii characters
+src = Path(__file__).parent / "hicumL2V2p4p0_vae.va"
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = Path(tmp) / "hicüm" / "hicumL2V2p4p0_vae.va"
+    path.parent.mkdir()
+    shutil.copy(src, path)
+
+    by_str = verilogae.load_info(str(path))
+    by_path = verilogae.load_info(path)
+    assert by_str.module_name == by_path.module_name
+    assert list(by_str.modelcard) == list(by_path.modelcard)
+
+    vfs = verilogae.export_vfs(path)
+    assert str(path) in vfs, f"{path} missing from {list(vfs)}"
+    assert vfs[str(path)] == path.read_text(encoding="utf-8")
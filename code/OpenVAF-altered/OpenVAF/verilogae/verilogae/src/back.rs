@@ -67,6 +67,7 @@ pub fn stub_callbacks<'ll>(
                 | CallBackKind::ParamInfo(_, _)
                 | CallBackKind::BuiltinLimit { .. }
                 | CallBackKind::StoreLimit(_)
+                | CallBackKind::StoreOperatorState(_)
                 | CallBackKind::LimDiscontinuity
                 | CallBackKind::CollapseHint(_, _) 
                 | CallBackKind::SetRetFlag { .. } => return None,
@@ -354,7 +355,8 @@ impl CodegenCtx<'_, '_> {
                     ParamKind::ImplicitUnknown(_)
                     | ParamKind::Abstime
                     | ParamKind::PrevState(_)
-                    | ParamKind::NewState(_) => codegen.builder.cx.const_real(0.0),
+                    | ParamKind::NewState(_)
+                    | ParamKind::PrevOperatorState(_) => codegen.builder.cx.const_real(0.0),
                     ParamKind::EnableIntegration | ParamKind::EnableLim => {
                         codegen.builder.cx.const_bool(false)
                     }
@@ -680,7 +682,8 @@ impl CodegenCtx<'_, '_> {
                     ParamKind::ImplicitUnknown(_)
                     | ParamKind::Abstime
                     | ParamKind::PrevState(_)
-                    | ParamKind::NewState(_) => builder.cx.const_real(0.0),
+                    | ParamKind::NewState(_)
+                    | ParamKind::PrevOperatorState(_) => builder.cx.const_real(0.0),
                     ParamKind::EnableIntegration | ParamKind::EnableLim => {
                         builder.cx.const_bool(false)
                     }
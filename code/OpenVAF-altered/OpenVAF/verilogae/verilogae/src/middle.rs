@@ -69,7 +69,6 @@ pub fn build_module_mir(
 
     let outputs: AHashSet<_> = info.functions.iter().map(|func| func.var).collect();
     let mut literals = Rodeo::new();
-    let placeholder_vec = Vec::new();
     let (mut func, mut intern) = MirBuilder::new(
         db,
         info.module,
@@ -83,7 +82,7 @@ pub fn build_module_mir(
         &mut outputs.iter().copied(),
     )
     .with_tagged_reads(dep_break)
-    .build(&mut literals, &placeholder_vec);
+    .build(&mut literals);
 
     // remove unused sideeffects
     for (id, _) in intern.callbacks.iter_enumerated() {
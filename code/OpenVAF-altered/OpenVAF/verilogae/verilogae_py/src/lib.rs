@@ -1,12 +1,9 @@
 #[macro_use]
-mod offsets;
-#[macro_use]
-mod ffi;
+extern crate pyffi_utils;
+
 mod load;
 mod model;
-mod numpy;
 mod typeref;
-mod unicode;
 mod util;
 
 use std::os::raw::{c_char, c_int};
@@ -1,11 +1,11 @@
 use libc::c_char;
+use pyffi_utils::unicode::OsStr;
+use pyffi_utils::PyDict_GET_SIZE;
 use pyo3_ffi::*;
 use verilogae_ffi::{verilogae_load, Opts, Slice, Vfs, VfsEntry, VfsExport};
 
-use crate::ffi::PyDict_GET_SIZE;
 use crate::model::VaeModel;
 use crate::typeref;
-use crate::unicode::OsStr;
 use crate::util::unlikely;
 
 use std::ptr;
@@ -112,7 +112,7 @@ macro_rules! parse_args {
         let mut $opts = Opts::default();
 
         if !$kwargs.is_null() {
-            let len = crate::ffi::PyDict_GET_SIZE($kwargs);
+            let len = pyffi_utils::PyDict_GET_SIZE($kwargs);
             let mut pos = 0isize;
             let mut arg: *mut PyObject = std::ptr::null_mut();
             let mut val: *mut PyObject = std::ptr::null_mut();
@@ -166,7 +166,7 @@ pub unsafe extern "C" fn load_py(
     kwds: *mut PyObject,
 ) -> *mut PyObject {
     parse_args!("load", args, kwds, path, opts);
-    let model = verilogae_load(path.data, true, opts.to_ffi());
+    let model = verilogae_load(path.as_bytes().into(), true, opts.to_ffi());
 
     if model.is_null() {
         return raise_runtime_runtime_exception("load() compilation failed");
@@ -184,7 +184,7 @@ pub unsafe extern "C" fn load_py(
     kwnames: *mut PyObject,
 ) -> *mut PyObject {
     parse_args!("load", args, nargs, kwnames, path, opts);
-    let model = verilogae_load(path.data, true, opts.to_ffi());
+    let model = verilogae_load(path.as_bytes().into(), true, opts.to_ffi());
 
     if model.is_null() {
         return raise_runtime_runtime_exception("load() compilation failed");
@@ -201,7 +201,7 @@ pub unsafe extern "C" fn load_info_py(
     kwds: *mut PyObject,
 ) -> *mut PyObject {
     parse_args!("load_info", args, kwds, path, opts);
-    let model = verilogae_load(path.data, false, opts.to_ffi());
+    let model = verilogae_load(path.as_bytes().into(), false, opts.to_ffi());
 
     if model.is_null() {
         return raise_runtime_runtime_exception("load_info() compilation failed");
@@ -219,7 +219,7 @@ pub unsafe extern "C" fn load_info_py(
     kwnames: *mut PyObject,
 ) -> *mut PyObject {
     parse_args!("load_info", args, nargs, kwnames, path, opts);
-    let model = verilogae_load(path.data, false, opts.to_ffi());
+    let model = verilogae_load(path.as_bytes().into(), false, opts.to_ffi());
 
     if model.is_null() {
         return raise_runtime_runtime_exception("load_info() compilation failed");
@@ -236,7 +236,7 @@ pub unsafe extern "C" fn load_vfs(
     kwds: *mut PyObject,
 ) -> *mut PyObject {
     parse_args!("load_vfs", args, kwds, path, opts);
-    let vfs = VfsExport::new(path.data, &opts);
+    let vfs = VfsExport::new(path.as_bytes(), &opts);
     match vfs {
         Some(vfs) => vfs_to_py(vfs),
         None => raise_runtime_runtime_exception("load_vfs() failed to create vfs"),
@@ -252,7 +252,7 @@ pub unsafe extern "C" fn load_vfs(
     kwnames: *mut PyObject,
 ) -> *mut PyObject {
     parse_args!("load_vfs", args, nargs, kwnames, path, opts);
-    let vfs = VfsExport::new(path.data.read(), &opts);
+    let vfs = VfsExport::new(path.as_bytes(), &opts);
     match vfs {
         Some(vfs) => vfs_to_py(vfs),
         None => raise_runtime_runtime_exception("load_vfs() failed to create vfs"),
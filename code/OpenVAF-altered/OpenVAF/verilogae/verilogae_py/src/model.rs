@@ -5,6 +5,8 @@ use std::ptr;
 use std::slice;
 
 use libc::{c_char, c_void};
+use pyffi_utils::new_type;
+use pyffi_utils::numpy::{ItemType, NumpyArray, PyArrayError};
 use pyo3_ffi::structmember::{PyMemberDef, READONLY, T_OBJECT, T_OBJECT_EX};
 use pyo3_ffi::*;
 use verilogae_ffi::{
@@ -25,8 +27,6 @@ use verilogae_ffi::{
     ParamFlags, PARAM_FLAGS_INVALID, PARAM_FLAGS_MAX_INCLUSIVE, PARAM_FLAGS_MIN_INCLUSIVE,
 };
 
-use crate::ffi::new_type;
-use crate::numpy::{ItemType, NumpyArray, PyArrayError};
 use crate::typeref::NUMPY_API;
 use crate::typeref::NUMPY_ARR_TYPE;
 use crate::typeref::TEMPERATURE_STR;